@@ -33,7 +33,7 @@
 #[cfg(test)]
 mod test;
 
-use crate::common::{engines, RawKey, RawValue};
+use crate::common::{engines, PreBytes, RawKey, RawValue, Registrable, VSDB};
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{borrow::Cow, ops::RangeBounds};
@@ -118,6 +118,12 @@ impl MapxRaw {
         self.inner.range(bounds)
     }
 
+    /// Iterate over the entries whose keys start with `prefix`.
+    #[inline(always)]
+    pub fn iter_prefix(&self, prefix: impl AsRef<[u8]>) -> MapxRawIter {
+        self.inner.iter_prefix(prefix.as_ref())
+    }
+
     #[inline(always)]
     pub fn iter_mut(&mut self) -> MapxRawIterMut {
         self.inner.iter_mut()
@@ -150,11 +156,28 @@ impl MapxRaw {
         self.inner.remove(key.as_ref())
     }
 
+    /// Remove the entries whose keys start with `prefix` in a batch,
+    /// return the number of them.
+    ///
+    /// NOTE: this is atomic on the rocksdb engine only, on the sled engine
+    /// a crash in the middle may leave some of the entries or a stale length.
+    #[inline(always)]
+    pub fn remove_prefix(&mut self, prefix: impl AsRef<[u8]>) -> usize {
+        self.inner.remove_prefix(prefix.as_ref())
+    }
+
     #[inline(always)]
     pub fn clear(&mut self) {
         self.inner.clear();
     }
 
+    /// Reclaim the disk space occupied by removed entries,
+    /// usually called after a `clear` of a huge instance.
+    #[inline(always)]
+    pub fn compact_instance(&self) {
+        self.inner.compact();
+    }
+
     /// # Safety
     ///
     /// Do not use this API unless you know the internal details extremely well.
@@ -190,6 +213,87 @@ impl Default for MapxRaw {
     }
 }
 
+impl Registrable for MapxRaw {
+    const KIND: &'static str = "MapxRaw";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.as_prefix_slice().try_into().unwrap()
+    }
+}
+
+/// Writes to one or more instances, they will be applied atomically.
+///
+/// # Examples
+///
+/// ```
+/// use vsdb_core::basic::mapx_raw::{MapxRaw, MapxRawBatch};
+///
+/// let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+/// vsdb_core::vsdb_set_base_dir(&dir);
+///
+/// let mut a = MapxRaw::new();
+/// let mut b = MapxRaw::new();
+/// a.insert(&[1], &[1]);
+///
+/// let mut batch = MapxRawBatch::new();
+/// batch.remove(&mut a, &[1]);
+/// batch.insert(&mut b, &[1], &[1]);
+/// batch.commit();
+///
+/// assert!(a.is_empty());
+/// assert_eq!(b.get(&[1]), Some(vec![1]));
+/// ```
+#[derive(Debug, Default)]
+pub struct MapxRawBatch {
+    ops: Vec<engines::BatchOp>,
+}
+
+impl MapxRawBatch {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline(always)]
+    pub fn insert(
+        &mut self,
+        hdr: &mut MapxRaw,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) {
+        self.ops.push((
+            hdr.instance_prefix(),
+            key.as_ref().to_vec(),
+            Some(value.as_ref().to_vec()),
+        ));
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, hdr: &mut MapxRaw, key: impl AsRef<[u8]>) {
+        self.ops
+            .push((hdr.instance_prefix(), key.as_ref().to_vec(), None));
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Apply all writes atomically, in the order they were added.
+    #[inline(always)]
+    pub fn commit(self) {
+        if !self.ops.is_empty() {
+            VSDB.write_batch(&self.ops);
+        }
+    }
+}
+
 pub struct Entry<'a> {
     key: &'a [u8],
     hdr: &'a mut MapxRaw,
@@ -24,6 +24,30 @@ fn test_insert() {
     });
     assert!(hdr.is_empty());
 }
+
+#[test]
+fn test_clear() {
+    let mut hdr = MapxRaw::new();
+    let mut neighbor = MapxRaw::new();
+    let max = 1000;
+    (0..max)
+        .map(|i: u64| (to_bytes(i), to_bytes(max + i)))
+        .for_each(|(key, value)| {
+            assert!(hdr.insert(&key, &value).is_none());
+            assert!(neighbor.insert(&key, &value).is_none());
+        });
+
+    hdr.clear();
+    hdr.compact_instance();
+    assert!(hdr.is_empty());
+    assert!(hdr.iter().next().is_none());
+    assert_eq!(max as usize, neighbor.len());
+    assert_eq!(max as usize, neighbor.iter().count());
+
+    assert!(hdr.insert(&to_bytes(0), &to_bytes(0)).is_none());
+    assert_eq!(1, hdr.len());
+}
+
 #[test]
 fn test_len() {
     let mut hdr = MapxRaw::new();
@@ -86,6 +110,80 @@ fn test_first_last() {
     assert_eq!(max - 1, val);
 }
 
+#[test]
+fn test_prefix() {
+    let mut hdr = MapxRaw::new();
+    [
+        &[1][..],
+        &[1, 0],
+        &[1, 1],
+        &[1, 1, 1],
+        &[2],
+        &[2, 1],
+        &[u8::MAX],
+        &[u8::MAX, 1],
+    ]
+    .iter()
+    .for_each(|k| {
+        hdr.insert(k, k);
+    });
+
+    assert_eq!(
+        hdr.iter_prefix([1]).map(|(k, _)| k).collect::<Vec<_>>(),
+        vec![vec![1], vec![1, 0], vec![1, 1], vec![1, 1, 1]]
+    );
+    assert_eq!(2, hdr.iter_prefix([1, 1]).count());
+    assert_eq!(vec![1, 1, 1], pnk!(hdr.iter_prefix([1, 1]).next_back()).0);
+    assert_eq!(2, hdr.iter_prefix([u8::MAX]).count());
+    assert_eq!(0, hdr.iter_prefix([3]).count());
+    assert_eq!(8, hdr.iter_prefix([]).count());
+
+    assert_eq!(2, hdr.remove_prefix([1, 1]));
+    assert_eq!(0, hdr.remove_prefix([1, 1]));
+    assert_eq!(6, hdr.len());
+    assert_eq!(2, hdr.remove_prefix([1]));
+    assert_eq!(2, hdr.remove_prefix([u8::MAX]));
+    assert_eq!(2, hdr.len());
+    assert_eq!(2, hdr.iter_prefix([2]).count());
+}
+
+#[test]
+fn test_batch() {
+    let mut a = MapxRaw::new();
+    let mut b = MapxRaw::new();
+    (0..10u64).for_each(|i| {
+        a.insert(to_bytes(i), to_bytes(i));
+    });
+
+    let mut batch = MapxRawBatch::new();
+    (0..10u64).for_each(|i| {
+        batch.remove(&mut a, to_bytes(i));
+        batch.insert(&mut b, to_bytes(i), to_bytes(i));
+    });
+    // a removed and then re-inserted key
+    batch.insert(&mut a, to_bytes(0), to_bytes(100));
+    // a repeated insert
+    batch.insert(&mut b, to_bytes(0), to_bytes(100));
+    // removing a key that does not exist
+    batch.remove(&mut b, to_bytes(100));
+    assert_eq!(23, batch.len());
+    assert_eq!(10, a.len());
+    assert!(b.is_empty());
+
+    batch.commit();
+    assert_eq!(1, a.len());
+    assert_eq!(100, to_u64(&pnk!(a.get(to_bytes(0)))));
+    assert_eq!(10, b.len());
+    assert_eq!(100, to_u64(&pnk!(b.get(to_bytes(0)))));
+    (1..10u64).for_each(|i| {
+        assert!(a.get(to_bytes(i)).is_none());
+        assert_eq!(i, to_u64(&pnk!(b.get(to_bytes(i)))));
+    });
+
+    MapxRawBatch::new().commit();
+    assert_eq!(1, a.len());
+}
+
 fn to_u64(bytes: &[u8]) -> u64 {
     u64::from_be_bytes(<[u8; size_of::<u64>()]>::try_from(bytes).unwrap())
 }
@@ -39,10 +39,10 @@ type EngineIter = sled_db::SledIter;
 
 use crate::common::{
     BranchIDBase as BranchID, Pre, PreBytes, RawKey, RawValue,
-    VersionIDBase as VersionID, PREFIX_SIZE, VSDB,
+    VersionIDBase as VersionID, BIGGEST_RESERVED_ID, PREFIX_SIZE, VSDB,
 };
 use once_cell::sync::Lazy;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
 use ruc::*;
 use serde::{de, Deserialize, Serialize};
 use std::{
@@ -56,6 +56,9 @@ use std::{
 static LEN_LK: Lazy<Vec<Mutex<()>>> =
     Lazy::new(|| (0..VSDB.db.area_count()).map(|_| Mutex::new(())).collect());
 
+// A write in a batch, `None` means removing the key.
+pub(crate) type BatchOp = (PreBytes, RawKey, Option<RawValue>);
+
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
@@ -94,10 +97,37 @@ pub trait Engine: Sized {
 
     fn remove(&self, meta_prefix: PreBytes, key: &[u8]) -> Option<RawValue>;
 
+    // apply all writes atomically, they may belong to different instances,
+    // the lengths of the instances are also updated in the same batch
+    fn write_batch(&self, ops: &[BatchOp]);
+
+    // iterate over the entries whose keys start with `key_prefix`
+    fn iter_prefix(&self, meta_prefix: PreBytes, key_prefix: &[u8]) -> EngineIter;
+
+    // remove all entries whose keys start with `key_prefix` in a batch,
+    // and decrease the length of the instance by the number of them,
+    // return the number of the removed entries
+    fn remove_prefix(&self, meta_prefix: PreBytes, key_prefix: &[u8]) -> u64;
+
+    // remove all entries of an instance in a batch,
+    // the length of the instance will NOT be changed here
+    fn clear_instance(&self, instance_prefix: PreBytes);
+
+    // reclaim the disk space released by the removed entries of an instance
+    fn compact_instance(&self, instance_prefix: PreBytes);
+
     fn get_instance_len(&self, instance_prefix: PreBytes) -> u64;
 
     fn set_instance_len(&self, instance_prefix: PreBytes, new_len: u64);
 
+    // the uncompressed size of all entries of an instance,
+    // used as an approximate value of the disk usage
+    fn get_instance_size(&self, instance_prefix: PreBytes) -> u64 {
+        self.iter(instance_prefix)
+            .map(|(k, v)| (PREFIX_SIZE + k.len() + v.len()) as u64)
+            .sum()
+    }
+
     #[allow(unused_variables)]
     fn increase_instance_len(&self, instance_prefix: PreBytes) {
         let x = LEN_LK[self.area_idx(instance_prefix)].lock();
@@ -113,6 +143,27 @@ pub trait Engine: Sized {
         let l = self.get_instance_len(instance_prefix);
         self.set_instance_len(instance_prefix, l - 1)
     }
+
+    // hold the length locks of all areas touched by the writes,
+    // in ascending order to avoid dead locks
+    fn lock_batch_areas(&self, ops: &[BatchOp]) -> (Vec<usize>, Vec<MutexGuard<()>>) {
+        let mut areas = ops
+            .iter()
+            .map(|(pre, _, _)| self.area_idx(*pre))
+            .collect::<Vec<_>>();
+        areas.sort_unstable();
+        areas.dedup();
+        let lks = areas.iter().map(|idx| LEN_LK[*idx].lock()).collect();
+        (areas, lks)
+    }
+
+    #[allow(unused_variables)]
+    fn decrease_instance_len_by(&self, instance_prefix: PreBytes, n: u64) {
+        let x = LEN_LK[self.area_idx(instance_prefix)].lock();
+
+        let l = self.get_instance_len(instance_prefix);
+        self.set_instance_len(instance_prefix, l - n)
+    }
 }
 
 /////////////////////////////////////////////////////////////////////////////
@@ -150,6 +201,23 @@ impl Mapx {
         }
     }
 
+    // Open an instance under a reserved prefix,
+    // which will never be allocated by `alloc_prefix`.
+    #[inline(always)]
+    pub(crate) fn new_reserved(prefix: Pre) -> Self {
+        debug_assert!(prefix <= BIGGEST_RESERVED_ID);
+
+        let prefix_bytes = prefix.to_be_bytes();
+
+        if VSDB.db.iter(prefix_bytes).next().is_none() {
+            VSDB.db.set_instance_len(prefix_bytes, 0);
+        }
+
+        Mapx {
+            prefix: prefix_bytes,
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn get(&self, key: &[u8]) -> Option<RawValue> {
         VSDB.db.get(self.prefix, key)
@@ -214,6 +282,14 @@ impl Mapx {
         }
     }
 
+    #[inline(always)]
+    pub(crate) fn iter_prefix(&self, key_prefix: &[u8]) -> MapxIter {
+        MapxIter {
+            db_iter: VSDB.db.iter_prefix(self.prefix, key_prefix),
+            _hdr: self,
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<RawValue> {
         let ret = VSDB.db.insert(self.prefix, key, value);
@@ -232,14 +308,22 @@ impl Mapx {
         ret
     }
 
+    #[inline(always)]
+    pub(crate) fn remove_prefix(&mut self, key_prefix: &[u8]) -> usize {
+        VSDB.db.remove_prefix(self.prefix, key_prefix) as usize
+    }
+
     #[inline(always)]
     pub(crate) fn clear(&mut self) {
-        VSDB.db.iter(self.prefix).for_each(|(k, _)| {
-            VSDB.db.remove(self.prefix, &k);
-        });
+        VSDB.db.clear_instance(self.prefix);
         VSDB.db.set_instance_len(self.prefix, 0);
     }
 
+    #[inline(always)]
+    pub(crate) fn compact(&self) {
+        VSDB.db.compact_instance(self.prefix);
+    }
+
     #[inline(always)]
     pub(crate) unsafe fn from_prefix_slice(s: impl AsRef<[u8]>) -> Self {
         debug_assert_eq!(s.as_ref().len(), PREFIX_SIZE);
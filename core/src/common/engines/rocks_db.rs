@@ -1,4 +1,5 @@
 use crate::common::{
+    engines::{BatchOp, LEN_LK},
     vsdb_get_base_dir, vsdb_set_base_dir, BranchIDBase as BranchID, Engine, Pre,
     PreBytes, RawBytes, RawKey, RawValue, VersionIDBase as VersionID, GB,
     INITIAL_BRANCH_ID, MB, PREFIX_SIZE, RESERVED_ID_CNT,
@@ -7,11 +8,12 @@ use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use rocksdb::{
     ColumnFamily, ColumnFamilyDescriptor, DBCompressionType, DBIterator, Direction,
-    IteratorMode, Options, ReadOptions, SliceTransform, DB,
+    IteratorMode, Options, ReadOptions, SliceTransform, WriteBatch, DB,
 };
 use ruc::*;
 use std::{
     borrow::Cow,
+    collections::{BTreeMap, HashMap},
     fs,
     mem::size_of,
     ops::{Bound, RangeBounds},
@@ -71,6 +73,21 @@ impl RocksEngine {
 
         max_guard
     }
+
+    // the `[lower, upper)` range that covers all keys of an instance
+    #[inline(always)]
+    fn get_instance_bounds(&self, instance_prefix: PreBytes) -> (Vec<u8>, Vec<u8>) {
+        let lower = instance_prefix.to_vec();
+        let upper = crate::parse_prefix!(instance_prefix)
+            .checked_add(1)
+            .map(|p| p.to_be_bytes().to_vec())
+            .unwrap_or_else(|| {
+                let mut b = self.get_upper_bound_value(instance_prefix);
+                b.push(0u8);
+                b
+            });
+        (lower, upper)
+    }
 }
 
 impl Engine for RocksEngine {
@@ -124,11 +141,9 @@ impl Engine for RocksEngine {
         let x = LK.lock();
 
         // step 1
-        let ret = crate::parse_prefix!(self
-            .meta
-            .get(self.prefix_allocator.key)
-            .unwrap()
-            .unwrap());
+        let ret = crate::parse_prefix!(
+            self.meta.get(self.prefix_allocator.key).unwrap().unwrap()
+        );
 
         // step 2
         self.meta
@@ -315,6 +330,100 @@ impl Engine for RocksEngine {
         old_v
     }
 
+    fn write_batch(&self, ops: &[BatchOp]) {
+        let (_, _lks) = self.lock_batch_areas(ops);
+
+        let mut batch = WriteBatch::default();
+        let mut lens: BTreeMap<PreBytes, i64> = BTreeMap::new();
+
+        // the existence of keys after the previous writes in this batch
+        let mut exists: HashMap<RawBytes, bool> = HashMap::new();
+
+        for (pre, key, value) in ops.iter() {
+            let cf = self.cf_hdr(self.area_idx(*pre));
+
+            let mut k = pre.to_vec();
+            k.extend_from_slice(key);
+
+            let existed = match exists.get(&k) {
+                Some(e) => *e,
+                None => self.meta.get_cf(cf, &k).unwrap().is_some(),
+            };
+
+            let delta = if let Some(v) = value {
+                if key.len() > self.get_max_keylen() {
+                    self.set_max_key_len(key.len());
+                }
+                batch.put_cf(cf, &k, v);
+                exists.insert(k, true);
+                alt!(existed, 0, 1)
+            } else {
+                batch.delete_cf(cf, &k);
+                exists.insert(k, false);
+                alt!(existed, -1, 0)
+            };
+            *lens.entry(*pre).or_default() += delta;
+        }
+
+        for (pre, delta) in lens.into_iter().filter(|(_, d)| 0 != *d) {
+            let l = self.get_instance_len(pre);
+            batch.put(pre, ((l as i64 + delta) as u64).to_be_bytes());
+        }
+
+        self.meta.write(batch).unwrap();
+    }
+
+    fn iter_prefix(&self, meta_prefix: PreBytes, key_prefix: &[u8]) -> RocksIter {
+        let hi = prefix_successor(key_prefix)
+            .map(|k| Bound::Excluded(Cow::Owned(k)))
+            .unwrap_or(Bound::Unbounded);
+        self.range(
+            meta_prefix,
+            (Bound::Included(Cow::Borrowed(key_prefix)), hi),
+        )
+    }
+
+    fn remove_prefix(&self, meta_prefix: PreBytes, key_prefix: &[u8]) -> u64 {
+        let area_idx = self.area_idx(meta_prefix);
+        let _lk = LEN_LK[area_idx].lock();
+
+        let cnt = self.iter_prefix(meta_prefix, key_prefix).count() as u64;
+
+        if 0 < cnt {
+            let mut lower = meta_prefix.to_vec();
+            lower.extend_from_slice(key_prefix);
+            let upper = prefix_successor(&lower).unwrap_or_else(|| {
+                let mut b = self.get_upper_bound_value(meta_prefix);
+                b.push(0u8);
+                b
+            });
+
+            // the removal and the new length are written atomically
+            let mut batch = WriteBatch::default();
+            batch.delete_range_cf(self.cf_hdr(area_idx), lower, upper);
+            let l = self.get_instance_len(meta_prefix);
+            batch.put(meta_prefix, (l - cnt).to_be_bytes());
+            self.meta.write(batch).unwrap();
+        }
+
+        cnt
+    }
+
+    fn clear_instance(&self, instance_prefix: PreBytes) {
+        let area_idx = self.area_idx(instance_prefix);
+        let (lower, upper) = self.get_instance_bounds(instance_prefix);
+        self.meta
+            .delete_range_cf(self.cf_hdr(area_idx), lower, upper)
+            .unwrap();
+    }
+
+    fn compact_instance(&self, instance_prefix: PreBytes) {
+        let area_idx = self.area_idx(instance_prefix);
+        let (lower, upper) = self.get_instance_bounds(instance_prefix);
+        self.meta
+            .compact_range_cf(self.cf_hdr(area_idx), Some(lower), Some(upper));
+    }
+
     fn get_instance_len(&self, instance_prefix: PreBytes) -> u64 {
         crate::parse_int!(self.meta.get(instance_prefix).unwrap().unwrap(), u64)
     }
@@ -350,6 +459,19 @@ impl DoubleEndedIterator for RocksIter {
     }
 }
 
+// the smallest key that is bigger than all keys starting with `prefix`,
+// `None` if all bytes of the `prefix` are `u8::MAX`
+fn prefix_successor(prefix: &[u8]) -> Option<RawBytes> {
+    let mut ret = prefix.to_vec();
+    while let Some(b) = ret.pop() {
+        if b < u8::MAX {
+            ret.push(b + 1);
+            return Some(ret);
+        }
+    }
+    None
+}
+
 // key of the prefix allocator in the 'meta'
 struct PreAllocator {
     key: [u8; 1],
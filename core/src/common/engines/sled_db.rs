@@ -1,14 +1,18 @@
 use crate::common::{
-    vsdb_get_base_dir, vsdb_set_base_dir, BranchIDBase as BranchID, Engine, Pre,
-    PreBytes, RawKey, RawValue, VersionIDBase as VersionID, GB, INITIAL_BRANCH_ID,
-    PREFIX_SIZE, RESERVED_ID_CNT,
+    engines::BatchOp, vsdb_get_base_dir, vsdb_set_base_dir, BranchIDBase as BranchID,
+    Engine, Pre, PreBytes, RawKey, RawValue, VersionIDBase as VersionID, GB,
+    INITIAL_BRANCH_ID, PREFIX_SIZE, RESERVED_ID_CNT,
 };
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use ruc::*;
-use sled::{Config, Db, IVec, Iter, Mode, Tree};
+use sled::{
+    transaction::{ConflictableTransactionError, Transactional},
+    Batch, Config, Db, IVec, Iter, Mode, Tree,
+};
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
     ops::{Bound, RangeBounds},
     thread::available_parallelism,
 };
@@ -24,6 +28,10 @@ const META_KEY_BRANCH_ID: [u8; 1] = [u8::MAX - 1];
 const META_KEY_VERSION_ID: [u8; 1] = [u8::MAX - 2];
 const META_KEY_PREFIX_ALLOCATOR: [u8; 1] = [u8::MIN];
 
+// max number of keys in one removing batch,
+// avoid holding too many keys in memory at the same time
+const CLEAR_BATCH_SIZE: usize = 100_000;
+
 pub struct SledEngine {
     meta: Db,
     areas: Vec<Tree>,
@@ -230,6 +238,81 @@ impl Engine for SledEngine {
             .map(|iv| iv.to_vec())
     }
 
+    fn write_batch(&self, ops: &[BatchOp]) {
+        let (areas, _lks) = self.lock_batch_areas(ops);
+
+        // the lengths of instances are stored in the 'meta'
+        let mut trees = vec![Tree::clone(&self.meta)];
+        trees.extend(areas.iter().map(|idx| self.areas[*idx].clone()));
+
+        trees
+            .as_slice()
+            .transaction(|txs| {
+                let mut lens: BTreeMap<PreBytes, i64> = BTreeMap::new();
+
+                for (pre, key, value) in ops.iter() {
+                    let area = areas.binary_search(&self.area_idx(*pre)).unwrap();
+                    let tx = &txs[1 + area];
+
+                    let mut k = pre.to_vec();
+                    k.extend_from_slice(key);
+
+                    let delta = if let Some(v) = value {
+                        alt!(tx.insert(k, &v[..])?.is_none(), 1, 0)
+                    } else {
+                        alt!(tx.remove(k)?.is_some(), -1, 0)
+                    };
+                    *lens.entry(*pre).or_default() += delta;
+                }
+
+                for (pre, delta) in lens.into_iter().filter(|(_, d)| 0 != *d) {
+                    let l = crate::parse_int!(txs[0].get(pre)?.unwrap(), u64);
+                    txs[0].insert(&pre[..], ((l as i64 + delta) as u64).to_be_bytes())?;
+                }
+
+                Ok::<_, ConflictableTransactionError<()>>(())
+            })
+            .unwrap();
+    }
+
+    fn iter_prefix(&self, meta_prefix: PreBytes, key_prefix: &[u8]) -> SledIter {
+        let area_idx = self.area_idx(meta_prefix);
+
+        let mut k = meta_prefix.to_vec();
+        k.extend_from_slice(key_prefix);
+
+        SledIter {
+            inner: self.areas[area_idx].scan_prefix(k),
+            bounds: (Bound::Unbounded, Bound::Unbounded),
+        }
+    }
+
+    fn remove_prefix(&self, meta_prefix: PreBytes, key_prefix: &[u8]) -> u64 {
+        let mut k = meta_prefix.to_vec();
+        k.extend_from_slice(key_prefix);
+
+        // NOTE: not atomic, the entries are removed in chunks
+        // before the length of the instance is updated
+        let n = remove_by_scan(&self.areas[self.area_idx(meta_prefix)], &k);
+        if 0 < n {
+            self.decrease_instance_len_by(meta_prefix, n);
+        }
+        n
+    }
+
+    fn clear_instance(&self, instance_prefix: PreBytes) {
+        remove_by_scan(
+            &self.areas[self.area_idx(instance_prefix)],
+            instance_prefix.as_slice(),
+        );
+    }
+
+    // sled reclaims the space of removed entries in its own GC process,
+    // the best we can do here is to persist all pending removals.
+    fn compact_instance(&self, instance_prefix: PreBytes) {
+        self.areas[self.area_idx(instance_prefix)].flush().unwrap();
+    }
+
     fn get_instance_len(&self, instance_prefix: PreBytes) -> u64 {
         crate::parse_int!(self.meta.get(instance_prefix).unwrap().unwrap(), u64)
     }
@@ -269,6 +352,32 @@ impl DoubleEndedIterator for SledIter {
     }
 }
 
+// remove all keys starting with `prefix` in batches,
+// return the number of the removed keys
+fn remove_by_scan(area: &Tree, prefix: &[u8]) -> u64 {
+    let mut batch = Batch::default();
+    let mut cnt = 0;
+    let mut total = 0;
+
+    for k in area.scan_prefix(prefix).keys() {
+        batch.remove(k.unwrap());
+        cnt += 1;
+        if CLEAR_BATCH_SIZE == cnt {
+            area.apply_batch(batch).unwrap();
+            batch = Batch::default();
+            total += cnt as u64;
+            cnt = 0;
+        }
+    }
+
+    if 0 < cnt {
+        area.apply_batch(batch).unwrap();
+        total += cnt as u64;
+    }
+
+    total
+}
+
 // key of the prefix allocator in the 'meta'
 struct PreAllocator {
     key: [u8; 1],
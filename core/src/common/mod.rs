@@ -3,6 +3,17 @@
 //!
 
 pub(crate) mod engines;
+mod registry;
+
+pub use registry::{
+    vsdb_begin_migration, vsdb_finish_migration, vsdb_get_named_raw,
+    vsdb_get_schema_version, vsdb_list_instances, vsdb_list_instances_with_sizes,
+    vsdb_list_pending_migrations, vsdb_register_raw, vsdb_set_schema_version,
+    vsdb_unregister, InstanceInfo, Registrable,
+};
+
+#[cfg(feature = "vs")]
+pub(crate) use registry::upgrade_once;
 
 #[cfg(feature = "vs")]
 pub use ruc::crypto::trie_root;
@@ -16,7 +27,7 @@ use std::{
     env, fs,
     mem::size_of,
     path::{Path, PathBuf},
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
 };
 use threadpool::ThreadPool;
 
@@ -57,6 +68,20 @@ pub struct VersionName<'a>(pub &'a [u8]);
 #[derive(Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct VersionNameOwned(pub Vec<u8>);
 
+/// The meta information of a branch.
+#[derive(Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub struct BranchInfo {
+    /// Who created this branch, an arbitrary tag given by the caller.
+    pub creator: String,
+    /// The version which the branch was created from,
+    /// `None` if the base branch had no versions at that time.
+    pub creation_version: Option<VersionNameOwned>,
+    /// A free-form description.
+    pub description: String,
+    /// Whether the branch is protected.
+    pub protected: bool,
+}
+
 pub const KB: u64 = 1 << 10;
 pub const MB: u64 = 1 << 20;
 pub const GB: u64 = 1 << 30;
@@ -71,6 +96,9 @@ pub const INITIAL_BRANCH_NAME: BranchName<'static> = BranchName(b"master");
 /// The default value for reserved number when pruning old data.
 pub const RESERVED_VERSION_NUM_DEFAULT: usize = 10;
 
+/// The default number of entries cached in memory for each derived index.
+pub const INDEX_CACHE_SIZE_DEFAULT: usize = 8192;
+
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
@@ -78,6 +106,17 @@ const BASE_DIR_VAR: &str = "VSDB_BASE_DIR";
 
 static VSDB_BASE_DIR: Lazy<Mutex<PathBuf>> = Lazy::new(|| Mutex::new(gen_data_dir()));
 
+static INDEX_CACHE_SIZE: AtomicUsize = AtomicUsize::new(INDEX_CACHE_SIZE_DEFAULT);
+
+#[cfg(feature = "vs")]
+static BRANCH_CREATOR: Lazy<Mutex<String>> = Lazy::new(|| {
+    Mutex::new(
+        env::var("USER")
+            .or_else(|_| env::var("USERNAME"))
+            .unwrap_or_default(),
+    )
+});
+
 static VSDB_CUSTOM_DIR: Lazy<PathBuf> = Lazy::new(|| {
     let mut d = VSDB_BASE_DIR.lock().clone();
     d.push("__CUSTOM__");
@@ -145,6 +184,11 @@ impl<T: Engine> VsDB<T> {
         self.db.alloc_ver_id()
     }
 
+    #[inline(always)]
+    pub(crate) fn write_batch(&self, ops: &[engines::BatchOp]) {
+        self.db.write_batch(ops)
+    }
+
     #[inline(always)]
     fn flush(&self) {
         self.db.flush()
@@ -196,6 +240,35 @@ pub fn vsdb_flush() {
     VSDB.flush();
 }
 
+/// The max number of entries cached in memory for each derived index,
+/// such as the version names of a version graph.
+#[inline(always)]
+pub fn vsdb_get_index_cache_size() -> usize {
+    INDEX_CACHE_SIZE.load(Ordering::Relaxed)
+}
+
+/// Set the max number of entries cached in memory for each derived index,
+/// `0` means no cache, existing caches will shrink on their next insertion.
+#[inline(always)]
+pub fn vsdb_set_index_cache_size(n: usize) {
+    INDEX_CACHE_SIZE.store(n, Ordering::Relaxed);
+}
+
+/// The tag recorded as the [creator](BranchInfo::creator) of new branches,
+/// defaults to the user running the process.
+#[cfg(feature = "vs")]
+#[inline(always)]
+pub fn vsdb_get_branch_creator() -> String {
+    BRANCH_CREATOR.lock().clone()
+}
+
+/// Set the tag recorded as the [creator](BranchInfo::creator) of new branches.
+#[cfg(feature = "vs")]
+#[inline(always)]
+pub fn vsdb_set_branch_creator(creator: impl Into<String>) {
+    *BRANCH_CREATOR.lock() = creator.into();
+}
+
 macro_rules! impl_from_for_name {
     ($target: tt) => {
         impl<'a> From<&'a [u8]> for $target<'a> {
@@ -0,0 +1,333 @@
+//!
+//! # Named instance registry
+//!
+//! Collections are identified by opaque prefixes internally,
+//! the registry maps human readable names to them,
+//! so that they can be found again after a restart or a crash.
+//!
+//! Some other per-instance meta infomation, such as the schema version,
+//! is also recorded here.
+//!
+
+use super::{
+    PREFIX_SIZE, Pre, PreBytes, RawBytes, VSDB,
+    engines::{self, Engine},
+};
+use crate::basic::mapx_raw::MapxRaw;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::mem::size_of;
+
+// NOTE: must be reserved IDs, see `RESERVED_ID_CNT`
+const REGISTRY_PREFIX: Pre = Pre::MIN;
+const SCHEMA_VERSION_PREFIX: Pre = 1 + REGISTRY_PREFIX;
+const MIGRATION_PREFIX: Pre = 2 + REGISTRY_PREFIX;
+#[cfg(feature = "vs")]
+const UPGRADE_PREFIX: Pre = 3 + REGISTRY_PREFIX;
+
+static REGISTRY: Lazy<Mutex<engines::Mapx>> =
+    Lazy::new(|| Mutex::new(engines::Mapx::new_reserved(REGISTRY_PREFIX)));
+
+// <instance prefix> => <schema version>
+static SCHEMA_VERSIONS: Lazy<Mutex<engines::Mapx>> =
+    Lazy::new(|| Mutex::new(engines::Mapx::new_reserved(SCHEMA_VERSION_PREFIX)));
+
+// <instance prefix> => <target schema version> ++ <prefix of the converted keys>
+static MIGRATIONS: Lazy<Mutex<engines::Mapx>> =
+    Lazy::new(|| Mutex::new(engines::Mapx::new_reserved(MIGRATION_PREFIX)));
+
+// <an instance in a legacy layout> => <the instances created when upgrading it>
+#[cfg(feature = "vs")]
+static UPGRADES: Lazy<Mutex<engines::Mapx>> =
+    Lazy::new(|| Mutex::new(engines::Mapx::new_reserved(UPGRADE_PREFIX)));
+
+/// Collections that can be recorded in the named instance registry.
+pub trait Registrable {
+    /// A stable name of the collection type, recorded in the registry
+    /// to detect mismatches when re-opening an instance.
+    ///
+    /// NOTE: it is a part of the persistent data, do NOT change it.
+    const KIND: &'static str;
+
+    /// The prefix of the underlying storage instance,
+    /// for versioned collections it is the prefix of the instance
+    /// that holds the (primary) versioned data.
+    fn instance_prefix(&self) -> PreBytes;
+}
+
+/// Meta infomation of a named instance.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct InstanceInfo {
+    /// The name used when registering.
+    pub name: String,
+    /// The kind of the registered collection,
+    /// see [Registrable::KIND].
+    pub kind: String,
+    /// The prefix of the underlying storage instance.
+    pub prefix: Pre,
+    /// The number of entries in the underlying storage instance.
+    pub entry_count: u64,
+    /// The uncompressed size of all entries,
+    /// an approximate value of the disk usage,
+    /// only available in [vsdb_list_instances_with_sizes].
+    pub disk_size: Option<u64>,
+}
+
+// The persistent format of a registry entry.
+struct Record {
+    kind: String,
+    prefix: PreBytes,
+    // the encoded instance, used to re-open it
+    handle: RawBytes,
+}
+
+impl Record {
+    fn encode(&self) -> RawBytes {
+        let mut ret = Vec::with_capacity(
+            size_of::<u32>() + self.kind.len() + PREFIX_SIZE + self.handle.len(),
+        );
+        ret.extend_from_slice(&(self.kind.len() as u32).to_be_bytes());
+        ret.extend_from_slice(self.kind.as_bytes());
+        ret.extend_from_slice(&self.prefix);
+        ret.extend_from_slice(&self.handle);
+        ret
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let l = size_of::<u32>();
+        if bytes.len() < l {
+            return Err(eg!("invalid registry record"));
+        }
+        let kind_len = crate::parse_int!(bytes[..l], u32) as usize;
+        if bytes.len() < l + kind_len + PREFIX_SIZE {
+            return Err(eg!("invalid registry record"));
+        }
+        let kind = String::from_utf8(bytes[l..l + kind_len].to_vec()).c(d!())?;
+        let mut prefix = PreBytes::default();
+        prefix.copy_from_slice(&bytes[l + kind_len..l + kind_len + PREFIX_SIZE]);
+        let handle = bytes[l + kind_len + PREFIX_SIZE..].to_vec();
+        Ok(Self {
+            kind,
+            prefix,
+            handle,
+        })
+    }
+}
+
+/// Record an instance under the given name.
+///
+/// - `kind` is a stable name of the collection type, see [Registrable::KIND]
+/// - `handle` is the encoded instance, it will be returned by [vsdb_get_named_raw]
+///
+/// Registering the same instance under the same name again is a no-op,
+/// but a name can not be reused by another instance before [vsdb_unregister].
+pub fn vsdb_register_raw(
+    name: &str,
+    kind: &str,
+    prefix: PreBytes,
+    handle: &[u8],
+) -> Result<()> {
+    let mut hdr = REGISTRY.lock();
+
+    if let Some(r) = hdr.get(name.as_bytes()) {
+        let r = Record::decode(&r).c(d!())?;
+        if r.prefix == prefix && r.kind == kind {
+            return Ok(());
+        } else {
+            return Err(eg!("name already registered: {}", name));
+        }
+    }
+
+    let r = Record {
+        kind: kind.to_owned(),
+        prefix,
+        handle: handle.to_vec(),
+    };
+    hdr.insert(name.as_bytes(), &r.encode());
+
+    Ok(())
+}
+
+/// Get the `(kind, handle)` of a named instance,
+/// `None` will be returned if the name has not been registered.
+pub fn vsdb_get_named_raw(name: &str) -> Result<Option<(String, RawBytes)>> {
+    REGISTRY
+        .lock()
+        .get(name.as_bytes())
+        .map(|r| Record::decode(&r).c(d!("invalid record: {}", name)))
+        .transpose()
+        .map(|r| r.map(|r| (r.kind, r.handle)))
+}
+
+/// Remove a name from the registry,
+/// the instance itself will NOT be touched.
+pub fn vsdb_unregister(name: &str) -> Result<()> {
+    REGISTRY
+        .lock()
+        .remove(name.as_bytes())
+        .c(d!("name not found: {}", name))
+        .map(|_| ())
+}
+
+/// List all named instances,
+/// the records that can not be decoded are reported and skipped.
+///
+/// NOTE: the disk sizes are not calculated,
+/// use [vsdb_list_instances_with_sizes] if they are needed.
+pub fn vsdb_list_instances() -> Vec<InstanceInfo> {
+    list_instances(false)
+}
+
+/// List all named instances along with their disk sizes.
+///
+/// NOTE: the disk sizes are calculated by scanning all entries,
+/// it may take a long time on huge instances.
+pub fn vsdb_list_instances_with_sizes() -> Vec<InstanceInfo> {
+    list_instances(true)
+}
+
+fn list_instances(with_sizes: bool) -> Vec<InstanceInfo> {
+    let hdr = REGISTRY.lock();
+    hdr.iter()
+        .filter_map(|(name, r)| {
+            let name = String::from_utf8_lossy(&name).into_owned();
+            let r = Record::decode(&r)
+                .c(d!("invalid record: {}", name))
+                .map_err(|e| e.print(None))
+                .ok()?;
+            Some(InstanceInfo {
+                name,
+                kind: r.kind,
+                prefix: crate::parse_prefix!(r.prefix),
+                entry_count: VSDB.db.get_instance_len(r.prefix),
+                disk_size: if with_sizes {
+                    Some(VSDB.db.get_instance_size(r.prefix))
+                } else {
+                    None
+                },
+            })
+        })
+        .collect()
+}
+
+/// Get the schema version of the values stored in an instance,
+/// `0` will be returned if it has never been set.
+pub fn vsdb_get_schema_version(instance: &impl Registrable) -> u32 {
+    SCHEMA_VERSIONS
+        .lock()
+        .get(&instance.instance_prefix())
+        .map(|v| crate::parse_int!(v, u32))
+        .unwrap_or(0)
+}
+
+/// Set the schema version of the values stored in an instance.
+pub fn vsdb_set_schema_version(instance: &impl Registrable, ver: u32) {
+    SCHEMA_VERSIONS
+        .lock()
+        .insert(&instance.instance_prefix(), &ver.to_be_bytes());
+}
+
+/// Begin a schema migration of the values stored in an instance,
+/// or resume the unfinished one.
+///
+/// Return the target schema version, and a map in which the keys
+/// whose values have been converted should be recorded,
+/// it must be updated along with the values in one batch,
+/// see [MapxRawBatch](crate::basic::mapx_raw::MapxRawBatch).
+pub fn vsdb_begin_migration(instance: &impl Registrable) -> Result<(u32, MapxRaw)> {
+    let prefix = instance.instance_prefix();
+    let mut hdr = MIGRATIONS.lock();
+
+    if let Some(r) = hdr.get(&prefix) {
+        return decode_migration(&r).c(d!());
+    }
+
+    let target = 1 + vsdb_get_schema_version(instance);
+    let markers = MapxRaw::new();
+
+    let mut r = target.to_be_bytes().to_vec();
+    r.extend_from_slice(markers.as_prefix_slice());
+    hdr.insert(&prefix, &r);
+
+    Ok((target, markers))
+}
+
+/// Finish the schema migration of an instance,
+/// its schema version will be set to the target one.
+pub fn vsdb_finish_migration(instance: &impl Registrable) -> Result<()> {
+    let prefix = instance.instance_prefix();
+    let hdr = MIGRATIONS.lock();
+
+    let (target, mut markers) = hdr
+        .get(&prefix)
+        .c(d!("no unfinished migration"))
+        .and_then(|r| decode_migration(&r).c(d!()))?;
+
+    // the two records must be changed together,
+    // or the converted values may be converted again after a crash
+    let _sv = SCHEMA_VERSIONS.lock();
+    VSDB.write_batch(&[
+        (
+            SCHEMA_VERSION_PREFIX.to_be_bytes(),
+            prefix.to_vec(),
+            Some(target.to_be_bytes().to_vec()),
+        ),
+        (MIGRATION_PREFIX.to_be_bytes(), prefix.to_vec(), None),
+    ]);
+
+    markers.clear();
+
+    Ok(())
+}
+
+/// List the prefixes of the instances whose schema migrations
+/// have not been finished, along with the target schema versions
+/// and the maps of converted keys, see [vsdb_begin_migration].
+///
+/// The records that can not be decoded are reported and skipped.
+pub fn vsdb_list_pending_migrations() -> Vec<(PreBytes, u32, MapxRaw)> {
+    MIGRATIONS
+        .lock()
+        .iter()
+        .filter_map(|(prefix, r)| {
+            PreBytes::try_from(&prefix[..])
+                .c(d!())
+                .and_then(|pre| {
+                    decode_migration(&r)
+                        .c(d!())
+                        .map(|(target, markers)| (pre, target, markers))
+                })
+                .c(d!("invalid migration record: {:?}", prefix))
+                .map_err(|e| e.print(None))
+                .ok()
+        })
+        .collect()
+}
+
+fn decode_migration(r: &[u8]) -> Result<(u32, MapxRaw)> {
+    let l = size_of::<u32>();
+    if r.len() != l + PREFIX_SIZE {
+        return Err(eg!("invalid migration record"));
+    }
+    let target = crate::parse_int!(r[..l], u32);
+    let markers = unsafe { MapxRaw::from_prefix_slice(&r[l..]) };
+    Ok((target, markers))
+}
+
+// Get the record of an instance that has been upgraded from a legacy layout,
+// `f` will be called to do the upgrade if it has not been done.
+//
+// The record is kept forever, the encoded instance may be decoded again
+// from the legacy bytes persisted in other places.
+#[cfg(feature = "vs")]
+pub(crate) fn upgrade_once(key: &[u8], f: impl FnOnce() -> RawBytes) -> RawBytes {
+    let mut hdr = UPGRADES.lock();
+    if let Some(r) = hdr.get(key) {
+        return r;
+    }
+    let r = f();
+    hdr.insert(key, &r);
+    r
+}
@@ -15,25 +15,40 @@ pub mod basic;
 #[cfg(feature = "vs")]
 pub mod versioned;
 
-pub use basic::mapx_raw::MapxRaw;
+pub use basic::mapx_raw::{MapxRaw, MapxRawBatch};
 
 #[cfg(feature = "vs")]
 pub use versioned::mapx_raw::MapxRawVs;
 
 #[cfg(feature = "vs")]
-pub use versioned::VsMgmt;
+pub use versioned::{
+    KeepEither, KeepEveryNth, KeepLastN, KeepNewerThan, PruneProgress, PrunePolicy,
+    PruneTask, VersionInfo,
+};
+
+#[cfg(feature = "vs")]
+pub use versioned::{ProtectionOverride, VersionGraph, VsDiff, VsMgmt, VsView};
+
+#[cfg(feature = "vs")]
+#[doc(hidden)]
+pub use versioned::{vs_fan_out, vs_graphs};
 
 #[cfg(feature = "vs")]
-pub use vsdb_derive::Vs;
+pub use vsdb_derive::{Vs, VsDiff, VsView};
 
 pub use common::{
-    vsdb_flush, vsdb_get_base_dir, vsdb_get_custom_dir, vsdb_set_base_dir, RawBytes,
-    RawKey, RawValue, GB, KB, MB, NULL,
+    vsdb_begin_migration, vsdb_finish_migration, vsdb_flush, vsdb_get_base_dir,
+    vsdb_get_custom_dir, vsdb_get_index_cache_size, vsdb_get_named_raw,
+    vsdb_get_schema_version, vsdb_list_instances, vsdb_list_instances_with_sizes,
+    vsdb_list_pending_migrations, vsdb_register_raw, vsdb_set_base_dir,
+    vsdb_set_index_cache_size, vsdb_set_schema_version, vsdb_unregister,
+    InstanceInfo, RawBytes, RawKey, RawValue, Registrable, GB, KB, MB, NULL,
 };
 
 #[cfg(feature = "vs")]
 pub use common::{
-    BranchName, BranchNameOwned, ParentBranchName, ParentBranchNameOwned, VersionName,
+    vsdb_get_branch_creator, vsdb_set_branch_creator, BranchInfo, BranchName,
+    BranchNameOwned, ParentBranchName, ParentBranchNameOwned, VersionName,
     VersionNameOwned,
 };
 
@@ -5,32 +5,49 @@
 #![allow(unused_variables)]
 
 use crate::{
-    basic::mapx_raw::{MapxRaw, MapxRawIter},
+    basic::mapx_raw::{MapxRaw, MapxRawBatch, MapxRawIter},
     common::{
-        BranchID, BranchIDBase, BranchName, BranchNameOwned, RawKey, RawValue,
-        VersionID, VersionIDBase, VersionName, VersionNameOwned, INITIAL_BRANCH_ID,
-        INITIAL_BRANCH_NAME, NULL, NULL_ID, RESERVED_VERSION_NUM_DEFAULT, TRASH_CLEANER,
-        VER_ID_MAX, VSDB,
+        upgrade_once, vsdb_get_branch_creator, vsdb_get_index_cache_size, BranchID,
+        BranchIDBase, BranchInfo, BranchName, BranchNameOwned, PreBytes, RawBytes,
+        RawKey, RawValue, Registrable, VersionID, VersionIDBase, VersionName,
+        VersionNameOwned, INITIAL_BRANCH_ID, INITIAL_BRANCH_NAME, NULL, NULL_ID,
+        PREFIX_SIZE, RESERVED_VERSION_NUM_DEFAULT, TRASH_CLEANER, VER_ID_MAX, VSDB,
     },
+    versioned::prune::{now_secs, PruneProgress, PrunePolicy, VersionInfo},
 };
-use parking_lot::RwLock;
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
 use ruc::{crypto::trie_root, *};
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
     cmp::Ordering,
-    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     mem::size_of,
     ops::{Bound, RangeBounds},
     result::Result as StdResult,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering as AtomicOrdering},
+        Arc, Weak,
+    },
 };
 
+pub(super) type Graph = Arc<RwLock<VerGraph>>;
+
+// All the version graphs that are alive in this process,
+// indexed by the prefix of their `br_to_its_vers`,
+// collections attached to the same graph will always
+// get the same in-memory instance after being deserialized.
+static LOADED_GRAPHS: Lazy<Mutex<HashMap<RawBytes, Weak<RwLock<VerGraph>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
+// The version graph, may be shared by multiple collections,
+// operations on versions and branches are applied to all of them at once.
 #[derive(Debug)]
-pub(super) struct MapxRawVs {
+pub(super) struct VerGraph {
     default_branch: BranchID,
 
     br_name_to_br_id: MapxRaw,   // MapxOrdRawKey<BranchID>,
@@ -40,520 +57,767 @@ pub(super) struct MapxRawVs {
     // created dirctly by it or inherited from its ancestors
     br_to_its_vers: MapxRaw, // MapxOrd<BranchID, MapxOrd<VersionID, ()>>,
 
-    // - 'empty value'(&[] ...) means 'not exist'
-    // - 'key -> multi-version(globally unique) -> multi-value'
-    layered_kv: MapxRaw, // <RawKey, MapxRaw<VersionID, RawValue>>,
+    // the base branch of every branch when it was created
+    br_to_parent: MapxRaw, // MapxOrd<BranchID, BranchID>,
+
+    // the meta information of branches, recorded since their creation
+    br_to_info: MapxRaw, // MapxOrd<BranchID, BranchInfo>,
+
+    // the height and the creation time of versions, recorded since their creation
+    ver_to_meta: MapxRaw, // MapxOrd<VersionID, (u64, u64)>,
+
+    // the `layered_kv` of all collections attached to this graph
+    members: MapxRaw, // MapxRaw<prefix of `layered_kv`, ()>
 
     // derived from `br_name_to_br_id` during starting
     br_id_to_br_name: Arc<RwLock<HashMap<BranchID, RawValue>>>,
 
-    // derived from `ver_name_to_ver_id` during starting
-    ver_id_to_ver_name: Arc<RwLock<HashMap<VersionID, RawValue>>>,
-
-    // Globally ever changed keys within each version
-    // derived from `layered_kv` during the starting process.
-    ver_to_change_set: Arc<RwLock<BTreeMap<VersionID, BTreeSet<RawKey>>>>,
-}
+    // the reverse index of `ver_name_to_ver_id`
+    ver_id_to_ver_name: Arc<RwLock<VerNames>>,
 
-// !^~^! 撸猫 !^~^!
-unsafe impl Send for MapxRawVs {}
-unsafe impl Sync for MapxRawVs {}
+    // derived from `members` during starting
+    member_hdrs: Arc<RwLock<BTreeMap<RawBytes, Member>>>,
 
-impl Clone for MapxRawVs {
-    fn clone(&self) -> Self {
-        Self {
-            default_branch: self.default_branch,
-            br_name_to_br_id: self.br_name_to_br_id.clone(),
-            ver_name_to_ver_id: self.ver_name_to_ver_id.clone(),
-            br_to_its_vers: self.br_to_its_vers.clone(),
-            layered_kv: self.layered_kv.clone(),
-            br_id_to_br_name: Arc::new(RwLock::new(
-                self.br_id_to_br_name.read().clone(),
-            )),
-            ver_id_to_ver_name: Arc::new(RwLock::new(
-                self.ver_id_to_ver_name.read().clone(),
-            )),
-            ver_to_change_set: Arc::new(RwLock::new(
-                self.ver_to_change_set.read().clone(),
-            )),
-        }
-    }
-}
+    // derived from `br_to_info` during starting
+    protected_brs: Arc<RwLock<HashSet<BranchID>>>,
 
-impl Serialize for MapxRawVs {
-    fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        MapxRawVsWithoutDerivedFields::from(self).serialize(serializer)
-    }
+    // the unfinished pruning of `prune_step`
+    pending_prune: Option<PrunePlan>,
 }
 
-impl<'de> Deserialize<'de> for MapxRawVs {
-    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        <MapxRawVsWithoutDerivedFields as Deserialize>::deserialize(deserializer)
-            .map(Self::from)
-    }
-}
+// !^~^! 撸猫 !^~^!
+unsafe impl Send for VerGraph {}
+unsafe impl Sync for VerGraph {}
 
 #[derive(Debug, Serialize, Deserialize)]
-pub(super) struct MapxRawVsWithoutDerivedFields {
+pub(super) struct VerGraphHeader {
     default_branch: BranchID,
-
-    br_name_to_br_id: MapxRaw,   // MapxOrdRawKey<BranchID>,
-    ver_name_to_ver_id: MapxRaw, // MapxOrdRawKey<VersionID>,
-
-    // versions on this branch,
-    // created dirctly by it or inherited from its ancestors
-    br_to_its_vers: MapxRaw, // MapxOrd<BranchID, MapxOrd<VersionID, ()>>,
-
-    layered_kv: MapxRaw, // <RawKey, MapxRaw<VersionID, RawValue>>
+    br_name_to_br_id: MapxRaw,
+    ver_name_to_ver_id: MapxRaw,
+    br_to_its_vers: MapxRaw,
+    br_to_parent: MapxRaw,
+    br_to_info: MapxRaw,
+    ver_to_meta: MapxRaw,
+    ver_id_to_ver_name: MapxRaw,
+    members: MapxRaw,
 }
 
-impl From<MapxRawVsWithoutDerivedFields> for MapxRawVs {
-    fn from(m: MapxRawVsWithoutDerivedFields) -> Self {
-        let br_id_to_br_name = m
+impl From<VerGraphHeader> for VerGraph {
+    fn from(h: VerGraphHeader) -> Self {
+        let br_id_to_br_name = h
             .br_name_to_br_id
             .iter()
             .map(|(n, id)| (to_brid(&id), n))
             .collect::<HashMap<_, _>>();
-        let ver_id_to_ver_name = m
-            .ver_name_to_ver_id
+        let mut members = h.members;
+        let member_hdrs = members
             .iter()
-            .map(|(n, id)| (to_verid(&id), n))
-            .collect::<HashMap<_, _>>();
-        let existing_vers =
-            m.br_to_its_vers
-                .iter()
-                .fold(BTreeMap::new(), |mut acc, (_br, vers)| {
-                    for (ver, _) in decode_map(vers).iter() {
-                        acc.insert(to_verid(&ver), BTreeSet::new());
-                    }
-                    acc
-                });
-        let ver_to_change_set =
-            m.layered_kv
-                .iter()
-                .fold(existing_vers, |mut acc, (k, vers)| {
-                    for (ver, _) in decode_map(vers).iter() {
-                        acc.entry(to_verid(&ver))
-                            .or_insert_with(BTreeSet::new)
-                            .insert(k.clone());
-                    }
-                    acc
-                });
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|(pre, chgset)| {
+                let m = if chgset.is_empty() {
+                    // created before the change sets are persisted
+                    let m = Member::build(decode_map(&pre));
+                    members.insert(&pre, m.chgset_bytes());
+                    m
+                } else {
+                    Member::load(decode_map(&pre), decode_map(chgset))
+                };
+                (pre, m)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let protected_brs = h
+            .br_to_info
+            .iter()
+            .filter(|(_, info)| {
+                decode_br_info(info).map(|i| i.protected).unwrap_or(false)
+            })
+            .map(|(id, _)| to_brid(&id))
+            .collect::<HashSet<_>>();
         Self {
-            default_branch: m.default_branch,
-            br_name_to_br_id: m.br_name_to_br_id,
-            ver_name_to_ver_id: m.ver_name_to_ver_id,
-            br_to_its_vers: m.br_to_its_vers,
-            layered_kv: m.layered_kv,
+            default_branch: h.default_branch,
+            br_name_to_br_id: h.br_name_to_br_id,
+            ver_name_to_ver_id: h.ver_name_to_ver_id,
+            br_to_its_vers: h.br_to_its_vers,
+            br_to_parent: h.br_to_parent,
+            br_to_info: h.br_to_info,
+            ver_to_meta: h.ver_to_meta,
+            members,
             br_id_to_br_name: Arc::new(RwLock::new(br_id_to_br_name)),
-            ver_id_to_ver_name: Arc::new(RwLock::new(ver_id_to_ver_name)),
-            ver_to_change_set: Arc::new(RwLock::new(ver_to_change_set)),
+            ver_id_to_ver_name: Arc::new(RwLock::new(VerNames::new(
+                h.ver_id_to_ver_name,
+            ))),
+            member_hdrs: Arc::new(RwLock::new(member_hdrs)),
+            protected_brs: Arc::new(RwLock::new(protected_brs)),
+            pending_prune: None,
         }
     }
 }
 
-impl From<&MapxRawVs> for MapxRawVsWithoutDerivedFields {
-    fn from(m: &MapxRawVs) -> Self {
+impl VerGraphHeader {
+    // Upgrade a graph persisted in the legacy layout, it was owned by one collection,
+    // the missing fields are created on the first upgrade and reused since then.
+    fn upgrade(
+        default_branch: BranchID,
+        br_name_to_br_id: MapxRaw,
+        ver_name_to_ver_id: MapxRaw,
+        br_to_its_vers: MapxRaw,
+        layered_kv: &MapxRaw,
+    ) -> Self {
+        let r = upgrade_once(br_to_its_vers.as_prefix_slice(), || {
+            // the heights of versions will be counted when they are missing
+            let br_to_parent = MapxRaw::new();
+            let br_to_info = MapxRaw::new();
+            let ver_to_meta = MapxRaw::new();
+
+            let mut ver_id_to_ver_name = MapxRaw::new();
+            ver_name_to_ver_id.iter().for_each(|(name, id)| {
+                ver_id_to_ver_name.insert(id, name);
+            });
+
+            // the change set will be built on loading
+            let mut members = MapxRaw::new();
+            members.insert(layered_kv.as_prefix_slice(), []);
+
+            [
+                &br_to_parent,
+                &br_to_info,
+                &ver_to_meta,
+                &ver_id_to_ver_name,
+                &members,
+            ]
+            .iter()
+            .flat_map(|m| m.as_prefix_slice().to_vec())
+            .collect()
+        });
+
+        let mut fields = r.chunks(PREFIX_SIZE).map(decode_map);
+        let mut next = || pnk!(fields.next(), "invalid upgrade record");
+        Self {
+            default_branch,
+            br_name_to_br_id,
+            ver_name_to_ver_id,
+            br_to_its_vers,
+            br_to_parent: next(),
+            br_to_info: next(),
+            ver_to_meta: next(),
+            ver_id_to_ver_name: next(),
+            members: next(),
+        }
+    }
+}
+
+impl From<&VerGraph> for VerGraphHeader {
+    fn from(g: &VerGraph) -> Self {
         unsafe {
             Self {
-                default_branch: m.default_branch,
-                br_name_to_br_id: m.br_name_to_br_id.shadow(),
-                ver_name_to_ver_id: m.ver_name_to_ver_id.shadow(),
-                br_to_its_vers: m.br_to_its_vers.shadow(),
-                layered_kv: m.layered_kv.shadow(),
+                default_branch: g.default_branch,
+                br_name_to_br_id: g.br_name_to_br_id.shadow(),
+                ver_name_to_ver_id: g.ver_name_to_ver_id.shadow(),
+                br_to_its_vers: g.br_to_its_vers.shadow(),
+                br_to_parent: g.br_to_parent.shadow(),
+                br_to_info: g.br_to_info.shadow(),
+                ver_to_meta: g.ver_to_meta.shadow(),
+                ver_id_to_ver_name: g.ver_id_to_ver_name.read().map.shadow(),
+                members: g.members.shadow(),
             }
         }
     }
 }
 
-////////////////////////////////////////////////////////////////////////////////////
-////////////////////////////////////////////////////////////////////////////////////
+// The versions to be cleaned by a pruning,
+// found out once and consumed by the following steps.
+#[derive(Debug, Default)]
+pub(super) struct PrunePlan {
+    reserved_ver_num: Option<usize>,
+    // versions that are not on any branch
+    orphans: VecDeque<VersionID>,
+    // the oldest shared version, the merged versions are merged into it
+    rewrite_ver: Option<VersionID>,
+    // the versions to be merged, in ascending order
+    merges: VecDeque<VersionID>,
+    // the oldest version to be reserved
+    first_reserved: Option<VersionID>,
+}
 
-impl MapxRawVs {
+// Globally ever changed keys within each version,
+// persisted as `[version id][key] -> []`,
+// so the keys of a version can be visited by a range scan.
+#[derive(Debug)]
+struct ChangeSet(MapxRaw);
+
+impl ChangeSet {
     #[inline(always)]
-    pub(super) unsafe fn shadow(&self) -> Self {
-        Self {
-            default_branch: self.default_branch,
-            br_name_to_br_id: self.br_name_to_br_id.shadow(),
-            ver_name_to_ver_id: self.ver_name_to_ver_id.shadow(),
-            br_to_its_vers: self.br_to_its_vers.shadow(),
-            layered_kv: self.layered_kv.shadow(),
-            br_id_to_br_name: Arc::clone(&self.br_id_to_br_name),
-            ver_id_to_ver_name: Arc::clone(&self.ver_id_to_ver_name),
-            ver_to_change_set: Arc::clone(&self.ver_to_change_set),
-        }
+    fn keys<'a>(&'a self, ver: &'a VersionID) -> impl Iterator<Item = RawKey> + 'a {
+        self.0
+            .range(Cow::Borrowed(&ver[..])..)
+            .map(|(k, _)| k)
+            .take_while(move |k| k.starts_with(ver))
+            .map(|k| k[size_of::<VersionID>()..].to_vec())
     }
 
     #[inline(always)]
-    pub(super) fn new() -> Self {
-        let mut ret = Self {
-            default_branch: BranchID::default(),
-
-            br_name_to_br_id: MapxRaw::new(),
-            ver_name_to_ver_id: MapxRaw::new(),
-            br_to_its_vers: MapxRaw::new(),
-            layered_kv: MapxRaw::new(),
+    fn contains(&self, ver: &VersionID) -> bool {
+        self.keys(ver).next().is_some()
+    }
 
-            br_id_to_br_name: Arc::new(RwLock::new(Default::default())),
-            ver_id_to_ver_name: Arc::new(RwLock::new(Default::default())),
-            ver_to_change_set: Arc::new(RwLock::new(Default::default())),
-        };
+    #[inline(always)]
+    fn get(&self, ver: &VersionID) -> Option<BTreeSet<RawKey>> {
+        let keys = self.keys(ver).collect::<BTreeSet<_>>();
+        alt!(keys.is_empty(), None, Some(keys))
+    }
 
-        ret.init();
-        ret
+    fn remove(&mut self, ver: &VersionID) -> Option<BTreeSet<RawKey>> {
+        let keys = self.get(ver)?;
+        keys.iter().for_each(|k| {
+            self.0.remove(chgset_key(ver, k));
+        });
+        Some(keys)
     }
 
     #[inline(always)]
-    fn init(&mut self) {
-        let initial_brid = INITIAL_BRANCH_ID.to_be_bytes();
-
-        self.default_branch = initial_brid;
-        self.br_name_to_br_id
-            .insert(INITIAL_BRANCH_NAME.0, &initial_brid[..]);
-        self.br_to_its_vers
-            .insert(&initial_brid[..], encode_map(&MapxRaw::new()));
-        self.br_id_to_br_name
-            .write()
-            .insert(initial_brid, INITIAL_BRANCH_NAME.0.to_vec());
+    fn insert(&mut self, ver: &VersionID, key: &[u8]) {
+        self.0.insert(chgset_key(ver, key), []);
     }
 
     #[inline(always)]
-    pub(super) fn insert(
-        &mut self,
-        key: &[u8],
-        value: &[u8],
-    ) -> Result<Option<RawValue>> {
-        self.insert_by_branch(key, value, self.branch_get_default())
-            .c(d!())
+    fn remove_key(&mut self, ver: &VersionID, key: &[u8]) {
+        self.0.remove(chgset_key(ver, key));
     }
 
     #[inline(always)]
-    pub(super) fn insert_by_branch(
-        &mut self,
-        key: &[u8],
-        value: &[u8],
-        br_id: BranchID,
-    ) -> Result<Option<RawValue>> {
-        decode_map(
-            self.br_to_its_vers
-                .get(&br_id[..])
-                .c(d!("branch not found"))?,
-        )
-        .last()
-        .c(d!("no version on this branch, create a version first"))
-        .and_then(|(ver_id, _)| {
-            self.insert_by_branch_version(key, value, br_id, to_verid(&ver_id))
-                .c(d!())
-        })
+    fn clear(&mut self) {
+        self.0.clear();
     }
+}
 
-    // This function should **NOT** be public,
-    // `write`-like operations should only be applied
-    // on the latest version of every branch,
-    // historical data version should be immutable in the user view.
+// The names of versions indexed by their ids, persisted on disk,
+// the recently used ones are cached in memory,
+// see `vsdb_set_index_cache_size`.
+#[derive(Debug)]
+struct VerNames {
+    map: MapxRaw, // MapxOrd<VersionID, RawValue>
+    cache: Mutex<LruCache>,
+}
+
+impl VerNames {
     #[inline(always)]
-    fn insert_by_branch_version(
-        &mut self,
-        key: &[u8],
-        value: &[u8],
-        br_id: BranchID,
-        ver_id: VersionID,
-    ) -> Result<Option<RawValue>> {
-        self.write_by_branch_version(key, Some(value), br_id, ver_id)
-            .c(d!())
+    fn new(map: MapxRaw) -> Self {
+        Self {
+            map,
+            cache: Default::default(),
+        }
+    }
+
+    fn get(&self, ver: &VersionID) -> Option<RawValue> {
+        let mut cache = self.cache.lock();
+        if let Some(name) = cache.get(ver) {
+            return Some(name);
+        }
+        let name = self.map.get(ver)?;
+        cache.put(*ver, name.clone());
+        Some(name)
     }
 
     #[inline(always)]
-    pub(super) fn remove(&mut self, key: &[u8]) -> Result<Option<RawValue>> {
-        self.remove_by_branch(key, self.branch_get_default())
-            .c(d!())
+    fn contains_key(&self, ver: &VersionID) -> bool {
+        self.get(ver).is_some()
     }
 
     #[inline(always)]
-    pub(super) fn remove_by_branch(
-        &mut self,
-        key: &[u8],
-        br_id: BranchID,
-    ) -> Result<Option<RawValue>> {
-        decode_map(self.br_to_its_vers.get(br_id).c(d!("branch not found"))?)
-            .last()
-            .c(d!("no version on this branch, create a version first"))
-            .and_then(|(ver_id, _)| {
-                self.remove_by_branch_version(key, br_id, to_verid(&ver_id))
-                    .c(d!())
-            })
+    fn insert(&mut self, ver: VersionID, name: RawValue) {
+        self.map.insert(ver, &name);
+        self.cache.lock().put(ver, name);
     }
 
-    // This function should **NOT** be public,
-    // `write`-like operations should only be applied
-    // on the latest version of every branch,
-    // historical data version should be immutable in the user view.
-    //
-    // The `remove` is essentially assign a `None` value to the key.
-    fn remove_by_branch_version(
-        &mut self,
-        key: &[u8],
-        br_id: BranchID,
-        ver_id: VersionID,
-    ) -> Result<Option<RawValue>> {
-        self.write_by_branch_version(key, None, br_id, ver_id)
-            .c(d!())
+    #[inline(always)]
+    fn remove(&mut self, ver: &VersionID) -> Option<RawValue> {
+        self.cache.lock().remove(ver);
+        self.map.remove(ver)
     }
 
-    // This function should **NOT** be public,
-    // `write`-like operations should only be applied
-    // on the latest version of every branch,
-    // historical data version should be immutable in the user view.
-    fn write_by_branch_version(
-        &mut self,
-        key: &[u8],
-        value: Option<&[u8]>,
-        br_id: BranchID,
-        ver_id: VersionID,
-    ) -> Result<Option<RawValue>> {
-        // clone it, keep a copy of the original unchanged value.
-        let ret = self.get_by_branch_version(key, br_id, ver_id);
+    #[inline(always)]
+    fn clear(&mut self) {
+        self.cache.lock().clear();
+        self.map.clear();
+    }
+}
 
-        // remove a non-existing value
-        if value.is_none() && ret.is_none() {
-            return Ok(None);
-        }
+// A bounded cache, the least recently used entries are evicted first.
+#[derive(Debug, Default)]
+struct LruCache {
+    tick: u64,
+    entries: HashMap<VersionID, (RawValue, u64)>,
+    order: BTreeMap<u64, VersionID>,
+}
 
-        let value = value.unwrap_or(NULL);
+impl LruCache {
+    fn get(&mut self, k: &VersionID) -> Option<RawValue> {
+        let (v, t) = self.entries.get_mut(k)?;
+        self.order.remove(t);
+        self.tick += 1;
+        *t = self.tick;
+        self.order.insert(self.tick, *k);
+        Some(v.clone())
+    }
 
-        decode_map(
-            &*self
-                .layered_kv
-                .entry(key)
-                .or_insert(encode_map(&MapxRaw::new())),
-        )
-        .insert(ver_id, value);
+    fn put(&mut self, k: VersionID, v: RawValue) {
+        self.remove(&k);
 
-        self.ver_to_change_set
-            .write()
-            .get_mut(&ver_id)
-            .c(d!())?
-            .insert(key.to_vec());
+        let cap = vsdb_get_index_cache_size();
+        while self.entries.len() >= cap {
+            match self.order.pop_first() {
+                Some((_, old)) => {
+                    self.entries.remove(&old);
+                }
+                None => return,
+            }
+        }
 
-        Ok(ret)
+        self.tick += 1;
+        self.entries.insert(k, (v, self.tick));
+        self.order.insert(self.tick, k);
     }
 
     #[inline(always)]
-    pub(super) fn get(&self, key: &[u8]) -> Option<RawValue> {
-        self.get_by_branch(key, self.branch_get_default())
+    fn remove(&mut self, k: &VersionID) {
+        if let Some((_, t)) = self.entries.remove(k) {
+            self.order.remove(&t);
+        }
     }
 
     #[inline(always)]
-    pub(super) fn get_by_branch(&self, key: &[u8], br_id: BranchID) -> Option<RawValue> {
-        if let Some(vers) = self.br_to_its_vers.get(br_id) {
-            if let Some(ver_id) = decode_map(vers).last().map(|(id, _)| id) {
-                return self.get_by_branch_version(key, br_id, to_verid(&ver_id));
-            }
-        }
-        None
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
     }
+}
 
+// A collection attached to a version graph.
+#[derive(Debug)]
+struct Member {
+    layered_kv: MapxRaw,
+    ver_to_change_set: Arc<RwLock<ChangeSet>>,
+}
+
+impl Member {
     #[inline(always)]
-    pub(super) fn get_by_branch_version(
-        &self,
-        key: &[u8],
-        br_id: BranchID,
-        ver_id: VersionID,
-    ) -> Option<RawValue> {
-        let vers = decode_map(self.br_to_its_vers.get(br_id)?);
+    fn load(layered_kv: MapxRaw, ver_to_change_set: MapxRaw) -> Self {
+        Self {
+            layered_kv,
+            ver_to_change_set: Arc::new(RwLock::new(ChangeSet(ver_to_change_set))),
+        }
+    }
 
-        decode_map(self.layered_kv.get(key)?)
-            .range(..=Cow::Borrowed(&ver_id[..]))
-            .rev()
-            .find(|(ver, _)| vers.contains_key(ver))
-            .and_then(|(_, value)| alt!(value.is_empty(), None, Some(value)))
+    // Derive the change sets from the `layered_kv`,
+    // for members created before the change sets are persisted,
+    // and for copies of other collections.
+    fn build(layered_kv: MapxRaw) -> Self {
+        let mut chgset = ChangeSet(MapxRaw::new());
+        layered_kv.iter().for_each(|(k, vers)| {
+            for (ver, _) in decode_map(vers).iter() {
+                chgset.insert(&to_verid(&ver), &k);
+            }
+        });
+        Self::load(layered_kv, chgset.0)
     }
 
     #[inline(always)]
-    pub(super) fn get_ge(&self, key: &[u8]) -> Option<(RawKey, RawValue)> {
-        self.range(Cow::Borrowed(key)..).next()
+    fn chgset_bytes(&self) -> RawBytes {
+        encode_map(&self.ver_to_change_set.read().0).to_vec()
     }
 
     #[inline(always)]
-    pub(super) fn get_ge_by_branch(
-        &self,
-        key: &[u8],
-        br_id: BranchID,
-    ) -> Option<(RawKey, RawValue)> {
-        self.range_by_branch(br_id, Cow::Borrowed(key)..).next()
+    fn has_change_set(&self, ver_id: VersionID) -> bool {
+        self.ver_to_change_set.read().contains(&ver_id)
     }
 
     #[inline(always)]
-    pub(super) fn get_ge_by_branch_version(
-        &self,
-        key: &[u8],
-        br_id: BranchID,
-        ver_id: VersionID,
-    ) -> Option<(RawKey, RawValue)> {
-        self.range_by_branch_version(br_id, ver_id, Cow::Borrowed(key)..)
-            .next()
+    fn is_empty_on(&self, vers: &MapxRaw) -> bool {
+        vers.iter()
+            .all(|(ver, _)| !self.has_change_set(to_verid(&ver)))
     }
 
-    #[inline(always)]
-    pub(super) fn get_le(&self, key: &[u8]) -> Option<(RawKey, RawValue)> {
-        self.range(..=Cow::Borrowed(key)).next_back()
+    fn chgset_trie_root(&self, ver: VersionID) -> Vec<u8> {
+        let entries = self
+            .ver_to_change_set
+            .read()
+            .keys(&ver)
+            .map(|k| {
+                let kvers = decode_map(pnk!(self.layered_kv.get(&k)));
+                let v = pnk!(kvers.get(ver));
+                (k, v)
+            })
+            .collect::<Vec<_>>();
+
+        trie_root(entries).to_vec()
     }
 
-    #[inline(always)]
-    pub(super) fn get_le_by_branch(
-        &self,
-        key: &[u8],
-        br_id: BranchID,
-    ) -> Option<(RawKey, RawValue)> {
-        self.range_by_branch(br_id, ..=Cow::Borrowed(key))
-            .next_back()
+    // Move all changes made by the `vers` into the `base_version`,
+    // the `vers` must be in ascending order.
+    fn rebase_versions(
+        &mut self,
+        vers: &[VersionID],
+        base_version: VersionID,
+    ) -> Result<()> {
+        let mut chgset_hdr = self.ver_to_change_set.write();
+
+        for verid in vers.iter() {
+            let chgset = match chgset_hdr.remove(verid) {
+                Some(chgset) => chgset,
+                None => continue,
+            };
+            for k in chgset.iter() {
+                chgset_hdr.insert(&base_version, k);
+                let mut kvers = decode_map(self.layered_kv.get(k).c(d!())?);
+                let v = kvers.remove(verid).c(d!())?;
+                kvers.insert(base_version, v);
+            }
+        }
+
+        Ok(())
     }
 
-    #[inline(always)]
-    pub(super) fn get_le_by_branch_version(
+    // All the keys changed by the `vers`.
+    fn changed_keys(&self, vers: &[VersionID]) -> BTreeSet<RawKey> {
+        let chgset_hdr = self.ver_to_change_set.read();
+        vers.iter().flat_map(|ver| chgset_hdr.keys(ver)).collect()
+    }
+
+    // Keys changed both by the `vers` and the `other_vers`.
+    fn rebase_conflicts(
         &self,
-        key: &[u8],
-        br_id: BranchID,
-        ver_id: VersionID,
-    ) -> Option<(RawKey, RawValue)> {
-        self.range_by_branch_version(br_id, ver_id, ..=Cow::Borrowed(key))
-            .next_back()
+        vers: &[VersionID],
+        other_vers: &[VersionID],
+    ) -> BTreeSet<RawKey> {
+        let others = self.changed_keys(other_vers);
+        self.changed_keys(vers)
+            .into_iter()
+            .filter(|k| others.contains(k))
+            .collect()
     }
 
-    #[inline(always)]
-    pub(super) fn iter(&self) -> MapxRawVsIter {
-        self.iter_by_branch(self.branch_get_default())
+    // Move all changes made by every `old` version into its `new` version,
+    // the writes are added to the `batch`.
+    fn move_versions(
+        &self,
+        moves: &[(VersionID, VersionID)],
+        batch: &mut MapxRawBatch,
+    ) -> Result<()> {
+        let mut chgset_hdr = self.ver_to_change_set.write();
+
+        for (old, new) in moves.iter() {
+            for k in chgset_hdr.keys(old).collect::<Vec<_>>() {
+                let mut kvers = decode_map(self.layered_kv.get(&k).c(d!())?);
+                let v = kvers.get(old).c(d!())?;
+                batch.remove(&mut kvers, old);
+                batch.insert(&mut kvers, new, v);
+                batch.remove(&mut chgset_hdr.0, chgset_key(old, &k));
+                batch.insert(&mut chgset_hdr.0, chgset_key(new, &k), []);
+            }
+        }
+
+        Ok(())
     }
 
-    #[inline(always)]
-    pub(super) fn iter_by_branch(&self, br_id: BranchID) -> MapxRawVsIter {
-        if let Some(vers) = self.br_to_its_vers.get(br_id) {
-            if let Some((ver_id, _)) = decode_map(vers).last() {
-                return self.iter_by_branch_version(to_brid(&br_id), to_verid(&ver_id));
+    // Remove all changes made by the `vers`.
+    fn remove_versions(&mut self, vers: &[VersionID]) -> Result<()> {
+        let mut chgset_hdr = self.ver_to_change_set.write();
+
+        for ver in vers.iter() {
+            let chgset = match chgset_hdr.remove(ver) {
+                Some(chgset) => chgset,
+                None => continue,
+            };
+            for k in chgset.iter() {
+                let mut lkv = decode_map(self.layered_kv.get(k).c(d!())?);
+                lkv.remove(ver).c(d!())?;
+                if lkv.is_empty() {
+                    self.layered_kv.remove(k).c(d!())?;
+                }
             }
         }
 
-        MapxRawVsIter {
-            hdr: self,
-            iter: self.layered_kv.range(..),
-            br_id: NULL_ID,
-            ver_id: NULL_ID,
+        Ok(())
+    }
+
+    // Merge all changes made by the `vers` into a newer version,
+    // the changes made by the `target_ver` itself take precedence,
+    // so the data of the `target_ver` are kept unchanged.
+    fn merge_versions_forward(
+        &mut self,
+        vers: &[VersionID],
+        target_ver: &VersionID,
+    ) -> Result<()> {
+        let mut chgset_hdr = self.ver_to_change_set.write();
+
+        // the newer changes are merged first
+        for ver in vers.iter().rev() {
+            let chgset = match chgset_hdr.remove(ver) {
+                Some(chgset) => chgset,
+                None => continue,
+            };
+            for k in chgset.into_iter() {
+                let mut kvers = decode_map(self.layered_kv.get(&k).c(d!())?);
+                let v = kvers.remove(ver).c(d!())?;
+                if !kvers.contains_key(target_ver) {
+                    kvers.insert(target_ver, v);
+                    chgset_hdr.insert(target_ver, &k);
+                }
+            }
         }
+
+        Ok(())
     }
 
-    #[inline(always)]
-    pub(super) fn iter_by_branch_version(
-        &self,
-        br_id: BranchID,
-        ver_id: VersionID,
-    ) -> MapxRawVsIter {
-        MapxRawVsIter {
-            hdr: self,
-            iter: self.layered_kv.range(..),
-            br_id,
-            ver_id,
+    // Merge all changes made by the `vers_to_be_merged` into the `rewrite_ver`,
+    // and clean up the lowest-level KVs with 'deleted' states.
+    fn prune_versions(
+        &mut self,
+        vers_to_be_merged: &[VersionID],
+        rewrite_ver: &VersionID,
+    ) -> Result<()> {
+        let mut chgset_hdr = self.ver_to_change_set.write();
+
+        let mut kvchgs = HashSet::new();
+        let mut rewrite_chgset = HashSet::new();
+
+        for ver in vers_to_be_merged.iter() {
+            let chgset = match chgset_hdr.remove(ver) {
+                Some(chgset) => chgset,
+                None => continue,
+            };
+            for k in chgset.iter() {
+                let mut kvers = decode_map(self.layered_kv.get(k).c(d!())?);
+                let v = kvers.remove(ver).c(d!())?;
+                kvers.insert(rewrite_ver, v);
+
+                rewrite_chgset.insert(k.clone());
+                kvchgs.insert(k.clone());
+            }
+        }
+
+        rewrite_chgset.into_iter().for_each(|k| {
+            chgset_hdr.insert(rewrite_ver, &k);
+        });
+
+        // lowest-level KVs with 'deleted' states should be cleaned up.
+        let mut empty_keys = vec![];
+        for k in kvchgs.iter() {
+            if let Some(vers) = self.layered_kv.get(k) {
+                let mut vers = decode_map(vers);
+                // A 'NULL' value means 'not exist'.
+                if vers.get(rewrite_ver).c(d!())?.is_empty() {
+                    vers.remove(rewrite_ver).c(d!())?;
+                    chgset_hdr.remove_key(rewrite_ver, k);
+                }
+                if vers.is_empty() {
+                    empty_keys.push(k);
+                }
+            }
+        }
+
+        for k in empty_keys.iter() {
+            self.layered_kv.remove(k).c(d!())?;
         }
+
+        Ok(())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl VerGraph {
+    fn new() -> Self {
+        let mut ret = Self {
+            default_branch: BranchID::default(),
+
+            br_name_to_br_id: MapxRaw::new(),
+            ver_name_to_ver_id: MapxRaw::new(),
+            br_to_its_vers: MapxRaw::new(),
+            br_to_parent: MapxRaw::new(),
+            br_to_info: MapxRaw::new(),
+            ver_to_meta: MapxRaw::new(),
+            members: MapxRaw::new(),
+
+            br_id_to_br_name: Default::default(),
+            ver_id_to_ver_name: Arc::new(RwLock::new(VerNames::new(MapxRaw::new()))),
+            member_hdrs: Default::default(),
+            protected_brs: Default::default(),
+            pending_prune: None,
+        };
+
+        ret.init();
+        ret
     }
 
     #[inline(always)]
-    pub(super) fn range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
-        &'a self,
-        bounds: R,
-    ) -> MapxRawVsIter<'a> {
-        self.range_by_branch(self.branch_get_default(), bounds)
+    fn init(&mut self) {
+        let initial_brid = INITIAL_BRANCH_ID.to_be_bytes();
+
+        self.default_branch = initial_brid;
+        self.br_name_to_br_id
+            .insert(INITIAL_BRANCH_NAME.0, &initial_brid[..]);
+        self.br_to_its_vers
+            .insert(&initial_brid[..], encode_map(&MapxRaw::new()));
+        self.br_id_to_br_name
+            .write()
+            .insert(initial_brid, INITIAL_BRANCH_NAME.0.to_vec());
     }
 
+    // Create a new graph, without any members.
     #[inline(always)]
-    pub(super) fn range_by_branch<'a, R: RangeBounds<Cow<'a, [u8]>>>(
-        &'a self,
-        br_id: BranchID,
-        bounds: R,
-    ) -> MapxRawVsIter<'a> {
-        if let Some(vers) = self.br_to_its_vers.get(br_id) {
-            if let Some((ver_id, _)) = decode_map(vers).last() {
-                return self.range_by_branch_version(br_id, to_verid(&ver_id), bounds);
-            }
-        }
+    pub(super) fn new_shared() -> Graph {
+        Self::share(&mut LOADED_GRAPHS.lock(), Self::new())
+    }
 
-        // An empty `Iter`
-        MapxRawVsIter {
-            hdr: self,
-            iter: self.layered_kv.range(..),
-            br_id,
-            ver_id: NULL_ID,
+    // Get the in-memory instance of a persisted graph,
+    // it will be loaded if it's not alive in this process.
+    pub(super) fn load_shared(h: VerGraphHeader) -> Graph {
+        let mut graphs = LOADED_GRAPHS.lock();
+        if let Some(g) = graphs
+            .get(h.br_to_its_vers.as_prefix_slice())
+            .and_then(Weak::upgrade)
+        {
+            return g;
         }
+        Self::share(&mut graphs, Self::from(h))
+    }
+
+    fn share(graphs: &mut HashMap<RawBytes, Weak<RwLock<VerGraph>>>, g: Self) -> Graph {
+        let key = g.br_to_its_vers.as_prefix_slice().to_vec();
+        let g = Arc::new(RwLock::new(g));
+        graphs.retain(|_, g| 0 < g.strong_count());
+        graphs.insert(key, Arc::downgrade(&g));
+        g
     }
 
     #[inline(always)]
-    pub(super) fn range_by_branch_version<'a, R: RangeBounds<Cow<'a, [u8]>>>(
-        &'a self,
-        br_id: BranchID,
-        ver_id: VersionID,
-        bounds: R,
-    ) -> MapxRawVsIter<'a> {
-        let l = match bounds.start_bound() {
-            Bound::Included(v) => Bound::Included(Cow::Owned(v.to_vec())),
-            Bound::Excluded(v) => Bound::Excluded(Cow::Owned(v.to_vec())),
-            Bound::Unbounded => Bound::Unbounded,
-        };
+    pub(super) fn header(&self) -> VerGraphHeader {
+        VerGraphHeader::from(self)
+    }
 
-        let h = match bounds.end_bound() {
-            Bound::Included(v) => Bound::Included(Cow::Owned(v.to_vec())),
-            Bound::Excluded(v) => Bound::Excluded(Cow::Owned(v.to_vec())),
-            Bound::Unbounded => Bound::Unbounded,
+    // A graph sharing everything with this one except the default branch,
+    // it will not be registered in the `LOADED_GRAPHS`.
+    unsafe fn shadow(&self) -> Graph {
+        Arc::new(RwLock::new(Self {
+            default_branch: self.default_branch,
+            br_name_to_br_id: self.br_name_to_br_id.shadow(),
+            ver_name_to_ver_id: self.ver_name_to_ver_id.shadow(),
+            br_to_its_vers: self.br_to_its_vers.shadow(),
+            br_to_parent: self.br_to_parent.shadow(),
+            br_to_info: self.br_to_info.shadow(),
+            ver_to_meta: self.ver_to_meta.shadow(),
+            members: self.members.shadow(),
+            br_id_to_br_name: Arc::clone(&self.br_id_to_br_name),
+            ver_id_to_ver_name: Arc::clone(&self.ver_id_to_ver_name),
+            member_hdrs: Arc::clone(&self.member_hdrs),
+            protected_brs: Arc::clone(&self.protected_brs),
+            pending_prune: None,
+        }))
+    }
+
+    // Copy all the meta data to a new graph, without any members.
+    fn duplicate(&self) -> Graph {
+        // the version lists of branches are nested instances,
+        // they must be copied one by one
+        let mut br_to_its_vers = MapxRaw::new();
+        self.br_to_its_vers.iter().for_each(|(br, vers)| {
+            br_to_its_vers.insert(br, encode_map(&decode_map(vers).clone()));
+        });
+
+        let g = Self {
+            default_branch: self.default_branch,
+            br_name_to_br_id: self.br_name_to_br_id.clone(),
+            ver_name_to_ver_id: self.ver_name_to_ver_id.clone(),
+            br_to_its_vers,
+            br_to_parent: self.br_to_parent.clone(),
+            br_to_info: self.br_to_info.clone(),
+            ver_to_meta: self.ver_to_meta.clone(),
+            members: MapxRaw::new(),
+            br_id_to_br_name: Arc::new(RwLock::new(
+                self.br_id_to_br_name.read().clone(),
+            )),
+            ver_id_to_ver_name: Arc::new(RwLock::new(VerNames::new(
+                self.ver_id_to_ver_name.read().map.clone(),
+            ))),
+            member_hdrs: Default::default(),
+            protected_brs: Arc::new(RwLock::new(self.protected_brs.read().clone())),
+            pending_prune: None,
         };
+        Self::share(&mut LOADED_GRAPHS.lock(), g)
+    }
 
-        MapxRawVsIter {
-            hdr: self,
-            iter: self.layered_kv.range((l, h)),
-            br_id,
-            ver_id,
+    // Attach a collection to this graph,
+    // return the change sets of it.
+    fn attach(&mut self, layered_kv: &MapxRaw) -> Arc<RwLock<ChangeSet>> {
+        let pre = layered_kv.as_prefix_slice();
+        if let Some(m) = self.member_hdrs.read().get(pre) {
+            return Arc::clone(&m.ver_to_change_set);
         }
+        let m = Member::build(unsafe { layered_kv.shadow() });
+        let ret = Arc::clone(&m.ver_to_change_set);
+        self.members.insert(pre, m.chgset_bytes());
+        self.member_hdrs.write().insert(pre.to_vec(), m);
+        ret
     }
 
-    // NOTE: just a stupid O(n) counter, very slow!
     #[inline(always)]
-    pub(super) fn len(&self) -> usize {
-        self.iter().count()
+    fn detach(&mut self, layered_kv: &MapxRaw) {
+        let pre = layered_kv.as_prefix_slice();
+        self.members.remove(pre);
+        self.member_hdrs.write().remove(pre);
     }
 
-    // NOTE: just a stupid O(n) counter, very slow!
     #[inline(always)]
-    pub(super) fn len_by_branch(&self, br_id: BranchID) -> usize {
-        self.iter_by_branch(br_id).count()
+    pub(super) fn members_num(&self) -> usize {
+        self.member_hdrs.read().len()
     }
 
-    // NOTE: just a stupid O(n) counter, very slow!
-    #[inline(always)]
-    pub(super) fn len_by_branch_version(
-        &self,
-        br_id: BranchID,
-        ver_id: VersionID,
-    ) -> usize {
-        self.iter_by_branch_version(br_id, ver_id).count()
+    // No version, no branch except the initial one, and no data.
+    pub(super) fn is_pristine(&self) -> bool {
+        self.ver_name_to_ver_id.is_empty()
+            && 1 == self.br_name_to_br_id.len()
+            && self.br_name_to_br_id.contains_key(INITIAL_BRANCH_NAME.0)
+            && self.default_branch == INITIAL_BRANCH_ID.to_be_bytes()
+            && self.br_to_info.is_empty()
+            && self
+                .member_hdrs
+                .read()
+                .values()
+                .all(|m| m.layered_kv.is_empty())
     }
 
-    // Clear all data, for testing purpose.
+    // Reset all the meta data, the members are kept as is.
     #[inline(always)]
-    pub(super) fn clear(&mut self) {
+    fn clear(&mut self) {
         self.br_name_to_br_id.clear();
         self.ver_name_to_ver_id.clear();
         self.br_to_its_vers.clear();
-        self.layered_kv.clear();
+        self.br_to_parent.clear();
+        self.br_to_info.clear();
+        self.ver_to_meta.clear();
 
         self.br_id_to_br_name.write().clear();
         self.ver_id_to_ver_name.write().clear();
-        self.ver_to_change_set.write().clear();
+        self.protected_brs.write().clear();
 
         self.init();
     }
 
+    #[inline(always)]
+    fn branch_vers(&self, br_id: BranchID) -> Option<MapxRaw> {
+        self.br_to_its_vers.get(br_id).map(decode_map)
+    }
+
+    #[inline(always)]
+    fn branch_last_version(&self, br_id: BranchID) -> Option<VersionID> {
+        self.branch_vers(br_id)?
+            .last()
+            .map(|(ver_id, _)| to_verid(&ver_id))
+    }
+
     #[inline(always)]
     pub(super) fn version_create(&mut self, ver_name: &[u8]) -> Result<()> {
         self.version_create_by_branch(ver_name, self.branch_get_default())
@@ -569,23 +833,25 @@ impl MapxRawVs {
             return Err(eg!("version already exists"));
         }
 
-        let mut vers = decode_map(
-            &*self
-                .br_to_its_vers
-                .get_mut(br_id)
-                .c(d!("branch not found"))?,
-        );
+        let mut vers = self.branch_vers(br_id).c(d!("branch not found"))?;
+
+        // branches created before the meta information was introduced
+        // will count their versions once
+        let height = vers
+            .last()
+            .and_then(|(ver, _)| self.ver_to_meta.get(&ver))
+            .map(|meta| decode_ver_meta(&meta).0 + 1)
+            .unwrap_or_else(|| vers.len() as u64);
 
         let ver_id = VSDB.alloc_ver_id().to_be_bytes();
         vers.insert(ver_id, []);
 
+        self.ver_to_meta
+            .insert(ver_id, encode_ver_meta(height, now_secs()));
         self.ver_name_to_ver_id.insert(ver_name, ver_id);
         self.ver_id_to_ver_name
             .write()
             .insert(ver_id, ver_name.to_vec());
-        self.ver_to_change_set
-            .write()
-            .insert(ver_id, BTreeSet::new());
 
         Ok(())
     }
@@ -599,7 +865,7 @@ impl MapxRawVs {
     // Check if a verison exists in the global scope
     #[inline(always)]
     pub(super) fn version_exists_globally(&self, ver_id: BranchID) -> bool {
-        self.ver_to_change_set.read().contains_key(&ver_id)
+        self.ver_id_to_ver_name.read().contains_key(&ver_id)
     }
 
     // Check if a version exists on a specified branch
@@ -609,9 +875,8 @@ impl MapxRawVs {
         ver_id: VersionID,
         br_id: BranchID,
     ) -> bool {
-        self.br_to_its_vers
-            .get(br_id)
-            .map(|vers| decode_map(vers).contains_key(ver_id))
+        self.branch_vers(br_id)
+            .map(|vers| vers.contains_key(ver_id))
             .unwrap_or(false)
     }
 
@@ -622,7 +887,7 @@ impl MapxRawVs {
     // and should not do any tracing.
     #[inline(always)]
     pub(super) fn version_pop(&mut self) -> Result<()> {
-        self.version_pop_by_branch(self.branch_get_default())
+        self.version_pop_by_branch(self.branch_get_default(), None)
             .c(d!())
     }
 
@@ -632,9 +897,14 @@ impl MapxRawVs {
     // while operations on branches and versions are limited to their own perspective,
     // and should not do any tracing.
     #[inline(always)]
-    pub(super) fn version_pop_by_branch(&mut self, br_id: BranchID) -> Result<()> {
-        let mut vers =
-            decode_map(self.br_to_its_vers.get(br_id).c(d!("branch not found"))?);
+    pub(super) fn version_pop_by_branch(
+        &mut self,
+        br_id: BranchID,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        self.branch_check_writable(br_id, ovr).c(d!())?;
+
+        let mut vers = self.branch_vers(br_id).c(d!("branch not found"))?;
 
         if let Some((ver_id, _)) = vers.last() {
             vers.remove(&ver_id)
@@ -670,8 +940,7 @@ impl MapxRawVs {
         base_version: VersionID,
         br_id: BranchID,
     ) -> Result<()> {
-        let mut brvers_hdr =
-            decode_map(self.br_to_its_vers.get(br_id).c(d!("branch not found"))?);
+        let mut brvers_hdr = self.branch_vers(br_id).c(d!("branch not found"))?;
         let mut brvers = brvers_hdr
             .range(Cow::Borrowed(&base_version[..])..)
             .map(|(ver, _)| to_verid(&ver[..]));
@@ -686,22 +955,18 @@ impl MapxRawVs {
 
         let vers_to_be_merged = brvers.collect::<Vec<_>>();
 
-        let mut ver_hdr = self.ver_id_to_ver_name.write();
-        let mut chgset_hdr = self.ver_to_change_set.write();
+        for m in self.member_hdrs.write().values_mut() {
+            m.rebase_versions(&vers_to_be_merged, base_version)
+                .c(d!())?;
+        }
 
         for verid in vers_to_be_merged.iter() {
-            let chgset = chgset_hdr.remove(verid).c(d!())?;
-            for k in chgset.iter() {
-                chgset_hdr.get_mut(&base_version).c(d!())?.insert(k.clone());
-                let mut kvers = decode_map(&*self.layered_kv.get_mut(k).c(d!())?);
-                let v = kvers.remove(verid).c(d!())?;
-                kvers.insert(base_version, v);
-            }
             brvers_hdr
                 .remove(verid)
                 .c(d!())
-                .and_then(|_| ver_hdr.remove(verid).c(d!()))
+                .and_then(|_| self.ver_id_to_ver_name.write().remove(verid).c(d!()))
                 .and_then(|vername| self.ver_name_to_ver_id.remove(&vername).c(d!()))?;
+            self.ver_to_meta.remove(verid);
         }
 
         Ok(())
@@ -727,11 +992,11 @@ impl MapxRawVs {
         &self,
         br_id: BranchID,
     ) -> Result<Vec<VersionNameOwned>> {
-        self.br_to_its_vers.get(br_id).c(d!()).map(|vers| {
-            let ver_hdr = self.ver_id_to_ver_name.read();
-            decode_map(vers)
-                .iter()
-                .map(|(ver, _)| ver_hdr.get(&to_verid(&ver)).unwrap().to_vec())
+        self.branch_vers(br_id).c(d!()).map(|vers| {
+            vers.iter()
+                .map(|(ver, _)| {
+                    self.ver_id_to_ver_name.read().get(&to_verid(&ver)).unwrap()
+                })
                 .map(VersionNameOwned)
                 .collect()
         })
@@ -739,22 +1004,25 @@ impl MapxRawVs {
 
     #[inline(always)]
     pub(super) fn version_list_globally(&self) -> Vec<VersionNameOwned> {
-        let ver_hdr = self.ver_id_to_ver_name.read();
-        self.ver_to_change_set
+        self.ver_id_to_ver_name
             .read()
+            .map
             .iter()
-            .map(|(ver, _)| ver_hdr.get(ver).unwrap().to_vec())
-            .map(VersionNameOwned)
+            .map(|(_, ver)| VersionNameOwned(ver))
             .collect()
     }
 
+    // Check if any member has changes in this version.
     #[inline(always)]
     pub(super) fn version_has_change_set(&self, ver_id: VersionID) -> Result<bool> {
-        self.ver_to_change_set
+        if !self.version_exists_globally(ver_id) {
+            return Err(eg!("version not found"));
+        }
+        Ok(self
+            .member_hdrs
             .read()
-            .get(&ver_id)
-            .c(d!())
-            .map(|chgset| !chgset.is_empty())
+            .values()
+            .any(|m| m.has_change_set(ver_id)))
     }
 
     /***
@@ -762,7 +1030,10 @@ impl MapxRawVs {
      */
     #[inline(always)]
     pub(super) fn version_clean_up_globally(&mut self) -> Result<()> {
-        self.do_prune(None, true).c(d!())
+        let mut plan = self.prune_plan(None, true).c(d!())?;
+        self.prune_by_plan(&mut plan, usize::MAX)
+            .c(d!())
+            .map(|_| ())
     }
 
     // # Safety
@@ -772,60 +1043,64 @@ impl MapxRawVs {
         &mut self,
         ver_id: VersionID,
     ) -> Result<()> {
-        let mut ver_hdr = self.ver_id_to_ver_name.write();
+        if !self.version_exists_globally(ver_id) {
+            return Err(eg!("version not found"));
+        }
 
-        for key in self
-            .ver_to_change_set
-            .write()
-            .remove(&ver_id)
-            .c(d!())?
-            .iter()
-        {
-            decode_map(&*self.layered_kv.get_mut(key).c(d!())?)
-                .remove(ver_id)
-                .c(d!())?;
+        for m in self.member_hdrs.write().values_mut() {
+            m.remove_versions(&[ver_id]).c(d!())?;
         }
 
         self.br_to_its_vers.iter().for_each(|(_, vers)| {
             decode_map(vers).remove(ver_id);
         });
+        self.ver_to_meta.remove(ver_id);
 
-        ver_hdr
+        self.ver_id_to_ver_name
+            .write()
             .remove(&ver_id)
             .c(d!())
             .and_then(|vername| self.ver_name_to_ver_id.remove(&vername).c(d!()))
             .map(|_| ())
     }
 
-    pub(super) fn version_chgset_trie_root(
+    // Find out the target version, the head of the default branch will be used by default.
+    fn version_resolve(
         &self,
         br_id: Option<BranchID>,
         ver_id: Option<VersionID>,
-    ) -> Result<Vec<u8>> {
+    ) -> Result<VersionID> {
         let ver = if let Some(v) = ver_id {
             v
         } else {
             let br = br_id.unwrap_or_else(|| self.branch_get_default());
-            let v = decode_map(self.br_to_its_vers.get(br).c(d!("branch not found"))?)
+            self.branch_vers(br)
+                .c(d!("branch not found"))?
                 .last()
-                .map(|(verid, _)| verid)
-                .c(d!("version not found"))?;
-            let mut ver = VersionID::default();
-            ver.copy_from_slice(&v);
-            ver
+                .map(|(verid, _)| to_verid(&verid))
+                .c(d!("version not found"))?
         };
 
-        let chgset_hdr = self.ver_to_change_set.read();
-        let chgset = chgset_hdr.get(&ver).c(d!())?;
-        let entries = chgset
+        if self.version_exists_globally(ver) {
+            Ok(ver)
+        } else {
+            Err(eg!("version not found"))
+        }
+    }
+
+    // The trie root of the change set roots of all members.
+    pub(super) fn version_chgset_trie_root(
+        &self,
+        br_id: Option<BranchID>,
+        ver_id: Option<VersionID>,
+    ) -> Result<Vec<u8>> {
+        let ver = self.version_resolve(br_id, ver_id).c(d!())?;
+        let entries = self
+            .member_hdrs
+            .read()
             .iter()
-            .map(|k| {
-                let kvers = decode_map(pnk!(self.layered_kv.get(k)));
-                let v = pnk!(kvers.get(ver));
-                (k.clone(), v)
-            })
+            .map(|(pre, m)| (pre.clone(), m.chgset_trie_root(ver)))
             .collect::<Vec<_>>();
-
         Ok(trie_root(entries).to_vec())
     }
 
@@ -857,13 +1132,11 @@ impl MapxRawVs {
             return Err(eg!("this version already exists"));
         }
 
-        let base_ver_id = decode_map(
-            self.br_to_its_vers
-                .get(base_br_id)
-                .c(d!("base branch not found"))?,
-        )
-        .last()
-        .map(|(ver_id, _)| ver_id);
+        let base_ver_id = self
+            .branch_vers(base_br_id)
+            .c(d!("base branch not found"))?
+            .last()
+            .map(|(ver_id, _)| ver_id);
 
         unsafe {
             self.do_branch_create_by_base_branch_version(
@@ -923,13 +1196,11 @@ impl MapxRawVs {
         base_br_id: BranchID,
         force: bool,
     ) -> Result<()> {
-        let base_ver_id = decode_map(
-            self.br_to_its_vers
-                .get(base_br_id)
-                .c(d!("base branch not found"))?,
-        )
-        .last()
-        .map(|(ver_id, _)| ver_id);
+        let base_ver_id = self
+            .branch_vers(base_br_id)
+            .c(d!("base branch not found"))?
+            .last()
+            .map(|(ver_id, _)| ver_id);
 
         self.do_branch_create_by_base_branch_version(
             br_name,
@@ -971,7 +1242,7 @@ impl MapxRawVs {
     ) -> Result<()> {
         if force {
             if let Some(brid) = self.br_name_to_br_id.get(br_name) {
-                self.branch_remove(to_brid(&brid)).c(d!())?;
+                self.branch_remove(to_brid(&brid), None).c(d!())?;
             }
         }
 
@@ -979,11 +1250,9 @@ impl MapxRawVs {
             return Err(eg!("branch already exists"));
         }
 
-        let vers = decode_map(
-            self.br_to_its_vers
-                .get(base_br_id)
-                .c(d!("base branch not exist"))?,
-        );
+        let vers = self
+            .branch_vers(base_br_id)
+            .c(d!("base branch not exist"))?;
 
         let vers_copied = if let Some(bv) = base_ver_id {
             if !vers.contains_key(bv) {
@@ -1007,6 +1276,16 @@ impl MapxRawVs {
             .write()
             .insert(br_id, br_name.to_vec());
         self.br_to_its_vers.insert(br_id, encode_map(&vers_copied));
+        self.br_to_parent.insert(br_id, base_br_id);
+
+        let info = BranchInfo {
+            creator: vsdb_get_branch_creator(),
+            creation_version: base_ver_id.map(|ver| {
+                VersionNameOwned(self.ver_id_to_ver_name.read().get(&ver).unwrap())
+            }),
+            ..Default::default()
+        };
+        self.br_to_info.insert(br_id, encode_br_info(&info));
 
         if let Some(vername) = ver_name {
             // create the first version of the new branch
@@ -1027,9 +1306,8 @@ impl MapxRawVs {
     pub(super) fn branch_has_versions(&self, br_id: BranchID) -> bool {
         self.branch_exists(br_id)
             && self
-                .br_to_its_vers
-                .get(br_id)
-                .map(|vers| !decode_map(vers).is_empty())
+                .branch_vers(br_id)
+                .map(|vers| !vers.is_empty())
                 .unwrap_or(false)
     }
 
@@ -1041,8 +1319,13 @@ impl MapxRawVs {
     // while operations on branches and versions are limited to their own perspective,
     // and should not do any tracing.
     #[inline(always)]
-    pub(super) fn branch_remove(&mut self, br_id: BranchID) -> Result<()> {
-        self.branch_truncate(br_id).c(d!())?;
+    pub(super) fn branch_remove(
+        &mut self,
+        br_id: BranchID,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        self.branch_check_removable(br_id, ovr).c(d!())?;
+        self.branch_truncate(br_id, ovr).c(d!())?;
 
         self.br_id_to_br_name
             .write()
@@ -1051,6 +1334,9 @@ impl MapxRawVs {
             .and_then(|brname| self.br_name_to_br_id.remove(&brname).c(d!()))?;
 
         let vers = self.br_to_its_vers.remove(br_id).c(d!())?;
+        self.br_to_parent.remove(br_id);
+        self.br_to_info.remove(br_id);
+        self.protected_brs.write().remove(&br_id);
 
         TRASH_CLEANER.lock().execute(move || {
             decode_map(vers).clear();
@@ -1064,14 +1350,18 @@ impl MapxRawVs {
         let brs = self
             .br_id_to_br_name
             .read()
-            .iter()
-            .map(|(brid, _)| brid)
+            .keys()
             .filter(|brid| !br_ids.contains(brid))
             .copied()
             .collect::<Vec<_>>();
-        let brs = brs;
+
+        // nothing will be removed if any of them can not be removed
+        for brid in brs.iter() {
+            self.branch_check_removable(*brid, None).c(d!())?;
+        }
+
         for brid in brs.into_iter() {
-            self.branch_remove(brid).c(d!())?;
+            self.branch_remove(brid, None).c(d!())?;
         }
 
         self.version_clean_up_globally().c(d!())
@@ -1085,9 +1375,15 @@ impl MapxRawVs {
     // while operations on branches and versions are limited to their own perspective,
     // and should not do any tracing.
     #[inline(always)]
-    pub(super) fn branch_truncate(&mut self, br_id: BranchID) -> Result<()> {
-        if let Some(vers) = self.br_to_its_vers.get(br_id) {
-            decode_map(vers).clear();
+    pub(super) fn branch_truncate(
+        &mut self,
+        br_id: BranchID,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        self.branch_check_writable(br_id, ovr).c(d!())?;
+
+        if let Some(mut vers) = self.branch_vers(br_id) {
+            vers.clear();
             Ok(())
         } else {
             Err(eg!(
@@ -1108,10 +1404,12 @@ impl MapxRawVs {
         &mut self,
         br_id: BranchID,
         last_ver_id: VersionID,
+        ovr: Option<&ProtectionOverride>,
     ) -> Result<()> {
-        if let Some(vers) = self.br_to_its_vers.get(br_id) {
+        self.branch_check_writable(br_id, ovr).c(d!())?;
+
+        if let Some(mut vers) = self.branch_vers(br_id) {
             // version id must be in descending order
-            let mut vers = decode_map(vers);
             let vers_shadow = unsafe { vers.shadow() };
             for (ver_id, _) in
                 vers_shadow
@@ -1141,8 +1439,12 @@ impl MapxRawVs {
     // while operations on branches and versions are limited to their own perspective,
     // and should not do any tracing.
     #[inline(always)]
-    pub(super) fn branch_pop_version(&mut self, br_id: BranchID) -> Result<()> {
-        self.version_pop_by_branch(br_id).c(d!())
+    pub(super) fn branch_pop_version(
+        &mut self,
+        br_id: BranchID,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        self.version_pop_by_branch(br_id, ovr).c(d!())
     }
 
     #[inline(always)]
@@ -1180,12 +1482,10 @@ impl MapxRawVs {
         target_br_id: BranchID,
         force: bool,
     ) -> Result<()> {
-        let vers = decode_map(self.br_to_its_vers.get(br_id).c(d!("branch not found"))?);
-        let mut target_vers = decode_map(
-            self.br_to_its_vers
-                .get(target_br_id)
-                .c(d!("target branch not found"))?,
-        );
+        let vers = self.branch_vers(br_id).c(d!("branch not found"))?;
+        let mut target_vers = self
+            .branch_vers(target_br_id)
+            .c(d!("target branch not found"))?;
 
         if !force {
             if let Some((ver, _)) = target_vers.last() {
@@ -1239,246 +1539,1643 @@ impl MapxRawVs {
             return Ok(());
         };
 
-        Ok(())
-    }
+        Ok(())
+    }
+
+    // The versions created on `br_id` after it forked from `base_br_id`,
+    // and the versions created on `base_br_id` since then, in ascending order.
+    pub(super) fn branch_fork_diff(
+        &self,
+        br_id: BranchID,
+        base_br_id: BranchID,
+    ) -> Result<(Vec<VersionID>, Vec<VersionID>)> {
+        if br_id == base_br_id {
+            return Err(eg!("can not rebase a branch onto itself"));
+        }
+
+        let vers = self.branch_vers(br_id).c(d!("branch not found"))?;
+        let base_vers = self
+            .branch_vers(base_br_id)
+            .c(d!("base branch not found"))?;
+
+        let private = vers
+            .iter()
+            .filter(|(ver, _)| !base_vers.contains_key(ver))
+            .map(|(ver, _)| to_verid(&ver))
+            .collect();
+        let base_only = base_vers
+            .iter()
+            .filter(|(ver, _)| !vers.contains_key(ver))
+            .map(|(ver, _)| to_verid(&ver))
+            .collect();
+
+        Ok((private, base_only))
+    }
+
+    // Re-parent the versions created on `br_id` onto the head of `base_br_id`,
+    // every one of them will be replayed as a new version with the same name.
+    //
+    // Fails without any change if some keys have been changed on both branches,
+    // or if the versions to be replayed are shared with other branches.
+    pub(super) fn branch_rebase_onto(
+        &mut self,
+        br_id: BranchID,
+        base_br_id: BranchID,
+    ) -> Result<()> {
+        self.branch_check_writable(br_id, None).c(d!())?;
+
+        let (private, base_only) = self.branch_fork_diff(br_id, base_br_id).c(d!())?;
+
+        let shared = self.br_to_its_vers.iter().any(|(br, vers)| {
+            let vers = decode_map(vers);
+            br[..] != br_id[..] && private.iter().any(|ver| vers.contains_key(ver))
+        });
+        if shared {
+            return Err(eg!("versions of this branch are used by other branches"));
+        }
+
+        let conflicts = self
+            .member_hdrs
+            .read()
+            .values()
+            .flat_map(|m| m.rebase_conflicts(&private, &base_only))
+            .collect::<BTreeSet<_>>();
+        if !conflicts.is_empty() {
+            return Err(eg!(
+                "unable to rebase, {} keys have been changed on both branches: {:?}",
+                conflicts.len(),
+                conflicts
+            ));
+        }
+
+        let moves = private
+            .iter()
+            .map(|ver| (*ver, VSDB.alloc_ver_id().to_be_bytes()))
+            .collect::<Vec<_>>();
+
+        // all the changes of the members and the meta data are written at once
+        let mut batch = MapxRawBatch::new();
+
+        for m in self.member_hdrs.read().values() {
+            m.move_versions(&moves, &mut batch).c(d!())?;
+        }
+
+        let base_vers = self.branch_vers(base_br_id).c(d!())?;
+        let base_head = base_vers.last().map(|(ver, _)| to_verid(&ver));
+        let height = base_head
+            .and_then(|ver| self.ver_to_meta.get(ver))
+            .map(|meta| decode_ver_meta(&meta).0 + 1)
+            .unwrap_or_else(|| base_vers.len() as u64);
+
+        let mut ver_hdr = self.ver_id_to_ver_name.write();
+        for (h, (old, new)) in (height..).zip(moves.iter()) {
+            let vername = ver_hdr.get(old).c(d!())?;
+            batch.remove(&mut ver_hdr.map, old);
+            batch.insert(&mut ver_hdr.map, new, &vername);
+            batch.insert(&mut self.ver_name_to_ver_id, &vername, new);
+
+            let created_at = self
+                .ver_to_meta
+                .get(old)
+                .map(|meta| decode_ver_meta(&meta).1)
+                .unwrap_or_else(now_secs);
+            batch.remove(&mut self.ver_to_meta, old);
+            let meta = encode_ver_meta(h, created_at);
+            batch.insert(&mut self.ver_to_meta, new, meta);
+        }
+
+        let mut vers = self.branch_vers(br_id).c(d!())?;
+        private.iter().for_each(|ver| batch.remove(&mut vers, ver));
+        base_only.iter().for_each(|ver| batch.insert(&mut vers, ver, []));
+        moves.iter().for_each(|(_, new)| batch.insert(&mut vers, new, []));
+
+        batch.insert(&mut self.br_to_parent, br_id, base_br_id);
+
+        let info = BranchInfo {
+            creation_version: base_head
+                .map(|ver| ver_hdr.get(&ver).c(d!()).map(VersionNameOwned))
+                .transpose()?,
+            ..self.branch_info(br_id).c(d!())?
+        };
+        batch.insert(&mut self.br_to_info, br_id, encode_br_info(&info));
+
+        batch.commit();
+
+        moves.iter().for_each(|(old, _)| ver_hdr.cache.lock().remove(old));
+
+        Ok(())
+    }
+
+    // The latest version shared by the two branches,
+    // searched backwards from the head of `br_id`.
+    pub(super) fn branch_merge_base(
+        &self,
+        br_id: BranchID,
+        other_br_id: BranchID,
+    ) -> Result<Option<VersionNameOwned>> {
+        let vers = self.branch_vers(br_id).c(d!("branch not found"))?;
+        let other_vers = self
+            .branch_vers(other_br_id)
+            .c(d!("other branch not found"))?;
+        vers.iter()
+            .rev()
+            .find(|(ver, _)| other_vers.contains_key(ver))
+            .map(|(ver, _)| {
+                self.ver_id_to_ver_name
+                    .read()
+                    .get(&to_verid(&ver))
+                    .c(d!("BUG: version name not found"))
+                    .map(VersionNameOwned)
+            })
+            .transpose()
+    }
+
+    // Check if a version is on the history of a branch, including its head.
+    #[inline(always)]
+    pub(super) fn version_is_ancestor(
+        &self,
+        ver_id: VersionID,
+        br_id: BranchID,
+    ) -> Result<bool> {
+        self.branch_vers(br_id)
+            .c(d!("branch not found"))
+            .map(|vers| vers.contains_key(ver_id))
+    }
+
+    #[inline(always)]
+    pub(super) fn branches_containing(&self, ver_id: VersionID) -> Vec<BranchNameOwned> {
+        let brname_hdr = self.br_id_to_br_name.read();
+        self.br_to_its_vers
+            .iter()
+            .filter(|(_, vers)| decode_map(vers).contains_key(ver_id))
+            .filter_map(|(br, _)| brname_hdr.get(&to_brid(&br)).cloned())
+            .map(BranchNameOwned)
+            .collect()
+    }
+
+    // `None` if the branch is the initial one, or its parent has been removed.
+    #[inline(always)]
+    pub(super) fn branch_parent(
+        &self,
+        br_id: BranchID,
+    ) -> Result<Option<BranchNameOwned>> {
+        if !self.branch_exists(br_id) {
+            return Err(eg!("branch not found"));
+        }
+        Ok(self.br_to_parent.get(br_id).and_then(|parent| {
+            self.br_id_to_br_name
+                .read()
+                .get(&to_brid(&parent))
+                .cloned()
+                .map(BranchNameOwned)
+        }))
+    }
+
+    pub(super) fn branch_rename(
+        &mut self,
+        br_id: BranchID,
+        new_br_name: &[u8],
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        self.branch_check_writable(br_id, ovr).c(d!())?;
+
+        if self.br_name_to_br_id.contains_key(new_br_name) {
+            return Err(eg!("branch already exists"));
+        }
+
+        let mut brname_hdr = self.br_id_to_br_name.write();
+        let brname = brname_hdr.get_mut(&br_id).c(d!("branch not found"))?;
+        self.br_name_to_br_id.remove(&brname[..]).c(d!())?;
+        self.br_name_to_br_id.insert(new_br_name, br_id);
+        *brname = new_br_name.to_vec();
+
+        Ok(())
+    }
+
+    // Branches created before the meta information was introduced
+    // will get a default one.
+    #[inline(always)]
+    pub(super) fn branch_info(&self, br_id: BranchID) -> Result<BranchInfo> {
+        if !self.branch_exists(br_id) {
+            return Err(eg!("branch not found"));
+        }
+        self.br_to_info
+            .get(br_id)
+            .map(|info| decode_br_info(&info).c(d!()))
+            .unwrap_or_else(|| Ok(Default::default()))
+    }
+
+    // The `creation_version` is kept as recorded.
+    pub(super) fn branch_set_info(
+        &mut self,
+        br_id: BranchID,
+        info: &BranchInfo,
+    ) -> Result<()> {
+        let creation_version = self.branch_info(br_id).c(d!())?.creation_version;
+        let info = BranchInfo {
+            creation_version,
+            ..info.clone()
+        };
+        self.br_to_info.insert(br_id, encode_br_info(&info));
+        if info.protected {
+            self.protected_brs.write().insert(br_id);
+        } else {
+            self.protected_brs.write().remove(&br_id);
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_is_protected(&self, br_id: BranchID) -> Result<bool> {
+        if !self.branch_exists(br_id) {
+            return Err(eg!("branch not found"));
+        }
+        Ok(self.protected_brs.read().contains(&br_id))
+    }
+
+    // A token for the privileged writes on a branch of this graph.
+    pub(super) fn branch_override_protection(
+        &self,
+        br_id: BranchID,
+    ) -> Result<ProtectionOverride> {
+        if !self.branch_exists(br_id) {
+            return Err(eg!("branch not found"));
+        }
+        Ok(ProtectionOverride {
+            graph: self.br_to_info.instance_prefix(),
+            br_id,
+        })
+    }
+
+    // Data writes, truncations and version pops
+    // are denied on protected branches without an override,
+    // the override must be issued for this branch by this graph.
+    #[inline(always)]
+    pub(super) fn branch_check_writable(
+        &self,
+        br_id: BranchID,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        if let Some(o) = ovr {
+            if o.graph != self.br_to_info.instance_prefix() || o.br_id != br_id {
+                return Err(eg!("the override does not belong to this branch"));
+            }
+            return Ok(());
+        }
+        if self.protected_brs.read().contains(&br_id) {
+            return Err(eg!(
+                "branch is protected: {}",
+                String::from_utf8_lossy(
+                    self.br_id_to_br_name
+                        .read()
+                        .get(&br_id)
+                        .map(|n| &n[..])
+                        .unwrap_or_default()
+                )
+            ));
+        }
+        Ok(())
+    }
+
+    // The default branch can never be removed,
+    // even if its protection has been overridden.
+    #[inline(always)]
+    fn branch_check_removable(
+        &self,
+        br_id: BranchID,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        if br_id == self.default_branch {
+            return Err(eg!("the default branch can not be removed"));
+        }
+        self.branch_check_writable(br_id, ovr).c(d!())
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_set_default(&mut self, br_id: BranchID) -> Result<()> {
+        if !self.branch_exists(br_id) {
+            return Err(eg!("branch not found"));
+        }
+        self.default_branch = br_id;
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_get_default(&self) -> BranchID {
+        self.default_branch
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_get_default_name(&self) -> BranchNameOwned {
+        self.branch_get_name(self.default_branch).unwrap()
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_get_name(&self, br_id: BranchID) -> Option<BranchNameOwned> {
+        self.br_id_to_br_name
+            .read()
+            .get(&br_id)
+            .map(|br| BranchNameOwned(br.to_vec()))
+    }
+
+    // Check if all members have no changes on this branch.
+    #[inline(always)]
+    pub(super) fn branch_is_empty(&self, br_id: BranchID) -> Result<bool> {
+        self.branch_vers(br_id).c(d!()).map(|vers| {
+            self.member_hdrs
+                .read()
+                .values()
+                .all(|m| m.is_empty_on(&vers))
+        })
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_list(&self) -> Vec<BranchNameOwned> {
+        self.br_name_to_br_id
+            .iter()
+            .map(|(brname, _)| brname.to_vec())
+            .map(BranchNameOwned)
+            .collect()
+    }
+
+    // Logically similar to `std::ptr::swap`
+    //
+    // For example: If you have a master branch and a test branch, the data is always trial-run on the test branch, and then periodically merged back into the master branch. Rather than merging the test branch into the master branch, and then recreating the new test branch, it is more efficient to just swap the two branches, and then recreating the new test branch.
+    //
+    // # Safety
+    //
+    // - Non-'thread safe'
+    // - Must ensure that there are no reads and writes to these two branches during the execution
+    pub(super) unsafe fn branch_swap(
+        &mut self,
+        branch_1: &[u8],
+        branch_2: &[u8],
+    ) -> Result<()> {
+        let brid_1 = to_brid(&self.br_name_to_br_id.get(branch_1).c(d!())?);
+        let brid_2 = to_brid(&self.br_name_to_br_id.get(branch_2).c(d!())?);
+
+        self.br_name_to_br_id.insert(branch_1, brid_2).c(d!())?;
+        self.br_name_to_br_id.insert(branch_2, brid_1).c(d!())?;
+
+        self.br_id_to_br_name
+            .write()
+            .insert(brid_1, branch_2.to_vec())
+            .c(d!())?;
+        self.br_id_to_br_name
+            .write()
+            .insert(brid_2, branch_1.to_vec())
+            .c(d!())?;
+
+        if self.default_branch == brid_1 {
+            self.default_branch = brid_2;
+        } else if self.default_branch == brid_2 {
+            self.default_branch = brid_1;
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_get_id_by_name(&self, br_name: BranchName) -> Option<BranchID> {
+        self.br_name_to_br_id
+            .get(br_name.0)
+            .map(|bytes| to_brid(&bytes))
+    }
+
+    #[inline(always)]
+    pub(super) fn prune(&mut self, reserved_ver_num: Option<usize>) -> Result<()> {
+        let mut plan = self.prune_plan(reserved_ver_num, false).c(d!())?;
+        self.prune_by_plan(&mut plan, usize::MAX)
+            .c(d!())
+            .map(|_| ())
+    }
+
+    // Like `prune`, but at most `budget` versions are cleaned in one step,
+    // the result of repeated steps is the same as a whole `prune`.
+    //
+    // The candidates are found out by the first step,
+    // and kept in the graph for the following steps.
+    #[inline(always)]
+    pub(super) fn prune_step(
+        &mut self,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> Result<PruneProgress> {
+        let mut plan = self.pending_prune.take();
+        let ret = self
+            .prune_step_by_plan(&mut plan, reserved_ver_num, budget)
+            .c(d!());
+        self.pending_prune = plan;
+        ret
+    }
+
+    // Do a step of the pruning described by the `plan`,
+    // a new plan will be made if it is missing or out of date.
+    fn prune_step_by_plan(
+        &mut self,
+        plan: &mut Option<PrunePlan>,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> Result<PruneProgress> {
+        let budget = budget.max(1);
+
+        let p = match plan.take() {
+            Some(p)
+                if p.reserved_ver_num == reserved_ver_num
+                    && self.prune_plan_is_valid(&p, budget) =>
+            {
+                p
+            }
+            _ => self.prune_plan(reserved_ver_num, false).c(d!())?,
+        };
+        let p = plan.insert(p);
+
+        let progress = self.prune_by_plan(p, budget).c(d!())?;
+        if progress.is_finished() {
+            *plan = None;
+        }
+        Ok(progress)
+    }
+
+    // Find out all the versions to be cleaned.
+    fn prune_plan(
+        &self,
+        reserved_ver_num: Option<usize>,
+        clean_only: bool,
+    ) -> Result<PrunePlan> {
+        let mut valid_vers = HashSet::new();
+        self.br_to_its_vers.iter().for_each(|(_, vers)| {
+            decode_map(vers).iter().for_each(|(ver, _)| {
+                valid_vers.insert(to_verid(&ver[..]));
+            })
+        });
+
+        let orphans = self
+            .ver_id_to_ver_name
+            .read()
+            .map
+            .iter()
+            .map(|(ver, _)| to_verid(&ver))
+            .filter(|ver| !valid_vers.contains(ver))
+            .collect::<VecDeque<_>>();
+
+        let mut plan = PrunePlan {
+            reserved_ver_num,
+            orphans,
+            ..Default::default()
+        };
+
+        if clean_only {
+            return Ok(plan);
+        }
+
+        // the '1' of this 'add 1' means the never-deleted initial version.
+        let reserved = 1 + reserved_ver_num.unwrap_or(RESERVED_VERSION_NUM_DEFAULT);
+        if 0 == reserved {
+            return Err(eg!("reserved version number should NOT be zero"));
+        }
+
+        let (_, shared) = self.vers_shared_by_all();
+        let l = shared.len();
+        if l > reserved {
+            plan.rewrite_ver = Some(shared[0]);
+            plan.merges = shared[1..l - reserved + 1].iter().copied().collect();
+            plan.first_reserved = shared.get(l - reserved + 1).copied();
+        }
+
+        Ok(plan)
+    }
+
+    // The graph may be changed between two steps,
+    // the versions to be merged in the next step, the version they are merged into,
+    // and the oldest reserved version must still be shared by all non-empty branches.
+    fn prune_plan_is_valid(&self, plan: &PrunePlan, budget: usize) -> bool {
+        let n = budget.saturating_sub(plan.orphans.len()).min(plan.merges.len());
+        if 0 == n {
+            return true;
+        }
+        let brvers = self
+            .br_to_its_vers
+            .iter()
+            .map(|(_, vers)| decode_map(vers))
+            .filter(|vers| !vers.is_empty())
+            .collect::<Vec<_>>();
+        plan.merges
+            .iter()
+            .take(n)
+            .chain(plan.rewrite_ver.iter())
+            .chain(plan.first_reserved.iter())
+            .all(|ver| brvers.iter().all(|vers| vers.contains_key(ver)))
+    }
+
+    // The oldest version will be kept as the final data container.
+    //
+    // NOTE: As it will become bigger and bigger,
+    // if we migrate the its data to other vesions when pruning,
+    // the 'prune' process will be slower and slower,
+    // so we should not do that.
+    fn prune_by_plan(
+        &mut self,
+        plan: &mut PrunePlan,
+        budget: usize,
+    ) -> Result<PruneProgress> {
+        let n = plan.orphans.len().min(budget);
+        let orphanvers = {
+            // may have been removed by others since the plan was made
+            let ver_names = self.ver_id_to_ver_name.read();
+            plan.orphans
+                .drain(..n)
+                .filter(|ver| ver_names.contains_key(ver))
+                .collect::<Vec<_>>()
+        };
+
+        for m in self.member_hdrs.write().values_mut() {
+            m.remove_versions(&orphanvers).c(d!())?;
+        }
+
+        self.version_names_remove(&orphanvers).c(d!())?;
+
+        let mut progress = PruneProgress {
+            done: n,
+            remaining: plan.orphans.len(),
+        };
+
+        // the oldest versions are merged first,
+        // so the result of every step is a valid pruning state.
+        let n = plan.merges.len().min(budget - progress.done);
+        progress.done += n;
+        progress.remaining += plan.merges.len() - n;
+        if 0 == n {
+            return Ok(progress);
+        }
+
+        let rewrite_ver = plan.rewrite_ver.c(d!())?;
+        let vers_to_be_merged = plan.merges.drain(..n).collect::<Vec<_>>();
+
+        for (_, vers) in self.br_to_its_vers.iter() {
+            let mut vers = decode_map(vers);
+            for ver in vers_to_be_merged.iter() {
+                vers.remove(ver);
+            }
+        }
+
+        for m in self.member_hdrs.write().values_mut() {
+            m.prune_versions(&vers_to_be_merged, &rewrite_ver).c(d!())?;
+        }
+
+        self.version_names_remove(&vers_to_be_merged)
+            .c(d!())
+            .map(|_| progress)
+    }
+
+    // The versions shared by all non-empty branches, in ascending order,
+    // they are the only candidates of pruning.
+    fn vers_shared_by_all(&self) -> (Vec<MapxRaw>, Vec<VersionID>) {
+        let brvers_non_empty = self
+            .br_to_its_vers
+            .iter()
+            .map(|(_, vers)| decode_map(vers))
+            .filter(|vers| !vers.is_empty())
+            .collect::<Vec<_>>();
+        let mut brvers = (0..brvers_non_empty.len())
+            .map(|i| brvers_non_empty[i].iter())
+            .collect::<Vec<_>>();
+
+        // filter out the longest common prefix
+        let mut guard = VER_ID_MAX;
+        let mut shared: Vec<VersionID> = vec![];
+        'x: loop {
+            alt!(brvers.is_empty(), break 'x);
+            for (idx, vers) in brvers.iter_mut().enumerate() {
+                if let Some((ver, _)) = vers.next() {
+                    alt!(0 == idx, guard = to_verid(&ver));
+                    alt!(guard[..] != ver[..], break 'x);
+                } else {
+                    break 'x;
+                }
+            }
+            assert_ne!(guard, VER_ID_MAX);
+            shared.push(guard);
+        }
+
+        (brvers_non_empty, shared)
+    }
+
+    // The oldest shared version is kept as the final data container like `prune`,
+    // and the newest one is kept for the changes of dropped versions to be merged into.
+    pub(super) fn prune_with_policy(&mut self, policy: &dyn PrunePolicy) -> Result<()> {
+        self.version_clean_up_globally().c(d!())?;
+
+        let (mut brvers_non_empty, shared) = self.vers_shared_by_all();
+        let l = shared.len();
+        if l < 3 {
+            return Ok(());
+        }
+
+        // (the target version, the dropped versions merged into it)
+        let mut merges: Vec<(VersionID, Vec<VersionID>)> = vec![];
+        let mut dropped = vec![];
+        {
+            let ver_names = self.ver_id_to_ver_name.read();
+            for (idx, ver) in shared.iter().enumerate().skip(1) {
+                let meta = self.ver_to_meta.get(ver).map(|m| decode_ver_meta(&m));
+                let name = ver_names.get(ver).c(d!())?;
+                let info = VersionInfo {
+                    name: VersionName(&name),
+                    height: meta.map(|m| m.0),
+                    created_at: meta.map(|m| m.1),
+                    depth: l - 1 - idx,
+                };
+                if idx < l - 1 && !policy.keep(&info) {
+                    dropped.push(*ver);
+                } else if !dropped.is_empty() {
+                    merges.push((*ver, dropped));
+                    dropped = vec![];
+                }
+            }
+        }
+
+        for (target, vers) in merges.iter() {
+            for brvers in brvers_non_empty.iter_mut() {
+                for ver in vers.iter() {
+                    brvers.remove(ver).c(d!())?;
+                }
+            }
+            for m in self.member_hdrs.write().values_mut() {
+                m.merge_versions_forward(vers, target).c(d!())?;
+            }
+            self.version_names_remove(vers).c(d!())?;
+        }
+
+        Ok(())
+    }
+
+    fn version_names_remove(&mut self, vers: &[VersionID]) -> Result<()> {
+        for ver in vers.iter() {
+            self.ver_id_to_ver_name
+                .write()
+                .remove(ver)
+                .c(d!())
+                .and_then(|vername| self.ver_name_to_ver_id.remove(&vername).c(d!()))?;
+            self.ver_to_meta.remove(ver);
+        }
+        Ok(())
+    }
+}
+
+/// An explicit token for writing to a protected branch,
+/// it only takes effect on the `*_with_override` calls it is passed to,
+/// and only for the branch and the version graph it was issued for.
+#[derive(Debug)]
+pub struct ProtectionOverride {
+    // the instance prefix of the `br_to_info` of the graph
+    graph: PreBytes,
+    br_id: BranchID,
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub(super) struct MapxRawVs {
+    // the version graph this instance attached to,
+    // may be shared with other instances
+    graph: Graph,
+
+    // - 'empty value'(&[] ...) means 'not exist'
+    // - 'key -> multi-version(globally unique) -> multi-value'
+    layered_kv: MapxRaw, // <RawKey, MapxRaw<VersionID, RawValue>>,
+
+    // Globally ever changed keys within each version,
+    // shared with the member record in the version graph.
+    ver_to_change_set: Arc<RwLock<ChangeSet>>,
+
+    // the branch created for this instance if it is a fork,
+    // used instead of the default branch of the graph
+    fork: Option<Arc<ForkBranch>>,
+}
+
+// !^~^! 撸猫 !^~^!
+unsafe impl Send for MapxRawVs {}
+unsafe impl Sync for MapxRawVs {}
+
+// A clone will be attached to a copy of the original version graph,
+// so it's always independent from the original instance.
+impl Clone for MapxRawVs {
+    fn clone(&self) -> Self {
+        let graph = self.graph.read().duplicate();
+        let layered_kv = self.layered_kv.clone();
+        let ver_to_change_set = graph.write().attach(&layered_kv);
+        let fork = self
+            .fork
+            .as_ref()
+            .map(|f| Arc::new(ForkBranch::new(&graph, f.br_id)));
+        Self {
+            graph,
+            layered_kv,
+            ver_to_change_set,
+            fork,
+        }
+    }
+}
+
+// The branch created for a fork, it's removed after all handles
+// of the fork have been dropped, unless the fork has been persisted.
+#[derive(Debug)]
+struct ForkBranch {
+    graph: Graph,
+    br_id: BranchID,
+    persisted: AtomicBool,
+}
+
+impl ForkBranch {
+    #[inline(always)]
+    fn new(graph: &Graph, br_id: BranchID) -> Self {
+        Self {
+            graph: Arc::clone(graph),
+            br_id,
+            persisted: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Drop for ForkBranch {
+    fn drop(&mut self) {
+        if self.persisted.load(AtomicOrdering::Acquire) {
+            return;
+        }
+        let mut g = self.graph.write();
+        // may have been removed or protected by others
+        if g.branch_exists(self.br_id) {
+            info_omit!(g.branch_remove(self.br_id, None));
+        }
+    }
+}
+
+impl Serialize for MapxRawVs {
+    fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        MapxRawVsWithoutDerivedFields::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MapxRawVs {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        <MapxRawVsWithoutDerivedFields as Deserialize>::deserialize(deserializer)
+            .map(Self::from)
+    }
+}
+
+// The persistent format of versioned collections and version graphs:
+//
+// - `[magic][format version][graph][layered_kv][fork branch]`, the current one
+// - `[magic][1][graph][layered_kv]`, used before forks can be persisted
+// - `[default_branch][br_name_to_br_id][ver_name_to_ver_id][br_to_its_vers]
+//   [layered_kv]`, the legacy one used before the version graph can be shared,
+//   it is upgraded in place when being decoded
+//
+// The magic can not be a valid branch id, so the two can be told apart
+// even in formats that are not self-describing.
+pub(super) const FORMAT_MAGIC: [u8; 8] = *b"\xffvsdbvg\xff";
+pub(super) const FORMAT_VERSION: u32 = 2;
+
+#[inline(always)]
+pub(super) fn check_format<E: serde::de::Error>(
+    magic: [u8; 8],
+    ver: u32,
+) -> StdResult<(), E> {
+    if magic != FORMAT_MAGIC {
+        return Err(E::custom("invalid version graph format"));
+    }
+    if !(1..=FORMAT_VERSION).contains(&ver) {
+        return Err(E::custom(format!("unsupported format version: {}", ver)));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct MapxRawVsWithoutDerivedFields {
+    magic: [u8; 8],
+    format_version: u32,
+    graph: VerGraphHeader,
+    layered_kv: MapxRaw, // <RawKey, MapxRaw<VersionID, RawValue>>
+    fork_branch: Option<BranchID>,
+}
+
+impl<'de> Deserialize<'de> for MapxRawVsWithoutDerivedFields {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // named by the legacy layout, which has more fields
+        const FIELDS: &[&str] = &[
+            "default_branch",
+            "br_name_to_br_id",
+            "ver_name_to_ver_id",
+            "br_to_its_vers",
+            "layered_kv",
+        ];
+        deserializer.deserialize_struct(
+            "MapxRawVsWithoutDerivedFields",
+            FIELDS,
+            MapxRawVsVisitor,
+        )
+    }
+}
+
+struct MapxRawVsVisitor;
+
+impl<'de> serde::de::Visitor<'de> for MapxRawVsVisitor {
+    type Value = MapxRawVsWithoutDerivedFields;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a versioned collection")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> StdResult<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        macro_rules! next {
+            ($idx: expr) => {
+                seq.next_element()?
+                    .ok_or_else(|| serde::de::Error::invalid_length($idx, &self))?
+            };
+        }
+
+        let first: [u8; 8] = next!(0);
+        if first == FORMAT_MAGIC {
+            let format_version = next!(1);
+            check_format(first, format_version)?;
+            let graph = next!(2);
+            let layered_kv = next!(3);
+            let fork_branch = if 1 < format_version { next!(4) } else { None };
+            Ok(MapxRawVsWithoutDerivedFields {
+                magic: first,
+                format_version,
+                graph,
+                layered_kv,
+                fork_branch,
+            })
+        } else {
+            let br_name_to_br_id = next!(1);
+            let ver_name_to_ver_id = next!(2);
+            let br_to_its_vers = next!(3);
+            let layered_kv: MapxRaw = next!(4);
+            Ok(MapxRawVsWithoutDerivedFields::new(
+                VerGraphHeader::upgrade(
+                    first,
+                    br_name_to_br_id,
+                    ver_name_to_ver_id,
+                    br_to_its_vers,
+                    &layered_kv,
+                ),
+                layered_kv,
+            ))
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> StdResult<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        use serde::de::Error;
+
+        let mut magic = None;
+        let mut format_version = None;
+        let mut graph = None;
+        let mut layered_kv = None;
+        let mut fork_branch = None;
+        let mut default_branch = None;
+        let mut br_name_to_br_id = None;
+        let mut ver_name_to_ver_id = None;
+        let mut br_to_its_vers = None;
+
+        while let Some(k) = map.next_key::<String>()? {
+            match k.as_str() {
+                "magic" => magic = Some(map.next_value()?),
+                "format_version" => format_version = Some(map.next_value()?),
+                "graph" => graph = Some(map.next_value()?),
+                "layered_kv" => layered_kv = Some(map.next_value()?),
+                "fork_branch" => fork_branch = map.next_value()?,
+                "default_branch" => default_branch = Some(map.next_value()?),
+                "br_name_to_br_id" => br_name_to_br_id = Some(map.next_value()?),
+                "ver_name_to_ver_id" => ver_name_to_ver_id = Some(map.next_value()?),
+                "br_to_its_vers" => br_to_its_vers = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
+            }
+        }
+
+        macro_rules! required {
+            ($field: ident) => {
+                $field.ok_or_else(|| A::Error::missing_field(stringify!($field)))?
+            };
+        }
+
+        let layered_kv: MapxRaw = required!(layered_kv);
+
+        if let Some(magic) = magic {
+            let format_version = required!(format_version);
+            check_format(magic, format_version)?;
+            return Ok(MapxRawVsWithoutDerivedFields {
+                magic,
+                format_version,
+                graph: required!(graph),
+                layered_kv,
+                fork_branch,
+            });
+        }
+
+        let graph = VerGraphHeader::upgrade(
+            required!(default_branch),
+            required!(br_name_to_br_id),
+            required!(ver_name_to_ver_id),
+            required!(br_to_its_vers),
+            &layered_kv,
+        );
+        Ok(MapxRawVsWithoutDerivedFields::new(graph, layered_kv))
+    }
+}
+
+impl MapxRawVsWithoutDerivedFields {
+    #[inline(always)]
+    fn new(graph: VerGraphHeader, layered_kv: MapxRaw) -> Self {
+        Self {
+            magic: FORMAT_MAGIC,
+            format_version: FORMAT_VERSION,
+            graph,
+            layered_kv,
+            fork_branch: None,
+        }
+    }
+}
+
+impl From<MapxRawVsWithoutDerivedFields> for MapxRawVs {
+    fn from(m: MapxRawVsWithoutDerivedFields) -> Self {
+        let graph = VerGraph::load_shared(m.graph);
+        let ver_to_change_set = graph.write().attach(&m.layered_kv);
+        // a persisted fork is never removed automatically
+        let fork = m.fork_branch.map(|br_id| {
+            let f = ForkBranch::new(&graph, br_id);
+            f.persisted.store(true, AtomicOrdering::Release);
+            Arc::new(f)
+        });
+        Self {
+            graph,
+            layered_kv: m.layered_kv,
+            ver_to_change_set,
+            fork,
+        }
+    }
+}
+
+impl From<&MapxRawVs> for MapxRawVsWithoutDerivedFields {
+    fn from(m: &MapxRawVs) -> Self {
+        let mut ret = Self::new(m.graph.read().header(), unsafe {
+            m.layered_kv.shadow()
+        });
+        // the branch of a fork is kept once the fork is persisted,
+        // it may be decoded again after being dropped
+        ret.fork_branch = m.fork.as_ref().map(|f| {
+            f.persisted.store(true, AtomicOrdering::Release);
+            f.br_id
+        });
+        ret
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl MapxRawVs {
+    #[inline(always)]
+    pub(super) unsafe fn shadow(&self) -> Self {
+        Self {
+            graph: self.graph.read().shadow(),
+            layered_kv: self.layered_kv.shadow(),
+            ver_to_change_set: Arc::clone(&self.ver_to_change_set),
+            fork: self.fork.clone(),
+        }
+    }
+
+    // A copy-on-write copy based on the head of the default branch,
+    // all its writes go to a new branch created for it,
+    // the version created on that branch shares the name of it.
+    //
+    // A new version is also created on the base branch,
+    // so the later writes of the base branch are invisible to the copy.
+    pub(super) fn fork(&self) -> Result<Self> {
+        let base = self.branch_get_default();
+
+        let br_id = {
+            let mut g = self.graph.write();
+
+            let name = loop {
+                let name = format!("fork-{}", rand::random::<u64>());
+                let seal = format!("{}-base", name);
+                if g.branch_get_id_by_name(BranchName(name.as_bytes())).is_none()
+                    && g.version_get_id_by_name(VersionName(name.as_bytes())).is_none()
+                    && g.version_get_id_by_name(VersionName(seal.as_bytes())).is_none()
+                {
+                    break name;
+                }
+            };
+
+            g.branch_create_by_base_branch(name.as_bytes(), name.as_bytes(), base, false)
+                .c(d!())?;
+            let br_id = g
+                .branch_get_id_by_name(BranchName(name.as_bytes()))
+                .c(d!())?;
+
+            if g.branch_has_versions(base) {
+                let seal = format!("{}-base", name);
+                if let Err(e) = g.version_create_by_branch(seal.as_bytes(), base) {
+                    info_omit!(g.branch_remove(br_id, None));
+                    return Err(e).c(d!());
+                }
+            }
+
+            br_id
+        };
+
+        Ok(Self {
+            graph: Arc::clone(&self.graph),
+            layered_kv: unsafe { self.layered_kv.shadow() },
+            ver_to_change_set: Arc::clone(&self.ver_to_change_set),
+            fork: Some(Arc::new(ForkBranch::new(&self.graph, br_id))),
+        })
+    }
+
+    // The branch created for this instance if it is a fork.
+    #[inline(always)]
+    pub(super) fn fork_branch(&self) -> Option<BranchNameOwned> {
+        let f = self.fork.as_ref()?;
+        self.graph.read().branch_get_name(f.br_id)
+    }
+
+    #[inline(always)]
+    pub(super) fn new() -> Self {
+        Self::new_in(&VerGraph::new_shared())
+    }
+
+    // Create an instance attached to an existing version graph.
+    #[inline(always)]
+    pub(super) fn new_in(graph: &Graph) -> Self {
+        let layered_kv = MapxRaw::new();
+        let ver_to_change_set = graph.write().attach(&layered_kv);
+        Self {
+            graph: Arc::clone(graph),
+            layered_kv,
+            ver_to_change_set,
+            fork: None,
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    // Move to another version graph,
+    // only empty instances can be moved.
+    pub(super) fn graph_attach(&mut self, graph: &Graph) -> Result<()> {
+        if Arc::ptr_eq(&self.graph, graph) {
+            return Ok(());
+        }
+
+        if !self.layered_kv.is_empty() {
+            return Err(eg!(
+                "only empty instances can be attached to another version graph"
+            ));
+        }
+
+        self.graph.write().detach(&self.layered_kv);
+        self.ver_to_change_set = graph.write().attach(&self.layered_kv);
+        self.graph = Arc::clone(graph);
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(super) fn instance_prefix(&self) -> PreBytes {
+        self.layered_kv.instance_prefix()
+    }
+
+    #[inline(always)]
+    fn branch_last_version(&self, br_id: BranchID) -> Option<VersionID> {
+        self.graph.read().branch_last_version(br_id)
+    }
+
+    #[inline(always)]
+    pub(super) fn insert(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Option<RawValue>> {
+        self.insert_by_branch(key, value, self.branch_get_default(), None)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub(super) fn insert_by_branch(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        br_id: BranchID,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<Option<RawValue>> {
+        let ver_id = self.branch_vers_head(br_id, ovr).c(d!())?;
+        self.insert_by_branch_version(key, value, br_id, ver_id)
+            .c(d!())
+    }
+
+    // The head version of a branch, for writing.
+    #[inline(always)]
+    fn branch_vers_head(
+        &self,
+        br_id: BranchID,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<VersionID> {
+        let graph = self.graph.read();
+        graph.branch_check_writable(br_id, ovr).c(d!())?;
+        graph
+            .branch_vers(br_id)
+            .c(d!("branch not found"))?
+            .last()
+            .map(|(ver_id, _)| to_verid(&ver_id))
+            .c(d!("no version on this branch, create a version first"))
+    }
+
+    // This function should **NOT** be public,
+    // `write`-like operations should only be applied
+    // on the latest version of every branch,
+    // historical data version should be immutable in the user view.
+    #[inline(always)]
+    fn insert_by_branch_version(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        br_id: BranchID,
+        ver_id: VersionID,
+    ) -> Result<Option<RawValue>> {
+        self.write_by_branch_version(key, Some(value), br_id, ver_id)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub(super) fn remove(&mut self, key: &[u8]) -> Result<Option<RawValue>> {
+        self.remove_by_branch(key, self.branch_get_default(), None)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub(super) fn remove_by_branch(
+        &mut self,
+        key: &[u8],
+        br_id: BranchID,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<Option<RawValue>> {
+        let ver_id = self.branch_vers_head(br_id, ovr).c(d!())?;
+        self.remove_by_branch_version(key, br_id, ver_id).c(d!())
+    }
+
+    // This function should **NOT** be public,
+    // `write`-like operations should only be applied
+    // on the latest version of every branch,
+    // historical data version should be immutable in the user view.
+    //
+    // The `remove` is essentially assign a `None` value to the key.
+    fn remove_by_branch_version(
+        &mut self,
+        key: &[u8],
+        br_id: BranchID,
+        ver_id: VersionID,
+    ) -> Result<Option<RawValue>> {
+        self.write_by_branch_version(key, None, br_id, ver_id)
+            .c(d!())
+    }
+
+    // This function should **NOT** be public,
+    // `write`-like operations should only be applied
+    // on the latest version of every branch,
+    // historical data version should be immutable in the user view.
+    fn write_by_branch_version(
+        &mut self,
+        key: &[u8],
+        value: Option<&[u8]>,
+        br_id: BranchID,
+        ver_id: VersionID,
+    ) -> Result<Option<RawValue>> {
+        // clone it, keep a copy of the original unchanged value.
+        let ret = self.get_by_branch_version(key, br_id, ver_id);
+
+        // remove a non-existing value
+        if value.is_none() && ret.is_none() {
+            return Ok(None);
+        }
+
+        let value = value.unwrap_or(NULL);
+
+        decode_map(
+            &*self
+                .layered_kv
+                .entry(key)
+                .or_insert(encode_map(&MapxRaw::new())),
+        )
+        .insert(ver_id, value);
+
+        self.ver_to_change_set.write().insert(&ver_id, key);
+
+        Ok(ret)
+    }
+
+    #[inline(always)]
+    pub(super) fn get(&self, key: &[u8]) -> Option<RawValue> {
+        self.get_by_branch(key, self.branch_get_default())
+    }
+
+    #[inline(always)]
+    pub(super) fn get_by_branch(&self, key: &[u8], br_id: BranchID) -> Option<RawValue> {
+        self.branch_last_version(br_id)
+            .and_then(|ver_id| self.get_by_branch_version(key, br_id, ver_id))
+    }
+
+    #[inline(always)]
+    pub(super) fn get_by_branch_version(
+        &self,
+        key: &[u8],
+        br_id: BranchID,
+        ver_id: VersionID,
+    ) -> Option<RawValue> {
+        let vers = self.graph.read().branch_vers(br_id)?;
+
+        decode_map(self.layered_kv.get(key)?)
+            .range(..=Cow::Borrowed(&ver_id[..]))
+            .rev()
+            .find(|(ver, _)| vers.contains_key(ver))
+            .and_then(|(_, value)| alt!(value.is_empty(), None, Some(value)))
+    }
+
+    // The keys changed between two versions of a branch, with their values in `ver_b`,
+    // `None` means that the key does not exist in `ver_b`.
+    pub(super) fn diff_by_branch(
+        &self,
+        br_id: BranchID,
+        ver_a: VersionID,
+        ver_b: VersionID,
+    ) -> Result<Vec<(RawKey, Option<RawValue>)>> {
+        let vers = self
+            .graph
+            .read()
+            .branch_vers(br_id)
+            .c(d!("branch not found"))?;
+        if !vers.contains_key(ver_a) || !vers.contains_key(ver_b) {
+            return Err(eg!("version not found on this branch"));
+        }
+
+        let (lo, hi) = alt!(ver_a <= ver_b, (ver_a, ver_b), (ver_b, ver_a));
+        let keys = {
+            let chgset_hdr = self.ver_to_change_set.read();
+            vers.range((
+                Bound::Excluded(Cow::Borrowed(&lo[..])),
+                Bound::Included(Cow::Borrowed(&hi[..])),
+            ))
+            .flat_map(|(ver, _)| chgset_hdr.get(&to_verid(&ver)))
+            .flatten()
+            .collect::<BTreeSet<_>>()
+        };
+
+        Ok(keys
+            .into_iter()
+            .filter_map(|k| {
+                let old = self.get_by_branch_version(&k, br_id, ver_a);
+                let new = self.get_by_branch_version(&k, br_id, ver_b);
+                alt!(old == new, None, Some((k, new)))
+            })
+            .collect())
+    }
+
+    #[inline(always)]
+    pub(super) fn get_ge(&self, key: &[u8]) -> Option<(RawKey, RawValue)> {
+        self.range(Cow::Borrowed(key)..).next()
+    }
+
+    #[inline(always)]
+    pub(super) fn get_ge_by_branch(
+        &self,
+        key: &[u8],
+        br_id: BranchID,
+    ) -> Option<(RawKey, RawValue)> {
+        self.range_by_branch(br_id, Cow::Borrowed(key)..).next()
+    }
+
+    #[inline(always)]
+    pub(super) fn get_ge_by_branch_version(
+        &self,
+        key: &[u8],
+        br_id: BranchID,
+        ver_id: VersionID,
+    ) -> Option<(RawKey, RawValue)> {
+        self.range_by_branch_version(br_id, ver_id, Cow::Borrowed(key)..)
+            .next()
+    }
+
+    #[inline(always)]
+    pub(super) fn get_le(&self, key: &[u8]) -> Option<(RawKey, RawValue)> {
+        self.range(..=Cow::Borrowed(key)).next_back()
+    }
+
+    #[inline(always)]
+    pub(super) fn get_le_by_branch(
+        &self,
+        key: &[u8],
+        br_id: BranchID,
+    ) -> Option<(RawKey, RawValue)> {
+        self.range_by_branch(br_id, ..=Cow::Borrowed(key))
+            .next_back()
+    }
+
+    #[inline(always)]
+    pub(super) fn get_le_by_branch_version(
+        &self,
+        key: &[u8],
+        br_id: BranchID,
+        ver_id: VersionID,
+    ) -> Option<(RawKey, RawValue)> {
+        self.range_by_branch_version(br_id, ver_id, ..=Cow::Borrowed(key))
+            .next_back()
+    }
+
+    #[inline(always)]
+    pub(super) fn iter(&self) -> MapxRawVsIter {
+        self.iter_by_branch(self.branch_get_default())
+    }
+
+    #[inline(always)]
+    pub(super) fn iter_by_branch(&self, br_id: BranchID) -> MapxRawVsIter {
+        if let Some(ver_id) = self.branch_last_version(br_id) {
+            return self.iter_by_branch_version(br_id, ver_id);
+        }
+
+        MapxRawVsIter {
+            hdr: self,
+            iter: self.layered_kv.range(..),
+            br_id: NULL_ID,
+            ver_id: NULL_ID,
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn iter_by_branch_version(
+        &self,
+        br_id: BranchID,
+        ver_id: VersionID,
+    ) -> MapxRawVsIter {
+        MapxRawVsIter {
+            hdr: self,
+            iter: self.layered_kv.range(..),
+            br_id,
+            ver_id,
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        bounds: R,
+    ) -> MapxRawVsIter<'a> {
+        self.range_by_branch(self.branch_get_default(), bounds)
+    }
+
+    #[inline(always)]
+    pub(super) fn range_by_branch<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        br_id: BranchID,
+        bounds: R,
+    ) -> MapxRawVsIter<'a> {
+        if let Some(ver_id) = self.branch_last_version(br_id) {
+            return self.range_by_branch_version(br_id, ver_id, bounds);
+        }
+
+        // An empty `Iter`
+        MapxRawVsIter {
+            hdr: self,
+            iter: self.layered_kv.range(..),
+            br_id,
+            ver_id: NULL_ID,
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn range_by_branch_version<'a, R: RangeBounds<Cow<'a, [u8]>>>(
+        &'a self,
+        br_id: BranchID,
+        ver_id: VersionID,
+        bounds: R,
+    ) -> MapxRawVsIter<'a> {
+        let l = match bounds.start_bound() {
+            Bound::Included(v) => Bound::Included(Cow::Owned(v.to_vec())),
+            Bound::Excluded(v) => Bound::Excluded(Cow::Owned(v.to_vec())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let h = match bounds.end_bound() {
+            Bound::Included(v) => Bound::Included(Cow::Owned(v.to_vec())),
+            Bound::Excluded(v) => Bound::Excluded(Cow::Owned(v.to_vec())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
 
-    #[inline(always)]
-    pub(super) fn branch_set_default(&mut self, br_id: BranchID) -> Result<()> {
-        if !self.branch_exists(br_id) {
-            return Err(eg!("branch not found"));
+        MapxRawVsIter {
+            hdr: self,
+            iter: self.layered_kv.range((l, h)),
+            br_id,
+            ver_id,
         }
-        self.default_branch = br_id;
-        Ok(())
     }
 
     #[inline(always)]
-    pub(super) fn branch_get_default(&self) -> BranchID {
-        self.default_branch
+    pub(super) fn iter_prefix(&self, prefix: &[u8]) -> MapxRawVsIter {
+        self.iter_prefix_by_branch(self.branch_get_default(), prefix)
     }
 
     #[inline(always)]
-    pub(super) fn branch_get_default_name(&self) -> BranchNameOwned {
-        self.br_id_to_br_name
-            .read()
-            .get(&self.default_branch)
-            .map(|br| BranchNameOwned(br.to_vec()))
-            .unwrap()
+    pub(super) fn iter_prefix_by_branch(
+        &self,
+        br_id: BranchID,
+        prefix: &[u8],
+    ) -> MapxRawVsIter {
+        let ver_id = self.branch_last_version(br_id).unwrap_or(NULL_ID);
+        self.iter_prefix_by_branch_version(br_id, ver_id, prefix)
     }
 
     #[inline(always)]
-    pub(super) fn branch_is_empty(&self, br_id: BranchID) -> Result<bool> {
-        self.br_to_its_vers.get(br_id).c(d!()).map(|vers| {
-            decode_map(vers)
-                .iter()
-                .all(|(ver, _)| !self.version_has_change_set(to_verid(&ver)).unwrap())
-        })
+    pub(super) fn iter_prefix_by_branch_version(
+        &self,
+        br_id: BranchID,
+        ver_id: VersionID,
+        prefix: &[u8],
+    ) -> MapxRawVsIter {
+        MapxRawVsIter {
+            hdr: self,
+            iter: self.layered_kv.iter_prefix(prefix),
+            br_id,
+            ver_id,
+        }
     }
 
+    // NOTE: just a stupid O(n) counter, very slow!
     #[inline(always)]
-    pub(super) fn branch_list(&self) -> Vec<BranchNameOwned> {
-        self.br_name_to_br_id
-            .iter()
-            .map(|(brname, _)| brname.to_vec())
-            .map(BranchNameOwned)
-            .collect()
+    pub(super) fn len(&self) -> usize {
+        self.iter().count()
     }
 
-    // Logically similar to `std::ptr::swap`
-    //
-    // For example: If you have a master branch and a test branch, the data is always trial-run on the test branch, and then periodically merged back into the master branch. Rather than merging the test branch into the master branch, and then recreating the new test branch, it is more efficient to just swap the two branches, and then recreating the new test branch.
-    //
-    // # Safety
-    //
-    // - Non-'thread safe'
-    // - Must ensure that there are no reads and writes to these two branches during the execution
-    pub(super) unsafe fn branch_swap(
-        &mut self,
-        branch_1: &[u8],
-        branch_2: &[u8],
-    ) -> Result<()> {
-        let mut br_hdr = self.br_id_to_br_name.write();
-
-        let brid_1 = to_brid(&self.br_name_to_br_id.get(branch_1).c(d!())?);
-        let brid_2 = to_brid(&self.br_name_to_br_id.get(branch_2).c(d!())?);
-
-        self.br_name_to_br_id.insert(branch_1, brid_2).c(d!())?;
-        self.br_name_to_br_id.insert(branch_2, brid_1).c(d!())?;
-
-        br_hdr.insert(brid_1, branch_2.to_vec()).c(d!())?;
-        br_hdr.insert(brid_2, branch_1.to_vec()).c(d!())?;
-
-        if self.default_branch == brid_1 {
-            self.default_branch = brid_2;
-        } else if self.default_branch == brid_2 {
-            self.default_branch = brid_1;
-        }
-
-        Ok(())
+    // NOTE: just a stupid O(n) counter, very slow!
+    #[inline(always)]
+    pub(super) fn len_by_branch(&self, br_id: BranchID) -> usize {
+        self.iter_by_branch(br_id).count()
     }
 
+    // NOTE: just a stupid O(n) counter, very slow!
     #[inline(always)]
-    pub(super) fn branch_get_id_by_name(&self, br_name: BranchName) -> Option<BranchID> {
-        self.br_name_to_br_id
-            .get(br_name.0)
-            .map(|bytes| to_brid(&bytes))
+    pub(super) fn len_by_branch_version(
+        &self,
+        br_id: BranchID,
+        ver_id: VersionID,
+    ) -> usize {
+        self.iter_by_branch_version(br_id, ver_id).count()
     }
 
-    #[inline(always)]
-    pub(super) fn prune(&mut self, reserved_ver_num: Option<usize>) -> Result<()> {
-        self.do_prune(reserved_ver_num, false).c(d!())
+    // All keys that exist in any version of any branch,
+    // along with the ones that have been deleted but not pruned.
+    pub(super) fn keys_globally(&self) -> impl Iterator<Item = RawKey> + '_ {
+        self.layered_kv.iter().map(|(k, _)| k)
     }
 
-    // The oldest version will be kept as the final data container.
-    //
-    // NOTE: As it will become bigger and bigger,
-    // if we migrate the its data to other vesions when pruning,
-    // the 'prune' process will be slower and slower,
-    // so we should not do that.
-    fn do_prune(
-        &mut self,
-        reserved_ver_num: Option<usize>,
-        clean_only: bool,
+    // Rewrite all existing values of the key in all versions,
+    // the deletion marks(empty values) will be kept as is,
+    // the writes are added to `batch` instead of being applied directly.
+    pub(super) fn rewrite_values_of(
+        &self,
+        key: &[u8],
+        f: &mut dyn FnMut(&[u8]) -> Result<RawValue>,
+        batch: &mut MapxRawBatch,
     ) -> Result<()> {
-        /////////////////////////////////////////////////////////////////////
-        let (mut ver_hdr, mut chgset_hdr) = if 0 == rand::random::<u32>() % 16 {
-            (
-                self.ver_id_to_ver_name.write(),
-                self.ver_to_change_set.write(),
-            )
-        } else {
-            (
-                self.ver_id_to_ver_name.try_write().c(d!("lock busy"))?,
-                self.ver_to_change_set.try_write().c(d!("lock busy"))?,
-            )
-        };
-        /////////////////////////////////////////////////////////////////////
-
-        let mut valid_vers = HashSet::new();
-        self.br_to_its_vers.iter().for_each(|(_, vers)| {
-            decode_map(vers).iter().for_each(|(ver, _)| {
-                valid_vers.insert(to_verid(&ver[..]));
-            })
-        });
+        let _g = self.graph.read();
 
-        let mut orphanvers = vec![];
-        for (ver, chgset) in chgset_hdr
-            .iter()
-            .filter(|(ver, _)| !valid_vers.contains(&ver[..]))
-        {
-            for k in chgset.iter() {
-                let mut lkv = decode_map(self.layered_kv.get(k).c(d!())?);
-                lkv.remove(ver).c(d!())?;
-                if lkv.is_empty() {
-                    self.layered_kv.remove(k).c(d!())?;
+        if let Some(vers) = self.layered_kv.get(key) {
+            let mut vers = decode_map(vers);
+            for (ver, v) in unsafe { vers.shadow() }.iter() {
+                if v.is_empty() {
+                    continue;
+                }
+                let new_v = f(&v).c(d!())?;
+                if new_v.is_empty() {
+                    return Err(eg!("empty values are reserved as deletion marks"));
                 }
+                batch.insert(&mut vers, ver, new_v);
             }
-            orphanvers.push(*ver);
-        }
-
-        for ver in orphanvers.iter() {
-            chgset_hdr
-                .remove(ver)
-                .c(d!())
-                .and_then(|_| ver_hdr.remove(ver).c(d!()))
-                .and_then(|vername| self.ver_name_to_ver_id.remove(&vername).c(d!()))?;
-        }
-
-        if clean_only {
-            return Ok(());
         }
 
-        // the '1' of this 'add 1' means the never-deleted initial version.
-        let reserved_ver_num =
-            1 + reserved_ver_num.unwrap_or(RESERVED_VERSION_NUM_DEFAULT);
-        if 0 == reserved_ver_num {
-            return Err(eg!("reserved version number should NOT be zero"));
-        }
+        Ok(())
+    }
 
-        let mut brvers_non_empty = self
-            .br_to_its_vers
-            .iter()
-            .map(|(_, vers)| decode_map(vers))
-            .filter(|vers| !vers.is_empty())
-            .collect::<Vec<_>>();
-        alt!(brvers_non_empty.is_empty(), return Ok(()));
-        let mut brvers = (0..brvers_non_empty.len())
-            .map(|i| brvers_non_empty[i].iter())
-            .collect::<Vec<_>>();
+    // Clear all data, for testing purpose.
+    //
+    // The version graph will also be reset
+    // if it is not shared with other instances.
+    #[inline(always)]
+    pub(super) fn clear(&mut self) {
+        let mut graph = self.graph.write();
 
-        // filter out the longest common prefix
-        let mut guard = VER_ID_MAX;
-        let mut vers_to_be_merged: Vec<VersionID> = vec![];
-        'x: loop {
-            for (idx, vers) in brvers.iter_mut().enumerate() {
-                if let Some((ver, _)) = vers.next() {
-                    alt!(0 == idx, guard = to_verid(&ver));
-                    alt!(guard[..] != ver[..], break 'x);
-                } else {
-                    break 'x;
-                }
-            }
-            assert_ne!(guard, VER_ID_MAX);
-            vers_to_be_merged.push(guard);
-        }
+        self.layered_kv.clear();
+        self.ver_to_change_set.write().clear();
 
-        let l = vers_to_be_merged.len();
-        if l <= reserved_ver_num {
-            return Ok(());
+        if 1 == graph.members_num() {
+            graph.clear();
         }
+    }
 
-        let (vers_to_be_merged, rewrite_ver) = {
-            let guard_idx = l - reserved_ver_num;
-            (&vers_to_be_merged[1..=guard_idx], &vers_to_be_merged[0])
-        };
+    #[inline(always)]
+    pub(super) fn version_get_id_by_name(
+        &self,
+        ver_name: VersionName,
+    ) -> Option<VersionID> {
+        self.graph.read().version_get_id_by_name(ver_name)
+    }
 
-        for vers in brvers_non_empty.iter_mut() {
-            for ver in vers_to_be_merged.iter() {
-                vers.remove(ver).c(d!())?;
-            }
+    // Check if this instance has changes in the version.
+    #[inline(always)]
+    pub(super) fn version_has_change_set(&self, ver_id: VersionID) -> Result<bool> {
+        if !self.graph.read().version_exists_globally(ver_id) {
+            return Err(eg!("version not found"));
         }
+        Ok(self.member().has_change_set(ver_id))
+    }
 
-        let mut kvchgs = HashSet::new();
-        let mut rewrite_chgset = HashSet::new();
-
-        for ver in vers_to_be_merged.iter() {
-            let chgset = chgset_hdr.remove(ver).c(d!())?;
-            for k in chgset.iter() {
-                let mut kvers = decode_map(self.layered_kv.get(k).c(d!())?);
-                let v = kvers.remove(ver).c(d!())?;
-                kvers.insert(rewrite_ver, v);
+    pub(super) fn version_chgset_trie_root(
+        &self,
+        br_id: Option<BranchID>,
+        ver_id: Option<VersionID>,
+    ) -> Result<Vec<u8>> {
+        let br_id = br_id.or_else(|| self.fork.as_ref().map(|f| f.br_id));
+        let ver = self.graph.read().version_resolve(br_id, ver_id).c(d!())?;
+        Ok(self.member().chgset_trie_root(ver))
+    }
 
-                rewrite_chgset.insert(k.clone());
-                kvchgs.insert(k.clone());
-            }
+    #[inline(always)]
+    pub(super) fn branch_get_default(&self) -> BranchID {
+        if let Some(f) = self.fork.as_ref() {
+            return f.br_id;
         }
+        self.graph.read().branch_get_default()
+    }
 
-        let rewrite_chgset_hdr = chgset_hdr.get_mut(&to_verid(rewrite_ver)).c(d!())?;
+    // Check if this instance has no changes on the branch.
+    #[inline(always)]
+    pub(super) fn branch_is_empty(&self, br_id: BranchID) -> Result<bool> {
+        self.graph
+            .read()
+            .branch_vers(br_id)
+            .c(d!())
+            .map(|vers| self.member().is_empty_on(&vers))
+    }
 
-        rewrite_chgset.into_iter().for_each(|k| {
-            rewrite_chgset_hdr.insert(k);
-        });
+    // Keys of this instance changed both on `br_id` and `base_br_id` since their fork point.
+    pub(super) fn branch_rebase_conflicts(
+        &self,
+        br_id: BranchID,
+        base_br_id: BranchID,
+    ) -> Result<Vec<RawKey>> {
+        let (private, base_only) = self
+            .graph
+            .read()
+            .branch_fork_diff(br_id, base_br_id)
+            .c(d!())?;
+        Ok(self
+            .member()
+            .rebase_conflicts(&private, &base_only)
+            .into_iter()
+            .collect())
+    }
 
-        // lowest-level KVs with 'deleted' states should be cleaned up.
-        let mut empty_keys = vec![];
-        for k in kvchgs.iter() {
-            if let Some(vers) = self.layered_kv.get(k) {
-                let mut vers = decode_map(vers);
-                // A 'NULL' value means 'not exist'.
-                if vers.get(rewrite_ver).c(d!())?.is_empty() {
-                    vers.remove(rewrite_ver).c(d!())?;
-                    rewrite_chgset_hdr.remove(k);
-                }
-                if vers.is_empty() {
-                    empty_keys.push(k);
-                }
-            }
-        }
+    #[inline(always)]
+    pub(super) fn branch_get_id_by_name(&self, br_name: BranchName) -> Option<BranchID> {
+        self.graph.read().branch_get_id_by_name(br_name)
+    }
 
-        for k in empty_keys.iter() {
-            self.layered_kv.remove(k).c(d!())?;
+    #[inline(always)]
+    fn member(&self) -> Member {
+        Member {
+            layered_kv: unsafe { self.layered_kv.shadow() },
+            ver_to_change_set: Arc::clone(&self.ver_to_change_set),
         }
-
-        Ok(())
     }
 }
 
@@ -1557,6 +3254,79 @@ fn decode_map(v: impl AsRef<[u8]>) -> MapxRaw {
     unsafe { MapxRaw::from_prefix_slice(v.as_ref()) }
 }
 
+// Layout: [height: u64][creation time: u64]
+#[inline(always)]
+fn encode_ver_meta(height: u64, created_at: u64) -> [u8; 16] {
+    let mut ret = [0; 16];
+    ret[..8].copy_from_slice(&height.to_be_bytes());
+    ret[8..].copy_from_slice(&created_at.to_be_bytes());
+    ret
+}
+
+#[inline(always)]
+fn decode_ver_meta(bytes: &[u8]) -> (u64, u64) {
+    (
+        crate::parse_int!(bytes[..8], u64),
+        crate::parse_int!(bytes[8..16], u64),
+    )
+}
+
+// Layout: [creator len: u32][creator][description len: u32][description]
+//         [protected: u8][has creation version: u8][creation version, the rest bytes]
+fn encode_br_info(info: &BranchInfo) -> RawBytes {
+    let mut ret = vec![];
+    ret.extend_from_slice(&(info.creator.len() as u32).to_be_bytes());
+    ret.extend_from_slice(info.creator.as_bytes());
+    ret.extend_from_slice(&(info.description.len() as u32).to_be_bytes());
+    ret.extend_from_slice(info.description.as_bytes());
+    ret.push(info.protected as u8);
+    ret.push(info.creation_version.is_some() as u8);
+    if let Some(ver) = info.creation_version.as_ref() {
+        ret.extend_from_slice(&ver.0);
+    }
+    ret
+}
+
+fn decode_br_info(bytes: &[u8]) -> Result<BranchInfo> {
+    let l = size_of::<u32>();
+    let mut rest = bytes;
+    let mut take_str = || -> Result<String> {
+        if rest.len() < l {
+            return Err(eg!("invalid branch info"));
+        }
+        let n = crate::parse_int!(rest[..l], u32) as usize;
+        if rest.len() < l + n {
+            return Err(eg!("invalid branch info"));
+        }
+        let s = String::from_utf8(rest[l..l + n].to_vec()).c(d!())?;
+        rest = &rest[l + n..];
+        Ok(s)
+    };
+    let creator = take_str().c(d!())?;
+    let description = take_str().c(d!())?;
+    if rest.len() < 2 {
+        return Err(eg!("invalid branch info"));
+    }
+    Ok(BranchInfo {
+        creator,
+        creation_version: alt!(
+            0 == rest[1],
+            None,
+            Some(VersionNameOwned(rest[2..].to_vec()))
+        ),
+        description,
+        protected: 0 != rest[0],
+    })
+}
+
+#[inline(always)]
+fn chgset_key(ver: &VersionID, key: &[u8]) -> RawBytes {
+    let mut k = Vec::with_capacity(ver.len() + key.len());
+    k.extend_from_slice(ver);
+    k.extend_from_slice(key);
+    k
+}
+
 #[inline(always)]
 fn to_brid(bytes: &[u8]) -> BranchID {
     <[u8; size_of::<BranchID>()]>::try_from(bytes).unwrap()
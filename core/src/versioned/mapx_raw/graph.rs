@@ -0,0 +1,921 @@
+//!
+//! # Version graph
+//!
+//! All versions and branches of versioned collections are managed by version graphs,
+//! every collection is attached to a private graph when being created.
+//!
+//! Collections attached to the same graph share all their versions and branches,
+//! so a `version_create` or a `branch_create` is a single operation on the graph,
+//! it takes effect on all the collections at once, no matter which one it is called on.
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb_core::{MapxRawVs, VersionGraph, VersionName, VsMgmt};
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb_core::vsdb_set_base_dir(&dir);
+//!
+//! let graph = VersionGraph::new();
+//!
+//! let mut a = MapxRawVs::new_in(&graph);
+//! let mut b = MapxRawVs::new();
+//! b.version_graph_attach(&graph).unwrap();
+//! assert_eq!(2, graph.members_num());
+//!
+//! a.version_create(VersionName(b"v1")).unwrap();
+//! assert!(b.version_exists(VersionName(b"v1")));
+//!
+//! a.insert(&[1], &[1]).unwrap();
+//! b.insert(&[2], &[2]).unwrap();
+//!
+//! // the changes of both collections are discarded at once
+//! a.version_pop().unwrap();
+//! assert!(a.get(&[1]).is_none());
+//! assert!(b.get(&[2]).is_none());
+//! ```
+//!
+
+use super::backend::{self, Graph, ProtectionOverride, VerGraph, VerGraphHeader};
+use crate::{
+    common::{BranchName, ParentBranchName, VersionName},
+    BranchInfo, BranchNameOwned, PruneProgress, PrunePolicy, PruneTask, VersionNameOwned,
+    VsMgmt,
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, result::Result as StdResult, sync::Arc};
+
+/// A version graph, can be shared by multiple versioned collections.
+#[derive(Clone, Debug)]
+pub struct VersionGraph {
+    pub(super) inner: Graph,
+}
+
+impl VersionGraph {
+    /// Create a new graph without any collections attached to it.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            inner: VerGraph::new_shared(),
+        }
+    }
+
+    /// The number of collections attached to this graph.
+    #[inline(always)]
+    pub fn members_num(&self) -> usize {
+        self.inner.read().members_num()
+    }
+
+    /// Nothing has been done on this graph since its creation,
+    /// so its members can be moved to another graph.
+    #[inline(always)]
+    pub(crate) fn is_pristine(&self) -> bool {
+        self.inner.read().is_pristine()
+    }
+
+    /// Protect a branch, data writes, truncations, version pops
+    /// and removal on it will be denied except the `*_with_override` calls,
+    /// new versions can still be created or merged into it.
+    #[inline(always)]
+    pub fn branch_protect(&mut self, br_name: BranchName) -> Result<()> {
+        self.branch_set_protected(br_name, true).c(d!())
+    }
+
+    #[inline(always)]
+    pub fn branch_unprotect(&mut self, br_name: BranchName) -> Result<()> {
+        self.branch_set_protected(br_name, false).c(d!())
+    }
+
+    fn branch_set_protected(
+        &mut self,
+        br_name: BranchName,
+        protected: bool,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        let br_id = g.branch_get_id_by_name(br_name).c(d!("branch not found"))?;
+        let info = g.branch_info(br_id).c(d!())?;
+        g.branch_set_info(br_id, &BranchInfo { protected, ..info })
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub fn branch_is_protected(&self, br_name: BranchName) -> Result<bool> {
+        let g = self.inner.read();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| g.branch_is_protected(br_id).c(d!()))
+    }
+
+    /// Issue a token for writing to a protected branch,
+    /// it must be passed to the `*_with_override` calls explicitly,
+    /// the branch stays protected against all other calls.
+    #[inline(always)]
+    pub fn branch_override_protection(
+        &self,
+        br_name: BranchName,
+    ) -> Result<ProtectionOverride> {
+        let g = self.inner.read();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| g.branch_override_protection(br_id).c(d!()))
+    }
+
+    /// Like `branch_remove`, but allowed on a protected branch
+    /// with an override issued for it,
+    /// the default branch can not be removed in any case.
+    #[inline(always)]
+    pub fn branch_remove_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+    ) -> Result<()> {
+        self.do_branch_remove(br_name, Some(ovr)).c(d!())
+    }
+
+    /// Like `branch_truncate`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn branch_truncate_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+    ) -> Result<()> {
+        self.do_branch_truncate(br_name, Some(ovr)).c(d!())
+    }
+
+    /// Like `branch_truncate_to`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn branch_truncate_to_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+        last_ver_name: VersionName,
+    ) -> Result<()> {
+        self.do_branch_truncate_to(br_name, last_ver_name, Some(ovr))
+            .c(d!())
+    }
+
+    /// Like `branch_pop_version`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn branch_pop_version_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+    ) -> Result<()> {
+        self.do_branch_pop_version(br_name, Some(ovr)).c(d!())
+    }
+
+    /// Like `branch_rename`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn branch_rename_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+        new_br_name: BranchName,
+    ) -> Result<()> {
+        self.do_branch_rename(br_name, new_br_name, Some(ovr))
+            .c(d!())
+    }
+
+    fn do_branch_remove(
+        &mut self,
+        br_name: BranchName,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        if let Some(br_id) = g.branch_get_id_by_name(br_name) {
+            g.branch_remove(br_id, ovr).c(d!())
+        } else {
+            Err(eg!("branch not found"))
+        }
+    }
+
+    fn do_branch_truncate(
+        &mut self,
+        br_name: BranchName,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| g.branch_truncate(br_id, ovr).c(d!()))
+    }
+
+    fn do_branch_truncate_to(
+        &mut self,
+        br_name: BranchName,
+        last_ver_name: VersionName,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| {
+                g.version_get_id_by_name(last_ver_name)
+                    .c(d!("version not found"))
+                    .and_then(|last_ver_id| {
+                        g.branch_truncate_to(br_id, last_ver_id, ovr).c(d!())
+                    })
+            })
+    }
+
+    fn do_branch_pop_version(
+        &mut self,
+        br_name: BranchName,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|id| g.branch_pop_version(id, ovr).c(d!()))
+    }
+
+    fn do_branch_rename(
+        &mut self,
+        br_name: BranchName,
+        new_br_name: BranchName,
+        ovr: Option<&ProtectionOverride>,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| g.branch_rename(br_id, new_br_name.0, ovr).c(d!()))
+    }
+
+    /// Prune on the background pool, at most `budget` versions per step,
+    /// the graph is only locked during each step, so writers are not stalled.
+    #[inline(always)]
+    pub fn prune_in_background(
+        &self,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> PruneTask {
+        let g = Arc::clone(&self.inner);
+        PruneTask::spawn(Box::new(move || {
+            g.write().prune_step(reserved_ver_num, budget).c(d!())
+        }))
+    }
+}
+
+impl Default for VersionGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialEq for VersionGraph {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for VersionGraph {}
+
+impl Serialize for VersionGraph {
+    fn serialize<S>(&self, serializer: S) -> StdResult<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        VersionGraphWithoutDerivedFields {
+            magic: backend::FORMAT_MAGIC,
+            format_version: backend::FORMAT_VERSION,
+            graph: self.inner.read().header(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionGraph {
+    fn deserialize<D>(deserializer: D) -> StdResult<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let g = <VersionGraphWithoutDerivedFields as Deserialize>::deserialize(
+            deserializer,
+        )?;
+        backend::check_format(g.magic, g.format_version)?;
+        Ok(Self {
+            inner: VerGraph::load_shared(g.graph),
+        })
+    }
+}
+
+// See `backend::FORMAT_MAGIC` for the layout.
+#[derive(Serialize, Deserialize)]
+struct VersionGraphWithoutDerivedFields {
+    magic: [u8; 8],
+    format_version: u32,
+    graph: VerGraphHeader,
+}
+
+impl VsMgmt for VersionGraph {
+    /// Create a new version on the default branch.
+    #[inline(always)]
+    fn version_create(&mut self, ver_name: VersionName) -> Result<()> {
+        let mut g = self.inner.write();
+        g.version_create(ver_name.0).c(d!())
+    }
+
+    /// Create a new version on a specified branch,
+    /// NOTE: the branch must has been created.
+    #[inline(always)]
+    fn version_create_by_branch(
+        &mut self,
+        ver_name: VersionName,
+        br_name: BranchName,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| g.version_create_by_branch(ver_name.0, br_id).c(d!()))
+    }
+
+    #[inline(always)]
+    fn version_exists_globally(&self, ver_name: VersionName) -> bool {
+        let g = self.inner.read();
+        g.version_get_id_by_name(ver_name)
+            .map(|verid| g.version_exists_globally(verid))
+            .unwrap_or(false)
+    }
+
+    /// Check if a verison exists on default branch.
+    #[inline(always)]
+    fn version_exists(&self, ver_name: VersionName) -> bool {
+        let g = self.inner.read();
+        g.version_get_id_by_name(ver_name)
+            .map(|id| g.version_exists(id))
+            .unwrap_or(false)
+    }
+
+    /// Check if a version exists on a specified branch(include its parents).
+    #[inline(always)]
+    fn version_exists_on_branch(
+        &self,
+        ver_name: VersionName,
+        br_name: BranchName,
+    ) -> bool {
+        let g = self.inner.read();
+        g.branch_get_id_by_name(br_name)
+            .and_then(|br_id| {
+                g.version_get_id_by_name(ver_name)
+                    .map(|ver_id| g.version_exists_on_branch(ver_id, br_id))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Remove the newest version on the default branch.
+    ///
+    /// 'Write'-like operations on branches and versions are different from operations on data.
+    ///
+    /// 'Write'-like operations on data require recursive tracing of all parent nodes,
+    /// while operations on branches and versions are limited to their own perspective,
+    /// and should not do any tracing.
+    #[inline(always)]
+    fn version_pop(&mut self) -> Result<()> {
+        let mut g = self.inner.write();
+        g.version_pop().c(d!())
+    }
+
+    /// Remove the newest version on a specified branch.
+    ///
+    /// 'Write'-like operations on branches and versions are different from operations on data.
+    ///
+    /// 'Write'-like operations on data require recursive tracing of all parent nodes,
+    /// while operations on branches and versions are limited to their own perspective,
+    /// and should not do any tracing.
+    #[inline(always)]
+    fn version_pop_by_branch(&mut self, br_name: BranchName) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| g.version_pop_by_branch(br_id, None).c(d!()))
+    }
+
+    /// Merge all changes made by new versions after the base version into the base version.
+    ///
+    /// # Safety
+    ///
+    /// It's the caller's duty to ensure that
+    /// the `base_version` was created directly by the `br_id`,
+    /// and versions newer than the `base_version` are not used by any other branches,
+    /// or the data records of other branches may be corrupted.
+    #[inline(always)]
+    unsafe fn version_rebase(&mut self, base_version: VersionName) -> Result<()> {
+        let mut g = self.inner.write();
+        g.version_get_id_by_name(base_version)
+            .c(d!())
+            .and_then(|bv| g.version_rebase(bv).c(d!()))
+    }
+
+    /// Merge all changes made by new versions after the base version into the base version.
+    ///
+    /// # Safety
+    ///
+    /// It's the caller's duty to ensure that
+    /// the `base_version` was created directly by the `br_id`,
+    /// and versions newer than the `base_version` are not used by any other branches,
+    /// or the data records of other branches may be corrupted.
+    #[inline(always)]
+    unsafe fn version_rebase_by_branch(
+        &mut self,
+        base_version: VersionName,
+        br_name: BranchName,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        let bv = g.version_get_id_by_name(base_version).c(d!())?;
+        let brid = g.branch_get_id_by_name(br_name).c(d!())?;
+        g.version_rebase_by_branch(bv, brid).c(d!())
+    }
+
+    #[inline(always)]
+    fn version_list(&self) -> Result<Vec<VersionNameOwned>> {
+        let g = self.inner.read();
+        g.version_list().c(d!())
+    }
+
+    #[inline(always)]
+    fn version_list_by_branch(
+        &self,
+        br_name: BranchName,
+    ) -> Result<Vec<VersionNameOwned>> {
+        let g = self.inner.read();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|brid| g.version_list_by_branch(brid).c(d!()))
+    }
+
+    #[inline(always)]
+    fn version_list_globally(&self) -> Vec<VersionNameOwned> {
+        let g = self.inner.read();
+        g.version_list_globally()
+    }
+
+    #[inline(always)]
+    fn version_has_change_set(&self, ver_name: VersionName) -> Result<bool> {
+        let g = self.inner.read();
+        g.version_get_id_by_name(ver_name)
+            .c(d!("version not found"))
+            .and_then(|verid| g.version_has_change_set(verid).c(d!()))
+    }
+
+    #[inline(always)]
+    fn version_clean_up_globally(&mut self) -> Result<()> {
+        let mut g = self.inner.write();
+        g.version_clean_up_globally().c(d!())
+    }
+
+    #[inline(always)]
+    unsafe fn version_revert_globally(&mut self, ver_name: VersionName) -> Result<()> {
+        let mut g = self.inner.write();
+        g.version_get_id_by_name(ver_name)
+            .c(d!("version not found"))
+            .and_then(|verid| g.version_revert_globally(verid).c(d!()))
+    }
+
+    #[inline(always)]
+    fn version_chgset_trie_root(
+        &self,
+        br_name: Option<BranchName>,
+        ver_name: Option<VersionName>,
+    ) -> Result<Vec<u8>> {
+        let g = self.inner.read();
+        let brid = if let Some(bn) = br_name {
+            Some(g.branch_get_id_by_name(bn).c(d!("version not found"))?)
+        } else {
+            None
+        };
+
+        let verid = if let Some(vn) = ver_name {
+            Some(g.version_get_id_by_name(vn).c(d!("version not found"))?)
+        } else {
+            None
+        };
+
+        g.version_chgset_trie_root(brid, verid).c(d!())
+    }
+
+    /// Create a new branch based on the head of the default branch.
+    #[inline(always)]
+    fn branch_create(
+        &mut self,
+        br_name: BranchName,
+        ver_name: VersionName,
+        force: bool,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_create(br_name.0, ver_name.0, force).c(d!())
+    }
+
+    /// Create a new branch based on the head of a specified branch.
+    #[inline(always)]
+    fn branch_create_by_base_branch(
+        &mut self,
+        br_name: BranchName,
+        ver_name: VersionName,
+        base_br_name: ParentBranchName,
+        force: bool,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(BranchName(base_br_name.0))
+            .c(d!("base branch not found"))
+            .and_then(|base_br_id| {
+                g.branch_create_by_base_branch(br_name.0, ver_name.0, base_br_id, force)
+                    .c(d!())
+            })
+    }
+
+    /// Create a new branch based on a specified version of a specified branch.
+    #[inline(always)]
+    fn branch_create_by_base_branch_version(
+        &mut self,
+        br_name: BranchName,
+        ver_name: VersionName,
+        base_br_name: ParentBranchName,
+        base_ver_name: VersionName,
+        force: bool,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        let base_br_id = g
+            .branch_get_id_by_name(BranchName(base_br_name.0))
+            .c(d!("base branch not found"))?;
+        let base_ver_id = g
+            .version_get_id_by_name(base_ver_name)
+            .c(d!("base vesion not found"))?;
+        g.branch_create_by_base_branch_version(
+            br_name.0,
+            ver_name.0,
+            base_br_id,
+            base_ver_id,
+            force,
+        )
+        .c(d!())
+    }
+
+    /// # Safety
+    ///
+    /// You should create a new version manually before writing to the new branch,
+    /// or the data records referenced by other branches may be corrupted.
+    #[inline(always)]
+    unsafe fn branch_create_without_new_version(
+        &mut self,
+        br_name: BranchName,
+        force: bool,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_create_without_new_version(br_name.0, force)
+            .c(d!())
+    }
+
+    /// # Safety
+    ///
+    /// You should create a new version manually before writing to the new branch,
+    /// or the data records referenced by other branches may be corrupted.
+    #[inline(always)]
+    unsafe fn branch_create_by_base_branch_without_new_version(
+        &mut self,
+        br_name: BranchName,
+        base_br_name: ParentBranchName,
+        force: bool,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(BranchName(base_br_name.0))
+            .c(d!("base branch not found"))
+            .and_then(|base_br_id| {
+                g.branch_create_by_base_branch_without_new_version(
+                    br_name.0, base_br_id, force,
+                )
+                .c(d!())
+            })
+    }
+
+    /// # Safety
+    ///
+    /// You should create a new version manually before writing to the new branch,
+    /// or the data records referenced by other branches may be corrupted.
+    #[inline(always)]
+    unsafe fn branch_create_by_base_branch_version_without_new_version(
+        &mut self,
+        br_name: BranchName,
+        base_br_name: ParentBranchName,
+        base_ver_name: VersionName,
+        force: bool,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        let base_br_id = g
+            .branch_get_id_by_name(BranchName(base_br_name.0))
+            .c(d!("base branch not found"))?;
+        let base_ver_id = g
+            .version_get_id_by_name(base_ver_name)
+            .c(d!("base vesion not found"))?;
+        g.branch_create_by_base_branch_version_without_new_version(
+            br_name.0,
+            base_br_id,
+            base_ver_id,
+            force,
+        )
+        .c(d!())
+    }
+
+    /// Check if a branch exists or not.
+    #[inline(always)]
+    fn branch_exists(&self, br_name: BranchName) -> bool {
+        let g = self.inner.read();
+        g.branch_get_id_by_name(br_name)
+            .map(|id| {
+                assert!(g.branch_exists(id));
+                true
+            })
+            .unwrap_or(false)
+    }
+
+    /// Check if a branch exists and has versions on it.
+    #[inline(always)]
+    fn branch_has_versions(&self, br_name: BranchName) -> bool {
+        let g = self.inner.read();
+        g.branch_get_id_by_name(br_name)
+            .map(|id| g.branch_has_versions(id))
+            .unwrap_or(false)
+    }
+
+    /// Remove a branch, remove all changes directly made by this branch.
+    ///
+    /// 'Write'-like operations on branches and versions are different from operations on data.
+    ///
+    /// 'Write'-like operations on data require recursive tracing of all parent nodes,
+    /// while operations on branches and versions are limited to their own perspective,
+    /// and should not do any tracing.
+    #[inline(always)]
+    fn branch_remove(&mut self, br_name: BranchName) -> Result<()> {
+        self.do_branch_remove(br_name, None).c(d!())
+    }
+
+    /// Clean up all other branches not in the list.
+    #[inline(always)]
+    fn branch_keep_only(&mut self, br_names: &[BranchName]) -> Result<()> {
+        let mut g = self.inner.write();
+        let br_ids = br_names
+            .iter()
+            .copied()
+            .map(|brname| g.branch_get_id_by_name(brname).c(d!("version not found")))
+            .collect::<Result<BTreeSet<_>>>()?
+            .into_iter()
+            .collect::<Vec<_>>();
+        g.branch_keep_only(&br_ids).c(d!())
+    }
+
+    /// Remove all changes directly made by versions(bigger than `last_ver_id`) of this branch.
+    ///
+    /// 'Write'-like operations on branches and versions are different from operations on data.
+    ///
+    /// 'Write'-like operations on data require recursive tracing of all parent nodes,
+    /// while operations on branches and versions are limited to their own perspective,
+    /// and should not do any tracing.
+    #[inline(always)]
+    fn branch_truncate(&mut self, br_name: BranchName) -> Result<()> {
+        self.do_branch_truncate(br_name, None).c(d!())
+    }
+
+    /// Remove all changes directly made by versions(bigger than `last_ver_id`) of this branch.
+    ///
+    /// 'Write'-like operations on branches and versions are different from operations on data.
+    ///
+    /// 'Write'-like operations on data require recursive tracing of all parent nodes,
+    /// while operations on branches and versions are limited to their own perspective,
+    /// and should not do any tracing.
+    #[inline(always)]
+    fn branch_truncate_to(
+        &mut self,
+        br_name: BranchName,
+        last_ver_name: VersionName,
+    ) -> Result<()> {
+        self.do_branch_truncate_to(br_name, last_ver_name, None)
+            .c(d!())
+    }
+
+    /// Remove the newest version on a specified branch.
+    ///
+    /// 'Write'-like operations on branches and versions are different from operations on data.
+    ///
+    /// 'Write'-like operations on data require recursive tracing of all parent nodes,
+    /// while operations on branches and versions are limited to their own perspective,
+    /// and should not do any tracing.
+    #[inline(always)]
+    fn branch_pop_version(&mut self, br_name: BranchName) -> Result<()> {
+        self.do_branch_pop_version(br_name, None).c(d!())
+    }
+
+    /// Merge a branch into another.
+    #[inline(always)]
+    fn branch_merge_to(
+        &mut self,
+        br_name: BranchName,
+        target_br_name: BranchName,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|brid| {
+                let target_brid = g
+                    .branch_get_id_by_name(target_br_name)
+                    .c(d!("target branch not found"))?;
+                g.branch_merge_to(brid, target_brid).c(d!())
+            })
+    }
+
+    /// Merge a branch into another,
+    /// even if new different versions have been created on the target branch.
+    ///
+    /// # Safety
+    ///
+    /// If new different versions have been created on the target branch,
+    /// the data records referenced by other branches may be corrupted.
+    #[inline(always)]
+    unsafe fn branch_merge_to_force(
+        &mut self,
+        br_name: BranchName,
+        target_br_name: BranchName,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|brid| {
+                let target_brid = g
+                    .branch_get_id_by_name(target_br_name)
+                    .c(d!("target branch not found"))?;
+                g.branch_merge_to_force(brid, target_brid).c(d!())
+            })
+    }
+
+    #[inline(always)]
+    fn branch_rebase_onto(
+        &mut self,
+        br_name: BranchName,
+        base_br_name: BranchName,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|brid| {
+                let base_brid = g
+                    .branch_get_id_by_name(base_br_name)
+                    .c(d!("base branch not found"))?;
+                g.branch_rebase_onto(brid, base_brid).c(d!())
+            })
+    }
+
+    /// Make a branch to be default,
+    /// all default operations will be applied to it.
+    #[inline(always)]
+    fn branch_set_default(&mut self, br_name: BranchName) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|brid| g.branch_set_default(brid).c(d!()))
+    }
+
+    #[inline(always)]
+    fn branch_is_empty(&self, br_name: BranchName) -> Result<bool> {
+        let g = self.inner.read();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|brid| g.branch_is_empty(brid).c(d!()))
+    }
+
+    #[inline(always)]
+    fn branch_list(&self) -> Vec<BranchNameOwned> {
+        let g = self.inner.read();
+        g.branch_list()
+    }
+
+    #[inline(always)]
+    fn branch_get_default(&self) -> BranchNameOwned {
+        let g = self.inner.read();
+        g.branch_get_default_name()
+    }
+
+    #[inline(always)]
+    fn branch_merge_base(
+        &self,
+        br_name: BranchName,
+        other_br_name: BranchName,
+    ) -> Result<Option<VersionNameOwned>> {
+        let g = self.inner.read();
+        let br_id = g.branch_get_id_by_name(br_name).c(d!("branch not found"))?;
+        let other_br_id = g
+            .branch_get_id_by_name(other_br_name)
+            .c(d!("other branch not found"))?;
+        g.branch_merge_base(br_id, other_br_id).c(d!())
+    }
+
+    #[inline(always)]
+    fn version_is_ancestor(
+        &self,
+        ver_name: VersionName,
+        br_name: BranchName,
+    ) -> Result<bool> {
+        let g = self.inner.read();
+        let ver_id = g
+            .version_get_id_by_name(ver_name)
+            .c(d!("version not found"))?;
+        let br_id = g.branch_get_id_by_name(br_name).c(d!("branch not found"))?;
+        g.version_is_ancestor(ver_id, br_id).c(d!())
+    }
+
+    #[inline(always)]
+    fn branches_containing(
+        &self,
+        ver_name: VersionName,
+    ) -> Result<Vec<BranchNameOwned>> {
+        let g = self.inner.read();
+        g.version_get_id_by_name(ver_name)
+            .c(d!("version not found"))
+            .map(|ver_id| g.branches_containing(ver_id))
+    }
+
+    #[inline(always)]
+    fn branch_parent(&self, br_name: BranchName) -> Result<Option<BranchNameOwned>> {
+        let g = self.inner.read();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| g.branch_parent(br_id).c(d!()))
+    }
+
+    #[inline(always)]
+    fn branch_rename(
+        &mut self,
+        br_name: BranchName,
+        new_br_name: BranchName,
+    ) -> Result<()> {
+        self.do_branch_rename(br_name, new_br_name, None).c(d!())
+    }
+
+    #[inline(always)]
+    fn branch_info(&self, br_name: BranchName) -> Result<BranchInfo> {
+        let g = self.inner.read();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| g.branch_info(br_id).c(d!()))
+    }
+
+    #[inline(always)]
+    fn branch_set_info(&mut self, br_name: BranchName, info: &BranchInfo) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| g.branch_set_info(br_id, info).c(d!()))
+    }
+
+    #[inline(always)]
+    unsafe fn branch_swap(
+        &mut self,
+        branch_1: BranchName,
+        branch_2: BranchName,
+    ) -> Result<()> {
+        let mut g = self.inner.write();
+        g.branch_swap(branch_1.0, branch_2.0).c(d!())
+    }
+
+    /// Clean outdated versions out of the default reserved number.
+    #[inline(always)]
+    fn prune(&mut self, reserved_ver_num: Option<usize>) -> Result<()> {
+        let mut g = self.inner.write();
+        g.prune(reserved_ver_num).c(d!())
+    }
+
+    #[inline(always)]
+    fn prune_with_policy(&mut self, policy: &dyn PrunePolicy) -> Result<()> {
+        let mut g = self.inner.write();
+        g.prune_with_policy(policy).c(d!())
+    }
+
+    #[inline(always)]
+    fn prune_step(
+        &mut self,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> Result<PruneProgress> {
+        self.inner
+            .write()
+            .prune_step(reserved_ver_num, budget)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    fn version_graphs(&self) -> Option<Vec<VersionGraph>> {
+        Some(vec![self.clone()])
+    }
+
+    /// A version graph can not be attached to another one.
+    #[inline(always)]
+    fn version_graph_attach(&mut self, graph: &VersionGraph) -> Result<()> {
+        if self == graph {
+            Ok(())
+        } else {
+            Err(eg!("a version graph can not be attached to another one"))
+        }
+    }
+}
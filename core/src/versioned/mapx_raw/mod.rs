@@ -33,24 +33,30 @@
 //!
 
 mod backend;
+mod graph;
 
 #[cfg(test)]
 mod test;
 
 use crate::{
-    common::{BranchName, ParentBranchName, RawKey, RawValue, VersionName, NULL_ID},
-    BranchNameOwned, VersionNameOwned, VsMgmt,
+    basic::mapx_raw::MapxRawBatch,
+    common::{
+        BranchName, ParentBranchName, PreBytes, RawKey, RawValue, Registrable,
+        VersionName, NULL_ID,
+    },
+    BranchInfo, BranchNameOwned, PruneProgress, PrunePolicy, PruneTask, VersionNameOwned,
+    VsDiff, VsMgmt,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
-    collections::BTreeSet,
     mem::transmute,
     ops::{Deref, DerefMut, RangeBounds},
 };
 
-pub use backend::MapxRawVsIter;
+pub use backend::{MapxRawVsIter, ProtectionOverride};
+pub use graph::VersionGraph;
 
 /// Advanced `MapxRaw`, with versioned feature.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -84,6 +90,46 @@ impl MapxRawVs {
         }
     }
 
+    /// Create an instance attached to an existing version graph.
+    #[inline(always)]
+    pub fn new_in(graph: &VersionGraph) -> Self {
+        Self {
+            inner: backend::MapxRawVs::new_in(&graph.inner),
+        }
+    }
+
+    /// A cheap copy-on-write copy based on the head of the default branch,
+    /// all existing data are shared, nothing is copied.
+    ///
+    /// The copy shares the version graph with this instance,
+    /// it writes to a new branch created for it, which is used as its default branch.
+    ///
+    /// A new version is created on the base branch at the same time,
+    /// so the later writes of this instance are invisible to the copy,
+    /// call `branch_rebase_onto` and then `branch_merge_to` on this instance
+    /// to keep the changes of the copy.
+    ///
+    /// The new branch is removed after the copy has been dropped,
+    /// unless the copy has been persisted(serialized).
+    #[inline(always)]
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self { inner })
+    }
+
+    // The own branch of a fork, used instead of the default branch of the graph.
+    #[inline(always)]
+    fn fork_branch(&self) -> Option<BranchNameOwned> {
+        self.inner.fork_branch()
+    }
+
+    /// The version graph this instance is attached to.
+    #[inline(always)]
+    pub fn version_graph(&self) -> VersionGraph {
+        VersionGraph {
+            inner: self.inner.graph().clone(),
+        }
+    }
+
     /// Insert a KV to the head version of the default branch.
     #[inline(always)]
     pub fn insert(
@@ -104,7 +150,23 @@ impl MapxRawVs {
     ) -> Result<Option<RawValue>> {
         let br_id = self.inner.branch_get_id_by_name(br_name).c(d!())?;
         self.inner
-            .insert_by_branch(key.as_ref(), value.as_ref(), br_id)
+            .insert_by_branch(key.as_ref(), value.as_ref(), br_id, None)
+            .c(d!())
+    }
+
+    /// Like `insert_by_branch`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn insert_by_branch_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+        br_name: BranchName,
+    ) -> Result<Option<RawValue>> {
+        let br_id = self.inner.branch_get_id_by_name(br_name).c(d!())?;
+        self.inner
+            .insert_by_branch(key.as_ref(), value.as_ref(), br_id, Some(ovr))
             .c(d!())
     }
 
@@ -122,7 +184,59 @@ impl MapxRawVs {
         br_name: BranchName,
     ) -> Result<Option<RawValue>> {
         let br_id = self.inner.branch_get_id_by_name(br_name).c(d!())?;
-        self.inner.remove_by_branch(key.as_ref(), br_id).c(d!())
+        self.inner
+            .remove_by_branch(key.as_ref(), br_id, None)
+            .c(d!())
+    }
+
+    /// Like `remove_by_branch`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn remove_by_branch_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        key: impl AsRef<[u8]>,
+        br_name: BranchName,
+    ) -> Result<Option<RawValue>> {
+        let br_id = self.inner.branch_get_id_by_name(br_name).c(d!())?;
+        self.inner
+            .remove_by_branch(key.as_ref(), br_id, Some(ovr))
+            .c(d!())
+    }
+
+    /// Remove all the KVs whose keys start with `prefix`
+    /// from the head version of the default branch,
+    /// return the number of them.
+    pub fn remove_prefix(&mut self, prefix: impl AsRef<[u8]>) -> Result<usize> {
+        let keys = self
+            .inner
+            .iter_prefix(prefix.as_ref())
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+        for k in keys.iter() {
+            self.inner.remove(k).c(d!())?;
+        }
+        Ok(keys.len())
+    }
+
+    /// Remove all the KVs whose keys start with `prefix`
+    /// from the head version of a specified branch,
+    /// return the number of them.
+    pub fn remove_prefix_by_branch(
+        &mut self,
+        prefix: impl AsRef<[u8]>,
+        br_name: BranchName,
+    ) -> Result<usize> {
+        let br_id = self.inner.branch_get_id_by_name(br_name).c(d!())?;
+        let keys = self
+            .inner
+            .iter_prefix_by_branch(br_id, prefix.as_ref())
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+        for k in keys.iter() {
+            self.inner.remove_by_branch(k, br_id, None).c(d!())?;
+        }
+        Ok(keys.len())
     }
 
     /// Get the value of a key from the default branch.
@@ -335,6 +449,43 @@ impl MapxRawVs {
         self.inner.range_by_branch_version(br_id, ver_id, bounds)
     }
 
+    /// Create an iterator over the KVs whose keys start with `prefix`
+    /// on the default branch.
+    #[inline(always)]
+    pub fn iter_prefix(&self, prefix: impl AsRef<[u8]>) -> MapxRawVsIter {
+        self.inner.iter_prefix(prefix.as_ref())
+    }
+
+    /// Create an iterator over the KVs whose keys start with `prefix`
+    /// on a specified branch.
+    #[inline(always)]
+    pub fn iter_prefix_by_branch(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        br_name: BranchName,
+    ) -> MapxRawVsIter {
+        let br_id = self.inner.branch_get_id_by_name(br_name).unwrap_or(NULL_ID);
+        self.inner.iter_prefix_by_branch(br_id, prefix.as_ref())
+    }
+
+    /// Create an iterator over the KVs whose keys start with `prefix`
+    /// on a specified version of a specified branch.
+    #[inline(always)]
+    pub fn iter_prefix_by_branch_version(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> MapxRawVsIter {
+        let br_id = self.inner.branch_get_id_by_name(br_name).unwrap_or(NULL_ID);
+        let ver_id = self
+            .inner
+            .version_get_id_by_name(ver_name)
+            .unwrap_or(NULL_ID);
+        self.inner
+            .iter_prefix_by_branch_version(br_id, ver_id, prefix.as_ref())
+    }
+
     /// Check if a key exist on the default branch.
     #[inline(always)]
     pub fn contains_key(&self, key: impl AsRef<[u8]>) -> bool {
@@ -425,18 +576,247 @@ impl MapxRawVs {
             .is_none()
     }
 
+    /// The changes made from `ver_a` to `ver_b` on the default branch,
+    /// every changed key is listed with its value in `ver_b`,
+    /// a `None` value means that the key does not exist in `ver_b`.
+    #[inline(always)]
+    pub fn diff(
+        &self,
+        ver_a: VersionName,
+        ver_b: VersionName,
+    ) -> Result<Vec<(RawKey, Option<RawValue>)>> {
+        self.diff_by_branch(self.branch_get_default().as_deref(), ver_a, ver_b)
+            .c(d!())
+    }
+
+    /// The changes made from `ver_a` to `ver_b` on a specified branch.
+    #[inline(always)]
+    pub fn diff_by_branch(
+        &self,
+        br_name: BranchName,
+        ver_a: VersionName,
+        ver_b: VersionName,
+    ) -> Result<Vec<(RawKey, Option<RawValue>)>> {
+        let br_id = self
+            .inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))?;
+        let ver_a = self
+            .inner
+            .version_get_id_by_name(ver_a)
+            .c(d!("version not found"))?;
+        let ver_b = self
+            .inner
+            .version_get_id_by_name(ver_b)
+            .c(d!("version not found"))?;
+        self.inner.diff_by_branch(br_id, ver_a, ver_b).c(d!())
+    }
+
+    /// Apply changes, eg. the result of [diff](Self::diff),
+    /// to the head of the default branch.
+    #[inline(always)]
+    pub fn apply_diff(&mut self, diff: &[(RawKey, Option<RawValue>)]) -> Result<()> {
+        for (k, v) in diff.iter() {
+            if let Some(v) = v {
+                self.insert(k, v).c(d!())?;
+            } else {
+                self.remove(k).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The keys of this instance that have been changed both on `br_name`
+    /// and on `base_br_name` since they forked,
+    /// a [branch_rebase_onto](VsMgmt::branch_rebase_onto) will fail if it is not empty.
+    #[inline(always)]
+    pub fn branch_rebase_conflicts(
+        &self,
+        br_name: BranchName,
+        base_br_name: BranchName,
+    ) -> Result<Vec<RawKey>> {
+        let br_id = self
+            .inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))?;
+        let base_br_id = self
+            .inner
+            .branch_get_id_by_name(base_br_name)
+            .c(d!("base branch not found"))?;
+        self.inner.branch_rebase_conflicts(br_id, base_br_id).c(d!())
+    }
+
+    /// Protect a branch, see [VersionGraph::branch_protect] for details.
+    #[inline(always)]
+    pub fn branch_protect(&mut self, br_name: BranchName) -> Result<()> {
+        self.version_graph().branch_protect(br_name).c(d!())
+    }
+
+    #[inline(always)]
+    pub fn branch_unprotect(&mut self, br_name: BranchName) -> Result<()> {
+        self.version_graph().branch_unprotect(br_name).c(d!())
+    }
+
+    #[inline(always)]
+    pub fn branch_is_protected(&self, br_name: BranchName) -> Result<bool> {
+        self.version_graph().branch_is_protected(br_name).c(d!())
+    }
+
+    /// Issue a token for writing to a protected branch,
+    /// see [VersionGraph::branch_override_protection] for details,
+    /// the token is accepted by all collections sharing the same version graph.
+    #[inline(always)]
+    pub fn branch_override_protection(
+        &self,
+        br_name: BranchName,
+    ) -> Result<ProtectionOverride> {
+        self.version_graph().branch_override_protection(br_name).c(d!())
+    }
+
+    /// Like `branch_remove`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn branch_remove_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+    ) -> Result<()> {
+        if self.fork_branch().map(|br| br.0 == br_name.0).unwrap_or(false) {
+            return Err(eg!("the default branch of a fork can not be removed"));
+        }
+        self.version_graph()
+            .branch_remove_with_override(ovr, br_name)
+            .c(d!())
+    }
+
+    /// Like `branch_truncate`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn branch_truncate_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+    ) -> Result<()> {
+        self.version_graph()
+            .branch_truncate_with_override(ovr, br_name)
+            .c(d!())
+    }
+
+    /// Like `branch_truncate_to`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn branch_truncate_to_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+        last_ver_name: VersionName,
+    ) -> Result<()> {
+        self.version_graph()
+            .branch_truncate_to_with_override(ovr, br_name, last_ver_name)
+            .c(d!())
+    }
+
+    /// Like `branch_pop_version`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn branch_pop_version_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+    ) -> Result<()> {
+        self.version_graph()
+            .branch_pop_version_with_override(ovr, br_name)
+            .c(d!())
+    }
+
+    /// Like `branch_rename`, but allowed on a protected branch
+    /// with an override issued for it.
+    #[inline(always)]
+    pub fn branch_rename_with_override(
+        &mut self,
+        ovr: &ProtectionOverride,
+        br_name: BranchName,
+        new_br_name: BranchName,
+    ) -> Result<()> {
+        self.version_graph()
+            .branch_rename_with_override(ovr, br_name, new_br_name)
+            .c(d!())
+    }
+
+    /// Prune the version graph on the background pool,
+    /// see `VersionGraph::prune_in_background`.
+    #[inline(always)]
+    pub fn prune_in_background(
+        &self,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> PruneTask {
+        self.version_graph()
+            .prune_in_background(reserved_ver_num, budget)
+    }
+
     /// Clear all data, mainly for testing purpose.
     #[inline(always)]
     pub fn clear(&mut self) {
         self.inner.clear();
     }
+
+    /// All keys that exist in any version of any branch,
+    /// the ones that have been deleted but not pruned are also included.
+    #[inline(always)]
+    pub fn keys_globally(&self) -> impl Iterator<Item = RawKey> + '_ {
+        self.inner.keys_globally()
+    }
+
+    /// Rewrite all existing values of the key in all branches and versions,
+    /// mainly used when migrating the schema of the stored values.
+    ///
+    /// The writes are added to `batch`,
+    /// nothing will be changed until it is committed.
+    #[inline(always)]
+    pub fn rewrite_values_of(
+        &self,
+        key: impl AsRef<[u8]>,
+        mut f: impl FnMut(&[u8]) -> Result<RawValue>,
+        batch: &mut MapxRawBatch,
+    ) -> Result<()> {
+        self.inner
+            .rewrite_values_of(key.as_ref(), &mut f, batch)
+            .c(d!())
+    }
+}
+
+impl Registrable for MapxRawVs {
+    const KIND: &'static str = "MapxRawVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl VsDiff for MapxRawVs {
+    type Diff = Vec<(RawKey, Option<RawValue>)>;
+
+    #[inline(always)]
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        MapxRawVs::diff(self, ver_a, ver_b).c(d!())
+    }
+
+    #[inline(always)]
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        MapxRawVs::apply_diff(self, diff).c(d!())
+    }
 }
 
 impl VsMgmt for MapxRawVs {
     /// Create a new version on the default branch.
     #[inline(always)]
     fn version_create(&mut self, ver_name: VersionName) -> Result<()> {
-        self.inner.version_create(ver_name.0).c(d!())
+        if let Some(br) = self.fork_branch() {
+            return self.version_create_by_branch(ver_name, br.as_deref()).c(d!());
+        }
+        self.version_graph().version_create(ver_name).c(d!())
     }
 
     /// Create a new version on a specified branch,
@@ -447,31 +827,21 @@ impl VsMgmt for MapxRawVs {
         ver_name: VersionName,
         br_name: BranchName,
     ) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .c(d!("branch not found"))
-            .and_then(|br_id| {
-                self.inner
-                    .version_create_by_branch(ver_name.0, br_id)
-                    .c(d!())
-            })
+        self.version_graph().version_create_by_branch(ver_name, br_name).c(d!())
     }
 
     #[inline(always)]
     fn version_exists_globally(&self, ver_name: VersionName) -> bool {
-        self.inner
-            .version_get_id_by_name(ver_name)
-            .map(|verid| self.inner.version_exists_globally(verid))
-            .unwrap_or(false)
+        self.version_graph().version_exists_globally(ver_name)
     }
 
     /// Check if a verison exists on default branch.
     #[inline(always)]
     fn version_exists(&self, ver_name: VersionName) -> bool {
-        self.inner
-            .version_get_id_by_name(ver_name)
-            .map(|id| self.inner.version_exists(id))
-            .unwrap_or(false)
+        if let Some(br) = self.fork_branch() {
+            return self.version_exists_on_branch(ver_name, br.as_deref());
+        }
+        self.version_graph().version_exists(ver_name)
     }
 
     /// Check if a version exists on a specified branch(include its parents).
@@ -481,14 +851,7 @@ impl VsMgmt for MapxRawVs {
         ver_name: VersionName,
         br_name: BranchName,
     ) -> bool {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .and_then(|br_id| {
-                self.inner
-                    .version_get_id_by_name(ver_name)
-                    .map(|ver_id| self.inner.version_exists_on_branch(ver_id, br_id))
-            })
-            .unwrap_or(false)
+        self.version_graph().version_exists_on_branch(ver_name, br_name)
     }
 
     /// Remove the newest version on the default branch.
@@ -500,7 +863,10 @@ impl VsMgmt for MapxRawVs {
     /// and should not do any tracing.
     #[inline(always)]
     fn version_pop(&mut self) -> Result<()> {
-        self.inner.version_pop().c(d!())
+        if let Some(br) = self.fork_branch() {
+            return self.version_pop_by_branch(br.as_deref()).c(d!());
+        }
+        self.version_graph().version_pop().c(d!())
     }
 
     /// Remove the newest version on a specified branch.
@@ -512,10 +878,7 @@ impl VsMgmt for MapxRawVs {
     /// and should not do any tracing.
     #[inline(always)]
     fn version_pop_by_branch(&mut self, br_name: BranchName) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .c(d!("branch not found"))
-            .and_then(|br_id| self.inner.version_pop_by_branch(br_id).c(d!()))
+        self.version_graph().version_pop_by_branch(br_name).c(d!())
     }
 
     /// Merge all changes made by new versions after the base version into the base version.
@@ -528,10 +891,12 @@ impl VsMgmt for MapxRawVs {
     /// or the data records of other branches may be corrupted.
     #[inline(always)]
     unsafe fn version_rebase(&mut self, base_version: VersionName) -> Result<()> {
-        self.inner
-            .version_get_id_by_name(base_version)
-            .c(d!())
-            .and_then(|bv| self.inner.version_rebase(bv).c(d!()))
+        if let Some(br) = self.fork_branch() {
+            return self
+                .version_rebase_by_branch(base_version, br.as_deref())
+                .c(d!());
+        }
+        self.version_graph().version_rebase(base_version).c(d!())
     }
 
     /// Merge all changes made by new versions after the base version into the base version.
@@ -548,14 +913,15 @@ impl VsMgmt for MapxRawVs {
         base_version: VersionName,
         br_name: BranchName,
     ) -> Result<()> {
-        let bv = self.inner.version_get_id_by_name(base_version).c(d!())?;
-        let brid = self.inner.branch_get_id_by_name(br_name).c(d!())?;
-        self.inner.version_rebase_by_branch(bv, brid).c(d!())
+        self.version_graph().version_rebase_by_branch(base_version, br_name).c(d!())
     }
 
     #[inline(always)]
     fn version_list(&self) -> Result<Vec<VersionNameOwned>> {
-        self.inner.version_list().c(d!())
+        if let Some(br) = self.fork_branch() {
+            return self.version_list_by_branch(br.as_deref()).c(d!());
+        }
+        self.version_graph().version_list().c(d!())
     }
 
     #[inline(always)]
@@ -563,15 +929,12 @@ impl VsMgmt for MapxRawVs {
         &self,
         br_name: BranchName,
     ) -> Result<Vec<VersionNameOwned>> {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .c(d!("branch not found"))
-            .and_then(|brid| self.inner.version_list_by_branch(brid).c(d!()))
+        self.version_graph().version_list_by_branch(br_name).c(d!())
     }
 
     #[inline(always)]
     fn version_list_globally(&self) -> Vec<VersionNameOwned> {
-        self.inner.version_list_globally()
+        self.version_graph().version_list_globally()
     }
 
     #[inline(always)]
@@ -584,15 +947,12 @@ impl VsMgmt for MapxRawVs {
 
     #[inline(always)]
     fn version_clean_up_globally(&mut self) -> Result<()> {
-        self.inner.version_clean_up_globally().c(d!())
+        self.version_graph().version_clean_up_globally().c(d!())
     }
 
     #[inline(always)]
     unsafe fn version_revert_globally(&mut self, ver_name: VersionName) -> Result<()> {
-        self.inner
-            .version_get_id_by_name(ver_name)
-            .c(d!("version not found"))
-            .and_then(|verid| self.inner.version_revert_globally(verid).c(d!()))
+        self.version_graph().version_revert_globally(ver_name).c(d!())
     }
 
     #[inline(always)]
@@ -632,9 +992,17 @@ impl VsMgmt for MapxRawVs {
         ver_name: VersionName,
         force: bool,
     ) -> Result<()> {
-        self.inner
-            .branch_create(br_name.0, ver_name.0, force)
-            .c(d!())
+        if let Some(br) = self.fork_branch() {
+            return self
+                .branch_create_by_base_branch(
+                    br_name,
+                    ver_name,
+                    ParentBranchName(&br.0),
+                    force,
+                )
+                .c(d!());
+        }
+        self.version_graph().branch_create(br_name, ver_name, force).c(d!())
     }
 
     /// Create a new branch based on the head of a specified branch.
@@ -646,16 +1014,7 @@ impl VsMgmt for MapxRawVs {
         base_br_name: ParentBranchName,
         force: bool,
     ) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(BranchName(base_br_name.0))
-            .c(d!("base branch not found"))
-            .and_then(|base_br_id| {
-                self.inner
-                    .branch_create_by_base_branch(
-                        br_name.0, ver_name.0, base_br_id, force,
-                    )
-                    .c(d!())
-            })
+        self.version_graph().branch_create_by_base_branch(br_name, ver_name, base_br_name, force).c(d!())
     }
 
     /// Create a new branch based on a specified version of a specified branch.
@@ -668,23 +1027,7 @@ impl VsMgmt for MapxRawVs {
         base_ver_name: VersionName,
         force: bool,
     ) -> Result<()> {
-        let base_br_id = self
-            .inner
-            .branch_get_id_by_name(BranchName(base_br_name.0))
-            .c(d!("base branch not found"))?;
-        let base_ver_id = self
-            .inner
-            .version_get_id_by_name(base_ver_name)
-            .c(d!("base vesion not found"))?;
-        self.inner
-            .branch_create_by_base_branch_version(
-                br_name.0,
-                ver_name.0,
-                base_br_id,
-                base_ver_id,
-                force,
-            )
-            .c(d!())
+        self.version_graph().branch_create_by_base_branch_version(br_name, ver_name, base_br_name, base_ver_name, force).c(d!())
     }
 
     /// # Safety
@@ -697,9 +1040,16 @@ impl VsMgmt for MapxRawVs {
         br_name: BranchName,
         force: bool,
     ) -> Result<()> {
-        self.inner
-            .branch_create_without_new_version(br_name.0, force)
-            .c(d!())
+        if let Some(br) = self.fork_branch() {
+            return self
+                .branch_create_by_base_branch_without_new_version(
+                    br_name,
+                    ParentBranchName(&br.0),
+                    force,
+                )
+                .c(d!());
+        }
+        self.version_graph().branch_create_without_new_version(br_name, force).c(d!())
     }
 
     /// # Safety
@@ -713,16 +1063,7 @@ impl VsMgmt for MapxRawVs {
         base_br_name: ParentBranchName,
         force: bool,
     ) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(BranchName(base_br_name.0))
-            .c(d!("base branch not found"))
-            .and_then(|base_br_id| {
-                self.inner
-                    .branch_create_by_base_branch_without_new_version(
-                        br_name.0, base_br_id, force,
-                    )
-                    .c(d!())
-            })
+        self.version_graph().branch_create_by_base_branch_without_new_version(br_name, base_br_name, force).c(d!())
     }
 
     /// # Safety
@@ -737,43 +1078,19 @@ impl VsMgmt for MapxRawVs {
         base_ver_name: VersionName,
         force: bool,
     ) -> Result<()> {
-        let base_br_id = self
-            .inner
-            .branch_get_id_by_name(BranchName(base_br_name.0))
-            .c(d!("base branch not found"))?;
-        let base_ver_id = self
-            .inner
-            .version_get_id_by_name(base_ver_name)
-            .c(d!("base vesion not found"))?;
-        self.inner
-            .branch_create_by_base_branch_version_without_new_version(
-                br_name.0,
-                base_br_id,
-                base_ver_id,
-                force,
-            )
-            .c(d!())
+        self.version_graph().branch_create_by_base_branch_version_without_new_version(br_name, base_br_name, base_ver_name, force).c(d!())
     }
 
     /// Check if a branch exists or not.
     #[inline(always)]
     fn branch_exists(&self, br_name: BranchName) -> bool {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .map(|id| {
-                assert!(self.inner.branch_exists(id));
-                true
-            })
-            .unwrap_or(false)
+        self.version_graph().branch_exists(br_name)
     }
 
     /// Check if a branch exists and has versions on it.
     #[inline(always)]
     fn branch_has_versions(&self, br_name: BranchName) -> bool {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .map(|id| self.inner.branch_has_versions(id))
-            .unwrap_or(false)
+        self.version_graph().branch_has_versions(br_name)
     }
 
     /// Remove a branch, remove all changes directly made by this branch.
@@ -785,28 +1102,21 @@ impl VsMgmt for MapxRawVs {
     /// and should not do any tracing.
     #[inline(always)]
     fn branch_remove(&mut self, br_name: BranchName) -> Result<()> {
-        if let Some(br_id) = self.inner.branch_get_id_by_name(br_name) {
-            self.inner.branch_remove(br_id).c(d!())
-        } else {
-            Err(eg!("branch not found"))
+        if self.fork_branch().map(|br| br.0 == br_name.0).unwrap_or(false) {
+            return Err(eg!("the default branch of a fork can not be removed"));
         }
+        self.version_graph().branch_remove(br_name).c(d!())
     }
 
     /// Clean up all other branches not in the list.
     #[inline(always)]
     fn branch_keep_only(&mut self, br_names: &[BranchName]) -> Result<()> {
-        let br_ids = br_names
-            .iter()
-            .copied()
-            .map(|brname| {
-                self.inner
-                    .branch_get_id_by_name(brname)
-                    .c(d!("version not found"))
-            })
-            .collect::<Result<BTreeSet<_>>>()?
-            .into_iter()
-            .collect::<Vec<_>>();
-        self.inner.branch_keep_only(&br_ids).c(d!())
+        if let Some(br) = self.fork_branch() {
+            let mut br_names = br_names.to_vec();
+            br_names.push(br.as_deref());
+            return self.version_graph().branch_keep_only(&br_names).c(d!());
+        }
+        self.version_graph().branch_keep_only(br_names).c(d!())
     }
 
     /// Remove all changes directly made by versions(bigger than `last_ver_id`) of this branch.
@@ -818,10 +1128,7 @@ impl VsMgmt for MapxRawVs {
     /// and should not do any tracing.
     #[inline(always)]
     fn branch_truncate(&mut self, br_name: BranchName) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .c(d!("branch not found"))
-            .and_then(|br_id| self.inner.branch_truncate(br_id).c(d!()))
+        self.version_graph().branch_truncate(br_name).c(d!())
     }
 
     /// Remove all changes directly made by versions(bigger than `last_ver_id`) of this branch.
@@ -837,17 +1144,7 @@ impl VsMgmt for MapxRawVs {
         br_name: BranchName,
         last_ver_name: VersionName,
     ) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .c(d!("branch not found"))
-            .and_then(|br_id| {
-                self.inner
-                    .version_get_id_by_name(last_ver_name)
-                    .c(d!("version not found"))
-                    .and_then(|last_ver_id| {
-                        self.inner.branch_truncate_to(br_id, last_ver_id).c(d!())
-                    })
-            })
+        self.version_graph().branch_truncate_to(br_name, last_ver_name).c(d!())
     }
 
     /// Remove the newest version on a specified branch.
@@ -859,10 +1156,7 @@ impl VsMgmt for MapxRawVs {
     /// and should not do any tracing.
     #[inline(always)]
     fn branch_pop_version(&mut self, br_name: BranchName) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .c(d!("branch not found"))
-            .and_then(|id| self.inner.branch_pop_version(id).c(d!()))
+        self.version_graph().branch_pop_version(br_name).c(d!())
     }
 
     /// Merge a branch into another.
@@ -872,16 +1166,7 @@ impl VsMgmt for MapxRawVs {
         br_name: BranchName,
         target_br_name: BranchName,
     ) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .c(d!("branch not found"))
-            .and_then(|brid| {
-                let target_brid = self
-                    .inner
-                    .branch_get_id_by_name(target_br_name)
-                    .c(d!("target branch not found"))?;
-                self.inner.branch_merge_to(brid, target_brid).c(d!())
-            })
+        self.version_graph().branch_merge_to(br_name, target_br_name).c(d!())
     }
 
     /// Merge a branch into another,
@@ -897,26 +1182,32 @@ impl VsMgmt for MapxRawVs {
         br_name: BranchName,
         target_br_name: BranchName,
     ) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .c(d!("branch not found"))
-            .and_then(|brid| {
-                let target_brid = self
-                    .inner
-                    .branch_get_id_by_name(target_br_name)
-                    .c(d!("target branch not found"))?;
-                self.inner.branch_merge_to_force(brid, target_brid).c(d!())
-            })
+        self.version_graph().branch_merge_to_force(br_name, target_br_name).c(d!())
+    }
+
+    /// Re-parent the versions created on a branch onto the head of another branch,
+    /// every one of them is replayed as a new version with the same name.
+    ///
+    /// Fails without any change if some data have been changed on both branches
+    /// since they forked, the conflicting keys are listed in the error,
+    /// they can also be found out by `branch_rebase_conflicts`.
+    #[inline(always)]
+    fn branch_rebase_onto(
+        &mut self,
+        br_name: BranchName,
+        base_br_name: BranchName,
+    ) -> Result<()> {
+        self.version_graph().branch_rebase_onto(br_name, base_br_name).c(d!())
     }
 
     /// Make a branch to be default,
     /// all default operations will be applied to it.
     #[inline(always)]
     fn branch_set_default(&mut self, br_name: BranchName) -> Result<()> {
-        self.inner
-            .branch_get_id_by_name(br_name)
-            .c(d!("branch not found"))
-            .and_then(|brid| self.inner.branch_set_default(brid).c(d!()))
+        if self.fork_branch().is_some() {
+            return Err(eg!("the default branch of a fork can not be changed"));
+        }
+        self.version_graph().branch_set_default(br_name).c(d!())
     }
 
     #[inline(always)]
@@ -929,12 +1220,62 @@ impl VsMgmt for MapxRawVs {
 
     #[inline(always)]
     fn branch_list(&self) -> Vec<BranchNameOwned> {
-        self.inner.branch_list()
+        self.version_graph().branch_list()
     }
 
     #[inline(always)]
     fn branch_get_default(&self) -> BranchNameOwned {
-        self.inner.branch_get_default_name()
+        if let Some(br) = self.fork_branch() {
+            return br;
+        }
+        self.version_graph().branch_get_default()
+    }
+
+    #[inline(always)]
+    fn branch_merge_base(
+        &self,
+        br_name: BranchName,
+        other_br_name: BranchName,
+    ) -> Result<Option<VersionNameOwned>> {
+        self.version_graph().branch_merge_base(br_name, other_br_name).c(d!())
+    }
+
+    #[inline(always)]
+    fn version_is_ancestor(
+        &self,
+        ver_name: VersionName,
+        br_name: BranchName,
+    ) -> Result<bool> {
+        self.version_graph().version_is_ancestor(ver_name, br_name).c(d!())
+    }
+
+    #[inline(always)]
+    fn branches_containing(&self, ver_name: VersionName) -> Result<Vec<BranchNameOwned>> {
+        self.version_graph().branches_containing(ver_name).c(d!())
+    }
+
+    #[inline(always)]
+    fn branch_parent(&self, br_name: BranchName) -> Result<Option<BranchNameOwned>> {
+        self.version_graph().branch_parent(br_name).c(d!())
+    }
+
+    #[inline(always)]
+    fn branch_rename(
+        &mut self,
+        br_name: BranchName,
+        new_br_name: BranchName,
+    ) -> Result<()> {
+        self.version_graph().branch_rename(br_name, new_br_name).c(d!())
+    }
+
+    #[inline(always)]
+    fn branch_info(&self, br_name: BranchName) -> Result<BranchInfo> {
+        self.version_graph().branch_info(br_name).c(d!())
+    }
+
+    #[inline(always)]
+    fn branch_set_info(&mut self, br_name: BranchName, info: &BranchInfo) -> Result<()> {
+        self.version_graph().branch_set_info(br_name, info).c(d!())
     }
 
     #[inline(always)]
@@ -943,16 +1284,47 @@ impl VsMgmt for MapxRawVs {
         branch_1: BranchName,
         branch_2: BranchName,
     ) -> Result<()> {
-        self.inner.branch_swap(branch_1.0, branch_2.0).c(d!())
+        self.version_graph().branch_swap(branch_1, branch_2).c(d!())
     }
 
     /// Clean outdated versions out of the default reserved number.
     #[inline(always)]
     fn prune(&mut self, reserved_ver_num: Option<usize>) -> Result<()> {
-        self.inner.prune(reserved_ver_num).c(d!())
+        self.version_graph().prune(reserved_ver_num).c(d!())
+    }
+
+    /// Clean outdated versions that are not kept by the policy.
+    #[inline(always)]
+    fn prune_with_policy(&mut self, policy: &dyn PrunePolicy) -> Result<()> {
+        self.version_graph().prune_with_policy(policy).c(d!())
+    }
+
+    /// Clean at most `budget` outdated versions.
+    #[inline(always)]
+    fn prune_step(
+        &mut self,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> Result<PruneProgress> {
+        self.version_graph()
+            .prune_step(reserved_ver_num, budget)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    fn version_graphs(&self) -> Option<Vec<VersionGraph>> {
+        Some(vec![self.version_graph()])
+    }
+
+    /// Attach to a shared version graph,
+    /// only empty instances can be attached.
+    #[inline(always)]
+    fn version_graph_attach(&mut self, graph: &VersionGraph) -> Result<()> {
+        self.inner.graph_attach(&graph.inner).c(d!())
     }
 }
 
+
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
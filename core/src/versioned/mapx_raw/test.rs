@@ -1,8 +1,13 @@
 use super::*;
 use crate::{
-    common::{BranchName, ParentBranchName, INITIAL_BRANCH_NAME},
-    VsMgmt, NULL,
+    basic::mapx_raw::MapxRaw,
+    common::{
+        vsdb_get_branch_creator, vsdb_set_branch_creator, BranchID, BranchName,
+        ParentBranchName, INITIAL_BRANCH_ID, INITIAL_BRANCH_NAME, VSDB,
+    },
+    KeepEveryNth, KeepLastN, KeepNewerThan, PrunePolicy, VersionInfo, VsMgmt, NULL,
 };
+use std::{cell::RefCell, time::Duration};
 
 #[test]
 fn test_master_branch_exists() {
@@ -397,3 +402,919 @@ fn test_prune() {
     assert!(!hdr.version_exists(VersionName(b"b")));
     assert!(hdr.version_exists(VersionName(b"c")));
 }
+
+#[test]
+fn test_prune_with_policy() {
+    let mut hdr = MapxRawVs::new();
+    let master = INITIAL_BRANCH_NAME;
+    let name = |i: u8| format!("v{}", i).into_bytes();
+
+    pnk!(hdr.prune_with_policy(&KeepLastN(1)));
+
+    for i in 0..=10u8 {
+        pnk!(hdr.version_create(VersionName(&name(i))));
+        pnk!(hdr.insert(&[0], &[i]));
+        pnk!(hdr.insert(&[i], &[i]));
+    }
+    pnk!(hdr.remove(&[1]));
+    pnk!(hdr.version_create(VersionName(b"v11")));
+    pnk!(hdr.remove(&[2]));
+    pnk!(hdr.insert(&[0], &[11]));
+    pnk!(hdr.version_create(VersionName(b"v12")));
+    pnk!(hdr.insert(&[2], &[12]));
+
+    // versions not shared by all branches are out of the pruning range
+    pnk!(hdr.branch_create(BranchName(b"fork"), VersionName(b"f0"), false));
+    pnk!(hdr.version_create(VersionName(b"v13")));
+
+    // everything is newer than one hour
+    pnk!(hdr.prune_with_policy(&KeepNewerThan(Duration::from_secs(3600))));
+    assert_eq!(14, pnk!(hdr.version_list()).len());
+
+    let policy = KeepEveryNth(3).or(|v: &VersionInfo| v.name.0 == b"v4");
+    pnk!(hdr.prune_with_policy(&policy));
+
+    let kept = [0, 3, 4, 6, 9, 12, 13];
+    for i in 0..=13u8 {
+        assert_eq!(
+            kept.contains(&i),
+            hdr.version_exists(VersionName(&name(i))),
+            "{}",
+            i
+        );
+    }
+
+    // the data of kept versions are not changed
+    let get =
+        |k: u8, v: u8| hdr.get_by_branch_version(&[k], master, VersionName(&name(v)));
+    assert_eq!(Some(vec![3]), get(0, 3));
+    assert_eq!(Some(vec![4]), get(0, 4));
+    assert_eq!(Some(vec![6]), get(0, 6));
+    assert_eq!(Some(vec![11]), get(0, 12));
+    assert_eq!(Some(vec![5]), get(5, 6));
+    assert!(get(5, 4).is_none());
+    assert_eq!(Some(vec![1]), get(1, 9));
+    assert!(get(1, 12).is_none());
+    assert_eq!(Some(vec![12]), get(2, 12));
+    assert_eq!(Some(vec![10]), get(10, 12));
+    assert!(get(10, 9).is_none());
+    assert_eq!(Some(vec![11]), hdr.get(&[0]));
+
+    // the heights are kept as recorded
+    pnk!(hdr.prune_with_policy(&policy));
+    assert_eq!(7, pnk!(hdr.version_list()).len());
+
+    // the oldest one and the newest shared one are always kept
+    pnk!(hdr.branch_remove(BranchName(b"fork")));
+    pnk!(hdr.prune_with_policy(&KeepLastN(0)));
+    assert_eq!(
+        vec![VersionNameOwned(name(0)), VersionNameOwned(name(13))],
+        pnk!(hdr.version_list())
+    );
+    assert_eq!(Some(vec![11]), hdr.get(&[0]));
+    assert_eq!(Some(vec![12]), hdr.get(&[2]));
+    assert!(hdr.get(&[1]).is_none());
+    assert_eq!(Some(vec![10]), hdr.get(&[10]));
+}
+
+#[test]
+fn test_prune_step() {
+    let mut hdr = MapxRawVs::new();
+
+    let p = pnk!(hdr.prune_step(None, 10));
+    assert!(p.is_finished());
+    assert_eq!(0, p.done);
+
+    for i in 0u8..10 {
+        pnk!(hdr.version_create(VersionName(&[i])));
+        pnk!(hdr.insert(&[0], &[i]));
+        pnk!(hdr.insert(&[i], &[i]));
+    }
+    pnk!(hdr.remove(&[3]));
+
+    // a zero budget still makes progress
+    let p = pnk!(hdr.prune_step(Some(2), 0));
+    assert_eq!(1, p.done);
+    let mut remaining = p.remaining;
+    assert!(0 < remaining);
+
+    // the oldest versions are merged first
+    let p = pnk!(hdr.prune_step(Some(2), 3));
+    assert_eq!(3, p.done);
+    assert_eq!(remaining - 3, p.remaining);
+    remaining = p.remaining;
+    assert!(!hdr.version_exists(VersionName(&[4])));
+    assert!(hdr.version_exists(VersionName(&[5])));
+    assert_eq!(Some(vec![6]), hdr.get(&[6]));
+
+    let mut done = 0;
+    loop {
+        let p = pnk!(hdr.prune_step(Some(2), 2));
+        done += p.done;
+        alt!(p.is_finished(), break);
+    }
+    assert_eq!(remaining, done);
+
+    assert!(!hdr.version_exists(VersionName(&[7])));
+    assert!(hdr.version_exists(VersionName(&[0])));
+    assert!(hdr.version_exists(VersionName(&[8])));
+    assert_eq!(Some(vec![9]), hdr.get(&[0]));
+    assert!(hdr.get(&[3]).is_none());
+    for i in (0u8..10).filter(|i| ![0, 3].contains(i)) {
+        assert_eq!(Some(vec![i]), hdr.get(&[i]));
+    }
+
+    // the same as a whole pruning
+    pnk!(hdr.prune(Some(2)));
+    assert_eq!(0, pnk!(hdr.prune_step(Some(2), 1)).done);
+}
+
+#[test]
+fn test_version_graph() {
+    let graph = VersionGraph::new();
+
+    let mut a = MapxRawVs::new_in(&graph);
+    let mut b = MapxRawVs::new_in(&graph);
+    assert_eq!(2, graph.members_num());
+    assert_eq!(a.version_graph(), b.version_graph());
+
+    pnk!(a.version_create(VersionName(b"v0")));
+    assert!(b.version_exists(VersionName(b"v0")));
+    pnk!(a.insert(&[0], &[0]));
+    pnk!(b.insert(&[1], &[1]));
+
+    pnk!(b.branch_create(BranchName(b"br"), VersionName(b"v1"), false));
+    assert!(a.branch_exists(BranchName(b"br")));
+    pnk!(a.insert_by_branch(&[2], &[2], BranchName(b"br")));
+
+    // the changes of one member do not leak into others
+    assert!(b.get(&[0]).is_none());
+    assert!(a.get(&[1]).is_none());
+    assert!(!pnk!(b.version_has_change_set(VersionName(b"v1"))));
+    assert!(pnk!(a.version_has_change_set(VersionName(b"v1"))));
+
+    // one removal on the graph for all members
+    pnk!(a.branch_remove(BranchName(b"br")));
+    assert!(!b.branch_exists(BranchName(b"br")));
+    assert!(a.get_by_branch(&[2], BranchName(b"br")).is_none());
+
+    // only empty instances can be attached
+    let mut c = MapxRawVs::new();
+    pnk!(c.version_create(VersionName(b"x")));
+    pnk!(c.insert(&[9], &[9]));
+    assert!(c.version_graph_attach(&graph).is_err());
+
+    // detached from the private graph
+    let mut d = MapxRawVs::new();
+    pnk!(d.version_graph_attach(&graph));
+    assert_eq!(3, graph.members_num());
+    assert!(d.version_exists(VersionName(b"v0")));
+
+    // clones have their own graphs
+    let mut e = a.clone();
+    assert_ne!(e.version_graph(), graph);
+    assert_eq!(&[0], &pnk!(e.get(&[0]))[..]);
+    pnk!(e.version_create(VersionName(b"only-e")));
+    assert!(!a.version_exists(VersionName(b"only-e")));
+    assert_eq!(
+        pnk!(a.version_list()).len() + 1,
+        pnk!(e.version_list()).len()
+    );
+
+    // deserialized members share one graph in memory
+    let a2: MapxRawVs = pnk!(bcs::from_bytes(&pnk!(bcs::to_bytes(&a))));
+    let b2: MapxRawVs = pnk!(bcs::from_bytes(&pnk!(bcs::to_bytes(&b))));
+    assert_eq!(a2.version_graph(), b2.version_graph());
+    assert_eq!(a2.version_graph(), graph);
+}
+
+#[test]
+fn test_change_set_persistence() {
+    let mut a = MapxRawVs::new();
+    for i in 0u8..5 {
+        pnk!(a.version_create(VersionName(&[i])));
+        pnk!(a.insert(&[i], &[i]));
+        pnk!(a.insert(&[100], &[i]));
+    }
+    pnk!(a.remove(&[1]));
+    pnk!(a.version_create(VersionName(b"empty")));
+
+    let roots = (0u8..5)
+        .map(|i| pnk!(a.version_chgset_trie_root(None, Some(VersionName(&[i])))))
+        .collect::<Vec<_>>();
+    let diff = pnk!(a.diff(VersionName(&[1]), VersionName(&[4])));
+
+    // drop all the in-memory instances, the graph will be loaded from disk
+    let bytes = pnk!(bcs::to_bytes(&a));
+    drop(a);
+    let mut a = pnk!(bcs::from_bytes::<MapxRawVs>(&bytes));
+
+    for i in 0u8..5 {
+        assert!(pnk!(a.version_has_change_set(VersionName(&[i]))));
+        assert_eq!(
+            roots[i as usize],
+            pnk!(a.version_chgset_trie_root(None, Some(VersionName(&[i]))))
+        );
+    }
+    assert!(!pnk!(a.version_has_change_set(VersionName(b"empty"))));
+    assert_eq!(diff, pnk!(a.diff(VersionName(&[1]), VersionName(&[4]))));
+
+    // the change sets are still maintained after being loaded
+    pnk!(a.insert(&[200], &[200]));
+    assert!(pnk!(a.version_has_change_set(VersionName(b"empty"))));
+    pnk!(a.version_pop());
+    assert!(a.get(&[200]).is_none());
+    assert_eq!(Some(vec![4]), a.get(&[100]));
+
+    pnk!(a.prune(Some(1)));
+    assert!(!a.version_exists(VersionName(&[2])));
+    assert_eq!(Some(vec![4]), a.get(&[100]));
+    assert!(a.get(&[1]).is_none());
+    assert_eq!(Some(vec![3]), a.get(&[3]));
+}
+
+#[test]
+fn test_version_names_with_small_cache() {
+    let cache_size = crate::vsdb_get_index_cache_size();
+    crate::vsdb_set_index_cache_size(2);
+
+    let mut a = MapxRawVs::new();
+    for i in 0u8..20 {
+        pnk!(a.version_create(VersionName(&[i])));
+        pnk!(a.insert(&[i], &[i]));
+    }
+    for i in 0u8..20 {
+        assert!(a.version_exists(VersionName(&[i])));
+    }
+    assert_eq!(
+        (0u8..20)
+            .map(|i| VersionNameOwned(vec![i]))
+            .collect::<Vec<_>>(),
+        pnk!(a.version_list())
+    );
+
+    pnk!(a.branch_create(BranchName(b"fork"), VersionName(b"f0"), false));
+    assert_eq!(
+        Some(VersionNameOwned(vec![19])),
+        pnk!(a.branch_info(BranchName(b"fork"))).creation_version
+    );
+
+    // the names are loaded from disk
+    let bytes = pnk!(bcs::to_bytes(&a));
+    drop(a);
+    let mut a = pnk!(bcs::from_bytes::<MapxRawVs>(&bytes));
+    assert_eq!(21, a.version_list_globally().len());
+    assert!(a.version_exists_globally(VersionName(&[3])));
+
+    pnk!(a.branch_remove(BranchName(b"fork")));
+    pnk!(a.prune(Some(3)));
+    assert!(!a.version_exists_globally(VersionName(b"f0")));
+    assert!(!a.version_exists_globally(VersionName(&[3])));
+    assert!(a.version_exists_globally(VersionName(&[17])));
+    assert_eq!(4, a.version_list_globally().len());
+
+    crate::vsdb_set_index_cache_size(cache_size);
+}
+
+#[test]
+fn test_fork() {
+    let mut a = MapxRawVs::new();
+    pnk!(a.version_create(VersionName(b"v0")));
+    for i in 0u8..10 {
+        pnk!(a.insert(&[i], &[i]));
+    }
+
+    let mut f = pnk!(a.fork());
+    let fork = f.branch_get_default();
+    assert!(a.branch_exists(fork.as_deref()));
+    assert_eq!(&[3], &pnk!(f.get(&[3]))[..]);
+
+    // the fork diverges on write
+    pnk!(f.insert(&[3], &[33]));
+    pnk!(f.remove(&[4]));
+    pnk!(f.insert(&[100], &[100]));
+    assert_eq!(&[3], &pnk!(a.get(&[3]))[..]);
+    assert!(a.get(&[4]).is_some());
+    assert!(a.get(&[100]).is_none());
+    assert_eq!(&[33], &pnk!(f.get(&[3]))[..]);
+    assert!(f.get(&[4]).is_none());
+    assert_eq!(10, f.len());
+    assert_eq!(10, a.len());
+
+    // the base version has been sealed, later writes are invisible to the fork
+    pnk!(a.insert(&[7], &[77]));
+    assert_eq!(&[7], &pnk!(f.get(&[7]))[..]);
+    assert_eq!(&[77], &pnk!(a.get(&[7]))[..]);
+
+    // the own branch of a fork is used as its default branch
+    pnk!(f.version_create(VersionName(b"f1")));
+    assert!(f.version_exists(VersionName(b"f1")));
+    assert!(!a.version_exists(VersionName(b"f1")));
+    assert!(f.branch_set_default(INITIAL_BRANCH_NAME).is_err());
+    assert!(f.branch_remove(fork.as_deref()).is_err());
+    assert_eq!(fork, f.branch_get_default());
+    assert_eq!(INITIAL_BRANCH_NAME.0, &a.branch_get_default().0[..]);
+
+    // discard a fork
+    let mut g = pnk!(a.fork());
+    pnk!(g.insert(&[6], &[66]));
+    let discarded = g.branch_get_default();
+    assert!(a.branch_exists(discarded.as_deref()));
+    drop(g);
+    assert!(!a.branch_exists(discarded.as_deref()));
+    assert_eq!(&[6], &pnk!(a.get(&[6]))[..]);
+
+    // keep a fork
+    assert!(a.branch_merge_to(fork.as_deref(), INITIAL_BRANCH_NAME).is_err());
+    pnk!(a.branch_rebase_onto(fork.as_deref(), INITIAL_BRANCH_NAME));
+    pnk!(a.branch_merge_to(fork.as_deref(), INITIAL_BRANCH_NAME));
+    assert_eq!(&[33], &pnk!(a.get(&[3]))[..]);
+    assert!(a.get(&[4]).is_none());
+    assert_eq!(&[100], &pnk!(a.get(&[100]))[..]);
+
+    // a persisted fork keeps its branch
+    let mut h = pnk!(a.fork());
+    pnk!(h.insert(&[5], &[55]));
+    let kept = h.branch_get_default();
+    let bytes = pnk!(bcs::to_bytes(&h));
+    drop(h);
+    assert!(a.branch_exists(kept.as_deref()));
+    let h = pnk!(bcs::from_bytes::<MapxRawVs>(&bytes));
+    assert_eq!(kept, h.branch_get_default());
+    assert_eq!(&[55], &pnk!(h.get(&[5]))[..]);
+    assert_eq!(&[5], &pnk!(a.get(&[5]))[..]);
+    pnk!(a.insert(&[5], &[50]));
+    assert_eq!(&[55], &pnk!(h.get(&[5]))[..]);
+    drop(h);
+    assert!(a.branch_exists(kept.as_deref()));
+}
+
+#[test]
+fn test_version_graph_prune() {
+    let graph = VersionGraph::new();
+    let mut a = MapxRawVs::new_in(&graph);
+    let mut b = MapxRawVs::new_in(&graph);
+
+    for i in 0u8..10 {
+        pnk!(a.version_create(VersionName(&[i])));
+        pnk!(a.insert(&[i], &[i]));
+        pnk!(b.insert(&[i], &[i + 1]));
+    }
+
+    unsafe {
+        pnk!(b.version_revert_globally(VersionName(&[9])));
+    }
+    assert!(a.get(&[9]).is_none());
+    assert!(b.get(&[9]).is_none());
+
+    pnk!(b.prune(Some(2)));
+    assert!(!a.version_exists(VersionName(&[1])));
+    assert!(!b.version_exists(VersionName(&[6])));
+    assert!(a.version_exists(VersionName(&[7])));
+    for i in 0u8..9 {
+        assert_eq!(&[i], &pnk!(a.get(&[i]))[..]);
+        assert_eq!(&[i + 1], &pnk!(b.get(&[i]))[..]);
+    }
+}
+
+#[test]
+fn test_version_graph_prune_under_contention() {
+    let graph = VersionGraph::new();
+    let mut a = MapxRawVs::new_in(&graph);
+    let mut b = MapxRawVs::new_in(&graph);
+
+    // pruning waits for the writers instead of failing
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for i in 0u8..100 {
+                pnk!(a.version_create(VersionName(&[i])));
+                pnk!(a.insert(&[i], &[i]));
+            }
+        });
+        for _ in 0..100 {
+            pnk!(b.prune(Some(2)));
+        }
+    });
+
+    pnk!(b.prune(Some(2)));
+    assert!(!a.version_exists(VersionName(&[97])));
+    assert!(a.version_exists(VersionName(&[98])));
+    for i in 0u8..100 {
+        assert_eq!(&[i], &pnk!(a.get(&[i]))[..]);
+    }
+}
+
+#[test]
+fn test_version_graph_prune_in_background() {
+    let graph = VersionGraph::new();
+    let mut a = MapxRawVs::new_in(&graph);
+    let mut b = MapxRawVs::new_in(&graph);
+
+    for i in 0u8..100 {
+        pnk!(a.version_create(VersionName(&[i])));
+        pnk!(a.insert(&[i], &[i]));
+        pnk!(b.insert(&[i], &[i + 1]));
+    }
+
+    let task = graph.prune_in_background(Some(2), 7);
+    // the graph is still writable while pruning
+    pnk!(a.insert(&[200], &[200]));
+
+    let p = pnk!(task.wait());
+    assert!(p.is_finished());
+    assert!(!a.version_exists(VersionName(&[97])));
+    assert!(b.version_exists(VersionName(&[98])));
+    assert_eq!(Some(vec![200]), a.get(&[200]));
+    for i in 0u8..100 {
+        assert_eq!(&[i], &pnk!(a.get(&[i]))[..]);
+        assert_eq!(&[i + 1], &pnk!(b.get(&[i]))[..]);
+    }
+
+    // a cancelled task stops after the current step
+    for i in 100u8..200 {
+        pnk!(a.version_create(VersionName(&[i])));
+        pnk!(b.insert(&[i], &[i]));
+    }
+    let task = a.prune_in_background(Some(2), 1);
+    task.cancel();
+    let p = pnk!(task.wait());
+    assert!(1 >= p.done);
+    assert!(b.version_exists(VersionName(&[150])));
+    for i in 100u8..200 {
+        assert_eq!(&[i], &pnk!(b.get(&[i]))[..]);
+    }
+}
+
+#[test]
+fn test_diff() {
+    let mut a = MapxRawVs::new();
+    pnk!(a.version_create(VersionName(b"v0")));
+    pnk!(a.insert(&[0], &[0]));
+    pnk!(a.insert(&[1], &[1]));
+    pnk!(a.version_create(VersionName(b"v1")));
+    pnk!(a.insert(&[1], &[2]));
+    pnk!(a.insert(&[2], &[2]));
+    pnk!(a.remove(&[0]));
+    pnk!(a.version_create(VersionName(b"v2")));
+    pnk!(a.insert(&[2], &[3]));
+    pnk!(a.remove(&[2]));
+
+    let d = pnk!(a.diff(VersionName(b"v0"), VersionName(b"v1")));
+    assert_eq!(
+        d,
+        vec![
+            (vec![0], None),
+            (vec![1], Some(vec![2])),
+            (vec![2], Some(vec![2])),
+        ]
+    );
+
+    // the key created and removed within `v2`
+    let d = pnk!(a.diff(VersionName(b"v1"), VersionName(b"v2")));
+    assert_eq!(d, vec![(vec![2], None)]);
+    assert!(pnk!(a.diff(VersionName(b"v2"), VersionName(b"v2"))).is_empty());
+    assert!(a.diff(VersionName(b"v0"), VersionName(b"xx")).is_err());
+
+    // the reverse direction restores the old state
+    let d = pnk!(a.diff(VersionName(b"v2"), VersionName(b"v0")));
+    pnk!(a.version_create(VersionName(b"v3")));
+    pnk!(a.apply_diff(&d));
+    assert_eq!(&[0], &pnk!(a.get(&[0]))[..]);
+    assert_eq!(&[1], &pnk!(a.get(&[1]))[..]);
+    assert!(a.get(&[2]).is_none());
+    assert!(pnk!(a.diff(VersionName(b"v0"), VersionName(b"v3"))).is_empty());
+}
+
+#[test]
+fn test_branch_rebase_onto() {
+    let mut a = MapxRawVs::new();
+    let master = INITIAL_BRANCH_NAME;
+    let fork = BranchName(b"fork");
+
+    pnk!(a.version_create(VersionName(b"v0")));
+    pnk!(a.insert(&[0], &[0]));
+    pnk!(a.branch_create(fork, VersionName(b"f0"), false));
+    pnk!(a.insert_by_branch(&[1], &[1], fork));
+    pnk!(a.version_create_by_branch(VersionName(b"f1"), fork));
+    pnk!(a.insert_by_branch(&[2], &[2], fork));
+    pnk!(a.version_create(VersionName(b"v1")));
+    pnk!(a.insert(&[3], &[3]));
+
+    assert!(a.branch_rebase_onto(fork, fork).is_err());
+    assert!(a.branch_rebase_onto(BranchName(b"xx"), master).is_err());
+    assert!(pnk!(a.branch_rebase_conflicts(fork, master)).is_empty());
+
+    pnk!(a.branch_rebase_onto(fork, master));
+    let vers = pnk!(a.version_list_by_branch(fork))
+        .into_iter()
+        .map(|v| v.0)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vers,
+        vec![
+            b"v0".to_vec(),
+            b"v1".to_vec(),
+            b"f0".to_vec(),
+            b"f1".to_vec()
+        ]
+    );
+    assert_eq!(&[3], &pnk!(a.get_by_branch(&[3], fork))[..]);
+    assert_eq!(&[2], &pnk!(a.get_by_branch(&[2], fork))[..]);
+    assert!(a.get(&[1]).is_none());
+
+    // every replayed version keeps its own changes
+    let f0 = VersionName(b"f0");
+    assert_eq!(&[3], &pnk!(a.get_by_branch_version(&[3], fork, f0))[..]);
+    assert_eq!(&[1], &pnk!(a.get_by_branch_version(&[1], fork, f0))[..]);
+    assert!(a.get_by_branch_version(&[2], fork, f0).is_none());
+
+    // the fork can be merged back now
+    pnk!(a.branch_merge_to(fork, master));
+    assert_eq!(&[2], &pnk!(a.get(&[2]))[..]);
+
+    // keys changed on both branches
+    pnk!(a.version_create_by_branch(VersionName(b"f2"), fork));
+    pnk!(a.insert_by_branch(&[2], &[20], fork));
+    pnk!(a.version_create(VersionName(b"v2")));
+    pnk!(a.insert(&[2], &[21]));
+    assert_eq!(vec![vec![2]], pnk!(a.branch_rebase_conflicts(fork, master)));
+    let e = a.branch_rebase_onto(fork, master).unwrap_err();
+    assert!(e.get_lowest_msg().ends_with("{[2]}"));
+    assert_eq!(&[20], &pnk!(a.get_by_branch(&[2], fork))[..]);
+    assert!(!a.version_exists_on_branch(VersionName(b"v2"), fork));
+
+    // versions used by other branches can not be replayed
+    pnk!(a.branch_remove(fork));
+    pnk!(a.branch_create(fork, VersionName(b"f3"), false));
+    pnk!(a.insert_by_branch(&[4], &[4], fork));
+    pnk!(a.branch_create_by_base_branch(
+        BranchName(b"sub"),
+        VersionName(b"s0"),
+        ParentBranchName(b"fork"),
+        false
+    ));
+    pnk!(a.version_create(VersionName(b"v3")));
+    assert!(a.branch_rebase_onto(fork, master).is_err());
+    pnk!(a.branch_remove(BranchName(b"sub")));
+    pnk!(a.branch_rebase_onto(fork, master));
+    assert!(a.version_exists_on_branch(VersionName(b"v3"), fork));
+    assert_eq!(&[4], &pnk!(a.get_by_branch(&[4], fork))[..]);
+}
+
+#[test]
+fn test_branch_rebase_onto_meta() {
+    let mut a = MapxRawVs::new();
+    let fork = BranchName(b"fork");
+
+    pnk!(a.version_create(VersionName(b"v0")));
+    pnk!(a.branch_create(fork, VersionName(b"f0"), false));
+    pnk!(a.version_create_by_branch(VersionName(b"f1"), fork));
+    pnk!(a.version_create(VersionName(b"v1")));
+    pnk!(a.version_create(VersionName(b"v2")));
+
+    pnk!(a.branch_rebase_onto(fork, INITIAL_BRANCH_NAME));
+    assert_eq!(
+        Some(VersionNameOwned(b"v2".to_vec())),
+        pnk!(a.branch_info(fork)).creation_version
+    );
+
+    // the replayed versions are counted after the versions of the base branch
+    pnk!(a.branch_set_default(fork));
+    pnk!(a.branch_remove(INITIAL_BRANCH_NAME));
+    let heights = RefCell::new(vec![]);
+    pnk!(a.prune_with_policy(&|v: &VersionInfo| {
+        heights.borrow_mut().push((v.name.0.to_vec(), v.height));
+        true
+    }));
+    assert_eq!(
+        heights.into_inner(),
+        vec![
+            (b"v1".to_vec(), Some(1)),
+            (b"v2".to_vec(), Some(2)),
+            (b"f0".to_vec(), Some(3)),
+        ]
+    );
+}
+
+#[test]
+fn test_branch_ancestry() {
+    let mut a = MapxRawVs::new();
+    let master = INITIAL_BRANCH_NAME;
+    let fork = BranchName(b"fork");
+    let sub = BranchName(b"sub");
+
+    pnk!(a.version_create(VersionName(b"v0")));
+    pnk!(a.version_create(VersionName(b"v1")));
+    pnk!(a.branch_create(fork, VersionName(b"f0"), false));
+    pnk!(a.branch_create_by_base_branch(
+        sub,
+        VersionName(b"s0"),
+        ParentBranchName(b"fork"),
+        false
+    ));
+    pnk!(a.version_create(VersionName(b"v2")));
+
+    let base = |a: &MapxRawVs, x: BranchName, y: BranchName| {
+        pnk!(a.branch_merge_base(x, y)).map(|v| v.0)
+    };
+    assert_eq!(Some(b"v1".to_vec()), base(&a, fork, master));
+    assert_eq!(Some(b"v1".to_vec()), base(&a, master, sub));
+    assert_eq!(Some(b"f0".to_vec()), base(&a, sub, fork));
+    assert_eq!(Some(b"v2".to_vec()), base(&a, master, master));
+    assert!(a.branch_merge_base(master, BranchName(b"xx")).is_err());
+
+    assert!(pnk!(a.version_is_ancestor(VersionName(b"v0"), sub)));
+    assert!(pnk!(a.version_is_ancestor(VersionName(b"s0"), sub)));
+    assert!(!pnk!(a.version_is_ancestor(VersionName(b"v2"), sub)));
+    assert!(!pnk!(a.version_is_ancestor(VersionName(b"f0"), master)));
+    assert!(a.version_is_ancestor(VersionName(b"xx"), sub).is_err());
+
+    let mut brs = pnk!(a.branches_containing(VersionName(b"f0")));
+    brs.sort_by(|x, y| x.0.cmp(&y.0));
+    assert_eq!(brs, vec![fork.into(), sub.into()]);
+    assert_eq!(
+        pnk!(a.branches_containing(VersionName(b"v2"))),
+        vec![master.into()]
+    );
+    assert_eq!(3, pnk!(a.branches_containing(VersionName(b"v0"))).len());
+
+    assert_eq!(None, pnk!(a.branch_parent(master)));
+    assert_eq!(Some(master.into()), pnk!(a.branch_parent(fork)));
+    assert_eq!(Some(fork.into()), pnk!(a.branch_parent(sub)));
+    assert!(a.branch_parent(BranchName(b"xx")).is_err());
+
+    // versions of a merged branch are in the history of the target branch
+    pnk!(a.branch_merge_to(sub, fork));
+    assert!(pnk!(a.version_is_ancestor(VersionName(b"s0"), fork)));
+
+    // the parent of a rebased branch is its new base
+    pnk!(a.branch_remove(fork));
+    assert_eq!(None, pnk!(a.branch_parent(sub)));
+    pnk!(a.branch_rebase_onto(sub, master));
+    assert_eq!(Some(master.into()), pnk!(a.branch_parent(sub)));
+    assert_eq!(Some(b"v2".to_vec()), base(&a, sub, master));
+}
+
+#[test]
+fn test_branch_rename_and_info() {
+    let mut a = MapxRawVs::new();
+    let fork = BranchName(b"fork");
+    let renamed = BranchName(b"renamed");
+
+    pnk!(a.version_create(VersionName(b"v0")));
+    pnk!(a.insert(&[0], &[0]));
+    pnk!(a.branch_create(fork, VersionName(b"f0"), false));
+    pnk!(a.insert_by_branch(&[1], &[1], fork));
+    pnk!(a.branch_set_default(fork));
+
+    let info = pnk!(a.branch_info(fork));
+    assert_eq!(
+        Some(VersionNameOwned(b"v0".to_vec())),
+        info.creation_version
+    );
+    assert_eq!(vsdb_get_branch_creator(), info.creator);
+    assert!(!info.protected);
+    assert_eq!(
+        BranchInfo::default(),
+        pnk!(a.branch_info(INITIAL_BRANCH_NAME))
+    );
+
+    pnk!(a.branch_set_info(
+        fork,
+        &BranchInfo {
+            creator: "alice".to_owned(),
+            creation_version: None,
+            description: "trial run".to_owned(),
+            protected: true,
+        }
+    ));
+
+    assert!(a.branch_rename(fork, INITIAL_BRANCH_NAME).is_err());
+    assert!(a.branch_rename(BranchName(b"xx"), renamed).is_err());
+
+    // protected branches can only be renamed with an override
+    let o = pnk!(a.branch_override_protection(fork));
+    assert!(a.branch_rename(fork, renamed).is_err());
+    pnk!(a.branch_rename_with_override(&o, fork, renamed));
+    assert!(!a.branch_exists(fork));
+    assert_eq!(renamed, a.branch_get_default().as_deref());
+    assert_eq!(&[1], &pnk!(a.get_by_branch(&[1], renamed))[..]);
+    assert!(a.version_exists_on_branch(VersionName(b"f0"), renamed));
+
+    // the meta information follows the branch
+    let info = pnk!(a.branch_info(renamed));
+    assert_eq!("alice", info.creator);
+    assert_eq!("trial run", info.description);
+    assert!(info.protected);
+    assert_eq!(
+        Some(VersionNameOwned(b"v0".to_vec())),
+        info.creation_version
+    );
+
+    // the old name is free to use again
+    vsdb_set_branch_creator("bob");
+    pnk!(a.branch_create_by_base_branch(
+        fork,
+        VersionName(b"f1"),
+        ParentBranchName(b"renamed"),
+        false
+    ));
+    assert_eq!("bob", pnk!(a.branch_info(fork)).creator);
+    assert_eq!(Some(renamed.into()), pnk!(a.branch_parent(fork)));
+    assert_eq!(
+        Some(VersionNameOwned(b"f0".to_vec())),
+        pnk!(a.branch_info(fork)).creation_version
+    );
+
+    // reloaded instances keep them
+    let b = pnk!(bcs::from_bytes::<MapxRawVs>(&pnk!(bcs::to_bytes(&a))));
+    assert_eq!("alice", pnk!(b.branch_info(renamed)).creator);
+    assert!(b.branch_info(BranchName(b"xx")).is_err());
+}
+
+#[test]
+fn test_branch_protection() {
+    let mut a = MapxRawVs::new();
+    let fork = BranchName(b"fork");
+
+    pnk!(a.version_create(VersionName(b"v0")));
+    pnk!(a.insert(&[0], &[0]));
+    pnk!(a.branch_create(fork, VersionName(b"f0"), false));
+
+    // the default branch can never be removed
+    assert!(a.branch_remove(INITIAL_BRANCH_NAME).is_err());
+    assert!(a.branch_keep_only(&[fork]).is_err());
+    assert!(a.branch_exists(fork));
+    assert!(
+        a.branch_create(INITIAL_BRANCH_NAME, VersionName(b"xx"), true)
+            .is_err()
+    );
+
+    pnk!(a.branch_protect(INITIAL_BRANCH_NAME));
+    pnk!(a.branch_protect(fork));
+    assert!(pnk!(a.branch_is_protected(fork)));
+    assert!(pnk!(a.branch_info(fork)).protected);
+    assert!(a.branch_is_protected(BranchName(b"xx")).is_err());
+
+    assert!(a.insert(&[1], &[1]).is_err());
+    assert!(a.insert_by_branch(&[1], &[1], fork).is_err());
+    assert!(a.remove(&[0]).is_err());
+    assert!(a.version_pop().is_err());
+    assert!(a.branch_pop_version(fork).is_err());
+    assert!(a.branch_truncate(fork).is_err());
+    assert!(a.branch_truncate_to(fork, VersionName(b"v0")).is_err());
+    assert!(a.branch_remove(fork).is_err());
+    assert!(a.branch_keep_only(&[INITIAL_BRANCH_NAME]).is_err());
+    assert!(a.branch_rebase_onto(fork, INITIAL_BRANCH_NAME).is_err());
+    assert!(a.branch_rename(fork, BranchName(b"xx")).is_err());
+    assert!(a.branch_exists(fork));
+    assert_eq!(&[0], &pnk!(a.get(&[0]))[..]);
+
+    // appending new versions is allowed
+    pnk!(a.version_create(VersionName(b"v1")));
+    assert!(a.insert(&[1], &[1]).is_err());
+
+    // the override only takes effect on the calls it is passed to
+    let master = INITIAL_BRANCH_NAME;
+    let o = pnk!(a.branch_override_protection(master));
+    assert!(a.insert(&[1], &[1]).is_err());
+    pnk!(a.insert_by_branch_with_override(&o, &[1], &[1], master));
+    pnk!(a.remove_by_branch_with_override(&o, &[0], master));
+    assert!(a.remove(&[1]).is_err());
+
+    // and only on the branch it was issued for
+    assert!(a.insert_by_branch_with_override(&o, &[1], &[1], fork).is_err());
+    assert!(a.branch_truncate_with_override(&o, fork).is_err());
+    assert!(a.branch_remove_with_override(&o, fork).is_err());
+    assert!(a.branch_exists(fork));
+    assert_eq!(&[1], &pnk!(a.get(&[1]))[..]);
+    assert!(a.get(&[0]).is_none());
+
+    // the override is accepted by all the members of the graph,
+    // but not by other graphs
+    let mut b = MapxRawVs::new_in(&a.version_graph());
+    let o = pnk!(b.branch_override_protection(fork));
+    assert!(b.insert_by_branch(&[1], &[1], fork).is_err());
+    pnk!(b.insert_by_branch_with_override(&o, &[1], &[1], fork));
+    let mut other = MapxRawVs::new();
+    pnk!(other.version_create(VersionName(b"v0")));
+    pnk!(other.branch_create(fork, VersionName(b"f0"), false));
+    assert!(other.insert_by_branch_with_override(&o, &[1], &[1], fork).is_err());
+    assert!(a.branch_pop_version(fork).is_err());
+    pnk!(a.branch_pop_version_with_override(&o, fork));
+    pnk!(a.branch_truncate_to_with_override(&o, fork, VersionName(b"v0")));
+    pnk!(a.branch_truncate_with_override(&o, fork));
+    pnk!(a.branch_remove_with_override(&o, fork));
+    assert!(!a.branch_exists(fork));
+
+    // the default branch is still protected against removal
+    let o = pnk!(a.branch_override_protection(master));
+    assert!(a.branch_remove_with_override(&o, master).is_err());
+
+    // reloaded instances keep the protection
+    let mut c = pnk!(bcs::from_bytes::<MapxRawVs>(&pnk!(bcs::to_bytes(&a))));
+    assert!(pnk!(c.branch_is_protected(INITIAL_BRANCH_NAME)));
+    assert!(c.insert(&[4], &[4]).is_err());
+
+    pnk!(a.branch_unprotect(INITIAL_BRANCH_NAME));
+    pnk!(c.insert(&[4], &[4]));
+    assert_eq!(&[4], &pnk!(a.get(&[4]))[..]);
+}
+
+#[test]
+fn test_prefix() {
+    let mut a = MapxRawVs::new();
+    pnk!(a.version_create(VersionName(b"v0")));
+    [&[1][..], &[1, 0], &[1, 1], &[1, 1, 1], &[2], &[2, 1]]
+        .iter()
+        .for_each(|k| {
+            pnk!(a.insert(k, k));
+        });
+
+    assert_eq!(
+        a.iter_prefix([1]).map(|(k, _)| k).collect::<Vec<_>>(),
+        vec![vec![1], vec![1, 0], vec![1, 1], vec![1, 1, 1]]
+    );
+    assert_eq!(6, a.iter_prefix([]).count());
+
+    pnk!(a.version_create(VersionName(b"v1")));
+    assert_eq!(2, pnk!(a.remove_prefix([1, 1])));
+    assert_eq!(0, pnk!(a.remove_prefix([1, 1])));
+    assert_eq!(2, a.iter_prefix([1]).count());
+
+    // the removed entries still exist in the old version
+    let br = a.branch_get_default();
+    assert_eq!(
+        4,
+        a.iter_prefix_by_branch_version([1], br.as_deref(), VersionName(b"v0"))
+            .count()
+    );
+
+    let fork = BranchName(b"fork");
+    pnk!(a.branch_create(fork, VersionName(b"f0"), false));
+    assert_eq!(2, pnk!(a.remove_prefix_by_branch([2], fork)));
+    assert_eq!(0, a.iter_prefix_by_branch([2], fork).count());
+    assert_eq!(2, a.iter_prefix([2]).count());
+}
+
+#[test]
+fn test_legacy_layout() {
+    // the layout used before the version graph can be shared
+    #[derive(Serialize)]
+    struct Legacy {
+        default_branch: BranchID,
+        br_name_to_br_id: MapxRaw,
+        ver_name_to_ver_id: MapxRaw,
+        br_to_its_vers: MapxRaw,
+        layered_kv: MapxRaw,
+    }
+
+    let master = INITIAL_BRANCH_ID.to_be_bytes();
+    let v0 = VSDB.alloc_ver_id().to_be_bytes();
+
+    let mut br_name_to_br_id = MapxRaw::new();
+    br_name_to_br_id.insert(INITIAL_BRANCH_NAME.0, master);
+    let mut ver_name_to_ver_id = MapxRaw::new();
+    ver_name_to_ver_id.insert(b"v0", v0);
+    let mut vers = MapxRaw::new();
+    vers.insert(v0, []);
+    let mut br_to_its_vers = MapxRaw::new();
+    br_to_its_vers.insert(master, vers.as_prefix_slice());
+    let mut values = MapxRaw::new();
+    values.insert(v0, [1]);
+    let mut layered_kv = MapxRaw::new();
+    layered_kv.insert([0], values.as_prefix_slice());
+
+    let legacy = pnk!(bcs::to_bytes(&Legacy {
+        default_branch: master,
+        br_name_to_br_id,
+        ver_name_to_ver_id,
+        br_to_its_vers,
+        layered_kv,
+    }));
+
+    let mut a = pnk!(bcs::from_bytes::<MapxRawVs>(&legacy));
+    assert!(a.version_exists(VersionName(b"v0")));
+    assert!(pnk!(a.version_has_change_set(VersionName(b"v0"))));
+    assert_eq!(Some(vec![1]), a.get(&[0]));
+
+    pnk!(a.version_create(VersionName(b"v1")));
+    pnk!(a.insert(&[0], &[2]));
+    let b = pnk!(bcs::from_bytes::<MapxRawVs>(&legacy));
+    assert_eq!(a.version_graph(), b.version_graph());
+
+    // upgraded once, the legacy bytes are still valid after reloading
+    let current = pnk!(bcs::to_bytes(&a));
+    assert_ne!(legacy, current);
+    drop(a);
+    drop(b);
+    for bytes in [&legacy, &current] {
+        let a = pnk!(bcs::from_bytes::<MapxRawVs>(bytes));
+        assert!(a.version_exists(VersionName(b"v1")));
+        assert_eq!(Some(vec![2]), a.get(&[0]));
+        assert_eq!(
+            Some(vec![1]),
+            a.get_by_branch_version(&[0], INITIAL_BRANCH_NAME, VersionName(b"v0"))
+        );
+    }
+}
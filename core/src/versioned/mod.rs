@@ -3,10 +3,17 @@
 //!
 
 pub mod mapx_raw;
+pub mod prune;
+
+pub use mapx_raw::{ProtectionOverride, VersionGraph};
+pub use prune::{
+    KeepEither, KeepEveryNth, KeepLastN, KeepNewerThan, PruneProgress, PrunePolicy,
+    PruneTask, VersionInfo,
+};
 
 use crate::{
-    basic::mapx_raw::MapxRaw, BranchName, BranchNameOwned, ParentBranchName,
-    VersionName, VersionNameOwned,
+    basic::mapx_raw::MapxRaw, BranchInfo, BranchName, BranchNameOwned,
+    ParentBranchName, VersionName, VersionNameOwned,
 };
 use ruc::*;
 use std::{
@@ -235,10 +242,14 @@ pub trait VsMgmt {
     /// 'Write'-like operations on data require recursive tracing of all parent nodes,
     /// while operations on branches and versions are limited to their own perspective,
     /// and should not do any tracing.
+    ///
+    /// The default branch can not be removed.
     fn branch_remove(&mut self, br_name: BranchName) -> Result<()>;
 
     /// Clean up all other branches not in the list,
     /// will also clean up all orphan versions.
+    ///
+    /// Fails without any change if the default branch is not in the list.
     fn branch_keep_only(&mut self, br_names: &[BranchName]) -> Result<()>;
 
     /// Remove all changes directly made by versions(bigger than `last_ver_id`) of this branch.
@@ -292,6 +303,18 @@ pub trait VsMgmt {
         target_br_name: BranchName,
     ) -> Result<()>;
 
+    /// Re-parent the versions created on a branch onto the head of another branch,
+    /// every one of them is replayed as a new version with the same name.
+    ///
+    /// Fails without any change if some data have been changed on both branches
+    /// since they forked, the conflicting keys are listed in the error,
+    /// or if the versions to be replayed are used by other branches.
+    fn branch_rebase_onto(
+        &mut self,
+        br_name: BranchName,
+        base_br_name: BranchName,
+    ) -> Result<()>;
+
     /// Make a branch to be default,
     /// all default operations will be applied to it.
     fn branch_set_default(&mut self, br_name: BranchName) -> Result<()>;
@@ -319,6 +342,40 @@ pub trait VsMgmt {
     /// Get the default branch name.
     fn branch_get_default(&self) -> BranchNameOwned;
 
+    /// The latest version shared by two branches, that is their fork point,
+    /// `None` if they have no common versions.
+    fn branch_merge_base(
+        &self,
+        br_name: BranchName,
+        other_br_name: BranchName,
+    ) -> Result<Option<VersionNameOwned>>;
+
+    /// Check if a version is in the history of a branch,
+    /// the head version of the branch is also included.
+    fn version_is_ancestor(
+        &self,
+        ver_name: VersionName,
+        br_name: BranchName,
+    ) -> Result<bool>;
+
+    /// All the branches whose history contains the version.
+    fn branches_containing(&self, ver_name: VersionName) -> Result<Vec<BranchNameOwned>>;
+
+    /// The base branch that a branch was created from(or rebased onto),
+    /// `None` for the initial branch, or if the base branch has been removed.
+    fn branch_parent(&self, br_name: BranchName) -> Result<Option<BranchNameOwned>>;
+
+    /// Change the name of a branch, its versions and data are kept as is.
+    fn branch_rename(&mut self, br_name: BranchName, new_br_name: BranchName)
+    -> Result<()>;
+
+    /// Get the meta information of a branch.
+    fn branch_info(&self, br_name: BranchName) -> Result<BranchInfo>;
+
+    /// Update the meta information of a branch,
+    /// the `creation_version` is recorded at the creation and will not be changed.
+    fn branch_set_info(&mut self, br_name: BranchName, info: &BranchInfo) -> Result<()>;
+
     /// Logically similar to `std::ptr::swap`
     ///
     /// For example: If you have a master branch and a test branch, the data is always trial-run on the test branch, and then periodically merged back into the master branch. Rather than merging the test branch into the master branch, and then recreating the new test branch, it is more efficient to just swap the two branches, and then recreating the new test branch.
@@ -335,6 +392,150 @@ pub trait VsMgmt {
 
     /// Clean outdated versions out of the default branch.
     fn prune(&mut self, reserved_ver_num: Option<usize>) -> Result<()>;
+
+    /// Clean outdated versions that are not kept by the policy,
+    /// the data of all kept versions are not changed.
+    fn prune_with_policy(&mut self, policy: &dyn PrunePolicy) -> Result<()>;
+
+    /// Like `prune`, but clean at most `budget` versions of each graph at a time,
+    /// call it repeatedly until the returned progress is finished.
+    fn prune_step(
+        &mut self,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> Result<PruneProgress>;
+
+    /// The version graphs used by this instance,
+    /// `None` means that the instance manages its versions by itself.
+    fn version_graphs(&self) -> Option<Vec<VersionGraph>>;
+
+    /// Attach to a shared version graph, all versions and branches
+    /// will be managed by the graph since then,
+    /// NOTE: only empty instances can be attached.
+    ///
+    /// Instances that manage their versions by themselves will ignore this.
+    fn version_graph_attach(&mut self, graph: &VersionGraph) -> Result<()>;
+}
+
+/// Typed changes between two versions of a versioned instance,
+/// can be derived by the `VsDiff` macro for structures built on versioned collections.
+pub trait VsDiff {
+    /// The changes of this instance.
+    type Diff;
+
+    /// The changes made from `ver_a` to `ver_b` on the default branch,
+    /// both versions must exist on the default branch.
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff>;
+
+    /// Apply the changes to the head of the default branch.
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()>;
+}
+
+/// Read-only handlers of a versioned instance pinned to a historical version,
+/// can be derived by the `VsView` macro for structures built on versioned collections.
+pub trait VsView {
+    /// The read-only handler of this instance.
+    type View<'a>
+    where
+        Self: 'a;
+
+    /// Pin the instance to a version of a branch,
+    /// all reads through the returned handler see the data of that version.
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>>;
+}
+
+/// Apply a 'write'-like operation of [VsMgmt] on a group of instances,
+/// the operation will be applied only once on each version graph,
+/// so instances sharing a graph will not see it twice.
+///
+/// Instances whose graphs are still pristine will be moved to one graph
+/// before the operation, so they share a graph by default.
+///
+/// Used by the `Vs` derive macro.
+#[doc(hidden)]
+pub fn vs_fan_out(
+    members: &mut [&mut dyn VsMgmt],
+    mut op: impl FnMut(&mut dyn VsMgmt) -> Result<()>,
+) -> Result<()> {
+    share_pristine_graphs(members).c(d!())?;
+
+    let mut graphs: Vec<VersionGraph> = vec![];
+    for m in members.iter_mut() {
+        if let Some(gs) = m.version_graphs() {
+            gs.into_iter().for_each(|g| {
+                if !graphs.contains(&g) {
+                    graphs.push(g);
+                }
+            });
+        } else {
+            op(&mut **m).c(d!())?;
+        }
+    }
+    for mut g in graphs.into_iter() {
+        op(&mut g).c(d!())?;
+    }
+    Ok(())
+}
+
+// Move the instances whose graphs are all pristine to one graph,
+// which is the first graph that has been used,
+// or the one shared by the most instances if all of them are pristine.
+//
+// Instances that have been used keep their own graphs,
+// so do the bare version graphs that have no members.
+fn share_pristine_graphs(members: &mut [&mut dyn VsMgmt]) -> Result<()> {
+    let graphs = members
+        .iter()
+        .map(|m| m.version_graphs())
+        .collect::<Vec<_>>();
+
+    let mut all = graphs.iter().flatten().flatten();
+    let shared_by = |g: &VersionGraph| {
+        let n = graphs.iter().flatten().filter(|gs| gs.contains(g)).count();
+        (n, g.members_num())
+    };
+    let target = if let Some(g) = all.clone().find(|g| !g.is_pristine()) {
+        g.clone()
+    } else if let Some(g) = all.next() {
+        all.fold(g, |acc, g| alt!(shared_by(g) > shared_by(acc), g, acc))
+            .clone()
+    } else {
+        return Ok(());
+    };
+
+    for (m, gs) in members.iter_mut().zip(graphs.iter()) {
+        if let Some(gs) = gs {
+            if gs
+                .iter()
+                .all(|g| g != &target && g.is_pristine() && 0 < g.members_num())
+            {
+                m.version_graph_attach(&target).c(d!())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the version graphs used by a group of instances,
+/// `None` will be returned if any of them manages its versions by itself.
+///
+/// Used by the `Vs` derive macro.
+#[doc(hidden)]
+pub fn vs_graphs(members: &[&dyn VsMgmt]) -> Option<Vec<VersionGraph>> {
+    let mut graphs: Vec<VersionGraph> = vec![];
+    for m in members.iter() {
+        for g in m.version_graphs()?.into_iter() {
+            if !graphs.contains(&g) {
+                graphs.push(g);
+            }
+        }
+    }
+    Some(graphs)
 }
 
 #[macro_export]
@@ -697,6 +898,17 @@ macro_rules! impl_vs_methods {
                 .c(d!())
         }
 
+        /// Re-parent the versions created on a branch onto the head of another branch,
+        /// every one of them is replayed as a new version with the same name.
+        #[inline(always)]
+        fn branch_rebase_onto(
+            &mut self,
+            br_name: $crate::BranchName,
+            base_br_name: $crate::BranchName,
+        ) -> ruc::Result<()> {
+            self.inner.branch_rebase_onto(br_name, base_br_name).c(d!())
+        }
+
         /// Make a branch to be default,
         /// all default operations will be applied to it.
         #[inline(always)]
@@ -719,6 +931,70 @@ macro_rules! impl_vs_methods {
             self.inner.branch_get_default()
         }
 
+        /// The latest version shared by two branches, that is their fork point.
+        #[inline(always)]
+        fn branch_merge_base(
+            &self,
+            br_name: $crate::BranchName,
+            other_br_name: $crate::BranchName,
+        ) -> ruc::Result<Option<$crate::VersionNameOwned>> {
+            self.inner.branch_merge_base(br_name, other_br_name).c(d!())
+        }
+
+        /// Check if a version is in the history of a branch.
+        #[inline(always)]
+        fn version_is_ancestor(
+            &self,
+            ver_name: $crate::VersionName,
+            br_name: $crate::BranchName,
+        ) -> ruc::Result<bool> {
+            self.inner.version_is_ancestor(ver_name, br_name).c(d!())
+        }
+
+        /// All the branches whose history contains the version.
+        #[inline(always)]
+        fn branches_containing(
+            &self,
+            ver_name: $crate::VersionName,
+        ) -> ruc::Result<Vec<$crate::BranchNameOwned>> {
+            self.inner.branches_containing(ver_name).c(d!())
+        }
+
+        /// The base branch that a branch was created from.
+        #[inline(always)]
+        fn branch_parent(
+            &self,
+            br_name: $crate::BranchName,
+        ) -> ruc::Result<Option<$crate::BranchNameOwned>> {
+            self.inner.branch_parent(br_name).c(d!())
+        }
+
+        /// Change the name of a branch, its versions and data are kept as is.
+        #[inline(always)]
+        fn branch_rename(
+            &mut self,
+            br_name: $crate::BranchName,
+            new_br_name: $crate::BranchName,
+        ) -> ruc::Result<()> {
+            self.inner.branch_rename(br_name, new_br_name).c(d!())
+        }
+
+        /// Get the meta information of a branch.
+        #[inline(always)]
+        fn branch_info(&self, br_name: $crate::BranchName) -> ruc::Result<$crate::BranchInfo> {
+            self.inner.branch_info(br_name).c(d!())
+        }
+
+        /// Update the meta information of a branch.
+        #[inline(always)]
+        fn branch_set_info(
+            &mut self,
+            br_name: $crate::BranchName,
+            info: &$crate::BranchInfo,
+        ) -> ruc::Result<()> {
+            self.inner.branch_set_info(br_name, info).c(d!())
+        }
+
         unsafe fn branch_swap(
             &mut self,
             branch_1: $crate::BranchName,
@@ -732,6 +1008,40 @@ macro_rules! impl_vs_methods {
         fn prune(&mut self, reserved_ver_num: Option<usize>) -> ruc::Result<()> {
             self.inner.prune(reserved_ver_num).c(d!())
         }
+
+        /// Clean outdated versions that are not kept by the policy.
+        #[inline(always)]
+        fn prune_with_policy(
+            &mut self,
+            policy: &dyn $crate::PrunePolicy,
+        ) -> ruc::Result<()> {
+            self.inner.prune_with_policy(policy).c(d!())
+        }
+
+        /// Clean at most `budget` outdated versions.
+        #[inline(always)]
+        fn prune_step(
+            &mut self,
+            reserved_ver_num: Option<usize>,
+            budget: usize,
+        ) -> ruc::Result<$crate::PruneProgress> {
+            self.inner.prune_step(reserved_ver_num, budget).c(d!())
+        }
+
+        #[inline(always)]
+        fn version_graphs(&self) -> Option<Vec<$crate::VersionGraph>> {
+            self.inner.version_graphs()
+        }
+
+        /// Attach to a shared version graph,
+        /// only empty instances can be attached.
+        #[inline(always)]
+        fn version_graph_attach(
+            &mut self,
+            graph: &$crate::VersionGraph,
+        ) -> ruc::Result<()> {
+            self.inner.version_graph_attach(graph).c(d!())
+        }
     };
 }
 
@@ -951,6 +1261,15 @@ macro_rules! impl_vs_methods_nope {
             Ok(())
         }
 
+        #[inline(always)]
+        fn branch_rebase_onto(
+            &mut self,
+            _: $crate::BranchName,
+            _: $crate::BranchName,
+        ) -> ruc::Result<()> {
+            Ok(())
+        }
+
         #[inline(always)]
         fn branch_set_default(&mut self, _: $crate::BranchName) -> ruc::Result<()> {
             Ok(())
@@ -968,6 +1287,63 @@ macro_rules! impl_vs_methods_nope {
             Default::default()
         }
 
+        #[inline(always)]
+        fn branch_merge_base(
+            &self,
+            _: $crate::BranchName,
+            _: $crate::BranchName,
+        ) -> ruc::Result<Option<$crate::VersionNameOwned>> {
+            Ok(None)
+        }
+
+        #[inline(always)]
+        fn version_is_ancestor(
+            &self,
+            _: $crate::VersionName,
+            _: $crate::BranchName,
+        ) -> ruc::Result<bool> {
+            Ok(true)
+        }
+
+        #[inline(always)]
+        fn branches_containing(
+            &self,
+            _: $crate::VersionName,
+        ) -> ruc::Result<Vec<$crate::BranchNameOwned>> {
+            Ok(Default::default())
+        }
+
+        #[inline(always)]
+        fn branch_parent(
+            &self,
+            _: $crate::BranchName,
+        ) -> ruc::Result<Option<$crate::BranchNameOwned>> {
+            Ok(None)
+        }
+
+        #[inline(always)]
+        fn branch_rename(
+            &mut self,
+            _: $crate::BranchName,
+            _: $crate::BranchName,
+        ) -> ruc::Result<()> {
+            Ok(())
+        }
+
+        #[inline(always)]
+        fn branch_info(&self, _: $crate::BranchName) -> ruc::Result<$crate::BranchInfo> {
+            Ok(Default::default())
+        }
+
+        #[inline(always)]
+        fn branch_set_info(
+            &mut self,
+            _: $crate::BranchName,
+            _: &$crate::BranchInfo,
+        ) -> ruc::Result<()> {
+            Ok(())
+        }
+
         unsafe fn branch_swap(
             &mut self,
             _: $crate::BranchName,
@@ -980,6 +1356,30 @@ macro_rules! impl_vs_methods_nope {
         fn prune(&mut self, _: Option<usize>) -> ruc::Result<()> {
             Ok(())
         }
+
+        #[inline(always)]
+        fn prune_with_policy(&mut self, _: &dyn $crate::PrunePolicy) -> ruc::Result<()> {
+            Ok(())
+        }
+
+        #[inline(always)]
+        fn prune_step(
+            &mut self,
+            _: Option<usize>,
+            _: usize,
+        ) -> ruc::Result<$crate::PruneProgress> {
+            Ok(Default::default())
+        }
+
+        #[inline(always)]
+        fn version_graphs(&self) -> Option<Vec<$crate::VersionGraph>> {
+            Some(vec![])
+        }
+
+        #[inline(always)]
+        fn version_graph_attach(&mut self, _: &$crate::VersionGraph) -> ruc::Result<()> {
+            Ok(())
+        }
     };
 }
 
@@ -1412,6 +1812,18 @@ impl<T: VsMgmt> VsMgmt for Option<T> {
         Ok(())
     }
 
+    #[inline(always)]
+    fn branch_rebase_onto(
+        &mut self,
+        br_name: BranchName,
+        base_br_name: BranchName,
+    ) -> Result<()> {
+        if let Some(i) = self.as_mut() {
+            i.branch_rebase_onto(br_name, base_br_name).c(d!())?;
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     fn branch_set_default(&mut self, br_name: BranchName) -> Result<()> {
         if let Some(i) = self.as_mut() {
@@ -1441,6 +1853,69 @@ impl<T: VsMgmt> VsMgmt for Option<T> {
         Default::default()
     }
 
+    fn branch_merge_base(
+        &self,
+        br_name: BranchName,
+        other_br_name: BranchName,
+    ) -> Result<Option<VersionNameOwned>> {
+        if let Some(i) = self.as_ref() {
+            return i.branch_merge_base(br_name, other_br_name).c(d!());
+        }
+        Ok(None)
+    }
+
+    fn version_is_ancestor(
+        &self,
+        ver_name: VersionName,
+        br_name: BranchName,
+    ) -> Result<bool> {
+        if let Some(i) = self.as_ref() {
+            return i.version_is_ancestor(ver_name, br_name).c(d!());
+        }
+        Ok(true)
+    }
+
+    fn branches_containing(&self, ver_name: VersionName) -> Result<Vec<BranchNameOwned>> {
+        if let Some(i) = self.as_ref() {
+            return i.branches_containing(ver_name).c(d!());
+        }
+        Ok(Default::default())
+    }
+
+    fn branch_parent(&self, br_name: BranchName) -> Result<Option<BranchNameOwned>> {
+        if let Some(i) = self.as_ref() {
+            return i.branch_parent(br_name).c(d!());
+        }
+        Ok(None)
+    }
+
+    #[inline(always)]
+    fn branch_rename(
+        &mut self,
+        br_name: BranchName,
+        new_br_name: BranchName,
+    ) -> Result<()> {
+        if let Some(i) = self.as_mut() {
+            i.branch_rename(br_name, new_br_name).c(d!())?;
+        }
+        Ok(())
+    }
+
+    fn branch_info(&self, br_name: BranchName) -> Result<BranchInfo> {
+        if let Some(i) = self.as_ref() {
+            return i.branch_info(br_name).c(d!());
+        }
+        Ok(Default::default())
+    }
+
+    #[inline(always)]
+    fn branch_set_info(&mut self, br_name: BranchName, info: &BranchInfo) -> Result<()> {
+        if let Some(i) = self.as_mut() {
+            i.branch_set_info(br_name, info).c(d!())?;
+        }
+        Ok(())
+    }
+
     unsafe fn branch_swap(
         &mut self,
         branch_1: BranchName,
@@ -1459,6 +1934,41 @@ impl<T: VsMgmt> VsMgmt for Option<T> {
         }
         Ok(())
     }
+
+    #[inline(always)]
+    fn prune_with_policy(&mut self, policy: &dyn PrunePolicy) -> Result<()> {
+        if let Some(i) = self.as_mut() {
+            i.prune_with_policy(policy).c(d!())?;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn prune_step(
+        &mut self,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> Result<PruneProgress> {
+        if let Some(i) = self.as_mut() {
+            return i.prune_step(reserved_ver_num, budget).c(d!());
+        }
+        Ok(Default::default())
+    }
+
+    fn version_graphs(&self) -> Option<Vec<VersionGraph>> {
+        if let Some(i) = self.as_ref() {
+            return i.version_graphs();
+        }
+        Some(vec![])
+    }
+
+    #[inline(always)]
+    fn version_graph_attach(&mut self, graph: &VersionGraph) -> Result<()> {
+        if let Some(i) = self.as_mut() {
+            i.version_graph_attach(graph).c(d!())?;
+        }
+        Ok(())
+    }
 }
 
 // impl<T: VsMgmt + Deref + DerefMut> VsMgmt for Pin<T> {
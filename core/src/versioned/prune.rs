@@ -0,0 +1,259 @@
+//!
+//! # Pruning policies
+//!
+//! A policy decides whether a version should be kept when pruning,
+//! the changes made by dropped versions are merged into the next kept version,
+//! so the data of every kept version stays unchanged.
+//!
+//! Policies can be combined with `or`, a version is kept if any of them keeps it.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::time::Duration;
+//! use vsdb_core::{
+//!     KeepEveryNth, KeepNewerThan, MapxRawVs, PrunePolicy, VersionInfo, VersionName,
+//!     VsMgmt,
+//! };
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb_core::vsdb_set_base_dir(&dir);
+//!
+//! let mut m = MapxRawVs::new();
+//! for i in 0..500u64 {
+//!     m.version_create(VersionName(format!("{}", i).as_bytes())).unwrap();
+//!     m.insert(&[0], &i.to_be_bytes()).unwrap();
+//! }
+//!
+//! // keep everything from the last 24 hours, then every 100th version,
+//! // and always keep the tagged checkpoints
+//! let policy = KeepNewerThan(Duration::from_secs(24 * 3600))
+//!     .or(KeepEveryNth(100))
+//!     .or(|v: &VersionInfo| v.name.0 == b"250");
+//! m.prune_with_policy(&policy).unwrap();
+//! assert_eq!(500, m.version_list().unwrap().len());
+//!
+//! let policy = KeepEveryNth(100).or(|v: &VersionInfo| v.name.0 == b"250");
+//! m.prune_with_policy(&policy).unwrap();
+//! assert_eq!(7, m.version_list().unwrap().len());
+//! assert!(m.version_exists(VersionName(b"250")));
+//! ```
+//!
+
+use crate::{common::TRASH_CLEANER, VersionName};
+use ruc::*;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The information of a version, used by a `PrunePolicy` to make decisions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VersionInfo<'a> {
+    pub name: VersionName<'a>,
+    /// The number of versions before it on the branch where it was created,
+    /// `None` for versions created before this information was recorded.
+    pub height: Option<u64>,
+    /// The creation time in seconds since the unix epoch,
+    /// `None` for versions created before this information was recorded.
+    pub created_at: Option<u64>,
+    /// The number of newer versions in the pruning range,
+    /// the newest one is `0`.
+    pub depth: usize,
+}
+
+/// Decide whether a version should be kept when pruning.
+///
+/// NOTE: the oldest version and the newest version shared by all branches
+/// are always kept, the versions that are not shared by all branches
+/// are out of the pruning range.
+pub trait PrunePolicy {
+    fn keep(&self, ver: &VersionInfo) -> bool;
+
+    /// Keep the versions kept by any of the two policies.
+    fn or<P: PrunePolicy>(self, other: P) -> KeepEither<Self, P>
+    where
+        Self: Sized,
+    {
+        KeepEither(self, other)
+    }
+}
+
+impl<F: Fn(&VersionInfo) -> bool> PrunePolicy for F {
+    #[inline(always)]
+    fn keep(&self, ver: &VersionInfo) -> bool {
+        self(ver)
+    }
+}
+
+/// Keep the newest N versions.
+#[derive(Clone, Copy, Debug)]
+pub struct KeepLastN(pub usize);
+
+impl PrunePolicy for KeepLastN {
+    #[inline(always)]
+    fn keep(&self, ver: &VersionInfo) -> bool {
+        ver.depth < self.0
+    }
+}
+
+/// Keep the versions whose height is a multiple of N,
+/// versions with unknown heights are always kept.
+#[derive(Clone, Copy, Debug)]
+pub struct KeepEveryNth(pub u64);
+
+impl PrunePolicy for KeepEveryNth {
+    #[inline(always)]
+    fn keep(&self, ver: &VersionInfo) -> bool {
+        ver.height.map(|h| 0 == h % self.0.max(1)).unwrap_or(true)
+    }
+}
+
+/// Keep the versions created within the specified duration,
+/// versions with unknown creation time are always kept.
+#[derive(Clone, Copy, Debug)]
+pub struct KeepNewerThan(pub Duration);
+
+impl PrunePolicy for KeepNewerThan {
+    #[inline(always)]
+    fn keep(&self, ver: &VersionInfo) -> bool {
+        let deadline = now_secs().saturating_sub(self.0.as_secs());
+        ver.created_at.map(|t| t >= deadline).unwrap_or(true)
+    }
+}
+
+/// Keep the versions kept by any of the two policies.
+#[derive(Clone, Copy, Debug)]
+pub struct KeepEither<A, B>(pub A, pub B);
+
+impl<A: PrunePolicy, B: PrunePolicy> PrunePolicy for KeepEither<A, B> {
+    #[inline(always)]
+    fn keep(&self, ver: &VersionInfo) -> bool {
+        self.0.keep(ver) || self.1.keep(ver)
+    }
+}
+
+/// The progress of an incremental pruning.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PruneProgress {
+    /// The number of versions cleaned.
+    pub done: usize,
+    /// The number of versions still waiting to be cleaned.
+    pub remaining: usize,
+}
+
+impl PruneProgress {
+    #[inline(always)]
+    pub fn is_finished(&self) -> bool {
+        0 == self.remaining
+    }
+
+    /// Accumulate the progress of another pruning,
+    /// used when multiple version graphs are pruned together.
+    #[inline(always)]
+    pub fn absorb(&mut self, other: PruneProgress) {
+        self.done += other.done;
+        self.remaining += other.remaining;
+    }
+}
+
+pub(crate) type PruneStep = Box<dyn FnMut() -> Result<PruneProgress> + Send>;
+
+/// A pruning running on the background pool,
+/// dropping the handle does not stop it, use `cancel` for that.
+#[derive(Debug)]
+pub struct PruneTask {
+    state: Arc<PruneState>,
+    result: Receiver<Result<PruneProgress>>,
+}
+
+#[derive(Debug, Default)]
+struct PruneState {
+    done: AtomicUsize,
+    remaining: AtomicUsize,
+    cancelled: AtomicBool,
+    finished: AtomicBool,
+}
+
+impl PruneTask {
+    pub(crate) fn spawn(step: PruneStep) -> Self {
+        let state = Arc::new(PruneState::default());
+        let (tx, rx) = mpsc::channel();
+        schedule(step, Arc::clone(&state), tx);
+        Self { state, result: rx }
+    }
+
+    /// The accumulated progress until now.
+    #[inline(always)]
+    pub fn progress(&self) -> PruneProgress {
+        PruneProgress {
+            done: self.state.done.load(Ordering::Acquire),
+            remaining: self.state.remaining.load(Ordering::Acquire),
+        }
+    }
+
+    /// Stop the pruning after the current step,
+    /// all the steps done are kept.
+    #[inline(always)]
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Finished, failed or cancelled.
+    #[inline(always)]
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::Acquire)
+    }
+
+    /// Block until the pruning stops, and return the accumulated progress.
+    #[inline(always)]
+    pub fn wait(self) -> Result<PruneProgress> {
+        self.result.recv().c(d!())?
+    }
+}
+
+// Every step is a separate job of the pool,
+// so other jobs can run between them.
+fn schedule(
+    mut step: PruneStep,
+    state: Arc<PruneState>,
+    tx: Sender<Result<PruneProgress>>,
+) {
+    TRASH_CLEANER.lock().execute(move || {
+        let mut ret = Ok(());
+        if !state.cancelled.load(Ordering::Acquire) {
+            match step() {
+                Ok(p) => {
+                    state.done.fetch_add(p.done, Ordering::AcqRel);
+                    state.remaining.store(p.remaining, Ordering::Release);
+                    // a step doing nothing means no further progress can be made
+                    if !p.is_finished() && 0 < p.done {
+                        schedule(step, state, tx);
+                        return;
+                    }
+                }
+                Err(e) => {
+                    ret = Err(e);
+                }
+            }
+        }
+        state.finished.store(true, Ordering::Release);
+        let progress = PruneProgress {
+            done: state.done.load(Ordering::Acquire),
+            remaining: state.remaining.load(Ordering::Acquire),
+        };
+        let _ = tx.send(ret.map(|_| progress));
+    });
+}
+
+#[inline(always)]
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -1,66 +1,147 @@
-use proc_macro2::TokenStream;
-use quote::{quote, quote_spanned};
+//!
+//! # vsdb_derive
+//!
+//! The `Vs` derive macro, an implementation of `VsMgmt` for structures and enums,
+//! all versioned operations are applied to their fields.
+//!
+//! Fields share one version graph by default, they are moved to it before
+//! the first versioned operation, fields that have been used before keep their own graphs.
+//!
+//! Field attributes:
+//!
+//! - `#[vs(skip)]`, the field will be ignored, eg. caches or configurations
+//! - `#[vs(with = "path")]`, the field will be managed through `path::as_vs(&field)`
+//!   and `path::as_vs_mut(&mut field)`, which return a reference to a `VsMgmt` implementor
+//!
+//! The `VsDiff` derive macro, an implementation of `VsDiff` for structures,
+//! a `<Name>Diff` structure is generated with one diff field per managed field,
+//! fields marked with `#[vs(skip)]` are excluded from it.
+//!
+//! The `VsView` derive macro, an implementation of `VsView` for structures,
+//! a `<Name>View<'_>` structure is generated with one read-only handler per managed field,
+//! all of them are pinned to the same version.
+//!
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Index};
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Field, Fields, LitStr, Path,
+};
 
-#[proc_macro_derive(Vs)]
+#[proc_macro_derive(Vs, attributes(vs))]
 pub fn derive_vsmgmt(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // Parse the input tokens into a syntax tree.
     let input = parse_macro_input!(input as DeriveInput);
 
+    match gen_vsmgmt(&input) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(e) => proc_macro::TokenStream::from(e.to_compile_error()),
+    }
+}
+
+fn gen_vsmgmt(input: &DeriveInput) -> syn::Result<TokenStream> {
     // Used in the quasi-quotation below as `#name`.
-    let name = input.ident;
+    let name = &input.ident;
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let version_create = gen_version_create(&input.data);
-    let version_create_by_branch = gen_version_create_by_branch(&input.data);
-    let version_exists = gen_version_exists(&input.data);
-    let version_exists_on_branch = gen_version_exists_on_branch(&input.data);
-    let version_pop = gen_version_pop(&input.data);
-    let version_pop_by_branch = gen_version_pop_by_branch(&input.data);
-    let version_rebase = gen_version_rebase(&input.data);
-    let version_rebase_by_branch = gen_version_rebase_by_branch(&input.data);
-
-    let branch_create = gen_branch_create(&input.data);
-    let branch_create_by_base_branch = gen_branch_create_by_base_branch(&input.data);
-    let branch_create_by_base_branch_version =
-        gen_branch_create_by_base_branch_version(&input.data);
-
-    let branch_create_without_new_version =
-        gen_branch_create_without_new_version(&input.data);
-    let branch_create_by_base_branch_without_new_version =
-        gen_branch_create_by_base_branch_without_new_version(&input.data);
-    let branch_create_by_base_branch_version_without_new_version =
-        gen_branch_create_by_base_branch_version_without_new_version(&input.data);
-
-    let branch_exists = gen_branch_exists(&input.data);
-    let branch_has_versions = gen_branch_has_versions(&input.data);
-    let branch_remove = gen_branch_remove(&input.data);
-    let branch_keep_only = gen_branch_keep_only(&input.data);
-    let branch_truncate = gen_branch_truncate(&input.data);
-    let branch_truncate_to = gen_branch_truncate_to(&input.data);
-    let branch_pop_version = gen_branch_pop_version(&input.data);
-    let branch_merge_to = gen_branch_merge_to(&input.data);
-    let branch_merge_to_force = gen_branch_merge_to_force(&input.data);
-    let branch_set_default = gen_branch_set_default(&input.data);
-    let prune = gen_prune(&input.data);
-
-    let version_exists_globally = gen_version_exists_globally(&input.data);
-    let version_list = gen_version_list(&input.data);
-    let version_list_by_branch = gen_version_list_by_branch(&input.data);
-    let version_list_globally = gen_version_list_globally(&input.data);
-    let version_has_change_set = gen_version_has_change_set(&input.data);
-    let version_clean_up_globally = gen_version_clean_up_globally(&input.data);
-    let version_revert_globally = gen_version_revert_globally(&input.data);
-    let version_chgset_trie_root = gen_version_chgset_trie_root(&input.data);
-    let branch_is_empty = gen_branch_is_empty(&input.data);
-    let branch_list = gen_branch_list(&input.data);
-    let branch_get_default = gen_branch_get_default(&input.data);
-    let branch_swap = gen_branch_swap(&input.data);
+    let vs = parse_variants(input)?;
+    let vs = &vs[..];
+
+    // an enum without any variant can not be instantiated,
+    // all the generated functions are unreachable
+    let allow_unreachable = if vs.is_empty() {
+        quote! { #[allow(unreachable_code)] }
+    } else {
+        quote! {}
+    };
+
+    let version_create = gen_fan_out(vs, quote! { x.version_create(ver_name) });
+    let version_create_by_branch =
+        gen_fan_out(vs, quote! { x.version_create_by_branch(ver_name, br_name) });
+    let version_exists = gen_version_exists(vs);
+    let version_exists_on_branch = gen_version_exists_on_branch(vs);
+    let version_pop = gen_fan_out(vs, quote! { x.version_pop() });
+    let version_pop_by_branch = gen_fan_out(vs, quote! { x.version_pop_by_branch(br_name) });
+    let version_rebase = gen_fan_out(vs, quote! { x.version_rebase(base_version) });
+    let version_rebase_by_branch = gen_fan_out(
+        vs,
+        quote! { x.version_rebase_by_branch(base_version, br_name) },
+    );
+
+    let branch_create = gen_fan_out(vs, quote! { x.branch_create(br_name, ver_name, force) });
+    let branch_create_by_base_branch = gen_fan_out(
+        vs,
+        quote! { x.branch_create_by_base_branch(br_name, ver_name, base_br_name, force) },
+    );
+    let branch_create_by_base_branch_version = gen_fan_out(
+        vs,
+        quote! { x.branch_create_by_base_branch_version(br_name, ver_name, base_br_name, base_ver_name, force) },
+    );
+
+    let branch_create_without_new_version = gen_fan_out(
+        vs,
+        quote! { x.branch_create_without_new_version(br_name, force) },
+    );
+    let branch_create_by_base_branch_without_new_version = gen_fan_out(
+        vs,
+        quote! { x.branch_create_by_base_branch_without_new_version(br_name, base_br_name, force) },
+    );
+    let branch_create_by_base_branch_version_without_new_version = gen_fan_out(
+        vs,
+        quote! { x.branch_create_by_base_branch_version_without_new_version(br_name, base_br_name, base_ver_name, force) },
+    );
+
+    let branch_exists = gen_branch_exists(vs);
+    let branch_has_versions = gen_branch_has_versions(vs);
+    let branch_remove = gen_fan_out(vs, quote! { x.branch_remove(br_name) });
+    let branch_keep_only = gen_fan_out(vs, quote! { x.branch_keep_only(br_names) });
+    let branch_truncate = gen_fan_out(vs, quote! { x.branch_truncate(br_name) });
+    let branch_truncate_to =
+        gen_fan_out(vs, quote! { x.branch_truncate_to(br_name, last_ver_name) });
+    let branch_pop_version = gen_fan_out(vs, quote! { x.branch_pop_version(br_name) });
+    let branch_merge_to = gen_fan_out(vs, quote! { x.branch_merge_to(br_name, target_br_name) });
+    let branch_merge_to_force = gen_fan_out(
+        vs,
+        quote! { x.branch_merge_to_force(br_name, target_br_name) },
+    );
+    let branch_rebase_onto =
+        gen_fan_out(vs, quote! { x.branch_rebase_onto(br_name, base_br_name) });
+    let branch_set_default = gen_fan_out(vs, quote! { x.branch_set_default(br_name) });
+    let prune = gen_fan_out(vs, quote! { x.prune(reserved_ver_num) });
+    let prune_with_policy = gen_fan_out(vs, quote! { x.prune_with_policy(policy) });
+    let prune_step = gen_fan_out(
+        vs,
+        quote! { x.prune_step(reserved_ver_num, budget).map(|p| progress.absorb(p)) },
+    );
+
+    let version_exists_globally = gen_version_exists_globally(vs);
+    let version_list = gen_version_list(vs);
+    let version_list_by_branch = gen_version_list_by_branch(vs);
+    let version_list_globally = gen_version_list_globally(vs);
+    let version_has_change_set = gen_version_has_change_set(vs);
+    let version_clean_up_globally = gen_fan_out(vs, quote! { x.version_clean_up_globally() });
+    let version_revert_globally = gen_fan_out(vs, quote! { x.version_revert_globally(ver_name) });
+    let version_chgset_trie_root = gen_version_chgset_trie_root(vs);
+    let branch_is_empty = gen_branch_is_empty(vs);
+    let branch_list = gen_branch_list(vs);
+    let branch_get_default = gen_branch_get_default(vs);
+    let branch_merge_base = gen_branch_merge_base(vs);
+    let version_is_ancestor = gen_version_is_ancestor(vs);
+    let branches_containing = gen_branches_containing(vs);
+    let branch_parent = gen_branch_parent(vs);
+    let branch_rename_check = gen_branch_rename_check(vs);
+    let branch_rename = gen_fan_out(vs, quote! { x.branch_rename(br_name, new_br_name) });
+    let branch_info = gen_branch_info(vs);
+    let branch_set_info = gen_fan_out(vs, quote! { x.branch_set_info(br_name, info) });
+    let version_graphs = gen_version_graphs(vs);
+    let version_graph_attach = gen_version_graph_attach(vs);
+    let branch_swap = gen_fan_out(vs, quote! { x.branch_swap(br1, br2) });
 
     let expanded = quote! {
         use ruc::*;
+        #allow_unreachable
         impl #impl_generics vsdb::VsMgmt for #name #ty_generics #where_clause {
             fn version_create(&mut self, ver_name: vsdb::VersionName) -> ruc::Result<()> {
                 #version_create
@@ -230,6 +311,15 @@ pub fn derive_vsmgmt(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                 Ok(())
             }
 
+            fn branch_rebase_onto(
+                &mut self,
+                br_name: vsdb::BranchName,
+                base_br_name: vsdb::BranchName
+            ) -> ruc::Result<()> {
+                #branch_rebase_onto
+                Ok(())
+            }
+
             fn branch_set_default(&mut self, br_name: vsdb::BranchName) -> ruc::Result<()> {
                 #branch_set_default
                 Ok(())
@@ -240,6 +330,24 @@ pub fn derive_vsmgmt(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                 Ok(())
             }
 
+            fn prune_with_policy(
+                &mut self,
+                policy: &dyn vsdb::PrunePolicy,
+            ) -> ruc::Result<()> {
+                #prune_with_policy
+                Ok(())
+            }
+
+            fn prune_step(
+                &mut self,
+                reserved_ver_num: Option<usize>,
+                budget: usize,
+            ) -> ruc::Result<vsdb::PruneProgress> {
+                let mut progress = vsdb::PruneProgress::default();
+                #prune_step
+                Ok(progress)
+            }
+
             fn version_exists_globally(&self, ver_name: vsdb::VersionName) -> bool {
                 #version_exists_globally
             }
@@ -311,6 +419,67 @@ pub fn derive_vsmgmt(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                 guard
             }
 
+            fn branch_merge_base(
+                &self,
+                br_name: vsdb::BranchName,
+                other_br_name: vsdb::BranchName
+            ) -> ruc::Result<Option<vsdb::VersionNameOwned>> {
+                let mut guard = None;
+                #branch_merge_base
+                Ok(guard)
+            }
+
+            fn version_is_ancestor(
+                &self,
+                ver_name: vsdb::VersionName,
+                br_name: vsdb::BranchName
+            ) -> ruc::Result<bool> {
+                #version_is_ancestor
+                Ok(true)
+            }
+
+            fn branches_containing(&self, ver_name: vsdb::VersionName)
+                -> ruc::Result<Vec<vsdb::BranchNameOwned>> {
+
+                let mut guard = vec![];
+                #branches_containing
+                Ok(guard)
+            }
+
+            fn branch_parent(&self, br_name: vsdb::BranchName)
+                -> ruc::Result<Option<vsdb::BranchNameOwned>> {
+
+                let mut guard = None;
+                #branch_parent
+                Ok(guard)
+            }
+
+            fn branch_rename(
+                &mut self,
+                br_name: vsdb::BranchName,
+                new_br_name: vsdb::BranchName
+            ) -> ruc::Result<()> {
+                #branch_rename_check
+                #branch_rename
+                Ok(())
+            }
+
+            fn branch_info(&self, br_name: vsdb::BranchName) -> ruc::Result<vsdb::BranchInfo> {
+                let guard_default = vsdb::BranchInfo::default();
+                let mut guard = vsdb::BranchInfo::default();
+                #branch_info
+                Ok(guard)
+            }
+
+            fn branch_set_info(
+                &mut self,
+                br_name: vsdb::BranchName,
+                info: &vsdb::BranchInfo
+            ) -> ruc::Result<()> {
+                #branch_set_info
+                Ok(())
+            }
+
             unsafe fn branch_swap(
                 &mut self,
                 br1: vsdb::BranchName,
@@ -319,1250 +488,850 @@ pub fn derive_vsmgmt(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                 #branch_swap
                 Ok(())
             }
+
+            fn version_graphs(&self) -> Option<Vec<vsdb::VersionGraph>> {
+                #version_graphs
+            }
+
+            fn version_graph_attach(&mut self, graph: &vsdb::VersionGraph) -> ruc::Result<()> {
+                #version_graph_attach
+                Ok(())
+            }
         }
     };
 
-    // Hand the output tokens back to the compiler.
-    proc_macro::TokenStream::from(expanded)
+    Ok(expanded)
 }
 
-fn gen_version_create(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_create(&mut self.#id, ver_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_create(&mut self.#id, ver_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+#[proc_macro_derive(VsDiff, attributes(vs))]
+pub fn derive_vsdiff(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match gen_vsdiff(&input) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(e) => proc_macro::TokenStream::from(e.to_compile_error()),
     }
 }
 
-fn gen_version_create_by_branch(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                        let id = &f.ident;
-                        quote_spanned! {f.span()=>
-                            vsdb::VsMgmt::version_create_by_branch(&mut self.#id, ver_name, br_name)
-                                .c(d!())?;
-                        }
-                    });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                        let id = Index::from(i);
-                        quote_spanned! {f.span()=>
-                            vsdb::VsMgmt::version_create_by_branch(&mut self.#id, ver_name, br_name)
-                                .c(d!())?;
-                        }
-                    });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+fn gen_vsdiff(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let (fields, managed) = parse_struct_fields(input, "VsDiff")?;
+
+    let name = &input.ident;
+    let diff_name = format_ident!("{}Diff", name);
+    let doc = format!("The changes of [{}] between two versions.", name);
+
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+
+    let diff_tys = managed
+        .iter()
+        .map(|f| {
+            let ty = &f.field.ty;
+            quote_spanned! {f.field.span()=> <#ty as vsdb::VsDiff>::Diff }
+        })
+        .collect::<Vec<_>>();
+
+    // every managed field must implement `VsDiff`
+    let mut bounded = input.generics.clone();
+    for f in managed.iter() {
+        let ty = &f.field.ty;
+        bounded
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote_spanned! {f.field.span()=> #ty: vsdb::VsDiff });
     }
-}
+    let bounded_where_clause = bounded.where_clause.clone();
+    for ty in diff_tys.iter() {
+        bounded
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote! { #ty: Default });
+    }
+    let default_where_clause = &bounded.where_clause;
+
+    let diff_decl = gen_struct_decl(
+        input,
+        fields,
+        &managed,
+        &diff_tys,
+        &diff_name,
+        quote! { #impl_generics },
+        &bounded_where_clause,
+        quote! {},
+    );
+
+    let diff_inits = managed.iter().map(|f| {
+        let (m, dm) = (&f.member, &f.out_member);
+        quote! { #dm: vsdb::VsDiff::diff(&self.#m, ver_a, ver_b).c(d!())? }
+    });
+    let default_inits = managed.iter().map(|f| {
+        let dm = &f.out_member;
+        quote! { #dm: Default::default() }
+    });
+    let applies = managed.iter().map(|f| {
+        let (m, dm) = (&f.member, &f.out_member);
+        quote! { vsdb::VsDiff::apply_diff(&mut self.#m, &diff.#dm).c(d!())?; }
+    });
+
+    // avoid warnings of unused arguments on structures without managed fields
+    let (unused_vers, unused_diff) = if managed.is_empty() {
+        (quote! { let _ = (ver_a, ver_b); }, quote! { let _ = diff; })
+    } else {
+        (quote! {}, quote! {})
+    };
 
-fn gen_version_exists(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_exists(&self.#id, ver_name) &&
-                    }
-                });
-                quote! {
-                    #(#recurse)* true
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_exists(&self.#id, ver_name) &&
-                    }
-                });
-                quote! {
-                    #(#recurse)* true
-                }
+    Ok(quote! {
+        #[doc = #doc]
+        #diff_decl
+
+        impl #impl_generics Default for #diff_name #ty_generics #default_where_clause {
+            fn default() -> Self {
+                #diff_name { #(#default_inits,)* }
             }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
-}
+        }
 
-fn gen_version_exists_on_branch(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                        let id = &f.ident;
-                        quote_spanned! {f.span()=>
-                            vsdb::VsMgmt::version_exists_on_branch(&self.#id, ver_name, br_name) &&
-                        }
-                    });
-                quote! {
-                    #(#recurse)* true
-                }
+        use ruc::*;
+        impl #impl_generics vsdb::VsDiff for #name #ty_generics #bounded_where_clause {
+            type Diff = #diff_name #ty_generics;
+
+            fn diff(
+                &self,
+                ver_a: vsdb::VersionName,
+                ver_b: vsdb::VersionName,
+            ) -> ruc::Result<Self::Diff> {
+                #unused_vers
+                Ok(#diff_name { #(#diff_inits,)* })
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                        let id = Index::from(i);
-                        quote_spanned! {f.span()=>
-                            vsdb::VsMgmt::version_exists_on_branch(&self.#id, ver_name, br_name) &&
-                        }
-                    });
-                quote! {
-                    #(#recurse)* true
-                }
+
+            fn apply_diff(&mut self, diff: &Self::Diff) -> ruc::Result<()> {
+                #unused_diff
+                #(#applies)*
+                Ok(())
             }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        }
+    })
 }
 
-fn gen_version_pop(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_pop(&mut self.#id).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_pop(&mut self.#id).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+#[proc_macro_derive(VsView, attributes(vs))]
+pub fn derive_vsview(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match gen_vsview(&input) {
+        Ok(expanded) => proc_macro::TokenStream::from(expanded),
+        Err(e) => proc_macro::TokenStream::from(e.to_compile_error()),
     }
 }
 
-fn gen_version_pop_by_branch(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_pop_by_branch(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_pop_by_branch(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+fn gen_vsview(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let (fields, managed) = parse_struct_fields(input, "VsView")?;
+
+    let name = &input.ident;
+    let view_name = format_ident!("{}View", name);
+    let doc = format!(
+        "A read-only handler of [{}] pinned to a version, created by `view_at`.",
+        name
+    );
+
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+
+    // the generated structure borrows `Self` with an extra lifetime
+    let lt: syn::Lifetime = syn::parse_quote! { '__vs };
+    let mut view_generics = input.generics.clone();
+    view_generics.params.insert(0, syn::parse_quote! { #lt });
+    let (view_impl_generics, view_ty_generics, _) = view_generics.split_for_impl();
+
+    let view_tys = managed
+        .iter()
+        .map(|f| {
+            let ty = &f.field.ty;
+            quote_spanned! {f.field.span()=> <#ty as vsdb::VsView>::View<#lt> }
+        })
+        .collect::<Vec<_>>();
+
+    // every managed field must implement `VsView`
+    let mut bounded = input.generics.clone();
+    for f in managed.iter() {
+        let ty = &f.field.ty;
+        bounded
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote_spanned! {f.field.span()=> #ty: vsdb::VsView });
     }
-}
+    let bounded_where_clause = bounded.where_clause.clone();
+    bounded
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote! { #name #ty_generics: #lt });
+    for f in managed.iter() {
+        let ty = &f.field.ty;
+        bounded
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote! { #ty: #lt });
+    }
+    let view_where_clause = &bounded.where_clause;
 
-fn gen_version_rebase(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_rebase(&mut self.#id, base_version).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_rebase(&mut self.#id, base_version).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+    let phantom = phantom_member(fields, managed.len());
+    let phantom_decl = match fields {
+        Fields::Named(_) => quote! { #phantom: std::marker::PhantomData<&#lt #name #ty_generics> },
+        _ => quote! { std::marker::PhantomData<&#lt #name #ty_generics> },
+    };
+    let view_decl = gen_struct_decl(
+        input,
+        fields,
+        &managed,
+        &view_tys,
+        &view_name,
+        quote! { #view_impl_generics },
+        view_where_clause,
+        phantom_decl,
+    );
+
+    let view_inits = managed.iter().map(|f| {
+        let (m, vm) = (&f.member, &f.out_member);
+        quote! { #vm: vsdb::VsView::view_at(&self.#m, br_name, ver_name).c(d!())? }
+    });
+
+    // avoid warnings of unused arguments on structures without managed fields
+    let unused = if managed.is_empty() {
+        quote! { let _ = (br_name, ver_name); }
+    } else {
+        quote! {}
+    };
+
+    Ok(quote! {
+        #[doc = #doc]
+        #view_decl
+
+        use ruc::*;
+        impl #impl_generics vsdb::VsView for #name #ty_generics #bounded_where_clause {
+            type View<#lt> = #view_name #view_ty_generics where Self: #lt;
+
+            fn view_at<#lt>(
+                &#lt self,
+                br_name: vsdb::BranchName,
+                ver_name: vsdb::VersionName,
+            ) -> ruc::Result<Self::View<#lt>> {
+                #unused
+                Ok(#view_name { #(#view_inits,)* #phantom: std::marker::PhantomData })
             }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        }
+    })
 }
 
-fn gen_version_rebase_by_branch(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_rebase_by_branch(&mut self.#id, base_version, br_name)
-                            .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_rebase_by_branch(&mut self.#id, base_version, br_name)
-                            .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+// The structure generated by the `VsView` derive holds a private marker,
+// so that all the generic parameters are used even without any managed field.
+fn phantom_member(fields: &Fields, managed_num: usize) -> TokenStream {
+    match fields {
+        Fields::Named(_) => quote! { __vs_phantom },
+        _ => {
+            let idx = syn::Index::from(managed_num);
+            quote! { #idx }
+        }
     }
 }
 
-fn gen_branch_create(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create(&mut self.#id, br_name, ver_name, force)
-                            .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create(&mut self.#id, br_name, ver_name, force)
-                            .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+// Declare a structure with one field per managed field, in the same form as the input,
+// followed by the `extra` field if it is not empty.
+#[allow(clippy::too_many_arguments)]
+fn gen_struct_decl(
+    input: &DeriveInput,
+    fields: &Fields,
+    managed: &[StructField],
+    tys: &[TokenStream],
+    name: &syn::Ident,
+    generics: TokenStream,
+    where_clause: &Option<syn::WhereClause>,
+    extra: TokenStream,
+) -> TokenStream {
+    let vis = &input.vis;
+    let members = managed.iter().zip(tys.iter()).map(|(f, ty)| {
+        let fvis = &f.field.vis;
+        let m = &f.out_member;
+        match f.field.ident {
+            Some(_) => quote! { #fvis #m: #ty },
+            None => quote! { #fvis #ty },
+        }
+    });
+    match fields {
+        Fields::Named(_) => quote! {
+            #vis struct #name #generics #where_clause {
+                #(#members,)* #extra
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+        _ => quote! {
+            #vis struct #name #generics (#(#members,)* #extra) #where_clause;
+        },
     }
 }
 
-fn gen_branch_create_by_base_branch(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create_by_base_branch(
-                            &mut self.#id,
-                            br_name,
-                            ver_name,
-                            base_br_name,
-                            force
-                        )
-                        .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create_by_base_branch(
-                            &mut self.#id,
-                            br_name,
-                            ver_name,
-                            base_br_name,
-                            force
-                        )
-                        .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+// A field of a structure managed by the `VsDiff` or `VsView` derive.
+struct StructField<'a> {
+    // the member in `Self`
+    member: TokenStream,
+    // the member in the generated structure, skipped fields are not counted
+    out_member: TokenStream,
+    field: &'a Field,
 }
 
-fn gen_branch_create_by_base_branch_version(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create_by_base_branch_version(
-                            &mut self.#id,
-                            br_name,
-                            ver_name,
-                            base_br_name,
-                            base_ver_name,
-                            force
-                        ).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create_by_base_branch_version(
-                            &mut self.#id,
-                            br_name,
-                            ver_name,
-                            base_br_name,
-                            base_ver_name,
-                            force
-                        )
-                        .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+fn parse_struct_fields<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+) -> syn::Result<(&'a Fields, Vec<StructField<'a>>)> {
+    check_no_attrs(&input.attrs, "structures")?;
+
+    let fields = match input.data {
+        Data::Struct(ref data) => &data.fields,
+        Data::Enum(ref data) => {
+            return Err(Error::new_spanned(
+                data.enum_token,
+                format!("`{}` can only be derived for structures", derive),
+            ));
+        }
+        Data::Union(ref data) => {
+            return Err(Error::new_spanned(
+                data.union_token,
+                format!("`{}` can only be derived for structures", derive),
+            ));
+        }
+    };
+
+    let mut managed = vec![];
+    for (i, f) in fields.iter().enumerate() {
+        match parse_field_attrs(f)? {
+            None => continue,
+            Some(Some(_)) => {
+                return Err(Error::new_spanned(
+                    f,
+                    format!(
+                        "`#[vs(with = \"...\")]` is not supported by `{}`, use `#[vs(skip)]`",
+                        derive
+                    ),
+                ));
+            }
+            Some(None) => {}
+        }
+        let (member, out_member) = match f.ident {
+            Some(ref id) => (quote! { #id }, quote! { #id }),
+            None => {
+                let idx = syn::Index::from(i);
+                let out_idx = syn::Index::from(managed.len());
+                (quote! { #idx }, quote! { #out_idx })
+            }
+        };
+        managed.push(StructField {
+            member,
+            out_member,
+            field: f,
+        });
     }
+
+    Ok((fields, managed))
 }
 
-fn gen_branch_create_without_new_version(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create_without_new_version(&mut self.#id, br_name, force)
-                            .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create_without_new_version(&mut self.#id, br_name, force)
-                            .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+// A field managed by the derived implementation.
+struct VsField {
+    // the binding of this field in the match patterns
+    binding: syn::Ident,
+    // the `path` of `#[vs(with = "path")]`
+    with: Option<Path>,
+    span: Span,
 }
 
-fn gen_branch_create_by_base_branch_without_new_version(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create_by_base_branch_without_new_version(
-                            &mut self.#id,
-                            br_name,
-                            base_br_name,
-                            force
-                        )
-                        .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+impl VsField {
+    // The expression referring to this field, `&T` or `&mut T`,
+    // where the `T` implements `VsMgmt`.
+    fn expr(&self, mutable: bool) -> TokenStream {
+        let binding = &self.binding;
+        match self.with {
+            Some(ref path) if mutable => quote_spanned! {self.span=>
+                #path::as_vs_mut(#binding)
+            },
+            Some(ref path) => quote_spanned! {self.span=>
+                #path::as_vs(#binding)
+            },
+            None => quote_spanned! {self.span=>
+                #binding
+            },
+        }
+    }
+
+    fn dyn_expr(&self, mutable: bool) -> TokenStream {
+        let expr = self.expr(mutable);
+        if mutable {
+            quote_spanned! {self.span=>
+                #expr as &mut dyn vsdb::VsMgmt
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_create_by_base_branch_without_new_version(
-                            &mut self.#id,
-                            br_name,
-                            base_br_name,
-                            force
-                        )
-                        .c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+        } else {
+            quote_spanned! {self.span=>
+                #expr as &dyn vsdb::VsMgmt
             }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+        }
     }
 }
 
-fn gen_branch_create_by_base_branch_version_without_new_version(
-    data: &Data,
-) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                        let id = &f.ident;
-                        quote_spanned! {f.span()=>
-                            vsdb::VsMgmt::branch_create_by_base_branch_version_without_new_version(
-                                &mut self.#id,
-                                br_name,
-                                base_br_name,
-                                base_ver_name,
-                                force
-                            )
-                            .c(d!())?;
-                        }
-                    });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                        let id = Index::from(i);
-                        quote_spanned! {f.span()=>
-                            vsdb::VsMgmt::branch_create_by_base_branch_version_without_new_version(
-                                &mut self.#id,
-                                br_name,
-                                base_br_name,
-                                base_ver_name,
-                                force
-                            )
-                            .c(d!())?;
-                        }
-                    });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+// A structure, or a variant of an enum.
+struct VsVariant {
+    // the pattern binding all the managed fields
+    pat: TokenStream,
+    fields: Vec<VsField>,
+}
+
+fn parse_variants(input: &DeriveInput) -> syn::Result<Vec<VsVariant>> {
+    check_no_attrs(&input.attrs, "structures or enums")?;
+
+    match input.data {
+        Data::Struct(ref data) => parse_variant(quote! { Self }, &data.fields).map(|v| vec![v]),
+        Data::Enum(ref data) => data
+            .variants
+            .iter()
+            .map(|v| {
+                check_no_attrs(&v.attrs, "variants")?;
+                let id = &v.ident;
+                parse_variant(quote! { Self::#id }, &v.fields)
+            })
+            .collect(),
+        Data::Union(ref data) => Err(Error::new_spanned(
+            data.union_token,
+            "`Vs` can not be derived for unions",
+        )),
     }
 }
 
-fn gen_branch_exists(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
+fn parse_variant(path: TokenStream, fields: &Fields) -> syn::Result<VsVariant> {
+    let mut managed = vec![];
+    let mut bindings = vec![];
+
+    for (i, f) in fields.iter().enumerate() {
+        let binding = format_ident!("__vs_field_{}", i, span = f.span());
+        if let Some(with) = parse_field_attrs(f)? {
+            bindings.push(Some(binding.clone()));
+            managed.push(VsField {
+                binding,
+                with,
+                span: f.span(),
+            });
+        } else {
+            bindings.push(None);
+        }
+    }
+
+    let pat = match fields {
+        Fields::Named(ref fields) => {
+            let elems = fields
+                .named
+                .iter()
+                .zip(bindings.iter())
+                .filter_map(|(f, b)| {
                     let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_exists(&self.#id, br_name) &&
-                    }
+                    b.as_ref().map(|b| quote! { #id: #b })
                 });
-                quote! {
-                    #(#recurse)* true
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_exists(&self.#id, br_name) &&
-                    }
-                });
-                quote! {
-                    #(#recurse)* true
-                }
+            quote! { #path { #(#elems,)* .. } }
+        }
+        Fields::Unnamed(_) => {
+            let elems = bindings.iter().map(|b| match b {
+                Some(b) => quote! { #b },
+                None => quote! { _ },
+            });
+            quote! { #path(#(#elems),*) }
+        }
+        Fields::Unit => path,
+    };
+
+    Ok(VsVariant {
+        pat,
+        fields: managed,
+    })
+}
+
+// `None` for skipped fields, or the `path` of `#[vs(with = "path")]`.
+fn parse_field_attrs(f: &Field) -> syn::Result<Option<Option<Path>>> {
+    let mut skip = false;
+    let mut with = None;
+
+    for attr in f.attrs.iter().filter(|attr| attr.path().is_ident("vs")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else if meta.path.is_ident("with") {
+                let path: LitStr = meta.value()?.parse()?;
+                with = Some(path.parse::<Path>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `vs` attribute, expected `skip` or `with = \"path\"`"))
             }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+        })?;
+    }
+
+    match (skip, with) {
+        (true, Some(_)) => Err(Error::new_spanned(
+            f,
+            "`#[vs(skip)]` and `#[vs(with = \"...\")]` can not be used together",
+        )),
+        (true, None) => Ok(None),
+        (false, with) => Ok(Some(with)),
     }
 }
-fn gen_branch_has_versions(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_has_versions(&self.#id, br_name) &&
-                    }
-                });
-                quote! {
-                    #(#recurse)* true
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_has_versions(&self.#id, br_name) &&
-                    }
-                });
-                quote! {
-                    #(#recurse)* true
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+
+fn check_no_attrs(attrs: &[Attribute], target: &str) -> syn::Result<()> {
+    if let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("vs")) {
+        return Err(Error::new_spanned(
+            attr,
+            format!("`#[vs(...)]` can not be applied to {}, only fields", target),
+        ));
     }
+    Ok(())
 }
 
-fn gen_branch_remove(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_remove(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_remove(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
-        },
-        Data::Enum(_) | Data::Union(_) => todo!(),
+// Match on the active variant, and generate the body of
+// every arm from the fields of the corresponding variant.
+fn gen_match(vs: &[VsVariant], arm: impl Fn(&[VsField]) -> TokenStream) -> TokenStream {
+    if vs.is_empty() {
+        return quote! {
+            match *self {}
+        };
+    }
+    let arms = vs.iter().map(|v| {
+        let pat = &v.pat;
+        let body = arm(&v.fields);
+        quote! {
+            #pat => { #body }
+        }
+    });
+    quote! {
+        match self {
+            #(#arms)*
+        }
     }
 }
 
-fn gen_branch_keep_only(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_keep_only(&mut self.#id, br_names).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_keep_only(&mut self.#id, br_names).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
+// Apply `op` to every field, then evaluate the `tail`.
+fn gen_per_field(
+    vs: &[VsVariant],
+    mutable: bool,
+    op: impl Fn(TokenStream) -> TokenStream,
+    tail: TokenStream,
+) -> TokenStream {
+    gen_match(vs, |fields| {
+        let recurse = fields.iter().map(|f| op(f.expr(mutable)));
+        quote! {
+            #(#recurse)* #tail
+        }
+    })
+}
+
+// Write-like operations are applied once per distinct version graph,
+// so a single metadata update covers all the fields sharing that graph.
+fn gen_fan_out(vs: &[VsVariant], op: TokenStream) -> TokenStream {
+    gen_match(vs, |fields| {
+        let fields = fields.iter().map(|f| f.dyn_expr(true));
+        quote! {
+            vsdb::vs_fan_out(&mut [#(#fields),*], |x| #op).c(d!())?;
+        }
+    })
+}
+
+fn gen_version_graphs(vs: &[VsVariant]) -> TokenStream {
+    gen_match(vs, |fields| {
+        let fields = fields.iter().map(|f| f.dyn_expr(false));
+        quote! {
+            vsdb::vs_graphs(&[#(#fields),*])
+        }
+    })
+}
+
+// Fields that manage their versions by themselves are skipped,
+// as `vs_fan_out` does.
+fn gen_version_graph_attach(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        true,
+        |f| {
+            quote! {
+                if vsdb::VsMgmt::version_graphs(#f).is_some() {
+                    vsdb::VsMgmt::version_graph_attach(#f, graph).c(d!())?;
                 }
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_branch_truncate(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_truncate(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+fn gen_version_exists(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                vsdb::VsMgmt::version_exists(#f, ver_name) &&
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_truncate(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! { true },
+    )
 }
 
-fn gen_branch_truncate_to(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                        let id = &f.ident;
-                        quote_spanned! {f.span()=>
-                            vsdb::VsMgmt::branch_truncate_to(&mut self.#id, br_name, last_ver_name).c(d!())?;
-                        }
-                    });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                        let id = Index::from(i);
-                        quote_spanned! {f.span()=>
-                            vsdb::VsMgmt::branch_truncate_to(&mut self.#id, br_name, last_ver_name).c(d!())?;
-                        }
-                    });
-                quote! {
-                    #(#recurse)*
-                }
+fn gen_version_exists_on_branch(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                vsdb::VsMgmt::version_exists_on_branch(#f, ver_name, br_name) &&
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! { true },
+    )
 }
 
-fn gen_branch_pop_version(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_pop_version(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+fn gen_branch_exists(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                vsdb::VsMgmt::branch_exists(#f, br_name) &&
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_pop_version(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! { true },
+    )
 }
 
-fn gen_branch_merge_to(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_merge_to(&mut self.#id, br_name, target_br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_merge_to(&mut self.#id, br_name, target_br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+fn gen_branch_has_versions(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                vsdb::VsMgmt::branch_has_versions(#f, br_name) &&
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! { true },
+    )
 }
 
-fn gen_branch_merge_to_force(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_merge_to_force(&mut self.#id, br_name, target_br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+fn gen_version_exists_globally(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                vsdb::VsMgmt::version_exists_globally(#f, ver_name) &&
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_merge_to_force(&mut self.#id, br_name, target_br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! { true },
+    )
 }
 
-fn gen_branch_set_default(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_set_default(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
+fn gen_version_list(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if guard == guard_default {
+                    guard = vsdb::VsMgmt::version_list(#f).c(d!())?;
                 }
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_set_default(&mut self.#id, br_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_prune(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::prune(&mut self.#id, reserved_ver_num).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
+fn gen_version_list_by_branch(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if guard == guard_default {
+                    guard = vsdb::VsMgmt::version_list_by_branch(#f, br_name).c(d!())?;
                 }
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::prune(&mut self.#id, reserved_ver_num).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_version_exists_globally(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_exists_globally(&self.#id, ver_name) &&
-                    }
-                });
-                quote! {
-                    #(#recurse)* true
+fn gen_version_list_globally(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if guard == guard_default {
+                    guard = vsdb::VsMgmt::version_list_globally(#f);
                 }
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_exists_globally(&self.#id, ver_name) &&
-                    }
-                });
-                quote! {
-                    #(#recurse)* true
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_version_list(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        if guard == guard_default {
-                            guard = vsdb::VsMgmt::version_list(&self.#id).c(d!())?;
-                        }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+fn gen_version_has_change_set(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if !vsdb::VsMgmt::version_has_change_set(#f, ver_name)? { return Ok(false); }
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        if guard == guard_default {
-                            guard = vsdb::VsMgmt::version_list(&self.#id).c(d!())?;
-                        }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_version_list_by_branch(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        if guard == guard_default {
-                            guard = vsdb::VsMgmt::version_list_by_branch(&self.#id, br_name).c(d!())?;
-                        }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        if guard == guard_default {
-                            guard = vsdb::VsMgmt::version_list_by_branch(&self.#id, br_name).c(d!())?;
-                        }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+fn gen_version_chgset_trie_root(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                res.append(&mut vsdb::VsMgmt::version_chgset_trie_root(#f, br_name, ver_name).c(d!())?);
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_version_list_globally(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        if guard == guard_default {
-                            guard = vsdb::VsMgmt::version_list_globally(&self.#id);
-                        }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+fn gen_branch_is_empty(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if !vsdb::VsMgmt::branch_is_empty(#f, br_name).c(d!())? { return Ok(false); }
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        if guard == guard_default {
-                            guard = vsdb::VsMgmt::version_list_globally(&self.#id);
-                        }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_version_has_change_set(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        if !vsdb::VsMgmt::version_has_change_set(&self.#id, ver_name)? { return Ok(false); }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        if !vsdb::VsMgmt::version_has_change_set(&self.#id, ver_name)? { return Ok(false); }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
+fn gen_branch_list(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if guard == guard_default {
+                    guard = vsdb::VsMgmt::branch_list(#f);
                 }
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_version_clean_up_globally(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_clean_up_globally(&mut self.#id).c(d!())?;
+fn gen_branch_get_default(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                let new = vsdb::VsMgmt::branch_get_default(#f);
+                if guard_default != new {
+                    if guard_default == guard {
+                        guard = new;
+                    } else {
+                        assert_eq!(guard, new);
                     }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_clean_up_globally(&mut self.#id).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
                 }
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_version_revert_globally(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_revert_globally(&mut self.#id, ver_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::version_revert_globally(&mut self.#id, ver_name).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
+fn gen_branch_merge_base(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if guard.is_none() {
+                    guard = vsdb::VsMgmt::branch_merge_base(#f, br_name, other_br_name).c(d!())?;
                 }
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_version_chgset_trie_root(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        res.append(&mut vsdb::VsMgmt::version_chgset_trie_root(&self.#id, br_name, ver_name).c(d!())?);
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        res.append(vsdb::VsMgmt::version_chgset_trie_root(&self.#id, br_name, ver_name).c(d!())?);
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+fn gen_version_is_ancestor(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if !vsdb::VsMgmt::version_is_ancestor(#f, ver_name, br_name).c(d!())? { return Ok(false); }
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_branch_is_empty(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        if !vsdb::VsMgmt::branch_is_empty(&self.#id, br_name).c(d!())? { return Ok(false); }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
+fn gen_branches_containing(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if guard.is_empty() {
+                    guard = vsdb::VsMgmt::branches_containing(#f, ver_name).c(d!())?;
                 }
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        if !vsdb::VsMgmt::branch_is_empty(&self.#id, br_name).c(d!())? { return Ok(false); }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_branch_list(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        if guard == guard_default {
-                            guard = vsdb::VsMgmt::branch_list(&self.#id);
-                        }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
+fn gen_branch_parent(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if guard.is_none() {
+                    guard = vsdb::VsMgmt::branch_parent(#f, br_name).c(d!())?;
                 }
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        if guard == guard_default {
-                            guard = vsdb::VsMgmt::branch_list(&self.#id);
-                        }
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_branch_get_default(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        let new = vsdb::VsMgmt::branch_get_default(&self.#id);
-                        if guard_default != new {
-                            if guard_default == guard {
-                                guard = new;
-                            } else {
-                                assert_eq!(guard, new);
-                            }
-                        }
+// Renaming is checked on all the stateful fields before any change,
+// so that the names will not be left half-renamed.
+fn gen_branch_rename_check(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if vsdb::VsMgmt::version_graphs(#f).map(|gs| !gs.is_empty()).unwrap_or(true) {
+                    if !vsdb::VsMgmt::branch_exists(#f, br_name) {
+                        return Err(eg!("branch not found"));
                     }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        let new = vsdb::VsMgmt::branch_get_default(&self.#id);
-                        if guard_default != new {
-                            if guard_default == guard {
-                                guard = new;
-                            } else {
-                                assert_eq!(guard, new);
-                            }
-                        }
+                    if vsdb::VsMgmt::branch_exists(#f, new_br_name) {
+                        return Err(eg!("branch already exists"));
                     }
-                });
-                quote! {
-                    #(#recurse)*
                 }
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
 
-fn gen_branch_swap(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let id = &f.ident;
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_swap(&mut self.#id, br1, br2).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let id = Index::from(i);
-                    quote_spanned! {f.span()=>
-                        vsdb::VsMgmt::branch_swap(&mut self.#id, br1, br2).c(d!())?;
-                    }
-                });
-                quote! {
-                    #(#recurse)*
+fn gen_branch_info(vs: &[VsVariant]) -> TokenStream {
+    gen_per_field(
+        vs,
+        false,
+        |f| {
+            quote! {
+                if guard == guard_default {
+                    guard = vsdb::VsMgmt::branch_info(#f, br_name).c(d!())?;
                 }
             }
-            Fields::Unit => todo!(),
         },
-        Data::Enum(_) | Data::Union(_) => todo!(),
-    }
+        quote! {},
+    )
 }
@@ -22,8 +22,8 @@ mod real {
         thread,
     };
     use vsdb::{
-        BranchName, MapxVs, OrphanVs, ValueEnDe, VecxVs, VersionName, VersionNameOwned,
-        Vs, VsMgmt,
+        BranchName, MapxVs, OrphanVs, ValueEnDe, VecxVs, VersionGraph, VersionName,
+        VersionNameOwned, Vs, VsMgmt,
     };
 
     type Amount = u64;
@@ -107,6 +107,10 @@ mod real {
                 a_consensus_int: OrphanVs::new(),
             };
 
+            // all fields share one version graph,
+            // so versions and branches are created in a single step
+            ws.version_graph_attach(&VersionGraph::new()).c(d!())?;
+
             if !ws.branch_is_found(MASTER_BRANCH) {
                 ws.push_version(b"init version").c(d!())?;
                 ws.new_branch(MASTER_BRANCH).c(d!())?;
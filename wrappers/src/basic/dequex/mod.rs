@@ -0,0 +1,257 @@
+//!
+//! A disk-storage replacement for the in-memory VecDeque.
+//!
+//! NOTE:
+//! - Values will be encoded by some `serde`-like methods
+//! - Elements are stored by their positions in an ordered key space,
+//!   the head is the first position and the tail is counted from it by the length,
+//!   so pushing or popping at both ends will not move any other element
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::Dequex;
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut l = Dequex::new();
+//!
+//! l.push_back(&1);
+//! l.push_back(&2);
+//! l.push_front(&0);
+//! assert_eq!(l.iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+//! assert_eq!(l.get(1), Some(1));
+//!
+//! assert_eq!(l.pop_front(), Some(0));
+//! assert_eq!(l.pop_back(), Some(2));
+//! assert_eq!(l.front(), Some(1));
+//! assert_eq!(l.back(), Some(1));
+//!
+//! l.clear();
+//! assert!(l.is_empty());
+//! ```
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::mapx_ord_rawkey::{MapxOrdRawKey, MapxOrdRawKeyIter},
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::ValueEnDe,
+        migrate::{Migrate, Migration},
+    },
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use vsdb_core::{common::PreBytes, Registrable};
+
+// The position of the first element pushed into an empty queue,
+// leaves enough room for both ends.
+pub(crate) const MID_POS: u64 = 1 << 63;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(bound = "")]
+pub struct Dequex<T, C = DefaultCodec> {
+    inner: MapxOrdRawKey<T, C>,
+}
+
+impl<T, C: Codec<T>> Dequex<T, C> {
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Do not use this API unless you know the internal details extremely well.
+    #[inline(always)]
+    pub unsafe fn from_bytes(s: impl AsRef<[u8]>) -> Self {
+        Self {
+            inner: MapxOrdRawKey::from_bytes(s),
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
+        Dequex {
+            inner: MapxOrdRawKey::with_codec(),
+        }
+    }
+
+    /// Get the element by its index counted from the front.
+    #[inline(always)]
+    pub fn get(&self, idx: usize) -> Option<T> {
+        alt!(idx >= self.len(), return None);
+        self.inner.get((self.head()? + idx as u64).to_be_bytes())
+    }
+
+    #[inline(always)]
+    pub fn front(&self) -> Option<T> {
+        self.inner.first().map(|(_, v)| v)
+    }
+
+    #[inline(always)]
+    pub fn back(&self) -> Option<T> {
+        self.inner.get(self.tail()?.to_be_bytes())
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn push_front(&mut self, v: &T) {
+        let p = self.head().map(|h| h - 1);
+        self.inner.insert(p.unwrap_or(MID_POS).to_be_bytes(), v);
+    }
+
+    #[inline(always)]
+    pub fn push_back(&mut self, v: &T) {
+        let p = self.tail().map(|t| t + 1);
+        self.inner.insert(p.unwrap_or(MID_POS).to_be_bytes(), v);
+    }
+
+    #[inline(always)]
+    pub fn pop_front(&mut self) -> Option<T> {
+        let h = self.head()?;
+        self.inner.remove(h.to_be_bytes())
+    }
+
+    #[inline(always)]
+    pub fn pop_back(&mut self) -> Option<T> {
+        let t = self.tail()?;
+        self.inner.remove(t.to_be_bytes())
+    }
+
+    /// Iterate from the front to the back.
+    #[inline(always)]
+    pub fn iter(&self) -> DequexIter<T, C> {
+        DequexIter(self.inner.iter())
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn head(&self) -> Option<u64> {
+        self.inner.first().map(|(k, _)| pos(&k))
+    }
+
+    fn tail(&self) -> Option<u64> {
+        self.head().map(|h| h + self.len() as u64 - 1)
+    }
+}
+
+impl<T> Dequex<T>
+where
+    T: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<T, C> Clone for Dequex<T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, C> Registrable for Dequex<T, C> {
+    const KIND: &'static str = "Dequex";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<Old, New, C> Migrate<Old, New> for Dequex<Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = Dequex<New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((Self::Output { inner }, m))
+    }
+}
+
+impl<T, C: Codec<T>> Default for Dequex<T, C> {
+    fn default() -> Self {
+        Self::with_codec()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "vs")]
+impl<T, C> vsdb_core::VsMgmt for Dequex<T, C> {
+    vsdb_core::impl_vs_methods_nope! {}
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct DequexIter<'a, T, C = DefaultCodec>(MapxOrdRawKeyIter<'a, T, C>);
+
+impl<'a, T, C> Iterator for DequexIter<'a, T, C>
+where
+    C: Codec<T>,
+{
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+}
+
+impl<'a, T, C> DoubleEndedIterator for DequexIter<'a, T, C>
+where
+    C: Codec<T>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(_, v)| v)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[inline(always)]
+pub(crate) fn pos(k: &[u8]) -> u64 {
+    crate::parse_int!(k, u64)
+}
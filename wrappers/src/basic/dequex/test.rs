@@ -0,0 +1,50 @@
+use super::*;
+
+#[test]
+fn test_both_ends() {
+    let mut hdr: Dequex<usize> = Dequex::new();
+    assert!(hdr.pop_front().is_none());
+    assert!(hdr.pop_back().is_none());
+
+    (0..100).for_each(|i| {
+        hdr.push_back(&(100 + i));
+        hdr.push_front(&(99 - i));
+    });
+    assert_eq!(200, hdr.len());
+    assert_eq!(hdr.iter().collect::<Vec<_>>(), (0..200).collect::<Vec<_>>());
+    assert_eq!(Some(199), hdr.iter().next_back());
+    (0..200).for_each(|i| {
+        assert_eq!(Some(i), hdr.get(i));
+    });
+    assert!(hdr.get(200).is_none());
+
+    (0..50).for_each(|i| {
+        assert_eq!(Some(i), hdr.pop_front());
+        assert_eq!(Some(199 - i), hdr.pop_back());
+    });
+    assert_eq!(Some(50), hdr.front());
+    assert_eq!(Some(149), hdr.back());
+    assert_eq!(Some(50), hdr.get(0));
+
+    // an emptied queue restarts from the middle
+    (0..100).for_each(|_| {
+        hdr.pop_back();
+    });
+    assert!(hdr.is_empty());
+    hdr.push_front(&1);
+    hdr.push_back(&2);
+    assert_eq!(hdr.iter().collect::<Vec<_>>(), vec![1, 2]);
+
+    hdr.clear();
+    assert!(hdr.is_empty());
+    assert!(hdr.front().is_none());
+}
+
+#[test]
+fn test_valueende() {
+    let mut hdr: Dequex<usize> = Dequex::new();
+    (0..10).for_each(|i| hdr.push_front(&i));
+    let hdr: Dequex<usize> = pnk!(ValueEnDe::decode(&hdr.encode()));
+    assert_eq!(Some(9), hdr.front());
+    assert_eq!(Some(0), hdr.back());
+}
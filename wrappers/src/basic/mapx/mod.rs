@@ -43,26 +43,35 @@ use crate::{
             self, MapxOrdRawKey, MapxOrdRawKeyIter, MapxOrdRawKeyIterMut, ValueMut,
         },
     },
-    common::ende::{KeyEnDe, ValueEnDe},
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::{KeyEnDe, ValueEnDe},
+        migrate::{Migrate, Migration},
+        overlay::{Overlay, OverlayBase},
+        RawKey,
+    },
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     marker::PhantomData,
     ops::{Deref, DerefMut},
+    sync::Arc,
 };
+use vsdb_core::{common::PreBytes, Registrable};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
-pub struct Mapx<K, V> {
-    inner: MapxOrdRawKey<V>,
+pub struct Mapx<K, V, C = DefaultCodec> {
+    inner: MapxOrdRawKey<V, C>,
     _p: PhantomData<K>,
 }
 
-impl<K, V> Mapx<K, V>
+impl<K, V, C> Mapx<K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     /// # Safety
     ///
@@ -76,6 +85,21 @@ where
         }
     }
 
+    /// A cheap copy-on-write copy, nothing is copied,
+    /// its writes are buffered in memory over the data of this instance,
+    /// see [Overlay](crate::Overlay).
+    ///
+    /// NOTE: the later writes of this instance are visible to the copy
+    /// except on the keys it has changed, and committing the copy
+    /// writes its changes into this instance.
+    #[inline(always)]
+    pub fn fork(&self) -> Overlay<Self>
+    where
+        V: Clone,
+    {
+        Overlay::new(unsafe { self.shadow() })
+    }
+
     /// # Safety
     ///
     /// Do not use this API unless you know the internal details extremely well.
@@ -92,10 +116,11 @@ where
         self.inner.as_bytes()
     }
 
+    /// Create an instance that encodes values by `C`.
     #[inline(always)]
-    pub fn new() -> Self {
+    pub fn with_codec() -> Self {
         Self {
-            inner: MapxOrdRawKey::new(),
+            inner: MapxOrdRawKey::with_codec(),
             _p: PhantomData,
         }
     }
@@ -106,7 +131,7 @@ where
     }
 
     #[inline(always)]
-    pub fn get_mut(&mut self, key: &K) -> Option<ValueMut<'_, V>> {
+    pub fn get_mut(&mut self, key: &K) -> Option<ValueMut<'_, V, C>> {
         self.inner.get_mut(key.encode())
     }
 
@@ -136,7 +161,7 @@ where
     }
 
     #[inline(always)]
-    pub fn entry(&mut self, key: &K) -> Entry<'_, V> {
+    pub fn entry(&mut self, key: &K) -> Entry<'_, V, C> {
         Entry {
             key: key.encode(),
             hdr: &mut self.inner,
@@ -144,7 +169,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxIter<K, V> {
+    pub fn iter(&self) -> MapxIter<K, V, C> {
         MapxIter {
             iter: self.inner.iter(),
             _p: PhantomData,
@@ -152,7 +177,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxIterMut<K, V> {
+    pub fn iter_mut(&mut self) -> MapxIterMut<K, V, C> {
         MapxIterMut {
             inner: self.inner.iter_mut(),
             _p: PhantomData,
@@ -160,17 +185,16 @@ where
     }
 
     #[inline(always)]
-    pub fn values(&self) -> MapxValues<V> {
+    pub fn values(&self) -> MapxValues<V, C> {
         MapxValues {
             inner: self.inner.iter(),
         }
     }
 
     #[inline(always)]
-    pub fn values_mut(&mut self) -> MapxValuesMut<V> {
+    pub fn values_mut(&mut self) -> MapxValuesMut<V, C> {
         MapxValuesMut {
-            inner: self.inner.inner.iter_mut(),
-            _p: PhantomData,
+            inner: self.inner.iter_mut(),
         }
     }
 
@@ -188,9 +212,27 @@ where
     pub fn clear(&mut self) {
         self.inner.clear();
     }
+
+    #[inline(always)]
+    pub fn compact_instance(&self) {
+        self.inner.compact_instance();
+    }
 }
 
-impl<K, V> Clone for Mapx<K, V> {
+impl<K, V> Mapx<K, V>
+where
+    K: KeyEnDe,
+    V: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<K, V, C> Clone for Mapx<K, V, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -199,32 +241,116 @@ impl<K, V> Clone for Mapx<K, V> {
     }
 }
 
-impl<K, V> Default for Mapx<K, V>
+impl<K, V, C> Registrable for Mapx<K, V, C> {
+    const KIND: &'static str = "Mapx";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K, Old, New, C> Migrate<Old, New> for Mapx<K, Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = Mapx<K, New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((
+            Self::Output {
+                inner,
+                _p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<K, V, C> OverlayBase for Mapx<K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
+{
+    type Key = K;
+    type Value = V;
+
+    #[inline(always)]
+    fn overlay_encode_key(key: &K) -> RawKey {
+        key.encode()
+    }
+
+    #[inline(always)]
+    fn overlay_decode_key(key: &[u8]) -> Result<K> {
+        <K as KeyEnDe>::decode(key).c(d!())
+    }
+
+    #[inline(always)]
+    fn overlay_get(&self, key: &[u8]) -> Option<V> {
+        self.inner.get(key)
+    }
+
+    #[inline(always)]
+    fn overlay_len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    fn overlay_iter(&self) -> Box<dyn Iterator<Item = (RawKey, V)> + '_> {
+        Box::new(self.inner.iter())
+    }
+
+    #[inline(always)]
+    fn overlay_insert(&mut self, key: &[u8], value: &V) -> Result<()> {
+        self.inner.insert(key, value);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn overlay_remove(&mut self, key: &[u8]) -> Result<()> {
+        self.inner.remove(key);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn overlay_apply(&mut self, changes: BTreeMap<RawKey, Option<V>>) -> Result<()> {
+        self.inner.write_batch(&changes);
+        Ok(())
+    }
+}
+
+impl<K, V, C> Default for Mapx<K, V, C>
+where
+    K: KeyEnDe,
+    C: Codec<V>,
 {
     fn default() -> Self {
-        Self::new()
+        Self::with_codec()
     }
 }
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxIter<'a, K, V>
+pub struct MapxIter<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    iter: MapxOrdRawKeyIter<'a, V>,
+    iter: MapxOrdRawKeyIter<'a, V, C>,
     _p: PhantomData<K>,
 }
 
-impl<'a, K, V> Iterator for MapxIter<'a, K, V>
+impl<'a, K, V, C> Iterator for MapxIter<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Item = (K, V);
     fn next(&mut self) -> Option<Self::Item> {
@@ -234,10 +360,10 @@ where
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for MapxIter<'a, K, V>
+impl<'a, K, V, C> DoubleEndedIterator for MapxIter<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.iter
@@ -249,21 +375,21 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxIterMut<'a, K, V>
+pub struct MapxIterMut<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyIterMut<'a, V>,
+    inner: MapxOrdRawKeyIterMut<'a, V, C>,
     _p: PhantomData<K>,
 }
 
-impl<'a, K, V> Iterator for MapxIterMut<'a, K, V>
+impl<'a, K, V, C> Iterator for MapxIterMut<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    type Item = (K, ValueIterMut<'a, V>);
+    type Item = (K, ValueIterMut<'a, V, C>);
     fn next(&mut self) -> Option<Self::Item> {
         self.inner
             .next()
@@ -271,10 +397,10 @@ where
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for MapxIterMut<'a, K, V>
+impl<'a, K, V, C> DoubleEndedIterator for MapxIterMut<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner
@@ -286,20 +412,20 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-type MapxValues<'a, V> = MapxOrdValues<'a, V>;
-type MapxValuesMut<'a, V> = MapxOrdValuesMut<'a, V>;
+type MapxValues<'a, V, C> = MapxOrdValues<'a, V, C>;
+type MapxValuesMut<'a, V, C> = MapxOrdValuesMut<'a, V, C>;
 
 #[derive(Debug)]
-pub struct ValueIterMut<'a, V>
+pub struct ValueIterMut<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    pub(crate) inner: mapx_ord_rawkey::ValueIterMut<'a, V>,
+    pub(crate) inner: mapx_ord_rawkey::ValueIterMut<'a, V, C>,
 }
 
-impl<'a, V> Deref for ValueIterMut<'a, V>
+impl<'a, V, C> Deref for ValueIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Target = V;
     fn deref(&self) -> &Self::Target {
@@ -307,9 +433,9 @@ where
     }
 }
 
-impl<'a, V> DerefMut for ValueIterMut<'a, V>
+impl<'a, V, C> DerefMut for ValueIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.inner
@@ -323,7 +449,7 @@ where
 /////////////////////////////////////////////////////////////////////////////
 
 #[cfg(feature = "vs")]
-impl<K, V> vsdb_core::VsMgmt for Mapx<K, V> {
+impl<K, V, C> vsdb_core::VsMgmt for Mapx<K, V, C> {
     vsdb_core::impl_vs_methods_nope! {}
 }
 
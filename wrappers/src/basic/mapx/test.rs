@@ -112,3 +112,30 @@ fn test_values_first_last() {
     let value = pnk!(hdr.values().next_back());
     assert_eq!(max - 1, value);
 }
+
+#[test]
+fn test_fork() {
+    let mut hdr: Mapx<usize, usize> = Mapx::new();
+    (0..100).for_each(|i| {
+        hdr.insert(&i, &i);
+    });
+
+    let mut fork = hdr.fork();
+    fork.insert(&1, &10);
+    fork.remove(&2);
+    assert_eq!(Some(10), fork.get(&1));
+    assert!(fork.get(&2).is_none());
+    assert_eq!(99, fork.len());
+    assert_eq!(Some(1), hdr.get(&1));
+    assert_eq!(Some(2), hdr.get(&2));
+    assert_eq!(100, hdr.len());
+
+    // discarded without touching the original
+    fork.discard();
+    assert_eq!(Some(1), hdr.get(&1));
+
+    let mut fork = hdr.fork();
+    fork.insert(&1, &10);
+    pnk!(fork.commit());
+    assert_eq!(Some(10), hdr.get(&1));
+}
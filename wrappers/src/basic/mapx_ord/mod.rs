@@ -39,9 +39,14 @@
 mod test;
 
 use crate::{
-    basic::mapx_ord_rawkey::{MapxOrdRawKey, MapxOrdRawKeyIter, ValueIterMut, ValueMut},
+    basic::mapx_ord_rawkey::{
+        MapxOrdRawKey, MapxOrdRawKeyIter, MapxOrdRawKeyIterMut, ValueIterMut, ValueMut,
+    },
     common::{
+        codec::{Codec, DefaultCodec},
         ende::{KeyEnDeOrdered, ValueEnDe},
+        migrate::{Migrate, Migration},
+        overlay::{Overlay, OverlayBase},
         RawKey,
     },
 };
@@ -49,22 +54,24 @@ use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
     marker::PhantomData,
     ops::{Bound, RangeBounds},
+    sync::Arc,
 };
-use vsdb_core::basic::mapx_raw;
+use vsdb_core::{common::PreBytes, Registrable};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
-pub struct MapxOrd<K, V> {
-    inner: MapxOrdRawKey<V>,
+pub struct MapxOrd<K, V, C = DefaultCodec> {
+    pub(crate) inner: MapxOrdRawKey<V, C>,
     _p: PhantomData<K>,
 }
 
-impl<K, V> MapxOrd<K, V>
+impl<K, V, C> MapxOrd<K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     /// # Safety
     ///
@@ -78,6 +85,21 @@ where
         }
     }
 
+    /// A cheap copy-on-write copy, nothing is copied,
+    /// its writes are buffered in memory over the data of this instance,
+    /// see [Overlay](crate::Overlay).
+    ///
+    /// NOTE: the later writes of this instance are visible to the copy
+    /// except on the keys it has changed, and committing the copy
+    /// writes its changes into this instance.
+    #[inline(always)]
+    pub fn fork(&self) -> Overlay<Self>
+    where
+        V: Clone,
+    {
+        Overlay::new(unsafe { self.shadow() })
+    }
+
     /// # Safety
     ///
     /// Do not use this API unless you know the internal details extremely well.
@@ -94,10 +116,11 @@ where
         self.inner.as_bytes()
     }
 
+    /// Create an instance that encodes values by `C`.
     #[inline(always)]
-    pub fn new() -> Self {
+    pub fn with_codec() -> Self {
         MapxOrd {
-            inner: MapxOrdRawKey::new(),
+            inner: MapxOrdRawKey::with_codec(),
             _p: PhantomData,
         }
     }
@@ -108,7 +131,7 @@ where
     }
 
     #[inline(always)]
-    pub fn get_mut<'a>(&'a mut self, key: &K) -> Option<ValueMut<'a, V>> {
+    pub fn get_mut<'a>(&'a mut self, key: &K) -> Option<ValueMut<'a, V, C>> {
         self.inner.get_mut(key.to_bytes())
     }
 
@@ -165,7 +188,7 @@ where
     }
 
     #[inline(always)]
-    pub fn entry(&mut self, key: &K) -> Entry<'_, V> {
+    pub fn entry(&mut self, key: &K) -> Entry<'_, V, C> {
         Entry {
             key: key.to_bytes(),
             hdr: &mut self.inner,
@@ -173,7 +196,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxOrdIter<K, V> {
+    pub fn iter(&self) -> MapxOrdIter<K, V, C> {
         MapxOrdIter {
             inner: self.inner.iter(),
             _p: PhantomData,
@@ -181,30 +204,29 @@ where
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxOrdIterMut<K, V> {
+    pub fn iter_mut(&mut self) -> MapxOrdIterMut<K, V, C> {
         MapxOrdIterMut {
-            inner: self.inner.inner.iter_mut(),
+            inner: self.inner.iter_mut(),
             _p: PhantomData,
         }
     }
 
     #[inline(always)]
-    pub fn values(&self) -> MapxOrdValues<V> {
+    pub fn values(&self) -> MapxOrdValues<V, C> {
         MapxOrdValues {
             inner: self.inner.iter(),
         }
     }
 
     #[inline(always)]
-    pub fn values_mut(&mut self) -> MapxOrdValuesMut<V> {
+    pub fn values_mut(&mut self) -> MapxOrdValuesMut<V, C> {
         MapxOrdValuesMut {
-            inner: self.inner.inner.iter_mut(),
-            _p: PhantomData,
+            inner: self.inner.iter_mut(),
         }
     }
 
     #[inline(always)]
-    pub fn range<R: RangeBounds<K>>(&self, bounds: R) -> MapxOrdIter<'_, K, V> {
+    pub fn range<R: RangeBounds<K>>(&self, bounds: R) -> MapxOrdIter<'_, K, V, C> {
         let l = match bounds.start_bound() {
             Bound::Included(lo) => Bound::Included(Cow::Owned(lo.to_bytes())),
             Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(lo.to_bytes())),
@@ -227,7 +249,7 @@ where
     pub fn range_mut<R: RangeBounds<K>>(
         &mut self,
         bounds: R,
-    ) -> MapxOrdIterMut<'_, K, V> {
+    ) -> MapxOrdIterMut<'_, K, V, C> {
         let l = match bounds.start_bound() {
             Bound::Included(lo) => Bound::Included(Cow::Owned(lo.to_bytes())),
             Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(lo.to_bytes())),
@@ -241,7 +263,7 @@ where
         };
 
         MapxOrdIterMut {
-            inner: self.inner.inner.range_mut((l, h)),
+            inner: self.inner.range_mut((l, h)),
             _p: PhantomData,
         }
     }
@@ -270,9 +292,27 @@ where
     pub fn clear(&mut self) {
         self.inner.clear();
     }
+
+    #[inline(always)]
+    pub fn compact_instance(&self) {
+        self.inner.compact_instance();
+    }
+}
+
+impl<K, V> MapxOrd<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
 }
 
-impl<K, V> Clone for MapxOrd<K, V> {
+impl<K, V, C> Clone for MapxOrd<K, V, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -281,32 +321,116 @@ impl<K, V> Clone for MapxOrd<K, V> {
     }
 }
 
-impl<K, V> Default for MapxOrd<K, V>
+impl<K, V, C> Registrable for MapxOrd<K, V, C> {
+    const KIND: &'static str = "MapxOrd";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K, Old, New, C> Migrate<Old, New> for MapxOrd<K, Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = MapxOrd<K, New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((
+            Self::Output {
+                inner,
+                _p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<K, V, C> OverlayBase for MapxOrd<K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
+{
+    type Key = K;
+    type Value = V;
+
+    #[inline(always)]
+    fn overlay_encode_key(key: &K) -> RawKey {
+        key.to_bytes()
+    }
+
+    #[inline(always)]
+    fn overlay_decode_key(key: &[u8]) -> Result<K> {
+        K::from_slice(key).c(d!())
+    }
+
+    #[inline(always)]
+    fn overlay_get(&self, key: &[u8]) -> Option<V> {
+        self.inner.get(key)
+    }
+
+    #[inline(always)]
+    fn overlay_len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    fn overlay_iter(&self) -> Box<dyn Iterator<Item = (RawKey, V)> + '_> {
+        Box::new(self.inner.iter())
+    }
+
+    #[inline(always)]
+    fn overlay_insert(&mut self, key: &[u8], value: &V) -> Result<()> {
+        self.inner.insert(key, value);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn overlay_remove(&mut self, key: &[u8]) -> Result<()> {
+        self.inner.remove(key);
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn overlay_apply(&mut self, changes: BTreeMap<RawKey, Option<V>>) -> Result<()> {
+        self.inner.write_batch(&changes);
+        Ok(())
+    }
+}
+
+impl<K, V, C> Default for MapxOrd<K, V, C>
+where
+    K: KeyEnDeOrdered,
+    C: Codec<V>,
 {
     fn default() -> Self {
-        Self::new()
+        Self::with_codec()
     }
 }
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdIter<'a, K, V>
+pub struct MapxOrdIter<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyIter<'a, V>,
+    inner: MapxOrdRawKeyIter<'a, V, C>,
     _p: PhantomData<K>,
 }
 
-impl<'a, K, V> Iterator for MapxOrdIter<'a, K, V>
+impl<'a, K, V, C> Iterator for MapxOrdIter<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Item = (K, V);
     fn next(&mut self) -> Option<Self::Item> {
@@ -314,10 +438,10 @@ where
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for MapxOrdIter<'a, K, V>
+impl<'a, K, V, C> DoubleEndedIterator for MapxOrdIter<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner
@@ -329,16 +453,16 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdValues<'a, V>
+pub struct MapxOrdValues<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    pub(crate) inner: MapxOrdRawKeyIter<'a, V>,
+    pub(crate) inner: MapxOrdRawKeyIter<'a, V, C>,
 }
 
-impl<'a, V> Iterator for MapxOrdValues<'a, V>
+impl<'a, V, C> Iterator for MapxOrdValues<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Item = V;
     fn next(&mut self) -> Option<Self::Item> {
@@ -346,9 +470,9 @@ where
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxOrdValues<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxOrdValues<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|(_, v)| v)
@@ -358,104 +482,85 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdValuesMut<'a, V>
+pub struct MapxOrdValuesMut<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    pub(crate) inner: mapx_raw::MapxRawIterMut<'a>,
-    pub(crate) _p: PhantomData<V>,
+    pub(crate) inner: MapxOrdRawKeyIterMut<'a, V, C>,
 }
 
-impl<'a, V> Iterator for MapxOrdValuesMut<'a, V>
+impl<'a, V, C> Iterator for MapxOrdValuesMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    type Item = ValueIterMut<'a, V>;
+    type Item = ValueIterMut<'a, V, C>;
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(_, v)| ValueIterMut {
-            value: pnk!(<V as ValueEnDe>::decode(&v)),
-            inner: v,
-        })
+        self.inner.next().map(|(_, v)| v)
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxOrdValuesMut<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxOrdValuesMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner.next_back().map(|(_, v)| ValueIterMut {
-            value: pnk!(<V as ValueEnDe>::decode(&v)),
-            inner: v,
-        })
+        self.inner.next_back().map(|(_, v)| v)
     }
 }
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdIterMut<'a, K, V>
+pub struct MapxOrdIterMut<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: mapx_raw::MapxRawIterMut<'a>,
-    _p: PhantomData<(K, V)>,
+    inner: MapxOrdRawKeyIterMut<'a, V, C>,
+    _p: PhantomData<K>,
 }
 
-impl<'a, K, V> Iterator for MapxOrdIterMut<'a, K, V>
+impl<'a, K, V, C> Iterator for MapxOrdIterMut<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    type Item = (K, ValueIterMut<'a, V>);
+    type Item = (K, ValueIterMut<'a, V, C>);
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(k, v)| {
-            (
-                pnk!(<K as KeyEnDeOrdered>::from_bytes(k)),
-                ValueIterMut {
-                    value: <V as ValueEnDe>::decode(&v).unwrap(),
-                    inner: v,
-                },
-            )
-        })
+        self.inner
+            .next()
+            .map(|(k, v)| (pnk!(<K as KeyEnDeOrdered>::from_bytes(k)), v))
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for MapxOrdIterMut<'a, K, V>
+impl<'a, K, V, C> DoubleEndedIterator for MapxOrdIterMut<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner.next_back().map(|(k, v)| {
-            (
-                pnk!(<K as KeyEnDeOrdered>::from_bytes(k)),
-                ValueIterMut {
-                    value: <V as ValueEnDe>::decode(&v).unwrap(),
-                    inner: v,
-                },
-            )
-        })
+        self.inner
+            .next_back()
+            .map(|(k, v)| (pnk!(<K as KeyEnDeOrdered>::from_bytes(k)), v))
     }
 }
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct Entry<'a, V>
+pub struct Entry<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     pub(crate) key: RawKey,
-    pub(crate) hdr: &'a mut MapxOrdRawKey<V>,
+    pub(crate) hdr: &'a mut MapxOrdRawKey<V, C>,
 }
 
-impl<'a, V> Entry<'a, V>
+impl<'a, V, C> Entry<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    pub fn or_insert(self, default: &V) -> ValueMut<'a, V> {
+    pub fn or_insert(self, default: &V) -> ValueMut<'a, V, C> {
         if !self.hdr.contains_key(&self.key) {
             self.hdr.set_value(&self.key, default);
         }
@@ -467,7 +572,7 @@ where
 /////////////////////////////////////////////////////////////////////////////
 
 #[cfg(feature = "vs")]
-impl<K, V> vsdb_core::VsMgmt for MapxOrd<K, V> {
+impl<K, V, C> vsdb_core::VsMgmt for MapxOrd<K, V, C> {
     vsdb_core::impl_vs_methods_nope! {}
 }
 
@@ -0,0 +1,391 @@
+//!
+//! A `BTreeMap`-like structure with secondary indexes.
+//!
+//! NOTE:
+//!
+//! - Index keys are produced from values by extractor closures,
+//!   all the indexes are updated along with each write of the map
+//!     - Each write and its index updates are applied atomically in one batch
+//! - Extractors can not be persisted,
+//!   re-register them by [add_index](MapxOrdIndexed::add_index) after a decoding,
+//!   writes will fail if the extractor of some index is missing
+//! - The index keys of an index must be of the same type
+//! - An index key keeps its emptied set of primary keys after its last entry
+//!   is removed, the set will be reused when the index key appears again
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::basic::mapx_ord_indexed::MapxOrdIndexed;
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! // animal ID => (owner, age)
+//! let mut m: MapxOrdIndexed<u64, (String, u8)> = MapxOrdIndexed::new();
+//! m.add_index("owner", |v: &(String, u8)| Some(v.0.clone()));
+//! m.add_index("age", |v: &(String, u8)| Some(v.1));
+//!
+//! m.insert(&1, &("alice".to_owned(), 3)).unwrap();
+//! m.insert(&2, &("bob".to_owned(), 5)).unwrap();
+//! m.insert(&3, &("alice".to_owned(), 7)).unwrap();
+//!
+//! let alice = m.get_by_index("owner", &"alice".to_owned()).unwrap();
+//! assert_eq!(alice.len(), 2);
+//!
+//! let young = m.range_by_index("age", ..6u8).unwrap();
+//! assert_eq!(young.len(), 2);
+//!
+//! m.remove(&1).unwrap();
+//! let alice = m.get_by_index("owner", &"alice".to_owned()).unwrap();
+//! assert_eq!(alice, vec![(3, ("alice".to_owned(), 7))]);
+//! ```
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::{
+        mapx::Mapx,
+        mapx_ord::{MapxOrd, MapxOrdIter},
+        mapx_ord_rawkey::MapxOrdRawKey,
+    },
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::{KeyEnDeOrdered, ValueEnDe},
+        RawKey,
+    },
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+use vsdb_core::{common::PreBytes, MapxRaw, MapxRawBatch, Registrable};
+
+type Extractor<V> = Arc<dyn Fn(&V) -> Vec<RawKey> + Send + Sync>;
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MapxOrdIndexed<K, V, C = DefaultCodec> {
+    map: MapxOrd<K, V, C>,
+    // index name => index key => primary keys
+    indexes: Mapx<String, MapxOrdRawKey<MapxRaw>>,
+    #[serde(skip)]
+    extractors: BTreeMap<String, Extractor<V>>,
+}
+
+impl<K, V, C> MapxOrdIndexed<K, V, C>
+where
+    K: KeyEnDeOrdered,
+    C: Codec<V>,
+{
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
+        Self {
+            map: MapxOrd::with_codec(),
+            indexes: Mapx::new(),
+            extractors: BTreeMap::new(),
+        }
+    }
+
+    /// Register an index by its extractor,
+    /// a new index will be built from the existing entries,
+    /// an existing one will be used as is.
+    pub fn add_index<I, T, F>(&mut self, name: &str, f: F)
+    where
+        I: KeyEnDeOrdered,
+        T: IntoIterator<Item = I>,
+        F: Fn(&V) -> T + Send + Sync + 'static,
+    {
+        let f: Extractor<V> =
+            Arc::new(move |v| f(v).into_iter().map(|i| i.into_bytes()).collect());
+        let name = name.to_owned();
+
+        if !self.indexes.contains_key(&name) {
+            let mut idx = MapxOrdRawKey::new();
+            for (k, v) in self.map.iter() {
+                let pk = k.to_bytes();
+                let mut batch = MapxRawBatch::new();
+                f(&v).into_iter().collect::<BTreeSet<_>>().iter().for_each(|ik| {
+                    index_add(&mut batch, &mut idx, ik, &pk);
+                });
+                batch.commit();
+            }
+            self.indexes.insert(&name, &idx);
+        }
+
+        self.extractors.insert(name, f);
+    }
+
+    /// Remove an index and all its data.
+    pub fn remove_index(&mut self, name: &str) -> bool {
+        let name = name.to_owned();
+        self.extractors.remove(&name);
+        if let Some(mut idx) = self.indexes.remove(&name) {
+            idx.iter().for_each(|(_, mut pks)| pks.clear());
+            idx.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    #[inline(always)]
+    pub fn index_names(&self) -> Vec<String> {
+        self.indexes.iter().map(|(name, _)| name).collect()
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.map.get(key)
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> MapxOrdIter<K, V, C> {
+        self.map.iter()
+    }
+
+    #[inline(always)]
+    pub fn range<R: RangeBounds<K>>(&self, bounds: R) -> MapxOrdIter<'_, K, V, C> {
+        self.map.range(bounds)
+    }
+
+    pub fn insert(&mut self, key: &K, value: &V) -> Result<Option<V>> {
+        let pk = key.to_bytes();
+        let old = self.map.get(key);
+
+        let mut batch = MapxRawBatch::new();
+        self.update_indexes(&mut batch, &pk, old.as_ref(), Some(value))
+            .c(d!())?;
+        batch.insert(&mut self.map.inner.inner, &pk, C::encode(value));
+        batch.commit();
+
+        Ok(old)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>> {
+        if let Some(old) = self.map.get(key) {
+            let pk = key.to_bytes();
+            let mut batch = MapxRawBatch::new();
+            self.update_indexes(&mut batch, &pk, Some(&old), None)
+                .c(d!())?;
+            batch.remove(&mut self.map.inner.inner, &pk);
+            batch.commit();
+            Ok(Some(old))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Remove all entries, the registered indexes will be kept.
+    pub fn clear(&mut self) {
+        self.map.clear();
+        for (_, mut idx) in self.indexes.iter() {
+            idx.iter().for_each(|(_, mut pks)| pks.clear());
+            idx.clear();
+        }
+    }
+
+    /// All the entries whose index key equals to `key`.
+    pub fn get_by_index<I: KeyEnDeOrdered>(
+        &self,
+        name: &str,
+        key: &I,
+    ) -> Result<Vec<(K, V)>> {
+        let idx = self.get_index(name).c(d!())?;
+        let mut ret = vec![];
+        if let Some(pks) = idx.get(key.to_bytes()) {
+            self.collect(&pks, &mut ret).c(d!())?;
+        }
+        Ok(ret)
+    }
+
+    /// All the entries whose index key is within `bounds`,
+    /// in the order of the index keys.
+    pub fn range_by_index<I: KeyEnDeOrdered, R: RangeBounds<I>>(
+        &self,
+        name: &str,
+        bounds: R,
+    ) -> Result<Vec<(K, V)>> {
+        let idx = self.get_index(name).c(d!())?;
+
+        let l = match bounds.start_bound() {
+            Bound::Included(lo) => Bound::Included(Cow::Owned(lo.to_bytes())),
+            Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(lo.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let h = match bounds.end_bound() {
+            Bound::Included(hi) => Bound::Included(Cow::Owned(hi.to_bytes())),
+            Bound::Excluded(hi) => Bound::Excluded(Cow::Owned(hi.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let mut ret = vec![];
+        for (_, pks) in idx.range((l, h)) {
+            self.collect(&pks, &mut ret).c(d!())?;
+        }
+        Ok(ret)
+    }
+
+    fn get_index(&self, name: &str) -> Result<MapxOrdRawKey<MapxRaw>> {
+        self.indexes
+            .get(&name.to_owned())
+            .c(d!("index not found: {}", name))
+    }
+
+    fn collect(&self, pks: &MapxRaw, ret: &mut Vec<(K, V)>) -> Result<()> {
+        for (pk, _) in pks.iter() {
+            let k = K::from_bytes(pk).c(d!())?;
+            let v = self.map.get(&k).c(d!("value not found"))?;
+            ret.push((k, v));
+        }
+        Ok(())
+    }
+
+    fn update_indexes(
+        &mut self,
+        batch: &mut MapxRawBatch,
+        pk: &[u8],
+        old: Option<&V>,
+        new: Option<&V>,
+    ) -> Result<()> {
+        let indexes = self
+            .indexes
+            .iter()
+            .map(|(name, idx)| {
+                self.extractors
+                    .get(&name)
+                    .map(|f| (Arc::clone(f), idx))
+                    .c(d!("extractor not registered: {}", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        for (f, mut idx) in indexes.into_iter() {
+            let old_keys = old.map(|v| f(v)).unwrap_or_default();
+            let new_keys = new.map(|v| f(v)).unwrap_or_default();
+            let old_keys = old_keys.into_iter().collect::<BTreeSet<_>>();
+            let new_keys = new_keys.into_iter().collect::<BTreeSet<_>>();
+            old_keys
+                .difference(&new_keys)
+                .for_each(|ik| index_remove(batch, &mut idx, ik, pk));
+            new_keys
+                .difference(&old_keys)
+                .for_each(|ik| index_add(batch, &mut idx, ik, pk));
+        }
+
+        Ok(())
+    }
+}
+
+impl<K, V> MapxOrdIndexed<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<K, V, C> Clone for MapxOrdIndexed<K, V, C> {
+    fn clone(&self) -> Self {
+        // the primary keys of each index key are nested instances,
+        // they must be copied one by one
+        let mut indexes = Mapx::new();
+        self.indexes.iter().for_each(|(name, idx)| {
+            let mut new_idx = MapxOrdRawKey::new();
+            idx.iter().filter(|(_, pks)| !pks.is_empty()).for_each(|(ik, pks)| {
+                new_idx.insert(ik, &pks.clone());
+            });
+            indexes.insert(&name, &new_idx);
+        });
+
+        Self {
+            map: self.map.clone(),
+            indexes,
+            extractors: self.extractors.clone(),
+        }
+    }
+}
+
+impl<K, V, C> Registrable for MapxOrdIndexed<K, V, C> {
+    const KIND: &'static str = "MapxOrdIndexed";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.map.instance_prefix()
+    }
+}
+
+impl<K, V, C> Default for MapxOrdIndexed<K, V, C>
+where
+    K: KeyEnDeOrdered,
+    C: Codec<V>,
+{
+    fn default() -> Self {
+        Self::with_codec()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "vs")]
+impl<K, V, C> vsdb_core::VsMgmt for MapxOrdIndexed<K, V, C> {
+    vsdb_core::impl_vs_methods_nope! {}
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+fn index_add(
+    batch: &mut MapxRawBatch,
+    idx: &mut MapxOrdRawKey<MapxRaw>,
+    ik: &[u8],
+    pk: &[u8],
+) {
+    let mut pks = idx.get(ik).unwrap_or_else(|| {
+        let pks = MapxRaw::new();
+        batch.insert(&mut idx.inner, ik, DefaultCodec::encode(&pks));
+        pks
+    });
+    batch.insert(&mut pks, pk, []);
+}
+
+fn index_remove(
+    batch: &mut MapxRawBatch,
+    idx: &mut MapxOrdRawKey<MapxRaw>,
+    ik: &[u8],
+    pk: &[u8],
+) {
+    // the emptied set is kept for the next insertion of `ik`,
+    // a removed one could never be reclaimed
+    if let Some(mut pks) = idx.get(ik) {
+        batch.remove(&mut pks, pk);
+    }
+}
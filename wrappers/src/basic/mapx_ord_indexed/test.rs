@@ -0,0 +1,140 @@
+use super::*;
+use ruc::*;
+
+// id => (owner, tags)
+type Animal = (String, Vec<u8>);
+
+fn owner(v: &Animal) -> Option<String> {
+    Some(v.0.clone())
+}
+
+fn tags(v: &Animal) -> Vec<u8> {
+    v.1.clone()
+}
+
+fn animal(owner: &str, tags: &[u8]) -> Animal {
+    (owner.to_owned(), tags.to_vec())
+}
+
+fn ids(entries: Vec<(u64, Animal)>) -> Vec<u64> {
+    entries.into_iter().map(|(k, _)| k).collect()
+}
+
+#[test]
+fn test_index_update() {
+    let mut hdr: MapxOrdIndexed<u64, Animal> = MapxOrdIndexed::new();
+    hdr.add_index("owner", owner);
+    hdr.add_index("tag", tags);
+
+    pnk!(hdr.insert(&1, &animal("a", &[1, 2])));
+    pnk!(hdr.insert(&2, &animal("b", &[2])));
+    pnk!(hdr.insert(&3, &animal("a", &[3])));
+
+    assert_eq!(
+        vec![1, 3],
+        ids(pnk!(hdr.get_by_index("owner", &"a".to_owned())))
+    );
+    assert_eq!(vec![1, 2], ids(pnk!(hdr.get_by_index("tag", &2u8))));
+    assert!(pnk!(hdr.get_by_index("tag", &9u8)).is_empty());
+    assert!(hdr.get_by_index("none", &9u8).is_err());
+
+    // updates move the entry between index keys
+    assert!(pnk!(hdr.insert(&1, &animal("b", &[2, 3]))).is_some());
+    assert_eq!(
+        vec![3],
+        ids(pnk!(hdr.get_by_index("owner", &"a".to_owned())))
+    );
+    assert_eq!(
+        vec![1, 2],
+        ids(pnk!(hdr.get_by_index("owner", &"b".to_owned())))
+    );
+    assert!(pnk!(hdr.get_by_index("tag", &1u8)).is_empty());
+    assert_eq!(vec![1, 3], ids(pnk!(hdr.get_by_index("tag", &3u8))));
+
+    assert!(pnk!(hdr.remove(&1)).is_some());
+    assert!(pnk!(hdr.remove(&1)).is_none());
+    assert_eq!(
+        vec![2],
+        ids(pnk!(hdr.get_by_index("owner", &"b".to_owned())))
+    );
+    assert_eq!(vec![3], ids(pnk!(hdr.get_by_index("tag", &3u8))));
+
+    // the emptied set of an index key is reused
+    let pks = |hdr: &MapxOrdIndexed<u64, Animal>| {
+        pnk!(hdr.get_index("tag")).get([1u8].to_bytes()).map(|pks| {
+            (pks.instance_prefix(), pks.len())
+        })
+    };
+    pnk!(hdr.insert(&4, &animal("c", &[1])));
+    let (prefix, len) = pks(&hdr).unwrap();
+    assert_eq!(1, len);
+    pnk!(hdr.remove(&4));
+    assert_eq!(Some((prefix, 0)), pks(&hdr));
+    assert!(pnk!(hdr.get_by_index("tag", &1u8)).is_empty());
+    pnk!(hdr.insert(&5, &animal("c", &[1])));
+    assert_eq!(Some((prefix, 1)), pks(&hdr));
+    assert_eq!(vec![5], ids(pnk!(hdr.get_by_index("tag", &1u8))));
+    pnk!(hdr.remove(&5));
+
+    hdr.clear();
+    assert!(hdr.is_empty());
+    assert!(pnk!(hdr.get_by_index("owner", &"a".to_owned())).is_empty());
+    assert!(pnk!(hdr.get_by_index("tag", &2u8)).is_empty());
+}
+
+#[test]
+fn test_range_by_index() {
+    let mut hdr: MapxOrdIndexed<u64, Animal> = MapxOrdIndexed::new();
+    hdr.add_index("tag", tags);
+
+    (0..10u8).for_each(|i| {
+        pnk!(hdr.insert(&(100 - i as u64), &animal("x", &[i])));
+    });
+
+    assert_eq!(
+        vec![97, 96, 95],
+        ids(pnk!(hdr.range_by_index("tag", 3u8..6)))
+    );
+    assert_eq!(vec![92, 91], ids(pnk!(hdr.range_by_index("tag", 8u8..))));
+    assert_eq!(10, pnk!(hdr.range_by_index::<u8, _>("tag", ..)).len());
+}
+
+#[test]
+fn test_index_lifecycle() {
+    let mut hdr: MapxOrdIndexed<u64, Animal> = MapxOrdIndexed::new();
+    (0..10u64).for_each(|i| {
+        pnk!(hdr.insert(&i, &animal(&format!("{}", i % 2), &[])));
+    });
+
+    // built from the existing entries
+    hdr.add_index("owner", owner);
+    assert_eq!(5, pnk!(hdr.get_by_index("owner", &"0".to_owned())).len());
+    assert_eq!(vec!["owner".to_owned()], hdr.index_names());
+
+    // extractors must be registered again after a decoding
+    let mut hdr: MapxOrdIndexed<u64, Animal> = pnk!(ValueEnDe::decode(&hdr.encode()));
+    assert!(hdr.insert(&10, &animal("0", &[])).is_err());
+    assert_eq!(5, pnk!(hdr.get_by_index("owner", &"0".to_owned())).len());
+    hdr.add_index("owner", owner);
+    pnk!(hdr.insert(&10, &animal("0", &[])));
+    assert_eq!(6, pnk!(hdr.get_by_index("owner", &"0".to_owned())).len());
+
+    assert!(hdr.remove_index("owner"));
+    assert!(!hdr.remove_index("owner"));
+    assert!(hdr.get_by_index("owner", &"0".to_owned()).is_err());
+    pnk!(hdr.insert(&11, &animal("0", &[])));
+}
+
+#[test]
+fn test_clone() {
+    let mut hdr: MapxOrdIndexed<u64, Animal> = MapxOrdIndexed::new();
+    hdr.add_index("tag", tags);
+    pnk!(hdr.insert(&1, &animal("a", &[1])));
+
+    // the indexes are copied along with the entries
+    let mut cloned = hdr.clone();
+    pnk!(cloned.insert(&2, &animal("a", &[1])));
+    pnk!(cloned.remove(&1));
+    assert_eq!(vec![2], ids(pnk!(cloned.get_by_index("tag", &1u8))));
+    assert_eq!(vec![1], ids(pnk!(hdr.get_by_index("tag", &1u8))));
+}
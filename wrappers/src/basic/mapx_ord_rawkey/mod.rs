@@ -35,26 +35,37 @@
 #[cfg(test)]
 mod test;
 
-use crate::common::{ende::ValueEnDe, RawKey};
+use crate::common::{
+    codec::{Codec, DefaultCodec},
+    ende::ValueEnDe,
+    migrate::{self, Migratable, Migrate, Migrating, Migration, RawStore},
+    RawKey,
+};
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
     marker::PhantomData,
     ops::{Deref, DerefMut, RangeBounds},
+    sync::Arc,
+};
+use vsdb_core::{
+    basic::mapx_raw::{self, MapxRaw, MapxRawBatch, MapxRawIter},
+    common::PreBytes,
+    Registrable,
 };
-use vsdb_core::basic::mapx_raw::{self, MapxRaw, MapxRawIter};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
-pub struct MapxOrdRawKey<V> {
+pub struct MapxOrdRawKey<V, C = DefaultCodec> {
     pub(crate) inner: MapxRaw,
-    _p: PhantomData<V>,
+    _p: PhantomData<(V, C)>,
 }
 
-impl<V> MapxOrdRawKey<V>
+impl<V, C> MapxOrdRawKey<V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     /// # Safety
     ///
@@ -84,8 +95,9 @@ where
         self.inner.as_bytes()
     }
 
+    /// Create an instance that encodes values by `C`.
     #[inline(always)]
-    pub fn new() -> Self {
+    pub fn with_codec() -> Self {
         MapxOrdRawKey {
             inner: MapxRaw::new(),
             _p: PhantomData,
@@ -94,16 +106,17 @@ where
 
     #[inline(always)]
     pub fn get(&self, key: impl AsRef<[u8]>) -> Option<V> {
-        self.inner
-            .get(key.as_ref())
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+        self.ensure_migrated(key.as_ref());
+        self.inner.get(key.as_ref()).map(|v| C::decode(&v).unwrap())
     }
 
     #[inline(always)]
-    pub fn get_mut(&mut self, key: impl AsRef<[u8]>) -> Option<ValueMut<'_, V>> {
+    pub fn get_mut(&mut self, key: impl AsRef<[u8]>) -> Option<ValueMut<'_, V, C>> {
+        self.ensure_migrated(key.as_ref());
         self.inner.get_mut(key.as_ref()).map(|inner| ValueMut {
-            value: <V as ValueEnDe>::decode(&inner).unwrap(),
+            value: C::decode(&inner).unwrap(),
             inner,
+            _p: PhantomData,
         })
     }
 
@@ -114,16 +127,24 @@ where
 
     #[inline(always)]
     pub fn get_le(&self, key: impl AsRef<[u8]>) -> Option<(RawKey, V)> {
-        self.inner
-            .get_le(key.as_ref())
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        let key = key.as_ref();
+        if let Some(m) = migrate::migrating(self) {
+            pnk!(m.next(|| self.inner.get_le(key), |v| v))
+        } else {
+            self.inner.get_le(key)
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
     pub fn get_ge(&self, key: impl AsRef<[u8]>) -> Option<(RawKey, V)> {
-        self.inner
-            .get_ge(key.as_ref())
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        let key = key.as_ref();
+        if let Some(m) = migrate::migrating(self) {
+            pnk!(m.next(|| self.inner.get_ge(key), |v| v))
+        } else {
+            self.inner.get_ge(key)
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
@@ -138,9 +159,10 @@ where
 
     #[inline(always)]
     pub fn insert(&mut self, key: impl AsRef<[u8]>, value: &V) -> Option<V> {
+        self.ensure_migrated(key.as_ref());
         self.inner
-            .insert(key.as_ref(), &value.encode())
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+            .insert(key.as_ref(), C::encode(value))
+            .map(|v| C::decode(&v).unwrap())
     }
 
     /// # Safety
@@ -153,32 +175,36 @@ where
         key: impl AsRef<[u8]>,
         value: impl AsRef<[u8]>,
     ) -> Option<V> {
+        self.ensure_migrated(key.as_ref());
         self.inner
             .insert(key.as_ref(), value.as_ref())
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+            .map(|v| C::decode(&v).unwrap())
     }
 
     #[inline(always)]
     pub fn set_value(&mut self, key: impl AsRef<[u8]>, value: &V) {
-        self.inner.insert(key.as_ref(), &value.encode());
+        self.ensure_migrated(key.as_ref());
+        self.inner.insert(key.as_ref(), C::encode(value));
     }
 
     #[inline(always)]
-    pub fn entry<'a>(&'a mut self, key: &'a [u8]) -> Entry<'a, V> {
+    pub fn entry<'a>(&'a mut self, key: &'a [u8]) -> Entry<'a, V, C> {
         Entry { key, hdr: self }
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxOrdRawKeyIter<V> {
+    pub fn iter(&self) -> MapxOrdRawKeyIter<V, C> {
         MapxOrdRawKeyIter {
+            migrating: migrate::migrating(self),
             inner: self.inner.iter(),
             _p: PhantomData,
         }
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxOrdRawKeyIterMut<V> {
+    pub fn iter_mut(&mut self) -> MapxOrdRawKeyIterMut<V, C> {
         MapxOrdRawKeyIterMut {
+            migrating: migrate::migrating(self),
             inner: self.inner.iter_mut(),
             _p: PhantomData,
         }
@@ -188,8 +214,9 @@ where
     pub fn range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
         &'a self,
         bounds: R,
-    ) -> MapxOrdRawKeyIter<'a, V> {
+    ) -> MapxOrdRawKeyIter<'a, V, C> {
         MapxOrdRawKeyIter {
+            migrating: migrate::migrating(self),
             inner: self.inner.range(bounds),
             _p: PhantomData,
         }
@@ -199,13 +226,24 @@ where
     pub fn range_mut<'a, R: RangeBounds<Cow<'a, [u8]>>>(
         &'a mut self,
         bounds: R,
-    ) -> MapxOrdRawKeyIterMut<'a, V> {
+    ) -> MapxOrdRawKeyIterMut<'a, V, C> {
         MapxOrdRawKeyIterMut {
+            migrating: migrate::migrating(self),
             inner: self.inner.range_mut(bounds),
             _p: PhantomData,
         }
     }
 
+    /// Iterate over the entries whose keys start with `prefix`.
+    #[inline(always)]
+    pub fn iter_prefix(&self, prefix: impl AsRef<[u8]>) -> MapxOrdRawKeyIter<V, C> {
+        MapxOrdRawKeyIter {
+            migrating: migrate::migrating(self),
+            inner: self.inner.iter_prefix(prefix),
+            _p: PhantomData,
+        }
+    }
+
     #[inline(always)]
     pub fn first(&self) -> Option<(RawKey, V)> {
         self.iter().next()
@@ -218,23 +256,69 @@ where
 
     #[inline(always)]
     pub fn remove(&mut self, key: impl AsRef<[u8]>) -> Option<V> {
+        self.ensure_migrated(key.as_ref());
         self.inner
             .remove(key.as_ref())
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+            .map(|v| C::decode(&v).unwrap())
     }
 
     #[inline(always)]
     pub fn unset_value(&mut self, key: impl AsRef<[u8]>) {
+        self.ensure_migrated(key.as_ref());
         self.inner.remove(key.as_ref());
     }
 
+    /// Remove the entries whose keys start with `prefix` in a batch,
+    /// return the number of them.
+    #[inline(always)]
+    pub fn remove_prefix(&mut self, prefix: impl AsRef<[u8]>) -> usize {
+        self.inner.remove_prefix(prefix)
+    }
+
     #[inline(always)]
     pub fn clear(&mut self) {
         self.inner.clear();
     }
+
+    #[inline(always)]
+    pub fn compact_instance(&self) {
+        self.inner.compact_instance();
+    }
+
+    // Write all the changes atomically, `None` means a removal.
+    pub(crate) fn write_batch(&mut self, changes: &BTreeMap<RawKey, Option<V>>) {
+        let mut batch = MapxRawBatch::new();
+        for (k, v) in changes.iter() {
+            self.ensure_migrated(k);
+            if let Some(v) = v {
+                batch.insert(&mut self.inner, k, C::encode(v));
+            } else {
+                batch.remove(&mut self.inner, k);
+            }
+        }
+        batch.commit();
+    }
+
+    // Convert the value of the key if the instance is being migrated.
+    #[inline(always)]
+    fn ensure_migrated(&self, key: &[u8]) {
+        pnk!(migrate::ensure_migrated(self, key));
+    }
+}
+
+impl<V> MapxOrdRawKey<V>
+where
+    V: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
 }
 
-impl<V> Clone for MapxOrdRawKey<V> {
+impl<V, C> Clone for MapxOrdRawKey<V, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -243,12 +327,58 @@ impl<V> Clone for MapxOrdRawKey<V> {
     }
 }
 
-impl<V> Default for MapxOrdRawKey<V>
+impl<V, C> Registrable for MapxOrdRawKey<V, C> {
+    const KIND: &'static str = "MapxOrdRawKey";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<V, C> Migratable for MapxOrdRawKey<V, C> {
+    #[inline(always)]
+    fn converter_id(&self) -> migrate::ConverterID {
+        migrate::converter_id::<V, C>()
+    }
+
+    #[inline(always)]
+    fn raw_store(&self) -> RawStore {
+        RawStore::Basic(unsafe { self.inner.shadow() })
+    }
+}
+
+impl<Old, New, C> Migrate<Old, New> for MapxOrdRawKey<Old, C>
 where
-    V: ValueEnDe,
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = MapxOrdRawKey<New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let store = RawStore::Basic(unsafe { self.inner.shadow() });
+        let m = migrate::begin_migration(&self, store, migrate::converter::<Old, New, C>(f))
+            .c(d!())?;
+        Ok((
+            Self::Output {
+                inner: self.inner,
+                _p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<V, C> Default for MapxOrdRawKey<V, C>
+where
+    C: Codec<V>,
 {
     fn default() -> Self {
-        Self::new()
+        Self::with_codec()
     }
 }
 
@@ -256,26 +386,27 @@ where
 /////////////////////////////////////////////////////////////////////////////
 
 #[derive(Debug)]
-pub struct ValueMut<'a, V>
+pub struct ValueMut<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     value: V,
     #[allow(unused)] inner: mapx_raw::ValueMut<'a>,
+    _p: PhantomData<C>,
 }
 
-impl<'a, V> Drop for ValueMut<'a, V>
+impl<'a, V, C> Drop for ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn drop(&mut self) {
-        // *self.inner = self.value.encode();
+        // *self.inner = C::encode(&self.value);
     }
 }
 
-impl<'a, V> Deref for ValueMut<'a, V>
+impl<'a, V, C> Deref for ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Target = V;
     fn deref(&self) -> &Self::Target {
@@ -283,9 +414,9 @@ where
     }
 }
 
-impl<'a, V> DerefMut for ValueMut<'a, V>
+impl<'a, V, C> DerefMut for ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.value
@@ -295,19 +426,19 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct Entry<'a, V>
+pub struct Entry<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     key: &'a [u8],
-    hdr: &'a mut MapxOrdRawKey<V>,
+    hdr: &'a mut MapxOrdRawKey<V, C>,
 }
 
-impl<'a, V> Entry<'a, V>
+impl<'a, V, C> Entry<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    pub fn or_insert(self, default: &V) -> ValueMut<'a, V> {
+    pub fn or_insert(self, default: &V) -> ValueMut<'a, V, C> {
         if !self.hdr.contains_key(self.key) {
             self.hdr.set_value(self.key, default);
         }
@@ -318,71 +449,91 @@ where
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdRawKeyIter<'a, V> {
+pub struct MapxOrdRawKeyIter<'a, V, C = DefaultCodec> {
     inner: MapxRawIter<'a>,
-    _p: PhantomData<V>,
+    migrating: Option<Migrating>,
+    _p: PhantomData<(V, C)>,
 }
 
-impl<'a, V> Iterator for MapxOrdRawKeyIter<'a, V>
+impl<'a, V, C> Iterator for MapxOrdRawKeyIter<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Item = (RawKey, V);
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next()
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = self.migrating.as_ref() {
+            pnk!(m.next(|| self.inner.next(), |v| v))
+        } else {
+            self.inner.next()
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxOrdRawKeyIter<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxOrdRawKeyIter<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next_back()
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = self.migrating.as_ref() {
+            pnk!(m.next(|| self.inner.next_back(), |v| v))
+        } else {
+            self.inner.next_back()
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 }
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdRawKeyIterMut<'a, V> {
+pub struct MapxOrdRawKeyIterMut<'a, V, C = DefaultCodec> {
     inner: mapx_raw::MapxRawIterMut<'a>,
-    _p: PhantomData<V>,
+    migrating: Option<Migrating>,
+    _p: PhantomData<(V, C)>,
 }
 
-impl<'a, V> Iterator for MapxOrdRawKeyIterMut<'a, V>
+impl<'a, V, C> Iterator for MapxOrdRawKeyIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    type Item = (RawKey, ValueIterMut<'a, V>);
+    type Item = (RawKey, ValueIterMut<'a, V, C>);
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(k, v)| {
+        if let Some(m) = self.migrating.as_ref() {
+            pnk!(m.next(|| self.inner.next(), |v| &mut **v))
+        } else {
+            self.inner.next()
+        }
+        .map(|(k, v)| {
             (
                 k,
                 ValueIterMut {
-                    value: <V as ValueEnDe>::decode(&v).unwrap(),
+                    value: C::decode(&v).unwrap(),
                     inner: v,
+                    _p: PhantomData,
                 },
             )
         })
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxOrdRawKeyIterMut<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxOrdRawKeyIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner.next_back().map(|(k, v)| {
+        if let Some(m) = self.migrating.as_ref() {
+            pnk!(m.next(|| self.inner.next_back(), |v| &mut **v))
+        } else {
+            self.inner.next_back()
+        }
+        .map(|(k, v)| {
             (
                 k,
                 ValueIterMut {
-                    value: <V as ValueEnDe>::decode(&v).unwrap(),
+                    value: C::decode(&v).unwrap(),
                     inner: v,
+                    _p: PhantomData,
                 },
             )
         })
@@ -393,26 +544,27 @@ where
 /////////////////////////////////////////////////////////////////////////////
 
 #[derive(Debug)]
-pub struct ValueIterMut<'a, V>
+pub struct ValueIterMut<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     pub(crate) value: V,
     pub(crate) inner: mapx_raw::ValueIterMut<'a>,
+    pub(crate) _p: PhantomData<C>,
 }
 
-impl<'a, V> Drop for ValueIterMut<'a, V>
+impl<'a, V, C> Drop for ValueIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn drop(&mut self) {
-        *self.inner = self.value.encode();
+        *self.inner = C::encode(&self.value);
     }
 }
 
-impl<'a, V> Deref for ValueIterMut<'a, V>
+impl<'a, V, C> Deref for ValueIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Target = V;
     fn deref(&self) -> &Self::Target {
@@ -420,9 +572,9 @@ where
     }
 }
 
-impl<'a, V> DerefMut for ValueIterMut<'a, V>
+impl<'a, V, C> DerefMut for ValueIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.value
@@ -433,7 +585,7 @@ where
 /////////////////////////////////////////////////////////////////////////////
 
 #[cfg(feature = "vs")]
-impl<V> vsdb_core::VsMgmt for MapxOrdRawKey<V> {
+impl<V, C> vsdb_core::VsMgmt for MapxOrdRawKey<V, C> {
     vsdb_core::impl_vs_methods_nope! {}
 }
 
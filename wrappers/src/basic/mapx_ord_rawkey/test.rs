@@ -122,3 +122,24 @@ fn test_values_first_last() {
     let (_, value) = pnk!(hdr.iter().next_back());
     assert_eq!(max - 1, value);
 }
+
+#[test]
+fn test_prefix() {
+    let mut hdr: MapxOrdRawKey<usize> = MapxOrdRawKey::new();
+    (0..10usize).for_each(|i| {
+        (0..10usize).for_each(|j| {
+            hdr.insert([i as u8, j as u8], &(i * j));
+        });
+    });
+
+    assert_eq!(
+        hdr.iter_prefix([3]).map(|(_, v)| v).collect::<Vec<_>>(),
+        (0..10).map(|j| 3 * j).collect::<Vec<_>>()
+    );
+    assert_eq!(1, hdr.iter_prefix([3, 3]).count());
+    assert_eq!(0, hdr.iter_prefix([10]).count());
+
+    assert_eq!(10, hdr.remove_prefix([3]));
+    assert_eq!(0, hdr.iter_prefix([3]).count());
+    assert_eq!(90, hdr.len());
+}
@@ -44,7 +44,11 @@ use std::{
     marker::PhantomData,
     ops::{Bound, Deref, DerefMut, RangeBounds},
 };
-use vsdb_core::basic::mapx_raw::{MapxRaw, MapxRawIter, MapxRawIterMut, ValueIterMut};
+use vsdb_core::{
+    basic::mapx_raw::{MapxRaw, MapxRawIter, MapxRawIterMut, ValueIterMut},
+    common::PreBytes,
+    Registrable,
+};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
@@ -250,6 +254,11 @@ where
     pub fn clear(&mut self) {
         self.inner.clear();
     }
+
+    #[inline(always)]
+    pub fn compact_instance(&self) {
+        self.inner.compact_instance();
+    }
 }
 
 impl<K> Clone for MapxOrdRawValue<K> {
@@ -261,6 +270,15 @@ impl<K> Clone for MapxOrdRawValue<K> {
     }
 }
 
+impl<K> Registrable for MapxOrdRawValue<K> {
+    const KIND: &'static str = "MapxOrdRawValue";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
 impl<K> Default for MapxOrdRawValue<K>
 where
     K: KeyEnDeOrdered,
@@ -2,10 +2,16 @@
 //! Unversioned functions.
 //!
 
+pub mod dequex;
 pub mod mapx;
 pub mod mapx_ord;
+pub mod mapx_ord_indexed;
 pub mod mapx_ord_rawkey;
 pub mod mapx_ord_rawvalue;
+pub mod multi_mapx;
 pub mod orphan;
+pub mod priority_queuex;
+pub mod setx;
+pub mod setx_ord;
 pub mod vecx;
 pub mod vecx_raw;
@@ -0,0 +1,278 @@
+//!
+//! A `BTreeMap<K, BTreeSet<V>>`-like structure but storing data in disk.
+//!
+//! NOTE:
+//!
+//! - Both keys and values will be encoded by `KeyEnDeOrdered`
+//! - It's your duty to ensure that the encoded key keeps a same order with the original key
+//! - All the pairs are stored as composite keys in one instance,
+//!   they are iterated in the order of keys, and then in the order of values
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::MultiMapx;
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut m = MultiMapx::new();
+//!
+//! m.insert(&1u32, &"b".to_owned());
+//! m.insert(&1u32, &"a".to_owned());
+//! m.insert(&2u32, &"c".to_owned());
+//! assert!(!m.insert(&1u32, &"a".to_owned()));
+//!
+//! assert_eq!(m.get_all(&1), vec!["a".to_owned(), "b".to_owned()]);
+//! assert_eq!(m.count(&1), 2);
+//! assert_eq!(m.len(), 3);
+//!
+//! assert!(m.remove_one(&1, &"a".to_owned()));
+//! assert_eq!(m.remove_all(&1), 1);
+//! assert_eq!(m.iter().collect::<Vec<_>>(), vec![(2, "c".to_owned())]);
+//! ```
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::mapx_ord_rawkey::{MapxOrdRawKey, MapxOrdRawKeyIter},
+    common::{
+        codec::RawCodec, ende::KeyEnDeOrdered, escape_key, unescape_key, RawBytes,
+        RawKey,
+    },
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use vsdb_core::{common::PreBytes, Registrable};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(bound = "")]
+pub struct MultiMapx<K, V> {
+    // (key, value) => NULL
+    inner: MapxOrdRawKey<RawBytes, RawCodec>,
+    _p: PhantomData<(K, V)>,
+}
+
+impl<K, V> MultiMapx<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Do not use this API unless you know the internal details extremely well.
+    #[inline(always)]
+    pub unsafe fn from_bytes(s: impl AsRef<[u8]>) -> Self {
+        Self {
+            inner: MapxOrdRawKey::from_bytes(s),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            inner: MapxOrdRawKey::with_codec(),
+            _p: PhantomData,
+        }
+    }
+
+    /// All the values of `key`, in order.
+    #[inline(always)]
+    pub fn get_all(&self, key: &K) -> Vec<V> {
+        self.values(key).collect()
+    }
+
+    /// Iterate over the values of `key`, in order.
+    #[inline(always)]
+    pub fn values(&self, key: &K) -> MultiMapxValues<'_, V> {
+        MultiMapxValues {
+            inner: self.inner.iter_prefix(escape_key(&key.to_bytes())),
+            _p: PhantomData,
+        }
+    }
+
+    /// The number of the values of `key`.
+    #[inline(always)]
+    pub fn count(&self, key: &K) -> usize {
+        self.values(key).count()
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.values(key).next().is_some()
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, key: &K, value: &V) -> bool {
+        self.inner.contains_key(pair_key(key, value))
+    }
+
+    /// The number of all the `(key, value)` pairs.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Return `false` if the pair has been there.
+    #[inline(always)]
+    pub fn insert(&mut self, key: &K, value: &V) -> bool {
+        self.inner.insert(pair_key(key, value), &vec![]).is_none()
+    }
+
+    /// Return `false` if the pair does not exist.
+    #[inline(always)]
+    pub fn remove_one(&mut self, key: &K, value: &V) -> bool {
+        self.inner.remove(pair_key(key, value)).is_some()
+    }
+
+    /// Remove all the values of `key`, return the number of them.
+    #[inline(always)]
+    pub fn remove_all(&mut self, key: &K) -> usize {
+        self.inner.remove_prefix(escape_key(&key.to_bytes()))
+    }
+
+    /// Iterate over all the `(key, value)` pairs, in order.
+    #[inline(always)]
+    pub fn iter(&self) -> MultiMapxIter<'_, K, V> {
+        MultiMapxIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+}
+
+impl<K, V> Clone for MultiMapx<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Registrable for MultiMapx<K, V> {
+    const KIND: &'static str = "MultiMapx";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K, V> Default for MultiMapx<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "vs")]
+impl<K, V> vsdb_core::VsMgmt for MultiMapx<K, V> {
+    vsdb_core::impl_vs_methods_nope! {}
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct MultiMapxIter<'a, K, V> {
+    inner: MapxOrdRawKeyIter<'a, RawBytes, RawCodec>,
+    _p: PhantomData<(K, V)>,
+}
+
+impl<'a, K, V> Iterator for MultiMapxIter<'a, K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| pnk!(decode_pair(&k)))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for MultiMapxIter<'a, K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| pnk!(decode_pair(&k)))
+    }
+}
+
+pub struct MultiMapxValues<'a, V> {
+    inner: MapxOrdRawKeyIter<'a, RawBytes, RawCodec>,
+    _p: PhantomData<V>,
+}
+
+impl<'a, V: KeyEnDeOrdered> Iterator for MultiMapxValues<'a, V> {
+    type Item = V;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(k, _)| pnk!(V::from_slice(unescape_key(&k).1)))
+    }
+}
+
+impl<'a, V: KeyEnDeOrdered> DoubleEndedIterator for MultiMapxValues<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|(k, _)| pnk!(V::from_slice(unescape_key(&k).1)))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[inline(always)]
+pub(crate) fn pair_key<K: KeyEnDeOrdered, V: KeyEnDeOrdered>(k: &K, v: &V) -> RawKey {
+    let mut ret = escape_key(&k.to_bytes());
+    ret.extend_from_slice(&v.to_bytes());
+    ret
+}
+
+#[inline(always)]
+pub(crate) fn decode_pair<K: KeyEnDeOrdered, V: KeyEnDeOrdered>(
+    k: &[u8],
+) -> Result<(K, V)> {
+    let (k, v) = unescape_key(k);
+    Ok((K::from_bytes(k).c(d!())?, V::from_slice(v).c(d!())?))
+}
@@ -0,0 +1,88 @@
+use super::*;
+use crate::ValueEnDe;
+
+#[test]
+fn test_insert_remove() {
+    let mut hdr: MultiMapx<u32, u64> = MultiMapx::new();
+    (0..10u32).for_each(|k| {
+        (0..10u64).rev().for_each(|v| {
+            assert!(hdr.insert(&k, &v));
+            assert!(!hdr.insert(&k, &v));
+            assert!(hdr.contains(&k, &v));
+        });
+    });
+    assert_eq!(100, hdr.len());
+    assert_eq!(10, hdr.count(&3));
+    assert_eq!(hdr.get_all(&3), (0..10).collect::<Vec<_>>());
+    assert!(hdr.get_all(&100).is_empty());
+    assert!(!hdr.contains_key(&100));
+
+    assert!(hdr.remove_one(&3, &5));
+    assert!(!hdr.remove_one(&3, &5));
+    assert!(!hdr.contains(&3, &5));
+    assert_eq!(9, hdr.count(&3));
+
+    assert_eq!(9, hdr.remove_all(&3));
+    assert_eq!(0, hdr.remove_all(&3));
+    assert!(!hdr.contains_key(&3));
+    assert_eq!(10, hdr.count(&2));
+    assert_eq!(10, hdr.count(&4));
+    assert_eq!(90, hdr.len());
+
+    hdr.clear();
+    assert!(hdr.is_empty());
+}
+
+#[test]
+fn test_iter_order() {
+    let mut hdr: MultiMapx<u32, u32> = MultiMapx::new();
+    [(2, 1), (1, 9), (2, 0), (1, 3), (3, 7)]
+        .iter()
+        .for_each(|(k, v)| {
+            hdr.insert(k, v);
+        });
+
+    assert_eq!(
+        hdr.iter().collect::<Vec<_>>(),
+        vec![(1, 3), (1, 9), (2, 0), (2, 1), (3, 7)]
+    );
+    assert_eq!(hdr.iter().next_back(), Some((3, 7)));
+    assert_eq!(hdr.values(&2).next_back(), Some(1));
+}
+
+#[test]
+fn test_variable_length_keys() {
+    let mut hdr: MultiMapx<Vec<u8>, Vec<u8>> = MultiMapx::new();
+    let keys = [vec![], vec![0], vec![0, 0], vec![0, 1], vec![1], vec![1, 0]];
+    keys.iter().for_each(|k| {
+        hdr.insert(k, &vec![0]);
+        hdr.insert(k, &k.clone());
+    });
+
+    keys.iter().for_each(|k| {
+        let mut expected = vec![vec![0], k.clone()];
+        expected.sort();
+        expected.dedup();
+        assert_eq!(hdr.get_all(k), expected);
+    });
+
+    let ks = hdr.iter().map(|(k, _)| k).collect::<Vec<_>>();
+    let mut sorted = ks.clone();
+    sorted.sort();
+    assert_eq!(ks, sorted);
+
+    assert_eq!(1, hdr.remove_all(&vec![0]));
+    assert_eq!(hdr.get_all(&vec![0, 0]), vec![vec![0], vec![0, 0]]);
+    assert_eq!(hdr.get_all(&vec![]), vec![vec![], vec![0]]);
+}
+
+#[test]
+fn test_value_ende() {
+    let mut hdr: MultiMapx<String, u32> = MultiMapx::new();
+    hdr.insert(&"a".to_owned(), &1);
+    hdr.insert(&"ab".to_owned(), &2);
+
+    let hdr2 = pnk!(<MultiMapx<String, u32> as ValueEnDe>::decode(&hdr.encode()));
+    assert_eq!(hdr2.get_all(&"a".to_owned()), vec![1]);
+    assert_eq!(hdr2.get_all(&"ab".to_owned()), vec![2]);
+}
@@ -82,7 +82,15 @@
 #[cfg(test)]
 mod test;
 
-use crate::{basic::mapx_ord_rawkey::MapxOrdRawKey, ValueEnDe};
+use crate::{
+    basic::mapx_ord_rawkey::MapxOrdRawKey,
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::ValueEnDe,
+        migrate::{Migrate, Migration},
+    },
+};
+use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
@@ -91,7 +99,9 @@ use std::{
         Deref, DerefMut, Div, DivAssign, Mul, MulAssign, Neg, Not, Rem, RemAssign, Shl,
         ShlAssign, Shr, ShrAssign, Sub, SubAssign,
     },
+    sync::Arc,
 };
+use vsdb_core::{common::PreBytes, Registrable};
 
 ////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////
@@ -100,16 +110,16 @@ use std::{
 /// such as any type of integer, an enum value, etc..
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
-pub struct Orphan<T> {
-    inner: MapxOrdRawKey<T>,
+pub struct Orphan<T, C = DefaultCodec> {
+    inner: MapxOrdRawKey<T, C>,
 }
 
 ////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////
 
-impl<T> Orphan<T>
+impl<T, C> Orphan<T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     /// # Safety
     ///
@@ -137,8 +147,9 @@ where
         self.inner.as_bytes()
     }
 
-    pub fn new(v: T) -> Self {
-        let mut hdr = MapxOrdRawKey::new();
+    /// Create an instance that encodes the value by `C`.
+    pub fn with_codec(v: T) -> Self {
+        let mut hdr = MapxOrdRawKey::with_codec();
         hdr.insert([], &v);
         Self { inner: hdr }
     }
@@ -159,13 +170,24 @@ where
     ///     - `*(<Orphan>).get_mut() = ...`
     /// - **NEVER** do this:
     ///     - `*(&mut <Orphan>) = Orphan::new(...)`
-    pub fn get_mut(&mut self) -> ValueMut<'_, T> {
+    pub fn get_mut(&mut self) -> ValueMut<'_, T, C> {
         let value = self.get_value();
         ValueMut { hdr: self, value }
     }
 }
 
-impl<T> Clone for Orphan<T> {
+impl<T> Orphan<T>
+where
+    T: ValueEnDe,
+{
+    /// Create an instance that encodes the value by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    pub fn new(v: T) -> Self {
+        Self::with_codec(v)
+    }
+}
+
+impl<T, C> Clone for Orphan<T, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -173,9 +195,35 @@ impl<T> Clone for Orphan<T> {
     }
 }
 
-impl<T: Default + ValueEnDe> Default for Orphan<T> {
+impl<T, C> Registrable for Orphan<T, C> {
+    const KIND: &'static str = "Orphan";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<Old, New, C> Migrate<Old, New> for Orphan<Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = Orphan<New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((Self::Output { inner }, m))
+    }
+}
+
+impl<T: Default, C: Codec<T>> Default for Orphan<T, C> {
     fn default() -> Self {
-        let mut hdr = MapxOrdRawKey::new();
+        let mut hdr = MapxOrdRawKey::with_codec();
         hdr.insert([], &T::default());
         Self { inner: hdr }
     }
@@ -184,20 +232,27 @@ impl<T: Default + ValueEnDe> Default for Orphan<T> {
 ////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////
 
-impl<T> Eq for Orphan<T> where T: ValueEnDe + PartialEq {}
+impl<T, C> Eq for Orphan<T, C>
+where
+    C: Codec<T>,
+    T: PartialEq,
+{
+}
 
-impl<T> PartialEq for Orphan<T>
+impl<T, C> PartialEq for Orphan<T, C>
 where
-    T: ValueEnDe + PartialEq,
+    C: Codec<T>,
+    T: PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
         self.get_value() == other.get_value()
     }
 }
 
-impl<T> PartialEq<T> for Orphan<T>
+impl<T, C> PartialEq<T> for Orphan<T, C>
 where
-    T: ValueEnDe + PartialEq,
+    C: Codec<T>,
+    T: PartialEq,
 {
     fn eq(&self, other: &T) -> bool {
         self.get_value() == *other
@@ -207,27 +262,30 @@ where
 ////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////
 
-impl<T> Ord for Orphan<T>
+impl<T, C> Ord for Orphan<T, C>
 where
-    T: ValueEnDe + Ord,
+    C: Codec<T>,
+    T: Ord,
 {
     fn cmp(&self, other: &Self) -> Ordering {
         self.get_value().cmp(&other.get_value())
     }
 }
 
-impl<T> PartialOrd for Orphan<T>
+impl<T, C> PartialOrd for Orphan<T, C>
 where
-    T: ValueEnDe + Ord,
+    C: Codec<T>,
+    T: Ord,
 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         self.get_value().partial_cmp(&other.get_value())
     }
 }
 
-impl<T> PartialOrd<T> for Orphan<T>
+impl<T, C> PartialOrd<T> for Orphan<T, C>
 where
-    T: ValueEnDe + Ord,
+    C: Codec<T>,
+    T: Ord,
 {
     fn partial_cmp(&self, other: &T) -> Option<Ordering> {
         self.get_value().partial_cmp(other)
@@ -239,9 +297,10 @@ where
 
 macro_rules! impl_ops {
     ($ops: tt, $fn: tt, $op: tt) => {
-        impl<T> $ops for Orphan<T>
+        impl<T, C> $ops for Orphan<T, C>
         where
-            T: ValueEnDe + Ord + Eq + $ops<Output = T>,
+            C: Codec<T>,
+            T: Ord + Eq + $ops<Output = T>,
         {
             type Output = T;
             fn $fn(self, other: Self) -> Self::Output {
@@ -249,9 +308,10 @@ macro_rules! impl_ops {
             }
         }
 
-        impl<T> $ops<T> for Orphan<T>
+        impl<T, C> $ops<T> for Orphan<T, C>
         where
-            T: ValueEnDe + Ord + Eq + $ops<Output = T>,
+            C: Codec<T>,
+            T: Ord + Eq + $ops<Output = T>,
         {
             type Output = T;
             fn $fn(self, other: T) -> Self::Output {
@@ -262,18 +322,20 @@ macro_rules! impl_ops {
     ($ops: tt, $fn: tt, $op: tt, $ops_assign: tt, $fn_assign: tt, $op_assign: tt) => {
         impl_ops!($ops, $fn, $op);
 
-        impl<T> $ops_assign for Orphan<T>
+        impl<T, C> $ops_assign for Orphan<T, C>
         where
-            T: ValueEnDe + Ord + Eq + $ops_assign,
+            C: Codec<T>,
+            T: Ord + Eq + $ops_assign,
         {
             fn $fn_assign(&mut self, other: Self) {
                 *self.get_mut() $op_assign other.get_value();
             }
         }
 
-        impl<T> $ops_assign<T> for Orphan<T>
+        impl<T, C> $ops_assign<T> for Orphan<T, C>
         where
-            T: ValueEnDe + Ord + Eq + $ops_assign,
+            C: Codec<T>,
+            T: Ord + Eq + $ops_assign,
         {
             fn $fn_assign(&mut self, other: T) {
                 *self.get_mut() $op_assign other;
@@ -281,9 +343,10 @@ macro_rules! impl_ops {
         }
     };
     (@$ops: tt, $fn: tt, $op: tt) => {
-        impl<T> $ops for Orphan<T>
+        impl<T, C> $ops for Orphan<T, C>
         where
-            T: ValueEnDe + Ord + Eq + $ops<Output = T>,
+            C: Codec<T>,
+            T: Ord + Eq + $ops<Output = T>,
         {
             type Output = T;
             fn $fn(self) -> Self::Output {
@@ -312,26 +375,26 @@ impl_ops!(@Neg, neg, -);
 ////////////////////////////////////////////////////////////////////
 
 /// A type returned by `get_mut()`.
-pub struct ValueMut<'a, T>
+pub struct ValueMut<'a, T, C = DefaultCodec>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
-    hdr: &'a mut Orphan<T>,
+    hdr: &'a mut Orphan<T, C>,
     value: T,
 }
 
-impl<'a, T> Drop for ValueMut<'a, T>
+impl<'a, T, C> Drop for ValueMut<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     fn drop(&mut self) {
         self.hdr.set_value(&self.value);
     }
 }
 
-impl<'a, T> Deref for ValueMut<'a, T>
+impl<'a, T, C> Deref for ValueMut<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     type Target = T;
     fn deref(&self) -> &Self::Target {
@@ -339,9 +402,9 @@ where
     }
 }
 
-impl<'a, T> DerefMut for ValueMut<'a, T>
+impl<'a, T, C> DerefMut for ValueMut<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.value
@@ -352,6 +415,6 @@ where
 ////////////////////////////////////////////////////////////////////
 
 #[cfg(feature = "vs")]
-impl<T> vsdb_core::VsMgmt for Orphan<T> {
+impl<T, C> vsdb_core::VsMgmt for Orphan<T, C> {
     vsdb_core::impl_vs_methods_nope! {}
 }
@@ -0,0 +1,300 @@
+//!
+//! A priority queue storing data in disk.
+//!
+//! NOTE:
+//! - Priorities will be encoded by `KeyEnDeOrdered`
+//! - It's your duty to ensure that the encoded priority keeps a same order with the original priority
+//! - Values will be encoded by some `serde`-like methods
+//! - Elements of a same priority are popped in the order of their pushing
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::PriorityQueuex;
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut q = PriorityQueuex::new();
+//!
+//! q.push(&2u32, &"b0".to_owned());
+//! q.push(&1u32, &"a".to_owned());
+//! q.push(&2u32, &"b1".to_owned());
+//! q.push(&3u32, &"c".to_owned());
+//!
+//! assert_eq!(q.peek_min(), Some((1, "a".to_owned())));
+//! assert_eq!(q.pop_max(), Some((3, "c".to_owned())));
+//! assert_eq!(q.pop_max(), Some((2, "b0".to_owned())));
+//! assert_eq!(q.pop_min(), Some((1, "a".to_owned())));
+//! assert_eq!(q.len(), 1);
+//!
+//! q.clear();
+//! assert!(q.is_empty());
+//! ```
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::mapx_ord_rawkey::{MapxOrdRawKey, MapxOrdRawKeyIter},
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::{KeyEnDeOrdered, ValueEnDe},
+        escape_key,
+        migrate::{Migrate, Migration},
+        unescape_key, RawBytes, RawKey,
+    },
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{marker::PhantomData, sync::Arc};
+use vsdb_core::{common::PreBytes, Registrable};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(bound = "")]
+pub struct PriorityQueuex<P, T, C = DefaultCodec> {
+    // (priority, sequence) => value
+    inner: MapxOrdRawKey<T, C>,
+    _p: PhantomData<P>,
+}
+
+impl<P, T, C> PriorityQueuex<P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Do not use this API unless you know the internal details extremely well.
+    #[inline(always)]
+    pub unsafe fn from_bytes(s: impl AsRef<[u8]>) -> Self {
+        Self {
+            inner: MapxOrdRawKey::from_bytes(s),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
+        Self {
+            inner: MapxOrdRawKey::with_codec(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, priority: &P, v: &T) {
+        let prefix = escape_key(&priority.to_bytes());
+        let seq = next_seq(&prefix, self.inner.get_le(seq_upper(&prefix)));
+        self.inner.insert(prio_key(prefix, seq), v);
+    }
+
+    /// The earliest pushed element of the lowest priority.
+    #[inline(always)]
+    pub fn peek_min(&self) -> Option<(P, T)> {
+        self.inner.first().map(|(k, v)| (decode_prio(&k), v))
+    }
+
+    /// The earliest pushed element of the highest priority.
+    #[inline(always)]
+    pub fn peek_max(&self) -> Option<(P, T)> {
+        let (k, _) = self.inner.last()?;
+        self.inner
+            .get_ge(&k[..k.len() - SEQ_SIZE])
+            .map(|(k, v)| (decode_prio(&k), v))
+    }
+
+    #[inline(always)]
+    pub fn pop_min(&mut self) -> Option<(P, T)> {
+        let (k, v) = self.inner.first()?;
+        self.inner.remove(&k);
+        Some((decode_prio(&k), v))
+    }
+
+    #[inline(always)]
+    pub fn pop_max(&mut self) -> Option<(P, T)> {
+        let (k, _) = self.inner.last()?;
+        let (k, v) = self.inner.get_ge(&k[..k.len() - SEQ_SIZE])?;
+        self.inner.remove(&k);
+        Some((decode_prio(&k), v))
+    }
+
+    /// Iterate from the lowest priority to the highest one,
+    /// elements of a same priority are in the order of their pushing,
+    /// and in the reversed order when iterating backward.
+    #[inline(always)]
+    pub fn iter(&self) -> PriorityQueuexIter<P, T, C> {
+        PriorityQueuexIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+}
+
+impl<P, T> PriorityQueuex<P, T>
+where
+    P: KeyEnDeOrdered,
+    T: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<P, T, C> Clone for PriorityQueuex<P, T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<P, T, C> Registrable for PriorityQueuex<P, T, C> {
+    const KIND: &'static str = "PriorityQueuex";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<P, Old, New, C> Migrate<Old, New> for PriorityQueuex<P, Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = PriorityQueuex<P, New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((
+            Self::Output {
+                inner,
+                _p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<P, T, C> Default for PriorityQueuex<P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    fn default() -> Self {
+        Self::with_codec()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "vs")]
+impl<P, T, C> vsdb_core::VsMgmt for PriorityQueuex<P, T, C> {
+    vsdb_core::impl_vs_methods_nope! {}
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct PriorityQueuexIter<'a, P, T, C = DefaultCodec> {
+    inner: MapxOrdRawKeyIter<'a, T, C>,
+    _p: PhantomData<P>,
+}
+
+impl<'a, P, T, C> Iterator for PriorityQueuexIter<'a, P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    type Item = (P, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (decode_prio(&k), v))
+    }
+}
+
+impl<'a, P, T, C> DoubleEndedIterator for PriorityQueuexIter<'a, P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, v)| (decode_prio(&k), v))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+// Keys are in the format of `escaped priority + sequence`,
+// see [escape_key](crate::common::escape_key).
+
+pub(crate) const SEQ_SIZE: usize = size_of::<u64>();
+
+#[inline(always)]
+pub(crate) fn prio_key(mut prefix: RawBytes, seq: u64) -> RawKey {
+    prefix.extend_from_slice(&seq.to_be_bytes());
+    prefix
+}
+
+/// The largest possible key of a priority.
+#[inline(always)]
+pub(crate) fn seq_upper(prefix: &[u8]) -> RawKey {
+    prio_key(prefix.to_vec(), u64::MAX)
+}
+
+/// The sequence for a new element,
+/// `last` is the result of `get_le(seq_upper(prefix))`.
+#[inline(always)]
+pub(crate) fn next_seq<V>(prefix: &[u8], last: Option<(RawKey, V)>) -> u64 {
+    last.filter(|(k, _)| k.len() == prefix.len() + SEQ_SIZE && k.starts_with(prefix))
+        .map(|(k, _)| 1 + crate::parse_int!(k[prefix.len()..], u64))
+        .unwrap_or(0)
+}
+
+#[inline(always)]
+pub(crate) fn decode_prio<P: KeyEnDeOrdered>(k: &[u8]) -> P {
+    pnk!(P::from_bytes(unescape_key(k).0))
+}
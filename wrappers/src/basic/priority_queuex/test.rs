@@ -0,0 +1,67 @@
+use super::*;
+
+#[test]
+fn test_order() {
+    let mut hdr: PriorityQueuex<u32, usize> = PriorityQueuex::new();
+    assert!(hdr.pop_min().is_none());
+    assert!(hdr.peek_max().is_none());
+
+    (0..100usize).for_each(|i| {
+        hdr.push(&((i % 10) as u32), &i);
+    });
+    assert_eq!(100, hdr.len());
+    assert_eq!(Some((0, 0)), hdr.peek_min());
+    assert_eq!(Some((9, 9)), hdr.peek_max());
+
+    // same priority, first in first out
+    (0..10).for_each(|i| {
+        assert_eq!(Some((0, i * 10)), hdr.pop_min());
+        assert_eq!(Some((9, i * 10 + 9)), hdr.pop_max());
+    });
+    assert_eq!(80, hdr.len());
+
+    let all = hdr.iter().collect::<Vec<_>>();
+    assert_eq!(all.first(), Some(&(1, 1)));
+    assert_eq!(all.last(), Some(&(8, 98)));
+    assert!(all.windows(2).all(|w| w[0] <= w[1]));
+
+    // sequences restart after a priority is drained
+    hdr.push(&0, &1000);
+    hdr.push(&0, &1001);
+    assert_eq!(Some((0, 1000)), hdr.pop_min());
+
+    hdr.clear();
+    assert!(hdr.is_empty());
+}
+
+#[test]
+fn test_variable_length_priority() {
+    let mut hdr: PriorityQueuex<Vec<u8>, usize> = PriorityQueuex::new();
+    let prios: Vec<Vec<u8>> = vec![
+        vec![],
+        vec![0],
+        vec![0, 0],
+        vec![0, 1],
+        vec![0, 0xFF],
+        vec![1],
+        vec![1, 0],
+        vec![0xFF],
+        vec![0xFF, 0xFF],
+    ];
+    prios.iter().enumerate().rev().for_each(|(i, p)| {
+        hdr.push(p, &i);
+        hdr.push(p, &(100 + i));
+    });
+
+    let all = hdr.iter().collect::<Vec<_>>();
+    let expected = prios
+        .iter()
+        .enumerate()
+        .flat_map(|(i, p)| [(p.clone(), i), (p.clone(), 100 + i)])
+        .collect::<Vec<_>>();
+    assert_eq!(all, expected);
+
+    assert_eq!(Some((vec![0xFF, 0xFF], 8)), hdr.pop_max());
+    assert_eq!(Some((vec![0xFF, 0xFF], 108)), hdr.pop_max());
+    assert_eq!(Some((vec![], 0)), hdr.pop_min());
+}
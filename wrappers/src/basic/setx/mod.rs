@@ -0,0 +1,302 @@
+//!
+//! A `HashSet`-like structure but storing data in disk.
+//!
+//! NOTE:
+//!
+//! - Keys will be encoded(serde) in this structure
+//! - Keys are iterated in the order of their encoded bytes,
+//!   use [SetxOrd](crate::basic::setx_ord::SetxOrd) for the order of the original keys
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::Setx;
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut a = Setx::new();
+//! let mut b = Setx::new();
+//!
+//! assert!(a.insert(&1));
+//! assert!(!a.insert(&1));
+//! a.insert(&2);
+//! b.insert(&2);
+//! b.insert(&3);
+//!
+//! assert!(a.contains(&1));
+//! assert_eq!(a.intersection(&b).collect::<Vec<_>>(), vec![2]);
+//! assert_eq!(a.difference(&b).collect::<Vec<_>>(), vec![1]);
+//! assert_eq!(a.union(&b).count(), 3);
+//!
+//! assert!(a.remove(&1));
+//! assert_eq!(a.len(), 1);
+//!
+//! a.clear();
+//! assert!(a.is_empty());
+//! ```
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::mapx_ord_rawkey::{MapxOrdRawKey, MapxOrdRawKeyIter},
+    common::{
+        codec::RawCodec,
+        ende::{KeyEnDe, KeyEnDeOrdered},
+        RawBytes, RawKey,
+    },
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, iter::Peekable, marker::PhantomData};
+use vsdb_core::{common::PreBytes, Registrable};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(bound = "")]
+pub struct Setx<K> {
+    inner: MapxOrdRawKey<RawBytes, RawCodec>,
+    _p: PhantomData<K>,
+}
+
+impl<K: KeyEnDe> Setx<K> {
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Do not use this API unless you know the internal details extremely well.
+    #[inline(always)]
+    pub unsafe fn from_bytes(s: impl AsRef<[u8]>) -> Self {
+        Self {
+            inner: MapxOrdRawKey::from_bytes(s),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            inner: MapxOrdRawKey::with_codec(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains_key(key.encode())
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Return `false` if the key has been there.
+    #[inline(always)]
+    pub fn insert(&mut self, key: &K) -> bool {
+        self.inner.insert(key.encode(), &vec![]).is_none()
+    }
+
+    /// Return `false` if the key does not exist.
+    #[inline(always)]
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.inner.remove(key.encode()).is_some()
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> SetxIter<K> {
+        SetxIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    /// The keys in either `self` or `other`.
+    #[inline(always)]
+    pub fn union<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(SetOp::Union, self.keys(), other.keys(), decode::<K>)
+    }
+
+    /// The keys in both `self` and `other`.
+    #[inline(always)]
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(SetOp::Intersection, self.keys(), other.keys(), decode::<K>)
+    }
+
+    /// The keys in `self` but not in `other`.
+    #[inline(always)]
+    pub fn difference<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(SetOp::Difference, self.keys(), other.keys(), decode::<K>)
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn keys(&self) -> RawKeys<'_> {
+        Box::new(self.inner.iter().map(|(k, _)| k))
+    }
+}
+
+impl<K> Clone for Setx<K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K> Registrable for Setx<K> {
+    const KIND: &'static str = "Setx";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K: KeyEnDe> Default for Setx<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "vs")]
+impl<K> vsdb_core::VsMgmt for Setx<K> {
+    vsdb_core::impl_vs_methods_nope! {}
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct SetxIter<'a, K> {
+    inner: MapxOrdRawKeyIter<'a, RawBytes, RawCodec>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K: KeyEnDe> Iterator for SetxIter<'a, K> {
+    type Item = K;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| pnk!(K::decode(&k)))
+    }
+}
+
+impl<'a, K: KeyEnDe> DoubleEndedIterator for SetxIter<'a, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| pnk!(K::decode(&k)))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub(crate) type RawKeys<'a> = Box<dyn Iterator<Item = RawKey> + 'a>;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum SetOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+/// A set operation streaming over two ordered key sequences,
+/// no keys will be buffered in memory.
+pub struct SetxOpIter<'a, K> {
+    op: SetOp,
+    a: Peekable<RawKeys<'a>>,
+    b: Peekable<RawKeys<'a>>,
+    decode: fn(&[u8]) -> Result<K>,
+}
+
+impl<'a, K> SetxOpIter<'a, K> {
+    pub(crate) fn new(
+        op: SetOp,
+        a: RawKeys<'a>,
+        b: RawKeys<'a>,
+        decode: fn(&[u8]) -> Result<K>,
+    ) -> Self {
+        Self {
+            op,
+            a: a.peekable(),
+            b: b.peekable(),
+            decode,
+        }
+    }
+}
+
+impl<'a, K> Iterator for SetxOpIter<'a, K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ord = match (self.a.peek(), self.b.peek(), self.op) {
+                (None, None, _) => return None,
+                (None, _, SetOp::Intersection | SetOp::Difference) => return None,
+                (_, None, SetOp::Intersection) => return None,
+                (Some(_), None, _) => Ordering::Less,
+                (None, Some(_), _) => Ordering::Greater,
+                (Some(a), Some(b), _) => a.cmp(b),
+            };
+
+            let k = match (self.op, ord) {
+                (SetOp::Union | SetOp::Difference, Ordering::Less) => self.a.next(),
+                (SetOp::Union, Ordering::Greater) => self.b.next(),
+                (SetOp::Union | SetOp::Intersection, Ordering::Equal) => {
+                    self.b.next();
+                    self.a.next()
+                }
+                (SetOp::Intersection, Ordering::Less) => {
+                    self.a.next();
+                    continue;
+                }
+                (SetOp::Intersection | SetOp::Difference, Ordering::Greater) => {
+                    self.b.next();
+                    continue;
+                }
+                (SetOp::Difference, Ordering::Equal) => {
+                    self.a.next();
+                    self.b.next();
+                    continue;
+                }
+            };
+
+            return k.map(|k| pnk!((self.decode)(&k)));
+        }
+    }
+}
+
+#[inline(always)]
+pub(crate) fn decode<K: KeyEnDe>(k: &[u8]) -> Result<K> {
+    K::decode(k)
+}
+
+#[inline(always)]
+pub(crate) fn decode_ordered<K: KeyEnDeOrdered>(k: &[u8]) -> Result<K> {
+    K::from_slice(k)
+}
@@ -0,0 +1,56 @@
+use super::*;
+
+#[test]
+fn test_insert_remove() {
+    let mut hdr: Setx<usize> = Setx::new();
+    let max = 100;
+    (0..max).for_each(|i| {
+        assert!(!hdr.contains(&i));
+        assert!(hdr.insert(&i));
+        assert!(!hdr.insert(&i));
+        assert!(hdr.contains(&i));
+    });
+    assert_eq!(max, hdr.len());
+    assert_eq!(max, hdr.iter().count());
+
+    (0..max).filter(|i| 0 == i % 2).for_each(|i| {
+        assert!(hdr.remove(&i));
+        assert!(!hdr.remove(&i));
+        assert!(!hdr.contains(&i));
+    });
+    assert_eq!(max / 2, hdr.len());
+
+    hdr.clear();
+    assert!(hdr.is_empty());
+}
+
+#[test]
+fn test_set_ops() {
+    let mut a: Setx<usize> = Setx::new();
+    let mut b: Setx<usize> = Setx::new();
+    (0..20).for_each(|i| {
+        a.insert(&i);
+    });
+    (10..30).for_each(|i| {
+        b.insert(&i);
+    });
+
+    let mut union = a.union(&b).collect::<Vec<_>>();
+    union.sort_unstable();
+    assert_eq!(union, (0..30).collect::<Vec<_>>());
+
+    let mut intersection = a.intersection(&b).collect::<Vec<_>>();
+    intersection.sort_unstable();
+    assert_eq!(intersection, (10..20).collect::<Vec<_>>());
+
+    let mut difference = a.difference(&b).collect::<Vec<_>>();
+    difference.sort_unstable();
+    assert_eq!(difference, (0..10).collect::<Vec<_>>());
+
+    let empty: Setx<usize> = Setx::new();
+    assert_eq!(20, a.union(&empty).count());
+    assert_eq!(20, empty.union(&a).count());
+    assert_eq!(0, a.intersection(&empty).count());
+    assert_eq!(20, a.difference(&empty).count());
+    assert_eq!(0, empty.difference(&a).count());
+}
@@ -0,0 +1,254 @@
+//!
+//! A `BTreeSet`-like structure but storing data in disk.
+//!
+//! NOTE:
+//!
+//! - Keys will be encoded by `KeyEnDeOrdered`
+//! - It's your duty to ensure that the encoded key keeps a same order with the original key
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::SetxOrd;
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut a = SetxOrd::new();
+//! let mut b = SetxOrd::new();
+//!
+//! (0..10u32).for_each(|i| {
+//!     a.insert(&i);
+//! });
+//! (5..15u32).for_each(|i| {
+//!     b.insert(&i);
+//! });
+//!
+//! assert_eq!(a.range(3..6).collect::<Vec<_>>(), vec![3, 4, 5]);
+//! assert_eq!(a.first(), Some(0));
+//! assert_eq!(a.last(), Some(9));
+//!
+//! assert_eq!(a.intersection(&b).collect::<Vec<_>>(), vec![5, 6, 7, 8, 9]);
+//! assert_eq!(a.difference(&b).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+//! assert_eq!(a.union(&b).collect::<Vec<_>>(), (0..15).collect::<Vec<_>>());
+//! ```
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::{
+        mapx_ord_rawkey::{MapxOrdRawKey, MapxOrdRawKeyIter},
+        setx::{decode_ordered, RawKeys, SetOp, SetxOpIter},
+    },
+    common::{codec::RawCodec, ende::KeyEnDeOrdered, RawBytes},
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+use vsdb_core::{common::PreBytes, Registrable};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(bound = "")]
+pub struct SetxOrd<K> {
+    inner: MapxOrdRawKey<RawBytes, RawCodec>,
+    _p: PhantomData<K>,
+}
+
+impl<K: KeyEnDeOrdered> SetxOrd<K> {
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Do not use this API unless you know the internal details extremely well.
+    #[inline(always)]
+    pub unsafe fn from_bytes(s: impl AsRef<[u8]>) -> Self {
+        Self {
+            inner: MapxOrdRawKey::from_bytes(s),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            inner: MapxOrdRawKey::with_codec(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains_key(key.to_bytes())
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Return `false` if the key has been there.
+    #[inline(always)]
+    pub fn insert(&mut self, key: &K) -> bool {
+        self.inner.insert(key.to_bytes(), &vec![]).is_none()
+    }
+
+    /// Return `false` if the key does not exist.
+    #[inline(always)]
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.inner.remove(key.to_bytes()).is_some()
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> SetxOrdIter<K> {
+        SetxOrdIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn range<R: RangeBounds<K>>(&self, bounds: R) -> SetxOrdIter<'_, K> {
+        let l = match bounds.start_bound() {
+            Bound::Included(lo) => Bound::Included(Cow::Owned(lo.to_bytes())),
+            Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(lo.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let h = match bounds.end_bound() {
+            Bound::Included(hi) => Bound::Included(Cow::Owned(hi.to_bytes())),
+            Bound::Excluded(hi) => Bound::Excluded(Cow::Owned(hi.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        SetxOrdIter {
+            inner: self.inner.range((l, h)),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn first(&self) -> Option<K> {
+        self.iter().next()
+    }
+
+    #[inline(always)]
+    pub fn last(&self) -> Option<K> {
+        self.iter().next_back()
+    }
+
+    /// The keys in either `self` or `other`, in order.
+    #[inline(always)]
+    pub fn union<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(SetOp::Union, self.keys(), other.keys(), decode_ordered::<K>)
+    }
+
+    /// The keys in both `self` and `other`, in order.
+    #[inline(always)]
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(
+            SetOp::Intersection,
+            self.keys(),
+            other.keys(),
+            decode_ordered::<K>,
+        )
+    }
+
+    /// The keys in `self` but not in `other`, in order.
+    #[inline(always)]
+    pub fn difference<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(
+            SetOp::Difference,
+            self.keys(),
+            other.keys(),
+            decode_ordered::<K>,
+        )
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn keys(&self) -> RawKeys<'_> {
+        Box::new(self.inner.iter().map(|(k, _)| k))
+    }
+}
+
+impl<K> Clone for SetxOrd<K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K> Registrable for SetxOrd<K> {
+    const KIND: &'static str = "SetxOrd";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K: KeyEnDeOrdered> Default for SetxOrd<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "vs")]
+impl<K> vsdb_core::VsMgmt for SetxOrd<K> {
+    vsdb_core::impl_vs_methods_nope! {}
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct SetxOrdIter<'a, K> {
+    inner: MapxOrdRawKeyIter<'a, RawBytes, RawCodec>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K: KeyEnDeOrdered> Iterator for SetxOrdIter<'a, K> {
+    type Item = K;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| pnk!(K::from_bytes(k)))
+    }
+}
+
+impl<'a, K: KeyEnDeOrdered> DoubleEndedIterator for SetxOrdIter<'a, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| pnk!(K::from_bytes(k)))
+    }
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+#[test]
+fn test_insert_remove() {
+    let mut hdr: SetxOrd<usize> = SetxOrd::new();
+    let max = 100;
+    (0..max).rev().for_each(|i| {
+        assert!(hdr.insert(&i));
+        assert!(!hdr.insert(&i));
+    });
+    assert_eq!(max, hdr.len());
+    assert_eq!(hdr.iter().collect::<Vec<_>>(), (0..max).collect::<Vec<_>>());
+    assert_eq!(
+        hdr.iter().rev().collect::<Vec<_>>(),
+        (0..max).rev().collect::<Vec<_>>()
+    );
+
+    assert!(hdr.remove(&0));
+    assert!(!hdr.remove(&0));
+    assert!(!hdr.contains(&0));
+    assert_eq!(Some(1), hdr.first());
+    assert_eq!(Some(max - 1), hdr.last());
+
+    hdr.clear();
+    assert!(hdr.is_empty());
+    assert!(hdr.first().is_none());
+}
+
+#[test]
+fn test_range() {
+    let mut hdr: SetxOrd<usize> = SetxOrd::new();
+    (0..100).for_each(|i| {
+        hdr.insert(&(i * 2));
+    });
+
+    assert_eq!(hdr.range(3..9).collect::<Vec<_>>(), vec![4, 6, 8]);
+    assert_eq!(hdr.range(4..=8).collect::<Vec<_>>(), vec![4, 6, 8]);
+    assert_eq!(hdr.range(195..).collect::<Vec<_>>(), vec![196, 198]);
+    assert_eq!(hdr.range(..3).collect::<Vec<_>>(), vec![0, 2]);
+    assert_eq!(hdr.range(3..4).count(), 0);
+}
+
+#[test]
+fn test_set_ops() {
+    let mut a: SetxOrd<usize> = SetxOrd::new();
+    let mut b: SetxOrd<usize> = SetxOrd::new();
+    (0..20).filter(|i| 0 == i % 2).for_each(|i| {
+        a.insert(&i);
+    });
+    (0..20).filter(|i| 0 == i % 3).for_each(|i| {
+        b.insert(&i);
+    });
+
+    assert_eq!(
+        a.union(&b).collect::<Vec<_>>(),
+        vec![0, 2, 3, 4, 6, 8, 9, 10, 12, 14, 15, 16, 18]
+    );
+    assert_eq!(a.intersection(&b).collect::<Vec<_>>(), vec![0, 6, 12, 18]);
+    assert_eq!(
+        a.difference(&b).collect::<Vec<_>>(),
+        vec![2, 4, 8, 10, 14, 16]
+    );
+    assert_eq!(b.difference(&a).collect::<Vec<_>>(), vec![3, 9, 15]);
+}
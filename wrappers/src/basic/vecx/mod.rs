@@ -36,19 +36,24 @@ use crate::{
     basic::mapx_ord_rawkey::{
         MapxOrdRawKey, MapxOrdRawKeyIter, MapxOrdRawKeyIterMut, ValueIterMut, ValueMut,
     },
-    common::ende::ValueEnDe,
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::ValueEnDe,
+        migrate::{Migrate, Migration},
+    },
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, cmp::Ordering};
+use std::{borrow::Cow, cmp::Ordering, sync::Arc};
+use vsdb_core::{common::PreBytes, Registrable};
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
-pub struct Vecx<T> {
-    inner: MapxOrdRawKey<T>,
+pub struct Vecx<T, C = DefaultCodec> {
+    inner: MapxOrdRawKey<T, C>,
 }
 
-impl<T: ValueEnDe> Vecx<T> {
+impl<T, C: Codec<T>> Vecx<T, C> {
     /// # Safety
     ///
     /// This API breaks the semantic safety guarantees,
@@ -75,10 +80,11 @@ impl<T: ValueEnDe> Vecx<T> {
         self.inner.as_bytes()
     }
 
+    /// Create an instance that encodes values by `C`.
     #[inline(always)]
-    pub fn new() -> Self {
+    pub fn with_codec() -> Self {
         Vecx {
-            inner: MapxOrdRawKey::new(),
+            inner: MapxOrdRawKey::with_codec(),
         }
     }
 
@@ -88,7 +94,7 @@ impl<T: ValueEnDe> Vecx<T> {
     }
 
     #[inline(always)]
-    pub fn get_mut(&mut self, idx: usize) -> Option<ValueMut<'_, T>> {
+    pub fn get_mut(&mut self, idx: usize) -> Option<ValueMut<'_, T, C>> {
         self.inner.get_mut((idx as u64).to_be_bytes())
     }
 
@@ -191,12 +197,12 @@ impl<T: ValueEnDe> Vecx<T> {
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> VecxIter<T> {
+    pub fn iter(&self) -> VecxIter<T, C> {
         VecxIter(self.inner.iter())
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> VecxIterMut<T> {
+    pub fn iter_mut(&mut self) -> VecxIterMut<T, C> {
         VecxIterMut(self.inner.iter_mut())
     }
 
@@ -204,9 +210,26 @@ impl<T: ValueEnDe> Vecx<T> {
     pub fn clear(&mut self) {
         self.inner.clear();
     }
+
+    #[inline(always)]
+    pub fn compact_instance(&self) {
+        self.inner.compact_instance();
+    }
+}
+
+impl<T> Vecx<T>
+where
+    T: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
 }
 
-impl<T> Clone for Vecx<T> {
+impl<T, C> Clone for Vecx<T, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -214,20 +237,46 @@ impl<T> Clone for Vecx<T> {
     }
 }
 
-impl<T: ValueEnDe> Default for Vecx<T> {
+impl<T, C> Registrable for Vecx<T, C> {
+    const KIND: &'static str = "Vecx";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<Old, New, C> Migrate<Old, New> for Vecx<Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = Vecx<New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((Self::Output { inner }, m))
+    }
+}
+
+impl<T, C: Codec<T>> Default for Vecx<T, C> {
     fn default() -> Self {
-        Self::new()
+        Self::with_codec()
     }
 }
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
-pub struct VecxIter<'a, T>(MapxOrdRawKeyIter<'a, T>);
+pub struct VecxIter<'a, T, C = DefaultCodec>(MapxOrdRawKeyIter<'a, T, C>);
 
-impl<'a, T> Iterator for VecxIter<'a, T>
+impl<'a, T, C> Iterator for VecxIter<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     type Item = T;
     fn next(&mut self) -> Option<Self::Item> {
@@ -235,30 +284,30 @@ where
     }
 }
 
-impl<'a, V> DoubleEndedIterator for VecxIter<'a, V>
+impl<'a, V, C> DoubleEndedIterator for VecxIter<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back().map(|(_, v)| v)
     }
 }
 
-pub struct VecxIterMut<'a, T>(MapxOrdRawKeyIterMut<'a, T>);
+pub struct VecxIterMut<'a, T, C = DefaultCodec>(MapxOrdRawKeyIterMut<'a, T, C>);
 
-impl<'a, T> Iterator for VecxIterMut<'a, T>
+impl<'a, T, C> Iterator for VecxIterMut<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
-    type Item = ValueIterMut<'a, T>;
+    type Item = ValueIterMut<'a, T, C>;
     fn next(&mut self) -> Option<Self::Item> {
         self.0.next().map(|(_, v)| v)
     }
 }
 
-impl<'a, V> DoubleEndedIterator for VecxIterMut<'a, V>
+impl<'a, V, C> DoubleEndedIterator for VecxIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.0.next_back().map(|(_, v)| v)
@@ -269,7 +318,7 @@ where
 ////////////////////////////////////////////////////////////////////
 
 #[cfg(feature = "vs")]
-impl<T> vsdb_core::VsMgmt for Vecx<T> {
+impl<T, C> vsdb_core::VsMgmt for Vecx<T, C> {
     vsdb_core::impl_vs_methods_nope! {}
 }
 
@@ -39,7 +39,7 @@ use crate::{
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, marker::PhantomData};
-use vsdb_core::basic::mapx_raw::MapxRawIter;
+use vsdb_core::{basic::mapx_raw::MapxRawIter, common::PreBytes, Registrable};
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(bound = "")]
@@ -200,6 +200,11 @@ impl VecxRaw {
     pub fn clear(&mut self) {
         self.inner.clear();
     }
+
+    #[inline(always)]
+    pub fn compact_instance(&self) {
+        self.inner.compact_instance();
+    }
 }
 
 impl Default for VecxRaw {
@@ -208,6 +213,15 @@ impl Default for VecxRaw {
     }
 }
 
+impl Registrable for VecxRaw {
+    const KIND: &'static str = "VecxRaw";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
@@ -18,6 +18,7 @@ use std::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
 };
+use vsdb_core::{common::PreBytes, Registrable};
 
 const KEY_SIZE: u32 = 2;
 
@@ -136,6 +137,15 @@ impl<K1, K2, V> Clone for MapxDk<K1, K2, V> {
     }
 }
 
+impl<K1, K2, V> Registrable for MapxDk<K1, K2, V> {
+    const KIND: &'static str = "MapxDk";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
 impl<K1, K2, V> Default for MapxDk<K1, K2, V>
 where
     K1: KeyEnDe,
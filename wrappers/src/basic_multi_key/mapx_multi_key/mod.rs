@@ -0,0 +1,364 @@
+//!
+//! A multi-key style of `Mapx`, the keys are tuples of any arity.
+//!
+//! NOTE:
+//! - Every element of the key tuples will be encoded separately by `KeyEnDe`
+//! - Values will be encoded by some `serde`-like methods
+//! - All the entries are stored as composite keys in one instance,
+//!   they are iterated in the order of the encoded first keys,
+//!   and then in the order of the encoded second keys, and so on
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::basic_multi_key::mapx_multi_key::MapxMk;
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut m: MapxMk<(u8, u8, u8), String> = MapxMk::new();
+//!
+//! m.insert(&(1, 1, 1), &"a".to_owned());
+//! m.insert(&(1, 1, 2), &"b".to_owned());
+//! m.insert(&(1, 2, 1), &"c".to_owned());
+//! m.insert(&(2, 1, 1), &"d".to_owned());
+//!
+//! assert_eq!(m.get(&(1, 2, 1)), Some("c".to_owned()));
+//! assert_eq!(m.iter_prefix(&(1u8,)).count(), 3);
+//! assert_eq!(m.iter_prefix(&(1u8, 1u8)).count(), 2);
+//!
+//! assert_eq!(m.remove_prefix(&(1u8,)), 3);
+//! assert_eq!(
+//!     m.iter().collect::<Vec<_>>(),
+//!     vec![((2, 1, 1), "d".to_owned())]
+//! );
+//! ```
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::mapx_ord_rawkey::{MapxOrdRawKey, MapxOrdRawKeyIter},
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::{KeyEnDe, ValueEnDe},
+        escape_key,
+        migrate::{Migrate, Migration},
+        unescape_key, RawBytes, RawKey,
+    },
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{marker::PhantomData, sync::Arc};
+use vsdb_core::{common::PreBytes, Registrable};
+
+/// Tuples of keys, the elements are encoded separately,
+/// and then joined as a composite key.
+pub trait MultiKey: Sized {
+    /// The number of the elements.
+    const KEY_SIZE: u32;
+
+    /// Encode to a composite key,
+    /// see [escape_key](crate::common::escape_key) for the format.
+    fn encode_keys(&self) -> RawKey;
+
+    /// Decode from a composite key.
+    fn decode_keys(k: &[u8]) -> Result<Self>;
+}
+
+/// The leading subsets of the keys of `K`,
+/// including the empty one `()` and `K` itself.
+pub trait MultiKeyPrefix<K: MultiKey>: MultiKey {}
+
+impl MultiKey for () {
+    const KEY_SIZE: u32 = 0;
+
+    fn encode_keys(&self) -> RawKey {
+        vec![]
+    }
+
+    fn decode_keys(_: &[u8]) -> Result<Self> {
+        Ok(())
+    }
+}
+
+macro_rules! impl_key_prefix {
+    ([$($p: ident),*] []) => {
+        impl<$($p: KeyEnDe),*> MultiKeyPrefix<($($p,)*)> for ($($p,)*) {}
+    };
+    ([$($p: ident),*] [$r: ident $(, $rest: ident)*]) => {
+        impl<$($p: KeyEnDe,)* $r: KeyEnDe, $($rest: KeyEnDe),*>
+            MultiKeyPrefix<($($p,)* $r, $($rest,)*)> for ($($p,)*) {}
+        impl_key_prefix!([$($p,)* $r] [$($rest),*]);
+    };
+}
+
+macro_rules! impl_multi_key {
+    ($n: expr; $($k: ident $i: tt),+) => {
+        impl<$($k: KeyEnDe),+> MultiKey for ($($k,)+) {
+            const KEY_SIZE: u32 = $n;
+
+            fn encode_keys(&self) -> RawKey {
+                let mut ret = vec![];
+                $(ret.extend_from_slice(&escape_key(&self.$i.encode()));)+
+                ret
+            }
+
+            fn decode_keys(k: &[u8]) -> Result<Self> {
+                let mut parts = split_keys(k).into_iter();
+                Ok(($(
+                    <$k as KeyEnDe>::decode(&parts.next().c(d!("invalid key size"))?)
+                        .c(d!())?,
+                )+))
+            }
+        }
+        impl_key_prefix!([] [$($k),+]);
+    };
+}
+
+impl_multi_key!(1; K1 0);
+impl_multi_key!(2; K1 0, K2 1);
+impl_multi_key!(3; K1 0, K2 1, K3 2);
+impl_multi_key!(4; K1 0, K2 1, K3 2, K4 3);
+impl_multi_key!(5; K1 0, K2 1, K3 2, K4 3, K5 4);
+impl_multi_key!(6; K1 0, K2 1, K3 2, K4 3, K5 4, K6 5);
+impl_multi_key!(7; K1 0, K2 1, K3 2, K4 3, K5 4, K6 5, K7 6);
+impl_multi_key!(8; K1 0, K2 1, K3 2, K4 3, K5 4, K6 5, K7 6, K8 7);
+
+/// Split a composite key into the original parts.
+fn split_keys(mut k: &[u8]) -> Vec<RawBytes> {
+    let mut ret = vec![];
+    while !k.is_empty() {
+        let (part, rest) = unescape_key(k);
+        ret.push(part);
+        k = rest;
+    }
+    ret
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+/// A map structure with keys of any arity.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+#[serde(bound = "")]
+pub struct MapxMk<K, V, C = DefaultCodec> {
+    inner: MapxOrdRawKey<V, C>,
+    _p: PhantomData<K>,
+}
+
+impl<K, V, C> MapxMk<K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// Do not use this API unless you know the internal details extremely well.
+    #[inline(always)]
+    pub unsafe fn from_bytes(s: impl AsRef<[u8]>) -> Self {
+        Self {
+            inner: MapxOrdRawKey::from_bytes(s),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
+        Self {
+            inner: MapxOrdRawKey::with_codec(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn key_size(&self) -> u32 {
+        K::KEY_SIZE
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.inner.get(key.encode_keys())
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key.encode_keys())
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn insert(&mut self, key: &K, value: &V) -> Option<V> {
+        self.inner.insert(key.encode_keys(), value)
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.inner.remove(key.encode_keys())
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> MapxMkIter<'_, K, V, C> {
+        MapxMkIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    /// Iterate over the entries whose leading keys are equal to `prefix`.
+    #[inline(always)]
+    pub fn iter_prefix<P: MultiKeyPrefix<K>>(
+        &self,
+        prefix: &P,
+    ) -> MapxMkIter<'_, K, V, C> {
+        MapxMkIter {
+            inner: self.inner.iter_prefix(prefix.encode_keys()),
+            _p: PhantomData,
+        }
+    }
+
+    /// Remove the entries whose leading keys are equal to `prefix`,
+    /// return the number of them.
+    #[inline(always)]
+    pub fn remove_prefix<P: MultiKeyPrefix<K>>(&mut self, prefix: &P) -> usize {
+        self.inner.remove_prefix(prefix.encode_keys())
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+}
+
+impl<K, V> MapxMk<K, V>
+where
+    K: MultiKey,
+    V: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<K, V, C> Clone for MapxMk<K, V, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K, V, C> Registrable for MapxMk<K, V, C> {
+    const KIND: &'static str = "MapxMk";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K, Old, New, C> Migrate<Old, New> for MapxMk<K, Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = MapxMk<K, New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((
+            Self::Output {
+                inner,
+                _p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<K, V, C> Default for MapxMk<K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    fn default() -> Self {
+        Self::with_codec()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "vs")]
+impl<K, V, C> vsdb_core::VsMgmt for MapxMk<K, V, C> {
+    vsdb_core::impl_vs_methods_nope! {}
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+pub struct MapxMkIter<'a, K, V, C = DefaultCodec> {
+    inner: MapxOrdRawKeyIter<'a, V, C>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K, V, C> Iterator for MapxMkIter<'a, K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(k, v)| (pnk!(K::decode_keys(&k)), v))
+    }
+}
+
+impl<'a, K, V, C> DoubleEndedIterator for MapxMkIter<'a, K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|(k, v)| (pnk!(K::decode_keys(&k)), v))
+    }
+}
@@ -0,0 +1,96 @@
+use super::*;
+use crate::ValueEnDe;
+
+#[test]
+fn test_insert_remove() {
+    let mut hdr: MapxMk<(u32, u32, u32), u64> = MapxMk::new();
+    assert_eq!(3, hdr.key_size());
+
+    (0..10u32).for_each(|i| {
+        (0..10u32).for_each(|j| {
+            let key = (i, j, i + j);
+            assert!(hdr.get(&key).is_none());
+            assert!(hdr.insert(&key, &((i * j) as u64)).is_none());
+            assert!(hdr.contains_key(&key));
+            assert_eq!(Some((i * j) as u64), hdr.get(&key));
+        });
+    });
+    assert_eq!(100, hdr.len());
+
+    assert_eq!(Some(6), hdr.insert(&(2, 3, 5), &0));
+    assert_eq!(Some(0), hdr.remove(&(2, 3, 5)));
+    assert!(hdr.remove(&(2, 3, 5)).is_none());
+    assert_eq!(99, hdr.len());
+
+    hdr.clear();
+    assert!(hdr.is_empty());
+}
+
+#[test]
+fn test_prefix() {
+    let mut hdr: MapxMk<(u32, String, u32), u32> = MapxMk::new();
+    (0..5u32).for_each(|i| {
+        ["", "a", "ab", "b"].iter().for_each(|s| {
+            (0..3u32).for_each(|j| {
+                hdr.insert(&(i, s.to_string(), j), &(i + j));
+            });
+        });
+    });
+    assert_eq!(60, hdr.len());
+
+    assert_eq!(60, hdr.iter_prefix(&()).count());
+    assert_eq!(12, hdr.iter_prefix(&(1u32,)).count());
+    assert_eq!(3, hdr.iter_prefix(&(1u32, "a".to_owned())).count());
+    assert_eq!(3, hdr.iter_prefix(&(1u32, "".to_owned())).count());
+    assert_eq!(1, hdr.iter_prefix(&(1u32, "ab".to_owned(), 2u32)).count());
+    assert_eq!(0, hdr.iter_prefix(&(9u32,)).count());
+
+    assert_eq!(
+        hdr.iter_prefix(&(1u32, "a".to_owned()))
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>(),
+        (0..3).map(|j| (1, "a".to_owned(), j)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        hdr.iter_prefix(&(1u32, "a".to_owned())).next_back(),
+        Some(((1, "a".to_owned(), 2), 3))
+    );
+
+    assert_eq!(3, hdr.remove_prefix(&(1u32, "a".to_owned())));
+    assert_eq!(3, hdr.iter_prefix(&(1u32, "ab".to_owned())).count());
+    assert_eq!(9, hdr.remove_prefix(&(1u32,)));
+    assert_eq!(0, hdr.remove_prefix(&(1u32,)));
+    assert_eq!(48, hdr.len());
+    assert_eq!(48, hdr.remove_prefix(&()));
+    assert!(hdr.is_empty());
+}
+
+#[test]
+fn test_arity() {
+    let mut hdr: MapxMk<(u8,), u8> = MapxMk::new();
+    hdr.insert(&(1,), &1);
+    assert_eq!(1, hdr.key_size());
+    assert_eq!(hdr.iter().collect::<Vec<_>>(), vec![((1,), 1)]);
+
+    let mut hdr: MapxMk<(u8, u8, u8, u8, u8, u8, u8, u8), u8> = MapxMk::new();
+    let key = (0, 1, 2, 3, 4, 5, 6, 7);
+    hdr.insert(&key, &8);
+    assert_eq!(8, hdr.key_size());
+    assert_eq!(Some(8), hdr.get(&key));
+    assert_eq!(1, hdr.iter_prefix(&(0u8, 1u8, 2u8, 3u8)).count());
+    assert_eq!(0, hdr.iter_prefix(&(0u8, 1u8, 3u8)).count());
+}
+
+#[test]
+fn test_valueende() {
+    let mut hdr: MapxMk<(u32, u32), u32> = MapxMk::new();
+    (0..10u32).for_each(|i| {
+        hdr.insert(&(i, i), &i);
+    });
+
+    let encoded = <MapxMk<(u32, u32), u32> as ValueEnDe>::encode(&hdr);
+    let reloaded = pnk!(<MapxMk<(u32, u32), u32> as ValueEnDe>::decode(&encoded));
+    (0..10u32).for_each(|i| {
+        assert_eq!(Some(i), reloaded.get(&(i, i)));
+    });
+}
@@ -15,6 +15,7 @@ use crate::{
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::ops::{Deref, DerefMut};
+use vsdb_core::{common::PreBytes, Registrable};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
@@ -386,6 +387,15 @@ impl<'a> Entry<'a> {
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
+impl Registrable for MapxRawMk {
+    const KIND: &'static str = "MapxRawMk";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
 #[cfg(feature = "vs")]
 impl vsdb_core::VsMgmt for MapxRawMk {
     vsdb_core::impl_vs_methods_nope! {}
@@ -15,6 +15,7 @@ use std::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
 };
+use vsdb_core::{common::PreBytes, Registrable};
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
@@ -135,6 +136,15 @@ impl<V> Clone for MapxRawKeyMk<V> {
     }
 }
 
+impl<V> Registrable for MapxRawKeyMk<V> {
+    const KIND: &'static str = "MapxRawKeyMk";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
 #[derive(Debug)]
 pub struct ValueMut<'a, V: ValueEnDe> {
     hdr: &'a mut MapxRawKeyMk<V>,
@@ -18,6 +18,7 @@ use std::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
 };
+use vsdb_core::{common::PreBytes, Registrable};
 
 const KEY_SIZE: u32 = 3;
 
@@ -149,6 +150,15 @@ impl<K1, K2, K3, V> Clone for MapxTk<K1, K2, K3, V> {
     }
 }
 
+impl<K1, K2, K3, V> Registrable for MapxTk<K1, K2, K3, V> {
+    const KIND: &'static str = "MapxTk";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
 impl<K1, K2, K3, V> Default for MapxTk<K1, K2, K3, V>
 where
     K1: KeyEnDe,
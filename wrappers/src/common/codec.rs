@@ -0,0 +1,230 @@
+//!
+//! # Codec
+//!
+//! Pluggable value encodings.
+//!
+//! Every typed collection takes a `C: Codec<V>` type parameter,
+//! it defaults to [DefaultCodec], which follows the `bcs_codec`, `json_codec`
+//! or `msgpack_codec` feature selected at compile time,
+//! so existing code keeps its behavior.
+//! Use `with_codec()` instead of `new()` to create a collection with another codec.
+//!
+//! The `bincode_codec`, `postcard_codec`, `borsh_codec` and `scale_codec` features
+//! only enable the corresponding per-collection codecs, they can be used together.
+//!
+//! Only the VALUES are affected, keys are always encoded by
+//! [KeyEnDe](crate::KeyEnDe) or [KeyEnDeOrdered](crate::KeyEnDeOrdered),
+//! the order of keys must not depend on the chosen codec.
+//! None of the `bincode`, `postcard`, `borsh` and `SCALE` formats
+//! keeps the order of the encoded values,
+//! so they are not available as key encodings.
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::{basic::mapx::Mapx, common::codec::RawCodec};
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut m: Mapx<u32, Vec<u8>, RawCodec> = Mapx::with_codec();
+//! m.insert(&1, &vec![1, 2, 3]);
+//! assert_eq!(m.get(&1), Some(vec![1, 2, 3]));
+//! ```
+//!
+
+use super::RawBytes;
+use crate::ValueEnDe;
+use ruc::*;
+
+#[cfg(any(
+    feature = "bcs",
+    feature = "serde_json",
+    feature = "rmp-serde",
+    feature = "bincode",
+    feature = "postcard"
+))]
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Methods used to encode and decode the values of a collection.
+pub trait Codec<T> {
+    /// Encode a value to bytes.
+    fn try_encode(t: &T) -> Result<RawBytes>;
+
+    /// Encode a value to bytes, panic on failures.
+    fn encode(t: &T) -> RawBytes {
+        pnk!(Self::try_encode(t))
+    }
+
+    /// Decode from bytes to the original value.
+    fn decode(bytes: &[u8]) -> Result<T>;
+}
+
+/// Use the [ValueEnDe] implementations,
+/// that is, the codec selected by the `bcs_codec`, `json_codec`
+/// or `msgpack_codec` feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DefaultCodec;
+
+impl<T: ValueEnDe> Codec<T> for DefaultCodec {
+    #[inline(always)]
+    fn try_encode(t: &T) -> Result<RawBytes> {
+        t.try_encode()
+    }
+
+    #[inline(always)]
+    fn encode(t: &T) -> RawBytes {
+        t.encode()
+    }
+
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<T> {
+        T::decode(bytes)
+    }
+}
+
+/// Store the raw bytes as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RawCodec;
+
+impl Codec<RawBytes> for RawCodec {
+    #[inline(always)]
+    fn try_encode(t: &RawBytes) -> Result<RawBytes> {
+        Ok(t.clone())
+    }
+
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<RawBytes> {
+        Ok(bytes.to_vec())
+    }
+}
+
+/// The `bcs` format.
+#[cfg(feature = "bcs")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BcsCodec;
+
+#[cfg(feature = "bcs")]
+impl<T: Serialize + DeserializeOwned> Codec<T> for BcsCodec {
+    #[inline(always)]
+    fn try_encode(t: &T) -> Result<RawBytes> {
+        bcs::to_bytes(t).c(d!())
+    }
+
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<T> {
+        bcs::from_bytes(bytes).c(d!())
+    }
+}
+
+/// The `json` format.
+#[cfg(feature = "serde_json")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonCodec;
+
+#[cfg(feature = "serde_json")]
+impl<T: Serialize + DeserializeOwned> Codec<T> for JsonCodec {
+    #[inline(always)]
+    fn try_encode(t: &T) -> Result<RawBytes> {
+        serde_json::to_vec(t).c(d!())
+    }
+
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<T> {
+        serde_json::from_slice(bytes).c(d!())
+    }
+}
+
+/// The `msgpack` format.
+#[cfg(feature = "rmp-serde")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MsgpackCodec;
+
+#[cfg(feature = "rmp-serde")]
+impl<T: Serialize + DeserializeOwned> Codec<T> for MsgpackCodec {
+    #[inline(always)]
+    fn try_encode(t: &T) -> Result<RawBytes> {
+        rmp_serde::to_vec(t).c(d!())
+    }
+
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<T> {
+        rmp_serde::from_slice(bytes).c(d!())
+    }
+}
+
+/// The `bincode` format.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl<T: Serialize + DeserializeOwned> Codec<T> for BincodeCodec {
+    #[inline(always)]
+    fn try_encode(t: &T) -> Result<RawBytes> {
+        bincode::serialize(t).c(d!())
+    }
+
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<T> {
+        bincode::deserialize(bytes).c(d!())
+    }
+}
+
+/// The `postcard` format, a compact one suitable for hot tables.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PostcardCodec;
+
+#[cfg(feature = "postcard")]
+impl<T: Serialize + DeserializeOwned> Codec<T> for PostcardCodec {
+    #[inline(always)]
+    fn try_encode(t: &T) -> Result<RawBytes> {
+        postcard::to_stdvec(t).c(d!())
+    }
+
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<T> {
+        postcard::from_bytes(bytes).c(d!())
+    }
+}
+
+/// The `borsh` format, for types that implement
+/// `BorshSerialize` and `BorshDeserialize` instead of `serde`.
+#[cfg(feature = "borsh")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BorshCodec;
+
+#[cfg(feature = "borsh")]
+impl<T: borsh::BorshSerialize + borsh::BorshDeserialize> Codec<T> for BorshCodec {
+    #[inline(always)]
+    fn try_encode(t: &T) -> Result<RawBytes> {
+        borsh::to_vec(t).c(d!())
+    }
+
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<T> {
+        borsh::from_slice(bytes).c(d!())
+    }
+}
+
+/// The SCALE format of `parity-scale-codec`, for types that implement
+/// `Encode` and `Decode` instead of `serde`.
+#[cfg(feature = "parity-scale-codec")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScaleCodec;
+
+#[cfg(feature = "parity-scale-codec")]
+impl<T: parity_scale_codec::Encode + parity_scale_codec::Decode> Codec<T>
+    for ScaleCodec
+{
+    #[inline(always)]
+    fn try_encode(t: &T) -> Result<RawBytes> {
+        Ok(t.encode())
+    }
+
+    #[inline(always)]
+    fn decode(bytes: &[u8]) -> Result<T> {
+        <T as parity_scale_codec::DecodeAll>::decode_all(&mut &*bytes).c(d!())
+    }
+}
\ No newline at end of file
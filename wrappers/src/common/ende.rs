@@ -14,6 +14,9 @@ use std::{
 #[cfg(feature = "serde_ende")]
 use serde::{de::DeserializeOwned, Serialize};
 
+#[cfg(not(feature = "serde_ende"))]
+use super::codec::Codec;
+
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
 
@@ -227,7 +230,7 @@ macro_rules! impl_v_ende {
     };
     (^$t: ty) => {
         #[cfg(not(feature = "serde_ende"))]
-        impl<V: ValueEnDe> ValueEnDe for $t {
+        impl<V, C: Codec<V>> ValueEnDe for $t {
             fn try_encode(&self) -> Result<RawBytes> {
                 Ok(self.encode())
             }
@@ -241,7 +244,39 @@ macro_rules! impl_v_ende {
     };
     (~$t: ty) => {
         #[cfg(not(feature = "serde_ende"))]
-        impl<K: KeyEnDeOrdered, V: ValueEnDe> ValueEnDe for $t {
+        impl<K: KeyEnDeOrdered, V, C: Codec<V>> ValueEnDe for $t {
+            fn try_encode(&self) -> Result<RawBytes> {
+                Ok(self.encode())
+            }
+            fn encode(&self) -> RawBytes {
+                self.as_bytes().into()
+            }
+            fn decode(bytes: &[u8]) -> Result<Self> {
+                unsafe { Ok(<$t>::from_bytes(bytes)) }
+            }
+        }
+    };
+    (%$t: ty) => {
+        #[cfg(not(feature = "serde_ende"))]
+        impl<K: KeyEnDeOrdered, V: KeyEnDeOrdered> ValueEnDe for $t {
+            fn try_encode(&self) -> Result<RawBytes> {
+                Ok(self.encode())
+            }
+            fn encode(&self) -> RawBytes {
+                self.as_bytes().into()
+            }
+            fn decode(bytes: &[u8]) -> Result<Self> {
+                unsafe { Ok(<$t>::from_bytes(bytes)) }
+            }
+        }
+    };
+    (#$t: ty) => {
+        #[cfg(not(feature = "serde_ende"))]
+        impl<K, V, C> ValueEnDe for $t
+        where
+            K: crate::basic_multi_key::mapx_multi_key::MultiKey,
+            C: Codec<V>,
+        {
             fn try_encode(&self) -> Result<RawBytes> {
                 Ok(self.encode())
             }
@@ -257,12 +292,18 @@ macro_rules! impl_v_ende {
 
 impl_v_ende!(vsdb_core::MapxRaw);
 impl_v_ende!(crate::basic::vecx_raw::VecxRaw);
-impl_v_ende!(~crate::basic::mapx::Mapx<K, V>);
-impl_v_ende!(~crate::basic::mapx_ord::MapxOrd<K, V>);
-impl_v_ende!(^crate::basic::vecx::Vecx<V>);
-impl_v_ende!(^crate::basic::orphan::Orphan<V>);
-impl_v_ende!(^crate::basic::mapx_ord_rawkey::MapxOrdRawKey<V>);
+impl_v_ende!(~crate::basic::mapx::Mapx<K, V, C>);
+impl_v_ende!(~crate::basic::mapx_ord::MapxOrd<K, V, C>);
+impl_v_ende!(^crate::basic::vecx::Vecx<V, C>);
+impl_v_ende!(^crate::basic::orphan::Orphan<V, C>);
+impl_v_ende!(^crate::basic::mapx_ord_rawkey::MapxOrdRawKey<V, C>);
+impl_v_ende!(^crate::basic::dequex::Dequex<V, C>);
+impl_v_ende!(~crate::basic::priority_queuex::PriorityQueuex<K, V, C>);
 impl_v_ende!(@crate::basic::mapx_ord_rawvalue::MapxOrdRawValue<K>);
+impl_v_ende!(@crate::basic::setx::Setx<K>);
+impl_v_ende!(@crate::basic::setx_ord::SetxOrd<K>);
+impl_v_ende!(%crate::basic::multi_mapx::MultiMapx<K, V>);
+impl_v_ende!(#crate::basic_multi_key::mapx_multi_key::MapxMk<K, V, C>);
 
 /////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////////////////////////////////////////////
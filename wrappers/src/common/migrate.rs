@@ -0,0 +1,528 @@
+//!
+//! # Schema migrations
+//!
+//! Convert the values stored in a collection from an old type to a new one,
+//! the schema version of the collection will be increased by `1` after each migration.
+//!
+//! A migration can be done in three ways:
+//!
+//! - [migrate], convert all values before returning
+//! - [migrate_lazily], convert each value on its first access,
+//!   the rest of them can be converted later by [Migration::run]
+//! - [migrate_in_background], convert each value on its first access,
+//!   and convert the rest of them in a background thread
+//!
+//! The converted keys are recorded along with the new values in one batch,
+//! so an interrupted migration can be resumed by calling any of them again
+//! with the same converter, no value will be converted twice.
+//! Range queries, such as `iter`, `range` and `get_le`,
+//! convert the values they meet one by one.
+//!
+//! A migration left by a previous process is resumed on the first access
+//! of the collection, if its converter has been registered by [register_converter].
+//!
+//! NOTE:
+//! - Accessing a value that has not been converted will panic
+//!   if the converter fails or the migration can not be resumed,
+//!   APIs returning a `Result` return an error instead
+//!
+//! # Examples
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use vsdb::{migrate, migrate_lazily, vsdb_get_schema_version, Mapx};
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct AccountV0 {
+//!     balance: u64,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct AccountV1 {
+//!     balance: u128,
+//!     nonce: u64,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct AccountV2 {
+//!     balance: u128,
+//! }
+//!
+//! let mut m: Mapx<u64, AccountV0> = Mapx::new();
+//! m.insert(&1, &AccountV0 { balance: 100 });
+//! m.insert(&2, &AccountV0 { balance: 200 });
+//! assert_eq!(0, vsdb_get_schema_version(&m));
+//!
+//! let m: Mapx<u64, AccountV1> = migrate(m, |old: AccountV0| AccountV1 {
+//!     balance: old.balance as u128,
+//!     nonce: 0,
+//! })
+//! .unwrap();
+//!
+//! assert_eq!(1, vsdb_get_schema_version(&m));
+//! assert_eq!(100, m.get(&1).unwrap().balance);
+//!
+//! let (m, migration) = migrate_lazily(m, |old: AccountV1| AccountV2 {
+//!     balance: old.balance,
+//! })
+//! .unwrap();
+//!
+//! // converted on read
+//! let m: Mapx<u64, AccountV2> = m;
+//! assert_eq!(100, m.get(&1).unwrap().balance);
+//! assert_eq!(1, vsdb_get_schema_version(&m));
+//!
+//! migration.run().unwrap();
+//! assert_eq!(2, vsdb_get_schema_version(&m));
+//! assert_eq!(200, m.get(&2).unwrap().balance);
+//! ```
+//!
+
+use crate::common::{RawValue, codec::Codec};
+use once_cell::sync::Lazy;
+use parking_lot::{Mutex, RwLock};
+use ruc::*;
+use std::{
+    any::type_name,
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+use vsdb_core::{
+    RawKey, Registrable,
+    basic::mapx_raw::{MapxRaw, MapxRawBatch},
+    common::PreBytes,
+    vsdb_begin_migration, vsdb_finish_migration, vsdb_list_pending_migrations,
+};
+
+#[cfg(feature = "vs")]
+use vsdb_core::versioned::mapx_raw::MapxRawVs;
+
+// The value of the converted keys in the markers.
+const CONVERTED: &[u8] = &[];
+
+/// Converters from the old encoded values to the new ones.
+pub(crate) type Converter = Arc<dyn Fn(&[u8]) -> Result<RawValue> + Send + Sync>;
+
+// The names of the value type and the codec of a collection,
+// used to find the registered converter of it.
+pub(crate) type ConverterID = (&'static str, &'static str);
+
+// The converters used to resume the migrations left by previous processes.
+static CONVERTERS: Lazy<RwLock<HashMap<ConverterID, Converter>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[inline(always)]
+pub(crate) fn converter_id<V, C>() -> ConverterID {
+    (type_name::<V>(), type_name::<C>())
+}
+
+// Wrap a typed converter into a raw one.
+pub(crate) fn converter<Old, New, C>(
+    f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+) -> Converter
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    Arc::new(move |v| {
+        let old = <C as Codec<Old>>::decode(v).c(d!())?;
+        <C as Codec<New>>::try_encode(&f(old)).c(d!())
+    })
+}
+
+/// Register the converter from `Old` to `New`, both encoded by `C`.
+///
+/// An unfinished migration left by a previous process
+/// will be resumed by it on the first access of the collection,
+/// so call this before accessing any collection of `New`.
+pub fn register_converter<Old, New, C>(f: impl Fn(Old) -> New + Send + Sync + 'static)
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    CONVERTERS.write().insert(
+        converter_id::<New, C>(),
+        converter::<Old, New, C>(Arc::new(f)),
+    );
+}
+
+// The typed collections whose values can be converted by migrations.
+pub(crate) trait Migratable: Registrable {
+    // The names of the value type and the codec.
+    fn converter_id(&self) -> ConverterID;
+
+    // A handle of the underlying storage.
+    fn raw_store(&self) -> RawStore;
+}
+
+/// Collections whose values can be converted from `Old` to `New` in place.
+pub trait Migrate<Old, New>: Registrable + Sized {
+    /// The same collection with the new value type.
+    type Output: Registrable;
+
+    /// Switch to the new value type, and register `f` to convert
+    /// the stored values, no value will be converted here.
+    ///
+    /// An unfinished migration of the same instance will be resumed.
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)>;
+}
+
+/// An unfinished migration, see the [module docs](self).
+#[derive(Debug)]
+pub struct Migration {
+    prefix: PreBytes,
+}
+
+impl Migration {
+    /// Convert all the values that have not been converted,
+    /// then increase the schema version of the collection.
+    pub fn run(self) -> Result<()> {
+        ensure_all_migrated(self.prefix).c(d!())
+    }
+}
+
+/// Convert all values of a collection by `f`,
+/// and increase its schema version by `1`.
+pub fn migrate<Old, New, M>(
+    map: M,
+    f: impl Fn(Old) -> New + Send + Sync + 'static,
+) -> Result<M::Output>
+where
+    M: Migrate<Old, New>,
+{
+    let (ret, m) = map.begin_migration(Arc::new(f)).c(d!())?;
+    m.run().c(d!())?;
+    Ok(ret)
+}
+
+/// Switch to the new value type without converting any value,
+/// values will be converted on their first access,
+/// use the returned [Migration] to convert the rest of them.
+pub fn migrate_lazily<Old, New, M>(
+    map: M,
+    f: impl Fn(Old) -> New + Send + Sync + 'static,
+) -> Result<(M::Output, Migration)>
+where
+    M: Migrate<Old, New>,
+{
+    map.begin_migration(Arc::new(f)).c(d!())
+}
+
+/// Do the same thing as [migrate_lazily],
+/// and convert the rest of the values in a background thread.
+pub fn migrate_in_background<Old, New, M>(
+    map: M,
+    f: impl Fn(Old) -> New + Send + Sync + 'static,
+) -> Result<(M::Output, JoinHandle<Result<()>>)>
+where
+    M: Migrate<Old, New>,
+{
+    let (ret, m) = migrate_lazily(map, f).c(d!())?;
+    Ok((ret, thread::spawn(move || m.run())))
+}
+
+/////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////////////////////////////////////
+
+// The underlying storage of a collection.
+pub(crate) enum RawStore {
+    Basic(MapxRaw),
+    #[cfg(feature = "vs")]
+    Versioned(MapxRawVs),
+}
+
+impl RawStore {
+    fn keys(&self) -> Box<dyn Iterator<Item = RawKey> + '_> {
+        match self {
+            Self::Basic(m) => Box::new(m.iter().map(|(k, _)| k)),
+            #[cfg(feature = "vs")]
+            Self::Versioned(m) => Box::new(m.keys_globally()),
+        }
+    }
+
+    // Convert the values of the key, and mark it as converted in one batch.
+    fn convert(
+        &mut self,
+        key: &[u8],
+        f: &Converter,
+        markers: &mut MapxRaw,
+    ) -> Result<()> {
+        let mut batch = MapxRawBatch::new();
+        match self {
+            Self::Basic(m) => {
+                if let Some(v) = m.get(key) {
+                    let v = f(&v).c(d!())?;
+                    batch.insert(m, key, v);
+                }
+            }
+            #[cfg(feature = "vs")]
+            Self::Versioned(m) => {
+                m.rewrite_values_of(key, &**f, &mut batch).c(d!())?;
+            }
+        }
+        batch.insert(markers, key, CONVERTED);
+        batch.commit();
+        Ok(())
+    }
+}
+
+struct Worker {
+    store: RawStore,
+    f: Converter,
+}
+
+// A migration that has not been finished.
+struct Pending {
+    // the converted keys
+    markers: MapxRaw,
+    // `None` if it is left by a previous process and has not been resumed,
+    // also used to serialize the conversions
+    worker: Mutex<Option<Worker>>,
+    finished: AtomicBool,
+}
+
+impl Pending {
+    fn new(markers: MapxRaw, worker: Option<Worker>) -> Self {
+        Self {
+            markers,
+            worker: Mutex::new(worker),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    // Convert the values of the key by the current worker.
+    fn convert_by_worker(&self, key: &[u8]) -> Result<()> {
+        if self.markers.contains_key(key) {
+            return Ok(());
+        }
+
+        let mut worker = self.worker.lock();
+
+        if self.finished.load(Ordering::Acquire) || self.markers.contains_key(key) {
+            return Ok(());
+        }
+
+        let w = worker.as_mut().c(d!(
+            "unfinished migration, resume it before accessing the values"
+        ))?;
+        let mut markers = unsafe { self.markers.shadow() };
+        w.store.convert(key, &w.f, &mut markers).c(d!())
+    }
+
+    // Resume the migration left by a previous process by the registered converter.
+    fn resume<'a>(
+        worker: &'a mut Option<Worker>,
+        hdr: &impl Migratable,
+    ) -> Result<&'a mut Worker> {
+        if worker.is_none() {
+            let f = CONVERTERS.read().get(&hdr.converter_id()).cloned().c(d!(
+                "unfinished migration, register its converter or resume it before accessing the values"
+            ))?;
+            *worker = Some(Worker {
+                store: hdr.raw_store(),
+                f,
+            });
+        }
+        worker.as_mut().c(d!())
+    }
+
+    fn convert(&self, key: &[u8], hdr: &impl Migratable) -> Result<()> {
+        if self.markers.contains_key(key) {
+            return Ok(());
+        }
+
+        let mut worker = self.worker.lock();
+
+        if self.finished.load(Ordering::Acquire) || self.markers.contains_key(key) {
+            return Ok(());
+        }
+
+        let w = Self::resume(&mut worker, hdr).c(d!())?;
+        let mut markers = unsafe { self.markers.shadow() };
+        w.store.convert(key, &w.f, &mut markers).c(d!())
+    }
+}
+
+/// A collection being migrated, held by its iterators.
+pub(crate) struct Migrating {
+    p: Arc<Pending>,
+    store: RawStore,
+}
+
+impl Migrating {
+    // Get the next entry of an iterator, `value` gives the raw value of an entry,
+    // which is replaced by the converted one if the key has not been converted.
+    //
+    // The conversions are blocked until the entry has been read,
+    // so the read value is known to be converted or not.
+    pub(crate) fn next<T>(
+        &self,
+        mut next: impl FnMut() -> Option<(RawKey, T)>,
+        mut value: impl FnMut(&mut T) -> &mut RawValue,
+    ) -> Result<Option<(RawKey, T)>> {
+        let mut worker = self.p.worker.lock();
+
+        loop {
+            let (k, mut v) = if let Some(kv) = next() {
+                kv
+            } else {
+                return Ok(None);
+            };
+
+            if self.p.finished.load(Ordering::Acquire) || self.p.markers.contains_key(&k)
+            {
+                // the basic iterators may read from a snapshot
+                // that was taken before the conversion, read it again
+                if let RawStore::Basic(m) = &self.store {
+                    if let Some(new_v) = m.get(&k) {
+                        *value(&mut v) = new_v;
+                    } else {
+                        continue;
+                    }
+                }
+                return Ok(Some((k, v)));
+            }
+
+            let w = worker.as_mut().c(d!(
+                "unfinished migration, register its converter or resume it before accessing the values"
+            ))?;
+            let mut markers = unsafe { self.p.markers.shadow() };
+            w.store.convert(&k, &w.f, &mut markers).c(d!())?;
+
+            let v_mut = value(&mut v);
+            *v_mut = (w.f)(v_mut).c(d!())?;
+
+            return Ok(Some((k, v)));
+        }
+    }
+}
+
+struct PendingSet {
+    // the number of instances in `map`, used as a fast path
+    cnt: AtomicUsize,
+    map: RwLock<HashMap<PreBytes, Arc<Pending>>>,
+}
+
+static PENDING: Lazy<PendingSet> = Lazy::new(|| {
+    let map = vsdb_list_pending_migrations()
+        .into_iter()
+        .map(|(prefix, _, markers)| (prefix, Arc::new(Pending::new(markers, None))))
+        .collect::<HashMap<_, _>>();
+    PendingSet {
+        cnt: AtomicUsize::new(map.len()),
+        map: RwLock::new(map),
+    }
+});
+
+#[inline(always)]
+fn get_pending(prefix: PreBytes) -> Option<Arc<Pending>> {
+    if 0 == PENDING.cnt.load(Ordering::Acquire) {
+        return None;
+    }
+    PENDING.map.read().get(&prefix).cloned()
+}
+
+// Register a migration of the instance,
+// called by the implementations of [Migrate].
+pub(crate) fn begin_migration(
+    instance: &impl Registrable,
+    store: RawStore,
+    f: Converter,
+) -> Result<Migration> {
+    let prefix = instance.instance_prefix();
+    let mut map = PENDING.map.write();
+
+    if let Some(p) = map.get(&prefix) {
+        if p.worker.lock().is_some() {
+            return Err(eg!("the instance is being migrated"));
+        }
+    }
+
+    let (_, markers) = vsdb_begin_migration(instance).c(d!())?;
+    let p = Pending::new(markers, Some(Worker { store, f }));
+    if map.insert(prefix, Arc::new(p)).is_none() {
+        PENDING.cnt.fetch_add(1, Ordering::Release);
+    }
+
+    Ok(Migration { prefix })
+}
+
+// Make sure that the values of the key have been converted,
+// must be called before accessing them.
+#[inline(always)]
+pub(crate) fn ensure_migrated(hdr: &impl Migratable, key: &[u8]) -> Result<()> {
+    if let Some(p) = get_pending(hdr.instance_prefix()) {
+        p.convert(key, hdr).c(d!())?;
+    }
+    Ok(())
+}
+
+// Get the state of an unfinished migration, used by iterators,
+// the migration left by a previous process will be resumed if possible.
+pub(crate) fn migrating(hdr: &impl Migratable) -> Option<Migrating> {
+    let p = get_pending(hdr.instance_prefix())?;
+    // it's fine if it can not be resumed, an error will be reported on conversions
+    let _ = Pending::resume(&mut p.worker.lock(), hdr);
+    Some(Migrating {
+        p,
+        store: hdr.raw_store(),
+    })
+}
+
+// Convert all the remaining values and finish the migration.
+pub(crate) fn ensure_all_migrated(prefix: PreBytes) -> Result<()> {
+    let p = if let Some(p) = get_pending(prefix) {
+        p
+    } else {
+        return Ok(());
+    };
+
+    let keys = {
+        let worker = p.worker.lock();
+        let w = worker.as_ref().c(d!(
+            "unfinished migration, resume it before accessing the values"
+        ))?;
+        match &w.store {
+            RawStore::Basic(m) => RawStore::Basic(unsafe { m.shadow() }),
+            #[cfg(feature = "vs")]
+            RawStore::Versioned(m) => RawStore::Versioned(unsafe { m.shadow() }),
+        }
+    };
+    for k in keys.keys() {
+        p.convert_by_worker(&k).c(d!())?;
+    }
+
+    let mut map = PENDING.map.write();
+    let _worker = p.worker.lock();
+    if !p.finished.swap(true, Ordering::AcqRel) {
+        vsdb_finish_migration(&Instance(prefix)).c(d!())?;
+        map.remove(&prefix);
+        PENDING.cnt.fetch_sub(1, Ordering::Release);
+    }
+
+    Ok(())
+}
+
+// The instances that are only known by their prefixes.
+struct Instance(PreBytes);
+
+impl Registrable for Instance {
+    const KIND: &'static str = "Instance";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.0
+    }
+}
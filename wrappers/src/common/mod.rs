@@ -2,6 +2,45 @@
 //! # Common components
 //!
 
+pub mod codec;
 pub mod ende;
+pub mod migrate;
+pub mod overlay;
+pub mod registry;
 
 pub use vsdb_core::common::*;
+
+/// Encode the leading part of a composite key,
+/// `0x00` is escaped to `0x00 0xFF` and a terminator `0x00 0x01` is appended,
+/// so the order of the leading parts of any length is kept in the composite keys.
+pub(crate) fn escape_key(k: &[u8]) -> RawBytes {
+    let mut ret = Vec::with_capacity(k.len() + 2);
+    for b in k.iter().copied() {
+        ret.push(b);
+        if 0 == b {
+            ret.push(0xFF);
+        }
+    }
+    ret.extend_from_slice(&[0, 1]);
+    ret
+}
+
+/// The reverse of [escape_key],
+/// returns the original leading part and the remaining bytes.
+pub(crate) fn unescape_key(k: &[u8]) -> (RawBytes, &[u8]) {
+    let mut ret = Vec::with_capacity(k.len());
+    let mut i = 0;
+    while i < k.len() {
+        if 0 == k[i] {
+            if Some(&1) == k.get(i + 1) {
+                return (ret, &k[i + 2..]);
+            }
+            i += 1;
+            ret.push(0);
+        } else {
+            ret.push(k[i]);
+        }
+        i += 1;
+    }
+    (ret, &[])
+}
@@ -0,0 +1,289 @@
+//!
+//! # Speculative overlays
+//!
+//! An in-memory layer over a collection,
+//! writes are buffered in it and reads go through it,
+//! all the changes can be committed into the underlying collection at once,
+//! or be dropped without touching the disk.
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::{Mapx, Overlay};
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut m: Mapx<u64, u64> = Mapx::new();
+//! m.insert(&1, &100);
+//!
+//! let mut o = Overlay::new(m);
+//! o.insert(&2, &200);
+//!
+//! let sp = o.savepoint();
+//! o.remove(&1);
+//! assert!(o.get(&1).is_none());
+//! o.rollback_to(sp).unwrap();
+//!
+//! assert_eq!(o.get(&1), Some(100));
+//! assert_eq!(o.len(), 2);
+//! assert!(o.base().get(&2).is_none());
+//!
+//! let m = o.commit().unwrap();
+//! assert_eq!(m.get(&2), Some(200));
+//! ```
+//!
+
+use crate::common::RawKey;
+use ruc::*;
+use std::{
+    cmp::Ordering,
+    collections::{btree_map::IntoIter, BTreeMap},
+    iter::Peekable,
+};
+
+/// Collections that can be covered by an [Overlay].
+pub trait OverlayBase {
+    type Key;
+    type Value;
+
+    /// Encode a key in the same way as the collection does,
+    /// the iteration order of the collection must follow the encoded keys.
+    fn overlay_encode_key(key: &Self::Key) -> RawKey;
+
+    fn overlay_decode_key(key: &[u8]) -> Result<Self::Key>;
+
+    fn overlay_get(&self, key: &[u8]) -> Option<Self::Value>;
+
+    fn overlay_len(&self) -> usize;
+
+    fn overlay_iter(&self) -> Box<dyn Iterator<Item = (RawKey, Self::Value)> + '_>;
+
+    fn overlay_insert(&mut self, key: &[u8], value: &Self::Value) -> Result<()>;
+
+    fn overlay_remove(&mut self, key: &[u8]) -> Result<()>;
+
+    /// Write all the changes, `None` means a removal.
+    ///
+    /// The default implementation writes them one by one,
+    /// collections that support batch writes should override it
+    /// to apply all the changes atomically.
+    fn overlay_apply(
+        &mut self,
+        changes: BTreeMap<RawKey, Option<Self::Value>>,
+    ) -> Result<()> {
+        for (k, v) in changes.iter() {
+            if let Some(v) = v {
+                self.overlay_insert(k, v).c(d!())?;
+            } else {
+                self.overlay_remove(k).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Buffered writes, `None` means a removal.
+type Layer<V> = BTreeMap<RawKey, Option<V>>;
+
+/// A position that the buffered writes can be rolled back to,
+/// got from [Overlay::savepoint].
+#[derive(Debug)]
+pub struct Savepoint {
+    id: u64,
+}
+
+/// An in-memory write buffer over a collection.
+pub struct Overlay<M: OverlayBase> {
+    base: M,
+    // the bottom layer has no savepoint
+    layers: Vec<(u64, Layer<M::Value>)>,
+    next_id: u64,
+}
+
+impl<M> Overlay<M>
+where
+    M: OverlayBase,
+    M::Value: Clone,
+{
+    #[inline(always)]
+    pub fn new(base: M) -> Self {
+        Self {
+            base,
+            layers: vec![(0, BTreeMap::new())],
+            next_id: 1,
+        }
+    }
+
+    /// The underlying collection, the buffered writes are invisible in it.
+    #[inline(always)]
+    pub fn base(&self) -> &M {
+        &self.base
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &M::Key) -> Option<M::Value> {
+        self.get_raw(&M::overlay_encode_key(key))
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &M::Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The number of entries with the buffered writes applied.
+    pub fn len(&self) -> usize {
+        self.flatten()
+            .iter()
+            .fold(self.base.overlay_len() as isize, |n, (k, v)| {
+                match (self.base.overlay_get(k).is_some(), v.is_some()) {
+                    (false, true) => n + 1,
+                    (true, false) => n - 1,
+                    _ => n,
+                }
+            }) as usize
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+
+    #[inline(always)]
+    pub fn insert(&mut self, key: &M::Key, value: &M::Value) -> Option<M::Value> {
+        self.write(M::overlay_encode_key(key), Some(value.clone()))
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, key: &M::Key) -> Option<M::Value> {
+        self.write(M::overlay_encode_key(key), None)
+    }
+
+    /// Iterate over the entries with the buffered writes applied,
+    /// in the order of the underlying collection.
+    #[inline(always)]
+    pub fn iter(&self) -> OverlayIter<'_, M> {
+        OverlayIter {
+            base: self.base.overlay_iter().peekable(),
+            changes: self.flatten().into_iter().peekable(),
+        }
+    }
+
+    /// Whether there are any buffered writes.
+    #[inline(always)]
+    pub fn is_dirty(&self) -> bool {
+        self.layers.iter().any(|(_, l)| !l.is_empty())
+    }
+
+    /// Mark the current position, savepoints can be nested.
+    #[inline(always)]
+    pub fn savepoint(&mut self) -> Savepoint {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.layers.push((id, BTreeMap::new()));
+        Savepoint { id }
+    }
+
+    /// Drop all the writes after the savepoint,
+    /// the savepoints created after it are dropped too.
+    pub fn rollback_to(&mut self, sp: Savepoint) -> Result<()> {
+        let idx = self.savepoint_idx(&sp).c(d!())?;
+        self.layers.truncate(idx);
+        Ok(())
+    }
+
+    /// Keep the writes after the savepoint and forget the savepoint itself,
+    /// the writes can still be dropped by an outer savepoint.
+    pub fn release(&mut self, sp: Savepoint) -> Result<()> {
+        let idx = self.savepoint_idx(&sp).c(d!())?;
+        for (_, l) in self.layers.split_off(idx) {
+            self.layers.last_mut().unwrap().1.extend(l);
+        }
+        Ok(())
+    }
+
+    /// Write all the buffered changes into the underlying collection,
+    /// the basic collections apply them atomically in one batch.
+    ///
+    /// NOTE: this operation is not atomic for the versioned collections,
+    /// the changes may be partially applied if an error occurs.
+    pub fn commit(mut self) -> Result<M> {
+        let changes = self.flatten();
+        self.base.overlay_apply(changes).c(d!())?;
+        Ok(self.base)
+    }
+
+    /// Drop all the buffered changes, nothing will be written.
+    #[inline(always)]
+    pub fn discard(self) -> M {
+        self.base
+    }
+
+    fn get_raw(&self, key: &[u8]) -> Option<M::Value> {
+        for (_, l) in self.layers.iter().rev() {
+            if let Some(v) = l.get(key) {
+                return v.clone();
+            }
+        }
+        self.base.overlay_get(key)
+    }
+
+    fn write(&mut self, key: RawKey, value: Option<M::Value>) -> Option<M::Value> {
+        let old = self.get_raw(&key);
+        self.layers.last_mut().unwrap().1.insert(key, value);
+        old
+    }
+
+    fn flatten(&self) -> Layer<M::Value> {
+        let mut ret = BTreeMap::new();
+        for (_, l) in self.layers.iter() {
+            ret.extend(l.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        ret
+    }
+
+    fn savepoint_idx(&self, sp: &Savepoint) -> Result<usize> {
+        self.layers
+            .iter()
+            .skip(1)
+            .position(|(id, _)| *id == sp.id)
+            .map(|i| 1 + i)
+            .c(d!("savepoint has been dropped"))
+    }
+}
+
+pub struct OverlayIter<'a, M: OverlayBase> {
+    base: Peekable<Box<dyn Iterator<Item = (RawKey, M::Value)> + 'a>>,
+    changes: Peekable<IntoIter<RawKey, Option<M::Value>>>,
+}
+
+impl<'a, M: OverlayBase> Iterator for OverlayIter<'a, M> {
+    type Item = (M::Key, M::Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ord = match (self.base.peek(), self.changes.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((bk, _)), Some((ck, _))) => bk.cmp(ck),
+            };
+
+            let (k, v) = match ord {
+                Ordering::Less => {
+                    let (k, v) = self.base.next().unwrap();
+                    (k, Some(v))
+                }
+                Ordering::Equal => {
+                    self.base.next();
+                    self.changes.next().unwrap()
+                }
+                Ordering::Greater => self.changes.next().unwrap(),
+            };
+
+            if let Some(v) = v {
+                return Some((pnk!(M::overlay_decode_key(&k)), v));
+            }
+        }
+    }
+}
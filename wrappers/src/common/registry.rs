@@ -0,0 +1,66 @@
+//!
+//! # Named instance registry
+//!
+//! Typed wrappers of the raw registry APIs in [vsdb_core].
+//!
+//! # Examples
+//!
+//! ```
+//! use vsdb::{vsdb_list_instances, vsdb_open_named, vsdb_register, Mapx};
+//!
+//! let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u128>());
+//! vsdb::vsdb_set_base_dir(&dir);
+//!
+//! let mut balances: Mapx<u64, u64> = Mapx::new();
+//! balances.insert(&1, &100);
+//! vsdb_register("balances", &balances).unwrap();
+//!
+//! let balances = vsdb_open_named::<Mapx<u64, u64>>("balances").unwrap();
+//! assert_eq!(balances.get(&1), Some(100));
+//!
+//! let info = vsdb_list_instances();
+//! let info = info.iter().find(|i| i.name == "balances").unwrap();
+//! assert_eq!(info.entry_count, 1);
+//! ```
+//!
+
+use crate::common::ende::ValueEnDe;
+use ruc::*;
+use vsdb_core::{Registrable, vsdb_get_named_raw, vsdb_register_raw};
+
+/// Record an instance under the given name,
+/// it can be re-opened by [vsdb_open_named] after a restart.
+#[inline(always)]
+pub fn vsdb_register<T>(name: &str, instance: &T) -> Result<()>
+where
+    T: Registrable + ValueEnDe,
+{
+    vsdb_register_raw(
+        name,
+        T::KIND,
+        instance.instance_prefix(),
+        &instance.try_encode().c(d!())?,
+    )
+    .c(d!())
+}
+
+/// Re-open a named instance,
+/// the kind of the type must be the same as the one used in [vsdb_register],
+/// see [Registrable::KIND].
+#[inline(always)]
+pub fn vsdb_open_named<T>(name: &str) -> Result<T>
+where
+    T: Registrable + ValueEnDe,
+{
+    let (kind, handle) = vsdb_get_named_raw(name)
+        .c(d!())?
+        .c(d!("name not found: {}", name))?;
+    if kind != T::KIND {
+        return Err(eg!(
+            "type mismatch, registered: {}, requested: {}",
+            kind,
+            T::KIND
+        ));
+    }
+    <T as ValueEnDe>::decode(&handle).c(d!())
+}
@@ -19,18 +19,35 @@ pub mod versioned;
 #[cfg(feature = "vs")]
 pub mod versioned_multi_key;
 
-pub use basic::{mapx::Mapx, mapx_ord::MapxOrd, vecx::Vecx};
+pub use basic::{
+    dequex::Dequex, mapx::Mapx, mapx_ord::MapxOrd, mapx_ord_indexed::MapxOrdIndexed,
+    multi_mapx::MultiMapx, priority_queuex::PriorityQueuex, setx::Setx,
+    setx_ord::SetxOrd, vecx::Vecx,
+};
+pub use basic_multi_key::mapx_multi_key::MapxMk;
 pub use common::{
+    codec::{Codec, DefaultCodec},
     ende::{KeyDe, KeyEn, KeyEnDe, KeyEnDeOrdered, ValueDe, ValueEn, ValueEnDe},
+    migrate::{
+        migrate, migrate_in_background, migrate_lazily, register_converter, Migrate,
+        Migration,
+    },
+    overlay::{Overlay, OverlayBase, OverlayIter, Savepoint},
+    registry::{vsdb_open_named, vsdb_register},
     NULL,
 };
 
 #[cfg(feature = "vs")]
-pub use versioned::{mapx::MapxVs, mapx_ord::MapxOrdVs, orphan::OrphanVs, vecx::VecxVs};
+pub use versioned::{
+    dequex::DequexVs, mapx::MapxVs, mapx_ord::MapxOrdVs, multi_mapx::MultiMapxVs,
+    orphan::OrphanVs, priority_queuex::PriorityQueuexVs, setx::SetxVs,
+    setx_ord::SetxOrdVs, vecx::VecxVs,
+};
 
 #[cfg(feature = "vs")]
 pub use versioned_multi_key::{
-    mapx_double_key::MapxDkVs, mapx_raw::MapxRawMkVs, mapx_triple_key::MapxTkVs,
+    mapx_double_key::MapxDkVs, mapx_multi_key::MapxMkVs, mapx_raw::MapxRawMkVs,
+    mapx_triple_key::MapxTkVs,
 };
 
 pub use vsdb_core::{self, *};
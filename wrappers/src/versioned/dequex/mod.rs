@@ -0,0 +1,482 @@
+//!
+//! Documents => [MapxRawVs](crate::versioned::mapx_raw)
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::dequex::{pos, MID_POS},
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::ValueEnDe,
+        migrate::{Migrate, Migration},
+    },
+    versioned::mapx_ord_rawkey::{MapxOrdRawKeyVs, MapxOrdRawKeyVsIter},
+    BranchName, BranchNameOwned, VersionName, VersionNameOwned, VsDiff, VsMgmt,
+    VsView,
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use vsdb_core::{common::PreBytes, Registrable};
+
+/// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct DequexVs<T, C = DefaultCodec> {
+    inner: MapxOrdRawKeyVs<T, C>,
+}
+
+impl<T, C: Codec<T>> DequexVs<T, C> {
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+        }
+    }
+
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
+    #[inline(always)]
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self { inner })
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
+        DequexVs {
+            inner: MapxOrdRawKeyVs::with_codec(),
+        }
+    }
+
+    /// Get the element by its index counted from the front.
+    #[inline(always)]
+    pub fn get(&self, idx: usize) -> Option<T> {
+        // the positions are continuous
+        let p = self.head()?.checked_add(idx as u64)?;
+        self.inner.get(&p.to_be_bytes())
+    }
+
+    #[inline(always)]
+    pub fn front(&self) -> Option<T> {
+        self.inner.first().map(|(_, v)| v)
+    }
+
+    #[inline(always)]
+    pub fn back(&self) -> Option<T> {
+        self.inner.last().map(|(_, v)| v)
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn push_front(&mut self, v: &T) -> Result<()> {
+        let p = self.head().map(|h| h - 1);
+        self.inner
+            .insert(&p.unwrap_or(MID_POS).to_be_bytes(), v)
+            .c(d!())
+            .map(|_| ())
+    }
+
+    #[inline(always)]
+    pub fn push_back(&mut self, v: &T) -> Result<()> {
+        let p = self.tail().map(|t| t + 1);
+        self.inner
+            .insert(&p.unwrap_or(MID_POS).to_be_bytes(), v)
+            .c(d!())
+            .map(|_| ())
+    }
+
+    #[inline(always)]
+    pub fn pop_front(&mut self) -> Result<Option<T>> {
+        let Some(h) = self.head() else {
+            return Ok(None);
+        };
+        self.inner.remove(&h.to_be_bytes()).c(d!())
+    }
+
+    #[inline(always)]
+    pub fn pop_back(&mut self) -> Result<Option<T>> {
+        let Some(t) = self.tail() else {
+            return Ok(None);
+        };
+        self.inner.remove(&t.to_be_bytes()).c(d!())
+    }
+
+    /// Iterate from the front to the back.
+    #[inline(always)]
+    pub fn iter(&self) -> DequexVsIter<'_, T, C> {
+        DequexVsIter {
+            inner: self.inner.iter(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    #[inline(always)]
+    pub fn get_by_branch(&self, idx: usize, br_name: BranchName) -> Option<T> {
+        let p = self.head_by_branch(br_name)?.checked_add(idx as u64)?;
+        self.inner.get_by_branch(&p.to_be_bytes(), br_name)
+    }
+
+    #[inline(always)]
+    pub fn front_by_branch(&self, br_name: BranchName) -> Option<T> {
+        self.inner.first_by_branch(br_name).map(|(_, v)| v)
+    }
+
+    #[inline(always)]
+    pub fn back_by_branch(&self, br_name: BranchName) -> Option<T> {
+        self.inner.last_by_branch(br_name).map(|(_, v)| v)
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch(&self, br_name: BranchName) -> usize {
+        self.inner.len_by_branch(br_name)
+    }
+
+    #[inline(always)]
+    pub fn is_empty_by_branch(&self, br_name: BranchName) -> bool {
+        self.inner.is_empty_by_branch(br_name)
+    }
+
+    #[inline(always)]
+    pub fn push_front_by_branch(&mut self, v: &T, br_name: BranchName) -> Result<()> {
+        let p = self.head_by_branch(br_name).map(|h| h - 1);
+        self.inner
+            .insert_by_branch(&p.unwrap_or(MID_POS).to_be_bytes(), v, br_name)
+            .c(d!())
+            .map(|_| ())
+    }
+
+    #[inline(always)]
+    pub fn push_back_by_branch(&mut self, v: &T, br_name: BranchName) -> Result<()> {
+        let p = self.tail_by_branch(br_name).map(|t| t + 1);
+        self.inner
+            .insert_by_branch(&p.unwrap_or(MID_POS).to_be_bytes(), v, br_name)
+            .c(d!())
+            .map(|_| ())
+    }
+
+    #[inline(always)]
+    pub fn pop_front_by_branch(&mut self, br_name: BranchName) -> Result<Option<T>> {
+        let Some(h) = self.head_by_branch(br_name) else {
+            return Ok(None);
+        };
+        self.inner
+            .remove_by_branch(&h.to_be_bytes(), br_name)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub fn pop_back_by_branch(&mut self, br_name: BranchName) -> Result<Option<T>> {
+        let Some(t) = self.tail_by_branch(br_name) else {
+            return Ok(None);
+        };
+        self.inner
+            .remove_by_branch(&t.to_be_bytes(), br_name)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch(&self, br_name: BranchName) -> DequexVsIter<'_, T, C> {
+        DequexVsIter {
+            inner: self.inner.iter_by_branch(br_name),
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_by_branch_version(
+        &self,
+        idx: usize,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Option<T> {
+        let p = self
+            .head_by_branch_version(br_name, ver_name)?
+            .checked_add(idx as u64)?;
+        self.inner
+            .get_by_branch_version(&p.to_be_bytes(), br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn front_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Option<T> {
+        self.inner
+            .first_by_branch_version(br_name, ver_name)
+            .map(|(_, v)| v)
+    }
+
+    #[inline(always)]
+    pub fn back_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Option<T> {
+        self.inner
+            .last_by_branch_version(br_name, ver_name)
+            .map(|(_, v)| v)
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> usize {
+        self.inner.len_by_branch_version(br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn is_empty_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> bool {
+        self.inner.is_empty_by_branch_version(br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> DequexVsIter<'_, T, C> {
+        DequexVsIter {
+            inner: self.inner.iter_by_branch_version(br_name, ver_name),
+        }
+    }
+
+    fn head(&self) -> Option<u64> {
+        self.inner.first().map(|(k, _)| pos(&k))
+    }
+
+    fn tail(&self) -> Option<u64> {
+        self.inner.last().map(|(k, _)| pos(&k))
+    }
+
+    fn head_by_branch(&self, br_name: BranchName) -> Option<u64> {
+        self.inner.first_by_branch(br_name).map(|(k, _)| pos(&k))
+    }
+
+    fn tail_by_branch(&self, br_name: BranchName) -> Option<u64> {
+        self.inner.last_by_branch(br_name).map(|(k, _)| pos(&k))
+    }
+
+    fn head_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Option<u64> {
+        self.inner
+            .first_by_branch_version(br_name, ver_name)
+            .map(|(k, _)| pos(&k))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl<T> DequexVs<T>
+where
+    T: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<T, C> Clone for DequexVs<T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T, C> Registrable for DequexVs<T, C> {
+    const KIND: &'static str = "DequexVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<Old, New, C> Migrate<Old, New> for DequexVs<Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = DequexVs<New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((Self::Output { inner }, m))
+    }
+}
+
+impl<T, C: Codec<T>> Default for DequexVs<T, C> {
+    fn default() -> Self {
+        Self::with_codec()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl<T, C: Codec<T>> VsMgmt for DequexVs<T, C> {
+    crate::impl_vs_methods!();
+}
+
+impl<T, C: Codec<T>> VsDiff for DequexVs<T, C> {
+    /// The changed internal positions, `None` means that the position has been popped.
+    type Diff = Vec<(u64, Option<T>)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| {
+                <[u8; 8]>::try_from(&k[..])
+                    .c(d!())
+                    .map(|p| (u64::from_be_bytes(p), v))
+            })
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (p, v) in diff.iter() {
+            let p = p.to_be_bytes();
+            if let Some(v) = v {
+                self.inner.insert(&p, v).c(d!())?;
+            } else {
+                self.inner.remove(&p).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T, C: Codec<T>> VsView for DequexVs<T, C> {
+    type View<'a>
+        = DequexVsView<'a, T, C>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(DequexVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+/// A read-only handler of [DequexVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct DequexVsView<'a, T, C = DefaultCodec> {
+    hdr: &'a DequexVs<T, C>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, T, C: Codec<T>> DequexVsView<'a, T, C> {
+    #[inline(always)]
+    pub fn get(&self, idx: usize) -> Option<T> {
+        self.hdr.get_by_branch_version(
+            idx,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn front(&self) -> Option<T> {
+        self.hdr
+            .front_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn back(&self) -> Option<T> {
+        self.hdr
+            .back_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.hdr
+            .len_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.hdr.is_empty_by_branch_version(
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> DequexVsIter<'a, T, C> {
+        self.hdr
+            .iter_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+pub struct DequexVsIter<'a, T, C: Codec<T> = DefaultCodec> {
+    inner: MapxOrdRawKeyVsIter<'a, T, C>,
+}
+
+impl<'a, T, C: Codec<T>> Iterator for DequexVsIter<'a, T, C> {
+    type Item = T;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|v| v.1)
+    }
+}
+
+impl<'a, T, C: Codec<T>> DoubleEndedIterator for DequexVsIter<'a, T, C> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|v| v.1)
+    }
+}
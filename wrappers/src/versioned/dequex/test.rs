@@ -0,0 +1,69 @@
+use super::*;
+use crate::{common::INITIAL_BRANCH_NAME, VersionName, VsDiff, VsView};
+
+#[test]
+fn test_versioned_ends() {
+    let mut hdr: DequexVs<usize> = DequexVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..10).for_each(|i| {
+        pnk!(hdr.push_back(&(10 + i)));
+        pnk!(hdr.push_front(&(9 - i)));
+    });
+    assert_eq!(hdr.iter().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+    assert_eq!(Some(5), hdr.get(5));
+
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    (0..5).for_each(|i| {
+        assert_eq!(Some(i), pnk!(hdr.pop_front()));
+        assert_eq!(Some(19 - i), pnk!(hdr.pop_back()));
+    });
+    assert_eq!(Some(5), hdr.front());
+    assert_eq!(Some(14), hdr.back());
+    assert_eq!(Some(5), hdr.get(0));
+    assert_eq!(Some(14), hdr.get(9));
+    assert!(hdr.get(10).is_none());
+    assert!(hdr.get(usize::MAX).is_none());
+
+    let v0 = pnk!(hdr.view_at(INITIAL_BRANCH_NAME, VersionName(b"v0")));
+    assert_eq!(20, v0.len());
+    assert_eq!(Some(0), v0.front());
+    assert_eq!(Some(19), v0.back());
+    assert_eq!(Some(5), v0.get(5));
+    assert!(v0.get(20).is_none());
+    assert_eq!(v0.iter().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+
+    let diff = pnk!(hdr.diff(VersionName(b"v0"), VersionName(b"v1")));
+    assert_eq!(10, diff.len());
+    assert!(diff.iter().all(|(_, v)| v.is_none()));
+
+    let mut other: DequexVs<usize> = DequexVs::new();
+    pnk!(other.version_create(VersionName(b"v0")));
+    let diff = pnk!(hdr.diff(VersionName(b"v1"), VersionName(b"v0")));
+    pnk!(other.apply_diff(&diff));
+    assert_eq!(
+        other.iter().collect::<Vec<_>>(),
+        (0..5).chain(15..20).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_by_branch() {
+    let mut hdr: DequexVs<usize> = DequexVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.push_back(&1));
+
+    let br = crate::BranchName(b"br");
+    pnk!(hdr.branch_create(br, VersionName(b"br0"), false));
+    pnk!(hdr.push_front_by_branch(&0, br));
+    pnk!(hdr.push_back_by_branch(&2, br));
+    assert_eq!(3, hdr.len_by_branch(br));
+    assert_eq!(Some(2), hdr.get_by_branch(2, br));
+    assert!(hdr.get_by_branch(3, br).is_none());
+    assert_eq!(Some(2), hdr.back_by_branch(br));
+    assert_eq!(Some(1), hdr.back_by_branch(INITIAL_BRANCH_NAME));
+    assert_eq!(1, hdr.len_by_branch(INITIAL_BRANCH_NAME));
+
+    assert_eq!(Some(0), pnk!(hdr.pop_front_by_branch(br)));
+    assert_eq!(Some(2), pnk!(hdr.pop_back_by_branch(br)));
+    assert_eq!(hdr.iter_by_branch(br).collect::<Vec<_>>(), vec![1]);
+}
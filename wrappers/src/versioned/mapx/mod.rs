@@ -6,31 +6,40 @@
 mod test;
 
 use crate::{
-    common::ende::{KeyEnDe, ValueEnDe},
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::{KeyEnDe, ValueEnDe},
+        migrate::{Migrate, Migration},
+        overlay::OverlayBase,
+        RawKey,
+    },
     versioned::mapx_ord_rawkey::{
         MapxOrdRawKeyVs, MapxOrdRawKeyVsIter, MapxOrdRawKeyVsIterMut, ValueIterMut,
     },
-    BranchName, VersionName, VsMgmt,
+    BranchName, BranchNameOwned, VersionName, VersionNameOwned, VsDiff, VsMgmt,
+    VsView,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
+    sync::Arc,
 };
+use vsdb_core::{common::PreBytes, Registrable};
 
 /// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
-pub struct MapxVs<K, V> {
-    inner: MapxOrdRawKeyVs<V>,
+pub struct MapxVs<K, V, C = DefaultCodec> {
+    inner: MapxOrdRawKeyVs<V, C>,
     _p: PhantomData<K>,
 }
 
-impl<K, V> MapxVs<K, V>
+impl<K, V, C> MapxVs<K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     /// # Safety
     ///
@@ -44,10 +53,21 @@ where
         }
     }
 
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
     #[inline(always)]
-    pub fn new() -> Self {
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
         MapxVs {
-            inner: MapxOrdRawKeyVs::new(),
+            inner: MapxOrdRawKeyVs::with_codec(),
             _p: PhantomData,
         }
     }
@@ -58,12 +78,12 @@ where
     }
 
     #[inline(always)]
-    pub fn get_mut<'a>(&'a mut self, key: &'a K) -> Option<ValueMut<'a, K, V>> {
+    pub fn get_mut<'a>(&'a mut self, key: &'a K) -> Option<ValueMut<'a, K, V, C>> {
         self.get(key).map(move |v| ValueMut::new(self, key, v))
     }
 
     #[inline(always)]
-    pub fn entry<'a>(&'a mut self, key: &'a K) -> Entry<'a, K, V> {
+    pub fn entry<'a>(&'a mut self, key: &'a K) -> Entry<'a, K, V, C> {
         Entry { key, hdr: self }
     }
 
@@ -97,7 +117,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxVsIter<K, V> {
+    pub fn iter(&self) -> MapxVsIter<K, V, C> {
         MapxVsIter {
             inner: self.inner.iter(),
             _p: PhantomData,
@@ -105,7 +125,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxVsIterMut<K, V> {
+    pub fn iter_mut(&mut self) -> MapxVsIterMut<K, V, C> {
         MapxVsIterMut {
             inner: self.inner.iter_mut(),
             _p: PhantomData,
@@ -113,14 +133,14 @@ where
     }
 
     #[inline(always)]
-    pub fn values(&self) -> MapxVsValues<V> {
+    pub fn values(&self) -> MapxVsValues<V, C> {
         MapxVsValues {
             inner: self.inner.iter(),
         }
     }
 
     #[inline(always)]
-    pub fn values_mut(&mut self) -> MapxVsValuesMut<V> {
+    pub fn values_mut(&mut self) -> MapxVsValuesMut<V, C> {
         MapxVsValuesMut {
             inner: self.inner.iter_mut(),
         }
@@ -193,7 +213,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter_by_branch(&self, br_name: BranchName) -> MapxVsIter<K, V> {
+    pub fn iter_by_branch(&self, br_name: BranchName) -> MapxVsIter<K, V, C> {
         MapxVsIter {
             inner: self.inner.iter_by_branch(br_name),
             _p: PhantomData,
@@ -282,7 +302,7 @@ where
         &self,
         br_name: BranchName,
         ver_name: VersionName,
-    ) -> MapxVsIter<K, V> {
+    ) -> MapxVsIter<K, V, C> {
         MapxVsIter {
             inner: self.inner.iter_by_branch_version(br_name, ver_name),
             _p: PhantomData,
@@ -322,7 +342,20 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<K, V> Clone for MapxVs<K, V> {
+impl<K, V> MapxVs<K, V>
+where
+    K: KeyEnDe,
+    V: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<K, V, C> Clone for MapxVs<K, V, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -331,45 +364,258 @@ impl<K, V> Clone for MapxVs<K, V> {
     }
 }
 
-impl<K, V> Default for MapxVs<K, V>
+impl<K, V, C> Registrable for MapxVs<K, V, C> {
+    const KIND: &'static str = "MapxVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K, Old, New, C> Migrate<Old, New> for MapxVs<K, Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = MapxVs<K, New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((
+            Self::Output {
+                inner,
+                _p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<K, V, C> OverlayBase for MapxVs<K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
+{
+    type Key = K;
+    type Value = V;
+
+    #[inline(always)]
+    fn overlay_encode_key(key: &K) -> RawKey {
+        key.encode()
+    }
+
+    #[inline(always)]
+    fn overlay_decode_key(key: &[u8]) -> Result<K> {
+        <K as KeyEnDe>::decode(key).c(d!())
+    }
+
+    #[inline(always)]
+    fn overlay_get(&self, key: &[u8]) -> Option<V> {
+        self.inner.get(key)
+    }
+
+    #[inline(always)]
+    fn overlay_len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    fn overlay_iter(&self) -> Box<dyn Iterator<Item = (RawKey, V)> + '_> {
+        Box::new(self.inner.iter())
+    }
+
+    #[inline(always)]
+    fn overlay_insert(&mut self, key: &[u8], value: &V) -> Result<()> {
+        self.inner.insert(key, value).c(d!()).map(|_| ())
+    }
+
+    #[inline(always)]
+    fn overlay_remove(&mut self, key: &[u8]) -> Result<()> {
+        self.inner.remove(key).c(d!()).map(|_| ())
+    }
+}
+
+impl<K, V, C> Default for MapxVs<K, V, C>
+where
+    K: KeyEnDe,
+    C: Codec<V>,
 {
     fn default() -> Self {
-        Self::new()
+        Self::with_codec()
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<K, V> VsMgmt for MapxVs<K, V>
+impl<K, V, C> VsMgmt for MapxVs<K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     crate::impl_vs_methods!();
 }
 
+impl<K, V, C> VsDiff for MapxVs<K, V, C>
+where
+    K: KeyEnDe,
+    C: Codec<V>,
+{
+    type Diff = Vec<(K, Option<V>)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| <K as KeyEnDe>::decode(&k).c(d!()).map(|k| (k, v)))
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (k, v) in diff.iter() {
+            if let Some(v) = v {
+                self.insert(k, v).c(d!())?;
+            } else {
+                self.remove(k).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K, V, C> VsView for MapxVs<K, V, C>
+where
+    K: KeyEnDe,
+    C: Codec<V>,
+{
+    type View<'a>
+        = MapxVsView<'a, K, V, C>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(MapxVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct Entry<'a, K, V>
+/// A read-only handler of [MapxVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct MapxVsView<'a, K, V, C = DefaultCodec> {
+    hdr: &'a MapxVs<K, V, C>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, K, V, C> MapxVsView<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
+{
+    #[inline(always)]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.hdr.get_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn get_le(&self, key: &K) -> Option<(K, V)> {
+        self.hdr.get_le_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn get_ge(&self, key: &K) -> Option<(K, V)> {
+        self.hdr.get_ge_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.hdr
+            .len_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.hdr.is_empty_by_branch_version(
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> MapxVsIter<'a, K, V, C> {
+        self.hdr
+            .iter_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn first(&self) -> Option<(K, V)> {
+        self.iter().next()
+    }
+
+    #[inline(always)]
+    pub fn last(&self) -> Option<(K, V)> {
+        self.iter().next_back()
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.hdr.contains_key_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+pub struct Entry<'a, K, V, C = DefaultCodec>
+where
+    K: KeyEnDe,
+    C: Codec<V>,
 {
-    hdr: &'a mut MapxVs<K, V>,
+    hdr: &'a mut MapxVs<K, V, C>,
     key: &'a K,
 }
 
-impl<'a, K, V> Entry<'a, K, V>
+impl<'a, K, V, C> Entry<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    pub fn or_insert(self, default: &V) -> ValueMut<'a, K, V> {
+    pub fn or_insert(self, default: &V) -> ValueMut<'a, K, V, C> {
         if !self.hdr.contains_key(self.key) {
             pnk!(self.hdr.insert(self.key, default));
         }
@@ -381,40 +627,40 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Debug)]
-pub struct ValueMut<'a, K, V>
+pub struct ValueMut<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    hdr: &'a mut MapxVs<K, V>,
+    hdr: &'a mut MapxVs<K, V, C>,
     key: &'a K,
     value: V,
 }
 
-impl<'a, K, V> ValueMut<'a, K, V>
+impl<'a, K, V, C> ValueMut<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    fn new(hdr: &'a mut MapxVs<K, V>, key: &'a K, value: V) -> Self {
+    fn new(hdr: &'a mut MapxVs<K, V, C>, key: &'a K, value: V) -> Self {
         ValueMut { hdr, key, value }
     }
 }
 
-impl<'a, K, V> Drop for ValueMut<'a, K, V>
+impl<'a, K, V, C> Drop for ValueMut<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn drop(&mut self) {
         pnk!(self.hdr.insert(self.key, &self.value));
     }
 }
 
-impl<'a, K, V> Deref for ValueMut<'a, K, V>
+impl<'a, K, V, C> Deref for ValueMut<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Target = V;
     fn deref(&self) -> &Self::Target {
@@ -422,10 +668,10 @@ where
     }
 }
 
-impl<'a, K, V> DerefMut for ValueMut<'a, K, V>
+impl<'a, K, V, C> DerefMut for ValueMut<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.value
@@ -435,19 +681,19 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxVsIter<'a, K, V>
+pub struct MapxVsIter<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyVsIter<'a, V>,
+    inner: MapxOrdRawKeyVsIter<'a, V, C>,
     _p: PhantomData<K>,
 }
 
-impl<'a, K, V> Iterator for MapxVsIter<'a, K, V>
+impl<'a, K, V, C> Iterator for MapxVsIter<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Item = (K, V);
     fn next(&mut self) -> Option<Self::Item> {
@@ -457,10 +703,10 @@ where
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for MapxVsIter<'a, K, V>
+impl<'a, K, V, C> DoubleEndedIterator for MapxVsIter<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner
@@ -472,16 +718,16 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxVsValues<'a, V>
+pub struct MapxVsValues<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyVsIter<'a, V>,
+    inner: MapxOrdRawKeyVsIter<'a, V, C>,
 }
 
-impl<'a, V> Iterator for MapxVsValues<'a, V>
+impl<'a, V, C> Iterator for MapxVsValues<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Item = V;
     fn next(&mut self) -> Option<Self::Item> {
@@ -489,9 +735,9 @@ where
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxVsValues<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxVsValues<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|(_, v)| v)
@@ -501,21 +747,21 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxVsIterMut<'a, K, V>
+pub struct MapxVsIterMut<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyVsIterMut<'a, V>,
+    inner: MapxOrdRawKeyVsIterMut<'a, V, C>,
     _p: PhantomData<K>,
 }
 
-impl<'a, K, V> Iterator for MapxVsIterMut<'a, K, V>
+impl<'a, K, V, C> Iterator for MapxVsIterMut<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    type Item = (K, ValueIterMut<'a, V>);
+    type Item = (K, ValueIterMut<'a, V, C>);
 
     fn next(&mut self) -> Option<Self::Item> {
         self.inner
@@ -524,10 +770,10 @@ where
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for MapxVsIterMut<'a, K, V>
+impl<'a, K, V, C> DoubleEndedIterator for MapxVsIterMut<'a, K, V, C>
 where
     K: KeyEnDe,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner
@@ -539,27 +785,27 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxVsValuesMut<'a, V>
+pub struct MapxVsValuesMut<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyVsIterMut<'a, V>,
+    inner: MapxOrdRawKeyVsIterMut<'a, V, C>,
 }
 
-impl<'a, V> Iterator for MapxVsValuesMut<'a, V>
+impl<'a, V, C> Iterator for MapxVsValuesMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    type Item = ValueIterMut<'a, V>;
+    type Item = ValueIterMut<'a, V, C>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|(_, v)| v)
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxVsValuesMut<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxVsValuesMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|(_, v)| v)
@@ -488,3 +488,31 @@ fn test_by_branch() {
     assert_eq!(10, pnk!(hdr.get_ge_by_branch(&10, INITIAL_BRANCH_NAME)).0);
     assert_eq!(10, pnk!(hdr.get_le_by_branch(&10, INITIAL_BRANCH_NAME)).0);
 }
+
+#[test]
+fn test_fork() {
+    let mut hdr: MapxVs<usize, usize> = MapxVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..100).for_each(|i| {
+        pnk!(hdr.insert(&i, &i));
+    });
+
+    let mut fork = pnk!(hdr.fork());
+    pnk!(fork.insert(&1, &10));
+    pnk!(fork.remove(&2));
+    assert_eq!(Some(10), fork.get(&1));
+    assert!(fork.get(&2).is_none());
+    assert_eq!(Some(3), fork.get(&3));
+    assert_eq!(Some(1), hdr.get(&1));
+    assert_eq!(Some(2), hdr.get(&2));
+
+    // the later writes of the original are invisible to the fork
+    pnk!(hdr.insert(&3, &30));
+    assert_eq!(Some(3), fork.get(&3));
+    assert_eq!(Some(30), hdr.get(&3));
+
+    let br = fork.branch_get_default();
+    assert_eq!(Some(10), hdr.get_by_branch(&1, br.as_deref()));
+    drop(fork);
+    assert!(!hdr.branch_exists(br.as_deref()));
+}
@@ -5,11 +5,16 @@
 mod test;
 
 use crate::{
-    common::ende::{KeyEnDeOrdered, ValueEnDe},
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::{KeyEnDeOrdered, ValueEnDe},
+        migrate::{Migrate, Migration},
+    },
     versioned::mapx_ord_rawkey::{
         MapxOrdRawKeyVs, MapxOrdRawKeyVsIter, MapxOrdRawKeyVsIterMut, ValueIterMut,
     },
-    BranchName, VersionName, VsMgmt,
+    BranchName, BranchNameOwned, VersionName, VersionNameOwned, VsDiff, VsMgmt,
+    VsView,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
@@ -17,20 +22,22 @@ use std::{
     borrow::Cow,
     marker::PhantomData,
     ops::{Bound, Deref, DerefMut, RangeBounds},
+    sync::Arc,
 };
+use vsdb_core::{common::PreBytes, Registrable};
 
 /// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
-pub struct MapxOrdVs<K, V> {
-    inner: MapxOrdRawKeyVs<V>,
+pub struct MapxOrdVs<K, V, C = DefaultCodec> {
+    inner: MapxOrdRawKeyVs<V, C>,
     _p: PhantomData<K>,
 }
 
-impl<K, V> MapxOrdVs<K, V>
+impl<K, V, C> MapxOrdVs<K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     /// # Safety
     ///
@@ -44,10 +51,21 @@ where
         }
     }
 
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
     #[inline(always)]
-    pub fn new() -> Self {
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
         MapxOrdVs {
-            inner: MapxOrdRawKeyVs::new(),
+            inner: MapxOrdRawKeyVs::with_codec(),
             _p: PhantomData,
         }
     }
@@ -58,12 +76,12 @@ where
     }
 
     #[inline(always)]
-    pub fn get_mut<'a>(&'a mut self, key: &'a K) -> Option<ValueMut<'a, K, V>> {
+    pub fn get_mut<'a>(&'a mut self, key: &'a K) -> Option<ValueMut<'a, K, V, C>> {
         self.get(key).map(move |v| ValueMut::new(self, key, v))
     }
 
     #[inline(always)]
-    pub fn entry<'a>(&'a mut self, key: &'a K) -> Entry<'a, K, V> {
+    pub fn entry<'a>(&'a mut self, key: &'a K) -> Entry<'a, K, V, C> {
         Entry { key, hdr: self }
     }
 
@@ -97,7 +115,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxOrdVsIter<K, V> {
+    pub fn iter(&self) -> MapxOrdVsIter<K, V, C> {
         MapxOrdVsIter {
             inner: self.inner.iter(),
             _p: PhantomData,
@@ -105,7 +123,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxOrdVsIterMut<K, V> {
+    pub fn iter_mut(&mut self) -> MapxOrdVsIterMut<K, V, C> {
         MapxOrdVsIterMut {
             inner: self.inner.iter_mut(),
             _p: PhantomData,
@@ -113,14 +131,14 @@ where
     }
 
     #[inline(always)]
-    pub fn values(&self) -> MapxOrdVsValues<V> {
+    pub fn values(&self) -> MapxOrdVsValues<V, C> {
         MapxOrdVsValues {
             inner: self.inner.iter(),
         }
     }
 
     #[inline(always)]
-    pub fn values_mut(&mut self) -> MapxOrdVsValuesMut<V> {
+    pub fn values_mut(&mut self) -> MapxOrdVsValuesMut<V, C> {
         MapxOrdVsValuesMut {
             inner: self.inner.iter_mut(),
         }
@@ -130,7 +148,7 @@ where
     pub fn range<'a, R: 'a + RangeBounds<K>>(
         &'a self,
         bounds: R,
-    ) -> MapxOrdVsIter<'a, K, V> {
+    ) -> MapxOrdVsIter<'a, K, V, C> {
         let l = match bounds.start_bound() {
             Bound::Included(i) => Bound::Included(Cow::Owned(i.to_bytes())),
             Bound::Excluded(i) => Bound::Excluded(Cow::Owned(i.to_bytes())),
@@ -152,7 +170,7 @@ where
     pub fn range_mut<'a, R: 'a + RangeBounds<K>>(
         &'a mut self,
         bounds: R,
-    ) -> MapxOrdVsIterMut<'a, K, V> {
+    ) -> MapxOrdVsIterMut<'a, K, V, C> {
         let l = match bounds.start_bound() {
             Bound::Included(i) => Bound::Included(Cow::Owned(i.to_bytes())),
             Bound::Excluded(i) => Bound::Excluded(Cow::Owned(i.to_bytes())),
@@ -237,7 +255,7 @@ where
     }
 
     #[inline(always)]
-    pub fn iter_by_branch(&self, br_name: BranchName) -> MapxOrdVsIter<K, V> {
+    pub fn iter_by_branch(&self, br_name: BranchName) -> MapxOrdVsIter<K, V, C> {
         MapxOrdVsIter {
             inner: self.inner.iter_by_branch(br_name),
             _p: PhantomData,
@@ -249,7 +267,7 @@ where
         &'a self,
         br_name: BranchName,
         bounds: R,
-    ) -> MapxOrdVsIter<'a, K, V> {
+    ) -> MapxOrdVsIter<'a, K, V, C> {
         let l = match bounds.start_bound() {
             Bound::Included(i) => Bound::Included(Cow::Owned(i.to_bytes())),
             Bound::Excluded(i) => Bound::Excluded(Cow::Owned(i.to_bytes())),
@@ -351,7 +369,7 @@ where
         &self,
         br_name: BranchName,
         ver_name: VersionName,
-    ) -> MapxOrdVsIter<K, V> {
+    ) -> MapxOrdVsIter<K, V, C> {
         MapxOrdVsIter {
             inner: self.inner.iter_by_branch_version(br_name, ver_name),
             _p: PhantomData,
@@ -364,7 +382,7 @@ where
         br_name: BranchName,
         ver_name: VersionName,
         bounds: R,
-    ) -> MapxOrdVsIter<'a, K, V> {
+    ) -> MapxOrdVsIter<'a, K, V, C> {
         let l = match bounds.start_bound() {
             Bound::Included(i) => Bound::Included(Cow::Owned(i.to_bytes())),
             Bound::Excluded(i) => Bound::Excluded(Cow::Owned(i.to_bytes())),
@@ -417,7 +435,20 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<K, V> Clone for MapxOrdVs<K, V> {
+impl<K, V> MapxOrdVs<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<K, V, C> Clone for MapxOrdVs<K, V, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -426,65 +457,246 @@ impl<K, V> Clone for MapxOrdVs<K, V> {
     }
 }
 
-impl<K, V> Default for MapxOrdVs<K, V>
+impl<K, V, C> Registrable for MapxOrdVs<K, V, C> {
+    const KIND: &'static str = "MapxOrdVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K, Old, New, C> Migrate<Old, New> for MapxOrdVs<K, Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = MapxOrdVs<K, New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((
+            Self::Output {
+                inner,
+                _p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<K, V, C> Default for MapxOrdVs<K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn default() -> Self {
-        Self::new()
+        Self::with_codec()
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<K, V> VsMgmt for MapxOrdVs<K, V>
+impl<K, V, C> VsMgmt for MapxOrdVs<K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     crate::impl_vs_methods!();
 }
 
+impl<K, V, C> VsDiff for MapxOrdVs<K, V, C>
+where
+    K: KeyEnDeOrdered,
+    C: Codec<V>,
+{
+    type Diff = Vec<(K, Option<V>)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| K::from_bytes(k).c(d!()).map(|k| (k, v)))
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (k, v) in diff.iter() {
+            if let Some(v) = v {
+                self.insert(k, v).c(d!())?;
+            } else {
+                self.remove(k).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K, V, C> VsView for MapxOrdVs<K, V, C>
+where
+    K: KeyEnDeOrdered,
+    C: Codec<V>,
+{
+    type View<'a>
+        = MapxOrdVsView<'a, K, V, C>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(MapxOrdVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+/// A read-only handler of [MapxOrdVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct MapxOrdVsView<'a, K, V, C = DefaultCodec> {
+    hdr: &'a MapxOrdVs<K, V, C>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, K, V, C> MapxOrdVsView<'a, K, V, C>
+where
+    K: KeyEnDeOrdered,
+    C: Codec<V>,
+{
+    #[inline(always)]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.hdr.get_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn get_le(&self, key: &K) -> Option<(K, V)> {
+        self.hdr.get_le_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn get_ge(&self, key: &K) -> Option<(K, V)> {
+        self.hdr.get_ge_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.hdr
+            .len_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.hdr.is_empty_by_branch_version(
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> MapxOrdVsIter<'a, K, V, C> {
+        self.hdr
+            .iter_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn range<R: 'a + RangeBounds<K>>(
+        &self,
+        bounds: R,
+    ) -> MapxOrdVsIter<'a, K, V, C> {
+        self.hdr.range_by_branch_version(
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+            bounds,
+        )
+    }
+
+    #[inline(always)]
+    pub fn first(&self) -> Option<(K, V)> {
+        self.iter().next()
+    }
+
+    #[inline(always)]
+    pub fn last(&self) -> Option<(K, V)> {
+        self.iter().next_back()
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.hdr.contains_key_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Debug)]
-pub struct ValueMut<'a, K, V>
+pub struct ValueMut<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    hdr: &'a mut MapxOrdVs<K, V>,
+    hdr: &'a mut MapxOrdVs<K, V, C>,
     key: &'a K,
     value: V,
 }
 
-impl<'a, K, V> ValueMut<'a, K, V>
+impl<'a, K, V, C> ValueMut<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    fn new(hdr: &'a mut MapxOrdVs<K, V>, key: &'a K, value: V) -> Self {
+    fn new(hdr: &'a mut MapxOrdVs<K, V, C>, key: &'a K, value: V) -> Self {
         ValueMut { hdr, key, value }
     }
 }
 
-impl<'a, K, V> Drop for ValueMut<'a, K, V>
+impl<'a, K, V, C> Drop for ValueMut<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn drop(&mut self) {
         pnk!(self.hdr.insert(self.key, &self.value));
     }
 }
 
-impl<'a, K, V> Deref for ValueMut<'a, K, V>
+impl<'a, K, V, C> Deref for ValueMut<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Target = V;
     fn deref(&self) -> &Self::Target {
@@ -492,10 +704,10 @@ where
     }
 }
 
-impl<'a, K, V> DerefMut for ValueMut<'a, K, V>
+impl<'a, K, V, C> DerefMut for ValueMut<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.value
@@ -505,21 +717,21 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct Entry<'a, K, V>
+pub struct Entry<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    hdr: &'a mut MapxOrdVs<K, V>,
+    hdr: &'a mut MapxOrdVs<K, V, C>,
     key: &'a K,
 }
 
-impl<'a, K, V> Entry<'a, K, V>
+impl<'a, K, V, C> Entry<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    pub fn or_insert(self, default: &V) -> ValueMut<'a, K, V> {
+    pub fn or_insert(self, default: &V) -> ValueMut<'a, K, V, C> {
         if !self.hdr.contains_key(self.key) {
             pnk!(self.hdr.insert(self.key, default));
         }
@@ -530,19 +742,19 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdVsIter<'a, K, V>
+pub struct MapxOrdVsIter<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyVsIter<'a, V>,
+    inner: MapxOrdRawKeyVsIter<'a, V, C>,
     _p: PhantomData<K>,
 }
 
-impl<'a, K, V> Iterator for MapxOrdVsIter<'a, K, V>
+impl<'a, K, V, C> Iterator for MapxOrdVsIter<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Item = (K, V);
     fn next(&mut self) -> Option<Self::Item> {
@@ -550,10 +762,10 @@ where
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for MapxOrdVsIter<'a, K, V>
+impl<'a, K, V, C> DoubleEndedIterator for MapxOrdVsIter<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner
@@ -565,16 +777,16 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdVsValues<'a, V>
+pub struct MapxOrdVsValues<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyVsIter<'a, V>,
+    inner: MapxOrdRawKeyVsIter<'a, V, C>,
 }
 
-impl<'a, V> Iterator for MapxOrdVsValues<'a, V>
+impl<'a, V, C> Iterator for MapxOrdVsValues<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Item = V;
     fn next(&mut self) -> Option<Self::Item> {
@@ -582,9 +794,9 @@ where
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxOrdVsValues<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxOrdVsValues<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|(_, v)| v)
@@ -594,21 +806,21 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdVsIterMut<'a, K, V>
+pub struct MapxOrdVsIterMut<'a, K, V, C = DefaultCodec>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyVsIterMut<'a, V>,
+    inner: MapxOrdRawKeyVsIterMut<'a, V, C>,
     _p: PhantomData<K>,
 }
 
-impl<'a, K, V> Iterator for MapxOrdVsIterMut<'a, K, V>
+impl<'a, K, V, C> Iterator for MapxOrdVsIterMut<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    type Item = (K, ValueIterMut<'a, V>);
+    type Item = (K, ValueIterMut<'a, V, C>);
 
     fn next(&mut self) -> Option<Self::Item> {
         self.inner
@@ -617,10 +829,10 @@ where
     }
 }
 
-impl<'a, K, V> DoubleEndedIterator for MapxOrdVsIterMut<'a, K, V>
+impl<'a, K, V, C> DoubleEndedIterator for MapxOrdVsIterMut<'a, K, V, C>
 where
     K: KeyEnDeOrdered,
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner
@@ -632,27 +844,27 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdVsValuesMut<'a, V>
+pub struct MapxOrdVsValuesMut<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    inner: MapxOrdRawKeyVsIterMut<'a, V>,
+    inner: MapxOrdRawKeyVsIterMut<'a, V, C>,
 }
 
-impl<'a, V> Iterator for MapxOrdVsValuesMut<'a, V>
+impl<'a, V, C> Iterator for MapxOrdVsValuesMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    type Item = ValueIterMut<'a, V>;
+    type Item = ValueIterMut<'a, V, C>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|(_, v)| v)
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxOrdVsValuesMut<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxOrdVsValuesMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|(_, v)| v)
@@ -6,8 +6,13 @@
 mod test;
 
 use crate::{
-    common::{ende::ValueEnDe, BranchName, RawKey, VersionName},
-    VsMgmt,
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::ValueEnDe,
+        migrate::{self, Migratable, Migrate, Migrating, Migration, RawStore},
+        BranchName, RawKey, VersionName,
+    },
+    VsDiff, VsMgmt,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
@@ -15,20 +20,25 @@ use std::{
     borrow::Cow,
     marker::PhantomData,
     ops::{Deref, DerefMut, RangeBounds},
+    sync::Arc,
+};
+use vsdb_core::{
+    common::PreBytes,
+    versioned::mapx_raw::{self, MapxRawVs, MapxRawVsIter, MapxRawVsIterMut},
+    Registrable,
 };
-use vsdb_core::versioned::mapx_raw::{self, MapxRawVs, MapxRawVsIter, MapxRawVsIterMut};
 
 /// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
-pub struct MapxOrdRawKeyVs<V> {
+pub struct MapxOrdRawKeyVs<V, C = DefaultCodec> {
     inner: MapxRawVs,
-    p: PhantomData<V>,
+    p: PhantomData<(V, C)>,
 }
 
-impl<V> MapxOrdRawKeyVs<V>
+impl<V, C> MapxOrdRawKeyVs<V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     /// # Safety
     ///
@@ -42,8 +52,19 @@ where
         }
     }
 
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
     #[inline(always)]
-    pub fn new() -> Self {
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self {
+            inner,
+            p: PhantomData,
+        })
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
         MapxOrdRawKeyVs {
             inner: MapxRawVs::new(),
             p: PhantomData,
@@ -52,26 +73,26 @@ where
 
     #[inline(always)]
     pub fn get(&self, key: &[u8]) -> Option<V> {
-        self.inner
-            .get(key)
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+        pnk!(self.ensure_migrated(key));
+        self.inner.get(key).map(|v| C::decode(&v).unwrap())
     }
 
     #[inline(always)]
-    pub fn get_mut<'a>(&'a mut self, key: &'a [u8]) -> Option<ValueMut<'_, V>> {
+    pub fn get_mut<'a>(&'a mut self, key: &'a [u8]) -> Option<ValueMut<'_, V, C>> {
         self.get(key).map(move |v| ValueMut::new(self, key, v))
     }
 
     #[inline(always)]
-    pub fn entry<'a>(&'a mut self, key: &'a [u8]) -> Entry<'a, V> {
+    pub fn entry<'a>(&'a mut self, key: &'a [u8]) -> Entry<'a, V, C> {
         Entry { key, hdr: self }
     }
 
     #[inline(always)]
     pub fn get_by_branch(&self, key: &[u8], br_name: BranchName) -> Option<V> {
+        pnk!(self.ensure_migrated(key));
         self.inner
             .get_by_branch(key, br_name)
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+            .map(|v| C::decode(&v).unwrap())
     }
 
     #[inline(always)]
@@ -81,16 +102,20 @@ where
         br_name: BranchName,
         ver_name: VersionName,
     ) -> Option<V> {
+        pnk!(self.ensure_migrated(key));
         self.inner
             .get_by_branch_version(key, br_name, ver_name)
-            .map(|v| <V as ValueEnDe>::decode(&v).unwrap())
+            .map(|v| C::decode(&v).unwrap())
     }
 
     #[inline(always)]
     pub fn get_le(&self, key: &[u8]) -> Option<(RawKey, V)> {
-        self.inner
-            .get_le(key)
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = migrate::migrating(self) {
+            pnk!(m.next(|| self.inner.get_le(key), |v| v))
+        } else {
+            self.inner.get_le(key)
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
@@ -99,9 +124,12 @@ where
         key: &[u8],
         br_name: BranchName,
     ) -> Option<(RawKey, V)> {
-        self.inner
-            .get_le_by_branch(key, br_name)
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = migrate::migrating(self) {
+            pnk!(m.next(|| self.inner.get_le_by_branch(key, br_name), |v| v))
+        } else {
+            self.inner.get_le_by_branch(key, br_name)
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
@@ -111,16 +139,22 @@ where
         br_name: BranchName,
         ver_name: VersionName,
     ) -> Option<(RawKey, V)> {
-        self.inner
-            .get_le_by_branch_version(key, br_name, ver_name)
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = migrate::migrating(self) {
+            pnk!(m.next(|| self.inner.get_le_by_branch_version(key, br_name, ver_name), |v| v))
+        } else {
+            self.inner.get_le_by_branch_version(key, br_name, ver_name)
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
     pub fn get_ge(&self, key: &[u8]) -> Option<(RawKey, V)> {
-        self.inner
-            .get_ge(key)
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = migrate::migrating(self) {
+            pnk!(m.next(|| self.inner.get_ge(key), |v| v))
+        } else {
+            self.inner.get_ge(key)
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
@@ -129,9 +163,12 @@ where
         key: &[u8],
         br_name: BranchName,
     ) -> Option<(RawKey, V)> {
-        self.inner
-            .get_ge_by_branch(key, br_name)
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = migrate::migrating(self) {
+            pnk!(m.next(|| self.inner.get_ge_by_branch(key, br_name), |v| v))
+        } else {
+            self.inner.get_ge_by_branch(key, br_name)
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
@@ -141,9 +178,12 @@ where
         br_name: BranchName,
         ver_name: VersionName,
     ) -> Option<(RawKey, V)> {
-        self.inner
-            .get_ge_by_branch_version(key, br_name, ver_name)
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = migrate::migrating(self) {
+            pnk!(m.next(|| self.inner.get_ge_by_branch_version(key, br_name, ver_name), |v| v))
+        } else {
+            self.inner.get_ge_by_branch_version(key, br_name, ver_name)
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
@@ -186,10 +226,11 @@ where
 
     #[inline(always)]
     pub fn insert(&mut self, key: &[u8], value: &V) -> Result<Option<V>> {
+        self.ensure_migrated(key).c(d!())?;
         self.inner
-            .insert(key, &value.encode())
+            .insert(key, C::encode(value))
             .c(d!())
-            .map(|v| v.map(|v| <V as ValueEnDe>::decode(&v).unwrap()))
+            .map(|v| v.map(|v| C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
@@ -199,31 +240,35 @@ where
         value: &V,
         br_name: BranchName,
     ) -> Result<Option<V>> {
+        self.ensure_migrated(key).c(d!())?;
         self.inner
-            .insert_by_branch(key, &value.encode(), br_name)
+            .insert_by_branch(key, C::encode(value), br_name)
             .c(d!())
-            .map(|v| v.map(|v| <V as ValueEnDe>::decode(&v).unwrap()))
+            .map(|v| v.map(|v| C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> MapxOrdRawKeyVsIter<'_, V> {
+    pub fn iter(&self) -> MapxOrdRawKeyVsIter<'_, V, C> {
         MapxOrdRawKeyVsIter {
+            migrating: migrate::migrating(self),
             inner: self.inner.iter(),
             p: PhantomData,
         }
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> MapxOrdRawKeyVsIterMut<'_, V> {
+    pub fn iter_mut(&mut self) -> MapxOrdRawKeyVsIterMut<'_, V, C> {
         MapxOrdRawKeyVsIterMut {
+            migrating: migrate::migrating(self),
             inner: self.inner.iter_mut(),
             p: PhantomData,
         }
     }
 
     #[inline(always)]
-    pub fn iter_by_branch(&self, br_name: BranchName) -> MapxOrdRawKeyVsIter<'_, V> {
+    pub fn iter_by_branch(&self, br_name: BranchName) -> MapxOrdRawKeyVsIter<'_, V, C> {
         MapxOrdRawKeyVsIter {
+            migrating: migrate::migrating(self),
             inner: self.inner.iter_by_branch(br_name),
             p: PhantomData,
         }
@@ -234,19 +279,60 @@ where
         &self,
         br_name: BranchName,
         ver_name: VersionName,
-    ) -> MapxOrdRawKeyVsIter<'_, V> {
+    ) -> MapxOrdRawKeyVsIter<'_, V, C> {
         MapxOrdRawKeyVsIter {
+            migrating: migrate::migrating(self),
             inner: self.inner.iter_by_branch_version(br_name, ver_name),
             p: PhantomData,
         }
     }
 
+    /// Iterate over the entries whose keys start with `prefix`.
+    #[inline(always)]
+    pub fn iter_prefix(&self, prefix: &[u8]) -> MapxOrdRawKeyVsIter<'_, V, C> {
+        MapxOrdRawKeyVsIter {
+            migrating: migrate::migrating(self),
+            inner: self.inner.iter_prefix(prefix),
+            p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn iter_prefix_by_branch(
+        &self,
+        prefix: &[u8],
+        br_name: BranchName,
+    ) -> MapxOrdRawKeyVsIter<'_, V, C> {
+        MapxOrdRawKeyVsIter {
+            migrating: migrate::migrating(self),
+            inner: self.inner.iter_prefix_by_branch(prefix, br_name),
+            p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn iter_prefix_by_branch_version(
+        &self,
+        prefix: &[u8],
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> MapxOrdRawKeyVsIter<'_, V, C> {
+        MapxOrdRawKeyVsIter {
+            migrating: migrate::migrating(self),
+            inner: self
+                .inner
+                .iter_prefix_by_branch_version(prefix, br_name, ver_name),
+            p: PhantomData,
+        }
+    }
+
     #[inline(always)]
     pub fn range<'a, R: RangeBounds<Cow<'a, [u8]>>>(
         &'a self,
         bounds: R,
-    ) -> MapxOrdRawKeyVsIter<'a, V> {
+    ) -> MapxOrdRawKeyVsIter<'a, V, C> {
         MapxOrdRawKeyVsIter {
+            migrating: migrate::migrating(self),
             inner: self.inner.range(bounds),
             p: PhantomData,
         }
@@ -256,8 +342,9 @@ where
     pub fn range_mut<'a, R: RangeBounds<Cow<'a, [u8]>>>(
         &'a mut self,
         bounds: R,
-    ) -> MapxOrdRawKeyVsIterMut<'a, V> {
+    ) -> MapxOrdRawKeyVsIterMut<'a, V, C> {
         MapxOrdRawKeyVsIterMut {
+            migrating: migrate::migrating(self),
             inner: self.inner.range_mut(bounds),
             p: PhantomData,
         }
@@ -268,8 +355,9 @@ where
         &'a self,
         br_name: BranchName,
         bounds: R,
-    ) -> MapxOrdRawKeyVsIter<'a, V> {
+    ) -> MapxOrdRawKeyVsIter<'a, V, C> {
         MapxOrdRawKeyVsIter {
+            migrating: migrate::migrating(self),
             inner: self.inner.range_by_branch(br_name, bounds),
             p: PhantomData,
         }
@@ -281,8 +369,9 @@ where
         br_name: BranchName,
         ver_name: VersionName,
         bounds: R,
-    ) -> MapxOrdRawKeyVsIter<'a, V> {
+    ) -> MapxOrdRawKeyVsIter<'a, V, C> {
         MapxOrdRawKeyVsIter {
+            migrating: migrate::migrating(self),
             inner: self
                 .inner
                 .range_by_branch_version(br_name, ver_name, bounds),
@@ -351,10 +440,11 @@ where
 
     #[inline(always)]
     pub fn remove(&mut self, key: &[u8]) -> Result<Option<V>> {
+        self.ensure_migrated(key).c(d!())?;
         self.inner
             .remove(key)
             .c(d!())
-            .map(|v| v.map(|v| <V as ValueEnDe>::decode(&v).unwrap()))
+            .map(|v| v.map(|v| C::decode(&v).unwrap()))
     }
 
     #[inline(always)]
@@ -363,22 +453,57 @@ where
         key: &[u8],
         br_name: BranchName,
     ) -> Result<Option<V>> {
+        self.ensure_migrated(key).c(d!())?;
         self.inner
             .remove_by_branch(key, br_name)
             .c(d!())
-            .map(|v| v.map(|v| <V as ValueEnDe>::decode(&v).unwrap()))
+            .map(|v| v.map(|v| C::decode(&v).unwrap()))
+    }
+
+    /// Remove the entries whose keys start with `prefix`,
+    /// return the number of them.
+    #[inline(always)]
+    pub fn remove_prefix(&mut self, prefix: &[u8]) -> Result<usize> {
+        self.inner.remove_prefix(prefix).c(d!())
+    }
+
+    #[inline(always)]
+    pub fn remove_prefix_by_branch(
+        &mut self,
+        prefix: &[u8],
+        br_name: BranchName,
+    ) -> Result<usize> {
+        self.inner.remove_prefix_by_branch(prefix, br_name).c(d!())
     }
 
     #[inline(always)]
     pub fn clear(&mut self) {
         self.inner.clear();
     }
+
+    // Convert the values of the key if the instance is being migrated.
+    #[inline(always)]
+    fn ensure_migrated(&self, key: &[u8]) -> Result<()> {
+        migrate::ensure_migrated(self, key).c(d!())
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<V> Clone for MapxOrdRawKeyVs<V> {
+impl<V> MapxOrdRawKeyVs<V>
+where
+    V: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<V, C> Clone for MapxOrdRawKeyVs<V, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -387,56 +512,145 @@ impl<V> Clone for MapxOrdRawKeyVs<V> {
     }
 }
 
-impl<V> Default for MapxOrdRawKeyVs<V>
+impl<V, C> Registrable for MapxOrdRawKeyVs<V, C> {
+    const KIND: &'static str = "MapxOrdRawKeyVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<V, C> Migratable for MapxOrdRawKeyVs<V, C> {
+    #[inline(always)]
+    fn converter_id(&self) -> migrate::ConverterID {
+        migrate::converter_id::<V, C>()
+    }
+
+    #[inline(always)]
+    fn raw_store(&self) -> RawStore {
+        RawStore::Versioned(unsafe { self.inner.shadow() })
+    }
+}
+
+impl<Old, New, C> Migrate<Old, New> for MapxOrdRawKeyVs<Old, C>
 where
-    V: ValueEnDe,
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = MapxOrdRawKeyVs<New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let store = RawStore::Versioned(unsafe { self.inner.shadow() });
+        let m = migrate::begin_migration(&self, store, migrate::converter::<Old, New, C>(f))
+            .c(d!())?;
+        Ok((
+            Self::Output {
+                inner: self.inner,
+                p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<V, C> Default for MapxOrdRawKeyVs<V, C>
+where
+    C: Codec<V>,
 {
     fn default() -> Self {
-        Self::new()
+        Self::with_codec()
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<V> VsMgmt for MapxOrdRawKeyVs<V>
+impl<V, C> VsMgmt for MapxOrdRawKeyVs<V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     crate::impl_vs_methods!();
 }
 
+impl<V, C> VsDiff for MapxOrdRawKeyVs<V, C>
+where
+    C: Codec<V>,
+{
+    type Diff = Vec<(RawKey, Option<V>)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        if migrate::migrating(self).is_some() {
+            // convert the changed keys first, then read the converted values
+            for (k, _) in self.inner.diff(ver_a, ver_b).c(d!())? {
+                self.ensure_migrated(&k).c(d!())?;
+            }
+        }
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| match v {
+                Some(v) => C::decode(&v).c(d!()).map(|v| (k, Some(v))),
+                None => Ok((k, None)),
+            })
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (k, v) in diff.iter() {
+            if let Some(v) = v {
+                self.insert(k, v).c(d!())?;
+            } else {
+                self.remove(k).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdRawKeyVsIter<'a, V>
+pub struct MapxOrdRawKeyVsIter<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     inner: MapxRawVsIter<'a>,
-    p: PhantomData<V>,
+    migrating: Option<Migrating>,
+    p: PhantomData<(V, C)>,
 }
 
-impl<'a, V> Iterator for MapxOrdRawKeyVsIter<'a, V>
+impl<'a, V, C> Iterator for MapxOrdRawKeyVsIter<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Item = (RawKey, V);
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next()
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = self.migrating.as_ref() {
+            pnk!(m.next(|| self.inner.next(), |v| v))
+        } else {
+            self.inner.next()
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxOrdRawKeyVsIter<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxOrdRawKeyVsIter<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner
-            .next_back()
-            .map(|(k, v)| (k, <V as ValueEnDe>::decode(&v).unwrap()))
+        if let Some(m) = self.migrating.as_ref() {
+            pnk!(m.next(|| self.inner.next_back(), |v| v))
+        } else {
+            self.inner.next_back()
+        }
+        .map(|(k, v)| (k, C::decode(&v).unwrap()))
     }
 }
 
@@ -444,33 +658,33 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Debug)]
-pub struct ValueMut<'a, V: ValueEnDe> {
-    hdr: &'a mut MapxOrdRawKeyVs<V>,
+pub struct ValueMut<'a, V, C: Codec<V> = DefaultCodec> {
+    hdr: &'a mut MapxOrdRawKeyVs<V, C>,
     key: &'a [u8],
     value: V,
 }
 
-impl<'a, V> ValueMut<'a, V>
+impl<'a, V, C> ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    fn new(hdr: &'a mut MapxOrdRawKeyVs<V>, key: &'a [u8], value: V) -> Self {
+    fn new(hdr: &'a mut MapxOrdRawKeyVs<V, C>, key: &'a [u8], value: V) -> Self {
         ValueMut { hdr, key, value }
     }
 }
 
-impl<'a, V> Drop for ValueMut<'a, V>
+impl<'a, V, C> Drop for ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn drop(&mut self) {
         pnk!(self.hdr.insert(self.key, &self.value));
     }
 }
 
-impl<'a, V> Deref for ValueMut<'a, V>
+impl<'a, V, C> Deref for ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Target = V;
     fn deref(&self) -> &Self::Target {
@@ -478,9 +692,9 @@ where
     }
 }
 
-impl<'a, V> DerefMut for ValueMut<'a, V>
+impl<'a, V, C> DerefMut for ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.value
@@ -490,16 +704,16 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct Entry<'a, V: ValueEnDe> {
-    hdr: &'a mut MapxOrdRawKeyVs<V>,
+pub struct Entry<'a, V, C: Codec<V> = DefaultCodec> {
+    hdr: &'a mut MapxOrdRawKeyVs<V, C>,
     key: &'a [u8],
 }
 
-impl<'a, V> Entry<'a, V>
+impl<'a, V, C> Entry<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    pub fn or_insert(self, default: &V) -> ValueMut<'a, V> {
+    pub fn or_insert(self, default: &V) -> ValueMut<'a, V, C> {
         if !self.hdr.contains_key(self.key) {
             pnk!(self.hdr.insert(self.key, default));
         }
@@ -510,26 +724,27 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct ValueIterMut<'a, V>
+pub struct ValueIterMut<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     value: V,
     inner: mapx_raw::ValueIterMut<'a>,
+    p: PhantomData<C>,
 }
 
-impl<'a, V> Drop for ValueIterMut<'a, V>
+impl<'a, V, C> Drop for ValueIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn drop(&mut self) {
-        *self.inner = self.value.encode();
+        *self.inner = C::encode(&self.value);
     }
 }
 
-impl<'a, V> Deref for ValueIterMut<'a, V>
+impl<'a, V, C> Deref for ValueIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Target = V;
     fn deref(&self) -> &Self::Target {
@@ -537,9 +752,9 @@ where
     }
 }
 
-impl<'a, V> DerefMut for ValueIterMut<'a, V>
+impl<'a, V, C> DerefMut for ValueIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.value
@@ -549,44 +764,57 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct MapxOrdRawKeyVsIterMut<'a, V>
+pub struct MapxOrdRawKeyVsIterMut<'a, V, C = DefaultCodec>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     inner: MapxRawVsIterMut<'a>,
-    p: PhantomData<V>,
+    migrating: Option<Migrating>,
+    p: PhantomData<(V, C)>,
 }
 
-impl<'a, V> Iterator for MapxOrdRawKeyVsIterMut<'a, V>
+impl<'a, V, C> Iterator for MapxOrdRawKeyVsIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    type Item = (RawKey, ValueIterMut<'a, V>);
+    type Item = (RawKey, ValueIterMut<'a, V, C>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|(k, v)| {
+        if let Some(m) = self.migrating.as_ref() {
+            pnk!(m.next(|| self.inner.next(), |v| &mut **v))
+        } else {
+            self.inner.next()
+        }
+        .map(|(k, v)| {
             (
                 k,
                 ValueIterMut {
-                    value: pnk!(<V as ValueEnDe>::decode(&v)),
+                    value: pnk!(C::decode(&v)),
                     inner: v,
+                    p: PhantomData,
                 },
             )
         })
     }
 }
 
-impl<'a, V> DoubleEndedIterator for MapxOrdRawKeyVsIterMut<'a, V>
+impl<'a, V, C> DoubleEndedIterator for MapxOrdRawKeyVsIterMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
-        self.inner.next_back().map(|(k, v)| {
+        if let Some(m) = self.migrating.as_ref() {
+            pnk!(m.next(|| self.inner.next_back(), |v| &mut **v))
+        } else {
+            self.inner.next_back()
+        }
+        .map(|(k, v)| {
             (
                 k,
                 ValueIterMut {
-                    value: pnk!(<V as ValueEnDe>::decode(&v)),
+                    value: pnk!(C::decode(&v)),
                     inner: v,
+                    p: PhantomData,
                 },
             )
         })
@@ -2,8 +2,13 @@
 //! Versioned functions.
 //!
 
+pub mod dequex;
 pub mod mapx;
 pub mod mapx_ord;
 pub mod mapx_ord_rawkey;
+pub mod multi_mapx;
 pub mod orphan;
+pub mod priority_queuex;
+pub mod setx;
+pub mod setx_ord;
 pub mod vecx;
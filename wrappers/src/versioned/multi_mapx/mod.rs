@@ -0,0 +1,456 @@
+//!
+//! Documents => [MapxRawVs](crate::versioned::mapx_raw)
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::multi_mapx::{decode_pair, pair_key},
+    common::{
+        ende::KeyEnDeOrdered, escape_key, unescape_key, BranchName, BranchNameOwned,
+        VersionName, VersionNameOwned,
+    },
+    versioned::setx::EXISTS,
+    VsDiff, VsMgmt, VsView,
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use vsdb_core::{
+    common::PreBytes,
+    versioned::mapx_raw::{MapxRawVs, MapxRawVsIter},
+    Registrable,
+};
+
+/// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct MultiMapxVs<K, V> {
+    inner: MapxRawVs,
+    _p: PhantomData<(K, V)>,
+}
+
+impl<K, V> MultiMapxVs<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
+    #[inline(always)]
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            inner: MapxRawVs::new(),
+            _p: PhantomData,
+        }
+    }
+
+    /// All the values of `key`, in order.
+    #[inline(always)]
+    pub fn get_all(&self, key: &K) -> Vec<V> {
+        self.values(key).collect()
+    }
+
+    /// Iterate over the values of `key`, in order.
+    #[inline(always)]
+    pub fn values(&self, key: &K) -> MultiMapxVsValues<'_, V> {
+        MultiMapxVsValues {
+            inner: self.inner.iter_prefix(escape_key(&key.to_bytes())),
+            _p: PhantomData,
+        }
+    }
+
+    /// The number of the values of `key`.
+    #[inline(always)]
+    pub fn count(&self, key: &K) -> usize {
+        self.values(key).count()
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.values(key).next().is_some()
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, key: &K, value: &V) -> bool {
+        self.inner.contains_key(pair_key(key, value))
+    }
+
+    /// The number of all the `(key, value)` pairs.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Return `false` if the pair has been there.
+    #[inline(always)]
+    pub fn insert(&mut self, key: &K, value: &V) -> Result<bool> {
+        self.inner
+            .insert(pair_key(key, value), EXISTS)
+            .c(d!())
+            .map(|old| old.is_none())
+    }
+
+    /// Return `false` if the pair does not exist.
+    #[inline(always)]
+    pub fn remove_one(&mut self, key: &K, value: &V) -> Result<bool> {
+        self.inner
+            .remove(pair_key(key, value))
+            .c(d!())
+            .map(|old| old.is_some())
+    }
+
+    /// Remove all the values of `key`, return the number of them.
+    #[inline(always)]
+    pub fn remove_all(&mut self, key: &K) -> Result<usize> {
+        self.inner
+            .remove_prefix(escape_key(&key.to_bytes()))
+            .c(d!())
+    }
+
+    /// Iterate over all the `(key, value)` pairs, in order.
+    #[inline(always)]
+    pub fn iter(&self) -> MultiMapxVsIter<'_, K, V> {
+        MultiMapxVsIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    #[inline(always)]
+    pub fn get_all_by_branch(&self, key: &K, br_name: BranchName) -> Vec<V> {
+        let prefix = escape_key(&key.to_bytes());
+        MultiMapxVsValues::<V> {
+            inner: self.inner.iter_prefix_by_branch(prefix, br_name),
+            _p: PhantomData,
+        }
+        .collect()
+    }
+
+    #[inline(always)]
+    pub fn contains_by_branch(&self, key: &K, value: &V, br_name: BranchName) -> bool {
+        self.inner
+            .contains_key_by_branch(pair_key(key, value), br_name)
+    }
+
+    #[inline(always)]
+    pub fn insert_by_branch(
+        &mut self,
+        key: &K,
+        value: &V,
+        br_name: BranchName,
+    ) -> Result<bool> {
+        self.inner
+            .insert_by_branch(pair_key(key, value), EXISTS, br_name)
+            .c(d!())
+            .map(|old| old.is_none())
+    }
+
+    #[inline(always)]
+    pub fn remove_one_by_branch(
+        &mut self,
+        key: &K,
+        value: &V,
+        br_name: BranchName,
+    ) -> Result<bool> {
+        self.inner
+            .remove_by_branch(pair_key(key, value), br_name)
+            .c(d!())
+            .map(|old| old.is_some())
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch(&self, br_name: BranchName) -> MultiMapxVsIter<'_, K, V> {
+        MultiMapxVsIter {
+            inner: self.inner.iter_by_branch(br_name),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_all_by_branch_version(
+        &self,
+        key: &K,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Vec<V> {
+        let prefix = escape_key(&key.to_bytes());
+        MultiMapxVsValues::<V> {
+            inner: self
+                .inner
+                .iter_prefix_by_branch_version(prefix, br_name, ver_name),
+            _p: PhantomData,
+        }
+        .collect()
+    }
+
+    #[inline(always)]
+    pub fn contains_by_branch_version(
+        &self,
+        key: &K,
+        value: &V,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> bool {
+        self.inner.contains_key_by_branch_version(
+            pair_key(key, value),
+            br_name,
+            ver_name,
+        )
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> usize {
+        self.inner.len_by_branch_version(br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> MultiMapxVsIter<'_, K, V> {
+        MultiMapxVsIter {
+            inner: self.inner.iter_by_branch_version(br_name, ver_name),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Clone for MultiMapxVs<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Registrable for MultiMapxVs<K, V> {
+    const KIND: &'static str = "MultiMapxVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K, V> Default for MultiMapxVs<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl<K, V> VsMgmt for MultiMapxVs<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    crate::impl_vs_methods!();
+}
+
+impl<K, V> VsDiff for MultiMapxVs<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    /// The changed pairs, `false` means that the pair has been removed.
+    type Diff = Vec<(K, V, bool)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| decode_pair(&k).c(d!()).map(|(k, vv)| (k, vv, v.is_some())))
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (k, v, exists) in diff.iter() {
+            if *exists {
+                self.insert(k, v).c(d!())?;
+            } else {
+                self.remove_one(k, v).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K, V> VsView for MultiMapxVs<K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    type View<'a>
+        = MultiMapxVsView<'a, K, V>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(MultiMapxVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+/// A read-only handler of [MultiMapxVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct MultiMapxVsView<'a, K, V> {
+    hdr: &'a MultiMapxVs<K, V>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, K, V> MultiMapxVsView<'a, K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    #[inline(always)]
+    pub fn get_all(&self, key: &K) -> Vec<V> {
+        self.hdr.get_all_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn count(&self, key: &K) -> usize {
+        self.get_all(key).len()
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, key: &K, value: &V) -> bool {
+        self.hdr.contains_by_branch_version(
+            key,
+            value,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.hdr
+            .len_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> MultiMapxVsIter<'a, K, V> {
+        self.hdr
+            .iter_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+pub struct MultiMapxVsIter<'a, K, V> {
+    inner: MapxRawVsIter<'a>,
+    _p: PhantomData<(K, V)>,
+}
+
+impl<'a, K, V> Iterator for MultiMapxVsIter<'a, K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| pnk!(decode_pair(&k)))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for MultiMapxVsIter<'a, K, V>
+where
+    K: KeyEnDeOrdered,
+    V: KeyEnDeOrdered,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| pnk!(decode_pair(&k)))
+    }
+}
+
+pub struct MultiMapxVsValues<'a, V> {
+    inner: MapxRawVsIter<'a>,
+    _p: PhantomData<V>,
+}
+
+impl<'a, V: KeyEnDeOrdered> Iterator for MultiMapxVsValues<'a, V> {
+    type Item = V;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(k, _)| pnk!(V::from_slice(unescape_key(&k).1)))
+    }
+}
+
+impl<'a, V: KeyEnDeOrdered> DoubleEndedIterator for MultiMapxVsValues<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|(k, _)| pnk!(V::from_slice(unescape_key(&k).1)))
+    }
+}
@@ -0,0 +1,66 @@
+use super::*;
+use crate::{VersionName, VsDiff, VsView};
+
+#[test]
+fn test_insert_remove() {
+    let mut hdr: MultiMapxVs<u32, u32> = MultiMapxVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..10).for_each(|k| {
+        (0..10).for_each(|v| {
+            assert!(pnk!(hdr.insert(&k, &v)));
+            assert!(!pnk!(hdr.insert(&k, &v)));
+        });
+    });
+    assert_eq!(100, hdr.len());
+    assert_eq!(hdr.get_all(&3), (0..10).collect::<Vec<_>>());
+
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    assert!(pnk!(hdr.remove_one(&2, &0)));
+    assert!(!pnk!(hdr.remove_one(&2, &0)));
+    assert_eq!(10, pnk!(hdr.remove_all(&3)));
+    assert_eq!(0, hdr.count(&3));
+    assert_eq!(9, hdr.count(&2));
+    assert_eq!(89, hdr.len());
+
+    let v0 = pnk!(hdr.view_at(hdr.branch_get_default().as_deref(), VersionName(b"v0")));
+    assert_eq!(100, v0.len());
+    assert_eq!(10, v0.count(&3));
+    assert!(v0.contains(&2, &0));
+    assert_eq!(v0.iter().next(), Some((0, 0)));
+
+    let diff = pnk!(hdr.diff(VersionName(b"v0"), VersionName(b"v1")));
+    assert_eq!(11, diff.len());
+    assert!(diff.iter().all(|(_, _, exists)| !exists));
+
+    let mut other: MultiMapxVs<u32, u32> = MultiMapxVs::new();
+    pnk!(other.version_create(VersionName(b"v0")));
+    pnk!(other.insert(&2, &0));
+    pnk!(other.insert(&3, &1));
+    pnk!(other.insert(&4, &4));
+    pnk!(other.apply_diff(&diff));
+    assert_eq!(other.iter().collect::<Vec<_>>(), vec![(4, 4)]);
+}
+
+#[test]
+fn test_branch() {
+    let mut hdr: MultiMapxVs<u32, u32> = MultiMapxVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.insert(&1, &1));
+    pnk!(hdr.insert(&1, &2));
+
+    pnk!(hdr.branch_create(BranchName(b"b"), VersionName(b"b0"), false));
+    assert!(pnk!(hdr.insert_by_branch(&1, &3, BranchName(b"b"))));
+    assert!(pnk!(hdr.remove_one_by_branch(&1, &1, BranchName(b"b"))));
+
+    assert_eq!(hdr.get_all(&1), vec![1, 2]);
+    assert_eq!(hdr.get_all_by_branch(&1, BranchName(b"b")), vec![2, 3]);
+    assert!(hdr.contains_by_branch(&1, &3, BranchName(b"b")));
+    assert!(!hdr.contains(&1, &3));
+    assert_eq!(
+        hdr.iter_by_branch(BranchName(b"b")).collect::<Vec<_>>(),
+        vec![(1, 2), (1, 3)]
+    );
+
+    let forked = pnk!(hdr.fork());
+    assert_eq!(forked.get_all(&1), vec![1, 2]);
+}
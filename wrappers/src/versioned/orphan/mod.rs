@@ -6,12 +6,22 @@
 mod test;
 
 use crate::{
-    versioned::mapx_ord_rawkey::MapxOrdRawKeyVs, BranchName, ValueEnDe, VersionName,
-    VsMgmt,
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::ValueEnDe,
+        migrate::{Migrate, Migration},
+    },
+    versioned::mapx_ord_rawkey::MapxOrdRawKeyVs,
+    BranchName, BranchNameOwned, VersionName, VersionNameOwned, VsDiff, VsMgmt,
+    VsView,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
-use std::ops::{Deref, DerefMut};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+use vsdb_core::{common::PreBytes, Registrable};
 
 /// Used to express some 'non-collection' types,
 /// such as any type of integer, an enum value, etc..
@@ -19,11 +29,11 @@ use std::ops::{Deref, DerefMut};
 /// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
-pub struct OrphanVs<T> {
-    inner: MapxOrdRawKeyVs<T>,
+pub struct OrphanVs<T, C = DefaultCodec> {
+    inner: MapxOrdRawKeyVs<T, C>,
 }
 
-impl<T: ValueEnDe> OrphanVs<T> {
+impl<T, C: Codec<T>> OrphanVs<T, C> {
     /// # Safety
     ///
     /// This API breaks the semantic safety guarantees,
@@ -35,10 +45,18 @@ impl<T: ValueEnDe> OrphanVs<T> {
         }
     }
 
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
     #[inline(always)]
-    pub fn new() -> Self {
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self { inner })
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
         Self {
-            inner: MapxOrdRawKeyVs::new(),
+            inner: MapxOrdRawKeyVs::with_codec(),
         }
     }
 
@@ -56,7 +74,7 @@ impl<T: ValueEnDe> OrphanVs<T> {
     /// - **NEVER** do this:
     ///     - `*(&mut <Orphan>) = Orphan::new(...)`
     ///     - OR you will loss the 'versioned' ability of this object
-    pub fn get_mut(&mut self) -> Option<ValueMut<'_, T>> {
+    pub fn get_mut(&mut self) -> Option<ValueMut<'_, T, C>> {
         self.get_value().map(|value| ValueMut { hdr: self, value })
     }
 
@@ -92,7 +110,19 @@ impl<T: ValueEnDe> OrphanVs<T> {
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<T> Clone for OrphanVs<T> {
+impl<T> OrphanVs<T>
+where
+    T: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<T, C> Clone for OrphanVs<T, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -100,46 +130,142 @@ impl<T> Clone for OrphanVs<T> {
     }
 }
 
-impl<T: ValueEnDe> Default for OrphanVs<T> {
+impl<T, C> Registrable for OrphanVs<T, C> {
+    const KIND: &'static str = "OrphanVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<Old, New, C> Migrate<Old, New> for OrphanVs<Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = OrphanVs<New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((Self::Output { inner }, m))
+    }
+}
+
+impl<T, C: Codec<T>> Default for OrphanVs<T, C> {
     fn default() -> Self {
-        Self::new()
+        Self::with_codec()
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<T> VsMgmt for OrphanVs<T>
+impl<T, C> VsMgmt for OrphanVs<T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     crate::impl_vs_methods!();
 }
 
+impl<T, C> VsDiff for OrphanVs<T, C>
+where
+    C: Codec<T>,
+{
+    /// `None` if the value is not changed,
+    /// `Some(None)` if the value does not exist in the target version.
+    type Diff = Option<Option<T>>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())
+            .map(|d| d.into_iter().next().map(|(_, v)| v))
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        match diff {
+            Some(Some(v)) => self.set_value(v).c(d!()).map(|_| ()),
+            Some(None) => self.inner.remove(&[]).c(d!()).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T, C> VsView for OrphanVs<T, C>
+where
+    C: Codec<T>,
+{
+    type View<'a>
+        = OrphanVsView<'a, T, C>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(OrphanVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+/// A read-only handler of [OrphanVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct OrphanVsView<'a, T, C = DefaultCodec> {
+    hdr: &'a OrphanVs<T, C>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, T, C: Codec<T>> OrphanVsView<'a, T, C> {
+    #[inline(always)]
+    pub fn get_value(&self) -> Option<T> {
+        self.hdr.get_value_by_branch_version(
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
 /// A type returned by `get_mut()`.
-pub struct ValueMut<'a, T>
+pub struct ValueMut<'a, T, C = DefaultCodec>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
-    hdr: &'a mut OrphanVs<T>,
+    hdr: &'a mut OrphanVs<T, C>,
     value: T,
 }
 
-impl<'a, T> Drop for ValueMut<'a, T>
+impl<'a, T, C> Drop for ValueMut<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     fn drop(&mut self) {
         pnk!(self.hdr.set_value(&self.value));
     }
 }
 
-impl<'a, T> Deref for ValueMut<'a, T>
+impl<'a, T, C> Deref for ValueMut<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     type Target = T;
     fn deref(&self) -> &Self::Target {
@@ -147,9 +273,9 @@ where
     }
 }
 
-impl<'a, T> DerefMut for ValueMut<'a, T>
+impl<'a, T, C> DerefMut for ValueMut<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.value
@@ -0,0 +1,491 @@
+//!
+//! Documents => [MapxRawVs](crate::versioned::mapx_raw)
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::priority_queuex::{decode_prio, next_seq, prio_key, seq_upper, SEQ_SIZE},
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::{KeyEnDeOrdered, ValueEnDe},
+        escape_key,
+        migrate::{Migrate, Migration},
+        RawKey,
+    },
+    versioned::mapx_ord_rawkey::{MapxOrdRawKeyVs, MapxOrdRawKeyVsIter},
+    BranchName, BranchNameOwned, VersionName, VersionNameOwned, VsDiff, VsMgmt,
+    VsView,
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{marker::PhantomData, sync::Arc};
+use vsdb_core::{common::PreBytes, Registrable};
+
+/// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct PriorityQueuexVs<P, T, C = DefaultCodec> {
+    inner: MapxOrdRawKeyVs<T, C>,
+    _p: PhantomData<P>,
+}
+
+impl<P, T, C> PriorityQueuexVs<P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
+    #[inline(always)]
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
+        Self {
+            inner: MapxOrdRawKeyVs::with_codec(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, priority: &P, v: &T) -> Result<()> {
+        let prefix = escape_key(&priority.to_bytes());
+        let seq = next_seq(&prefix, self.inner.get_le(&seq_upper(&prefix)));
+        self.inner
+            .insert(&prio_key(prefix, seq), v)
+            .c(d!())
+            .map(|_| ())
+    }
+
+    /// The earliest pushed element of the lowest priority.
+    #[inline(always)]
+    pub fn peek_min(&self) -> Option<(P, T)> {
+        self.inner.first().map(|(k, v)| (decode_prio(&k), v))
+    }
+
+    /// The earliest pushed element of the highest priority.
+    #[inline(always)]
+    pub fn peek_max(&self) -> Option<(P, T)> {
+        let (k, _) = self.inner.last()?;
+        self.inner
+            .get_ge(&k[..k.len() - SEQ_SIZE])
+            .map(|(k, v)| (decode_prio(&k), v))
+    }
+
+    #[inline(always)]
+    pub fn pop_min(&mut self) -> Result<Option<(P, T)>> {
+        let Some((k, v)) = self.inner.first() else {
+            return Ok(None);
+        };
+        self.inner.remove(&k).c(d!())?;
+        Ok(Some((decode_prio(&k), v)))
+    }
+
+    #[inline(always)]
+    pub fn pop_max(&mut self) -> Result<Option<(P, T)>> {
+        let Some((k, v)) = self.max_entry() else {
+            return Ok(None);
+        };
+        self.inner.remove(&k).c(d!())?;
+        Ok(Some((decode_prio(&k), v)))
+    }
+
+    /// Iterate from the lowest priority to the highest one,
+    /// elements of a same priority are in the order of their pushing,
+    /// and in the reversed order when iterating backward.
+    #[inline(always)]
+    pub fn iter(&self) -> PriorityQueuexVsIter<'_, P, T, C> {
+        PriorityQueuexVsIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch(&self, br_name: BranchName) -> usize {
+        self.inner.len_by_branch(br_name)
+    }
+
+    #[inline(always)]
+    pub fn is_empty_by_branch(&self, br_name: BranchName) -> bool {
+        self.inner.is_empty_by_branch(br_name)
+    }
+
+    #[inline(always)]
+    pub fn push_by_branch(
+        &mut self,
+        priority: &P,
+        v: &T,
+        br_name: BranchName,
+    ) -> Result<()> {
+        let prefix = escape_key(&priority.to_bytes());
+        let last = self.inner.get_le_by_branch(&seq_upper(&prefix), br_name);
+        let seq = next_seq(&prefix, last);
+        self.inner
+            .insert_by_branch(&prio_key(prefix, seq), v, br_name)
+            .c(d!())
+            .map(|_| ())
+    }
+
+    #[inline(always)]
+    pub fn peek_min_by_branch(&self, br_name: BranchName) -> Option<(P, T)> {
+        self.inner
+            .first_by_branch(br_name)
+            .map(|(k, v)| (decode_prio(&k), v))
+    }
+
+    #[inline(always)]
+    pub fn peek_max_by_branch(&self, br_name: BranchName) -> Option<(P, T)> {
+        let (k, _) = self.inner.last_by_branch(br_name)?;
+        self.inner
+            .get_ge_by_branch(&k[..k.len() - SEQ_SIZE], br_name)
+            .map(|(k, v)| (decode_prio(&k), v))
+    }
+
+    #[inline(always)]
+    pub fn pop_min_by_branch(&mut self, br_name: BranchName) -> Result<Option<(P, T)>> {
+        let Some((k, v)) = self.inner.first_by_branch(br_name) else {
+            return Ok(None);
+        };
+        self.inner.remove_by_branch(&k, br_name).c(d!())?;
+        Ok(Some((decode_prio(&k), v)))
+    }
+
+    #[inline(always)]
+    pub fn pop_max_by_branch(&mut self, br_name: BranchName) -> Result<Option<(P, T)>> {
+        let Some((k, _)) = self.inner.last_by_branch(br_name) else {
+            return Ok(None);
+        };
+        let (k, v) = self
+            .inner
+            .get_ge_by_branch(&k[..k.len() - SEQ_SIZE], br_name)
+            .c(d!())?;
+        self.inner.remove_by_branch(&k, br_name).c(d!())?;
+        Ok(Some((decode_prio(&k), v)))
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch(
+        &self,
+        br_name: BranchName,
+    ) -> PriorityQueuexVsIter<'_, P, T, C> {
+        PriorityQueuexVsIter {
+            inner: self.inner.iter_by_branch(br_name),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> usize {
+        self.inner.len_by_branch_version(br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn is_empty_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> bool {
+        self.inner.is_empty_by_branch_version(br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn peek_min_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Option<(P, T)> {
+        self.inner
+            .first_by_branch_version(br_name, ver_name)
+            .map(|(k, v)| (decode_prio(&k), v))
+    }
+
+    #[inline(always)]
+    pub fn peek_max_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Option<(P, T)> {
+        let (k, _) = self.inner.last_by_branch_version(br_name, ver_name)?;
+        self.inner
+            .get_ge_by_branch_version(&k[..k.len() - SEQ_SIZE], br_name, ver_name)
+            .map(|(k, v)| (decode_prio(&k), v))
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> PriorityQueuexVsIter<'_, P, T, C> {
+        PriorityQueuexVsIter {
+            inner: self.inner.iter_by_branch_version(br_name, ver_name),
+            _p: PhantomData,
+        }
+    }
+
+    fn max_entry(&self) -> Option<(RawKey, T)> {
+        let (k, _) = self.inner.last()?;
+        self.inner.get_ge(&k[..k.len() - SEQ_SIZE])
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl<P, T> PriorityQueuexVs<P, T>
+where
+    P: KeyEnDeOrdered,
+    T: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<P, T, C> Clone for PriorityQueuexVs<P, T, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<P, T, C> Registrable for PriorityQueuexVs<P, T, C> {
+    const KIND: &'static str = "PriorityQueuexVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<P, Old, New, C> Migrate<Old, New> for PriorityQueuexVs<P, Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = PriorityQueuexVs<P, New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((
+            Self::Output {
+                inner,
+                _p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<P, T, C> Default for PriorityQueuexVs<P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    fn default() -> Self {
+        Self::with_codec()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl<P, T, C> VsMgmt for PriorityQueuexVs<P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    crate::impl_vs_methods!();
+}
+
+impl<P, T, C> VsDiff for PriorityQueuexVs<P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    /// The changed `(priority, sequence)`s,
+    /// `None` means that the element has been popped.
+    type Diff = Vec<(P, u64, Option<T>)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        Ok(self
+            .inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| {
+                let seq = crate::parse_int!(k[k.len() - SEQ_SIZE..], u64);
+                (decode_prio(&k), seq, v)
+            })
+            .collect())
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (priority, seq, v) in diff.iter() {
+            let k = prio_key(escape_key(&priority.to_bytes()), *seq);
+            if let Some(v) = v {
+                self.inner.insert(&k, v).c(d!())?;
+            } else {
+                self.inner.remove(&k).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<P, T, C> VsView for PriorityQueuexVs<P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    type View<'a>
+        = PriorityQueuexVsView<'a, P, T, C>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(PriorityQueuexVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+/// A read-only handler of [PriorityQueuexVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct PriorityQueuexVsView<'a, P, T, C = DefaultCodec> {
+    hdr: &'a PriorityQueuexVs<P, T, C>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, P, T, C> PriorityQueuexVsView<'a, P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.hdr
+            .len_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.hdr.is_empty_by_branch_version(
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn peek_min(&self) -> Option<(P, T)> {
+        self.hdr.peek_min_by_branch_version(
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn peek_max(&self) -> Option<(P, T)> {
+        self.hdr.peek_max_by_branch_version(
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> PriorityQueuexVsIter<'a, P, T, C> {
+        self.hdr
+            .iter_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+pub struct PriorityQueuexVsIter<'a, P, T, C: Codec<T> = DefaultCodec> {
+    inner: MapxOrdRawKeyVsIter<'a, T, C>,
+    _p: PhantomData<P>,
+}
+
+impl<'a, P, T, C> Iterator for PriorityQueuexVsIter<'a, P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    type Item = (P, T);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, v)| (decode_prio(&k), v))
+    }
+}
+
+impl<'a, P, T, C> DoubleEndedIterator for PriorityQueuexVsIter<'a, P, T, C>
+where
+    P: KeyEnDeOrdered,
+    C: Codec<T>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, v)| (decode_prio(&k), v))
+    }
+}
@@ -0,0 +1,62 @@
+use super::*;
+use crate::{common::INITIAL_BRANCH_NAME, VersionName, VsDiff, VsView};
+
+#[test]
+fn test_versioned_order() {
+    let mut hdr: PriorityQueuexVs<u32, usize> = PriorityQueuexVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..30usize).for_each(|i| {
+        pnk!(hdr.push(&((i % 3) as u32), &i));
+    });
+    assert_eq!(Some((0, 0)), hdr.peek_min());
+    assert_eq!(Some((2, 2)), hdr.peek_max());
+
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    (0..10).for_each(|i| {
+        assert_eq!(Some((0, i * 3)), pnk!(hdr.pop_min()));
+        assert_eq!(Some((2, i * 3 + 2)), pnk!(hdr.pop_max()));
+    });
+    assert!(hdr.iter().all(|(p, _)| 1 == p));
+    assert_eq!(None, hdr.iter().find(|(_, v)| 0 == v % 3));
+
+    let v0 = pnk!(hdr.view_at(INITIAL_BRANCH_NAME, VersionName(b"v0")));
+    assert_eq!(30, v0.len());
+    assert_eq!(Some((0, 0)), v0.peek_min());
+    assert_eq!(Some((2, 2)), v0.peek_max());
+
+    let diff = pnk!(hdr.diff(VersionName(b"v0"), VersionName(b"v1")));
+    assert_eq!(20, diff.len());
+    assert!(diff.iter().all(|(p, _, v)| 1 != *p && v.is_none()));
+
+    let mut other: PriorityQueuexVs<u32, usize> = PriorityQueuexVs::new();
+    pnk!(other.version_create(VersionName(b"v0")));
+    let diff = pnk!(hdr.diff(VersionName(b"v1"), VersionName(b"v0")));
+    pnk!(other.apply_diff(&diff));
+    assert_eq!(20, other.len());
+    assert_eq!(Some((0, 0)), other.peek_min());
+    assert_eq!(Some((2, 2)), other.peek_max());
+
+    // sequences continue after the ones in the diff
+    pnk!(other.push(&0, &100));
+    assert_eq!(Some((0, 100)), other.iter().nth(10));
+}
+
+#[test]
+fn test_by_branch() {
+    let mut hdr: PriorityQueuexVs<u32, usize> = PriorityQueuexVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.push(&1, &1));
+
+    let br = crate::BranchName(b"br");
+    pnk!(hdr.branch_create(br, VersionName(b"br0"), false));
+    pnk!(hdr.push_by_branch(&0, &0, br));
+    pnk!(hdr.push_by_branch(&2, &2, br));
+    assert_eq!(3, hdr.len_by_branch(br));
+    assert_eq!(1, hdr.len_by_branch(INITIAL_BRANCH_NAME));
+    assert_eq!(Some((0, 0)), hdr.peek_min_by_branch(br));
+    assert_eq!(Some((2, 2)), hdr.peek_max_by_branch(br));
+
+    assert_eq!(Some((2, 2)), pnk!(hdr.pop_max_by_branch(br)));
+    assert_eq!(Some((0, 0)), pnk!(hdr.pop_min_by_branch(br)));
+    assert_eq!(hdr.iter_by_branch(br).collect::<Vec<_>>(), vec![(1, 1)]);
+}
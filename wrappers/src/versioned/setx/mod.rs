@@ -0,0 +1,335 @@
+//!
+//! Documents => [MapxRawVs](crate::versioned::mapx_raw)
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::setx::{decode, RawKeys, SetOp, SetxOpIter},
+    common::{ende::KeyEnDe, BranchName, BranchNameOwned, VersionName, VersionNameOwned},
+    VsDiff, VsMgmt, VsView,
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use vsdb_core::{
+    common::PreBytes,
+    versioned::mapx_raw::{MapxRawVs, MapxRawVsIter},
+    Registrable,
+};
+
+// A `NULL` value means 'not exist' in versioned collections.
+pub(crate) const EXISTS: &[u8] = &[1];
+
+/// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct SetxVs<K> {
+    inner: MapxRawVs,
+    _p: PhantomData<K>,
+}
+
+impl<K: KeyEnDe> SetxVs<K> {
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
+    #[inline(always)]
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            inner: MapxRawVs::new(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains_key(key.encode())
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Return `false` if the key has been there.
+    #[inline(always)]
+    pub fn insert(&mut self, key: &K) -> Result<bool> {
+        self.inner
+            .insert(key.encode(), EXISTS)
+            .c(d!())
+            .map(|old| old.is_none())
+    }
+
+    /// Return `false` if the key does not exist.
+    #[inline(always)]
+    pub fn remove(&mut self, key: &K) -> Result<bool> {
+        self.inner
+            .remove(key.encode())
+            .c(d!())
+            .map(|old| old.is_some())
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> SetxVsIter<'_, K> {
+        SetxVsIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    /// The keys in either `self` or `other`.
+    #[inline(always)]
+    pub fn union<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(SetOp::Union, self.keys(), other.keys(), decode::<K>)
+    }
+
+    /// The keys in both `self` and `other`.
+    #[inline(always)]
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(SetOp::Intersection, self.keys(), other.keys(), decode::<K>)
+    }
+
+    /// The keys in `self` but not in `other`.
+    #[inline(always)]
+    pub fn difference<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(SetOp::Difference, self.keys(), other.keys(), decode::<K>)
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    #[inline(always)]
+    pub fn contains_by_branch(&self, key: &K, br_name: BranchName) -> bool {
+        self.inner.contains_key_by_branch(key.encode(), br_name)
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch(&self, br_name: BranchName) -> usize {
+        self.inner.len_by_branch(br_name)
+    }
+
+    #[inline(always)]
+    pub fn insert_by_branch(&mut self, key: &K, br_name: BranchName) -> Result<bool> {
+        self.inner
+            .insert_by_branch(key.encode(), EXISTS, br_name)
+            .c(d!())
+            .map(|old| old.is_none())
+    }
+
+    #[inline(always)]
+    pub fn remove_by_branch(&mut self, key: &K, br_name: BranchName) -> Result<bool> {
+        self.inner
+            .remove_by_branch(key.encode(), br_name)
+            .c(d!())
+            .map(|old| old.is_some())
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch(&self, br_name: BranchName) -> SetxVsIter<'_, K> {
+        SetxVsIter {
+            inner: self.inner.iter_by_branch(br_name),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains_by_branch_version(
+        &self,
+        key: &K,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> bool {
+        self.inner
+            .contains_key_by_branch_version(key.encode(), br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> usize {
+        self.inner.len_by_branch_version(br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> SetxVsIter<'_, K> {
+        SetxVsIter {
+            inner: self.inner.iter_by_branch_version(br_name, ver_name),
+            _p: PhantomData,
+        }
+    }
+
+    fn keys(&self) -> RawKeys<'_> {
+        Box::new(self.inner.iter().map(|(k, _)| k))
+    }
+}
+
+impl<K> Clone for SetxVs<K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K> Registrable for SetxVs<K> {
+    const KIND: &'static str = "SetxVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K: KeyEnDe> Default for SetxVs<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl<K: KeyEnDe> VsMgmt for SetxVs<K> {
+    crate::impl_vs_methods!();
+}
+
+impl<K: KeyEnDe> VsDiff for SetxVs<K> {
+    /// The changed keys, `false` means that the key has been removed.
+    type Diff = Vec<(K, bool)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| K::decode(&k).c(d!()).map(|k| (k, v.is_some())))
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (k, exists) in diff.iter() {
+            if *exists {
+                self.insert(k).c(d!())?;
+            } else {
+                self.remove(k).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K: KeyEnDe> VsView for SetxVs<K> {
+    type View<'a>
+        = SetxVsView<'a, K>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(SetxVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+/// A read-only handler of [SetxVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct SetxVsView<'a, K> {
+    hdr: &'a SetxVs<K>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, K: KeyEnDe> SetxVsView<'a, K> {
+    #[inline(always)]
+    pub fn contains(&self, key: &K) -> bool {
+        self.hdr.contains_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.hdr
+            .len_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> SetxVsIter<'a, K> {
+        self.hdr
+            .iter_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+pub struct SetxVsIter<'a, K> {
+    inner: MapxRawVsIter<'a>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K: KeyEnDe> Iterator for SetxVsIter<'a, K> {
+    type Item = K;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| pnk!(K::decode(&k)))
+    }
+}
+
+impl<'a, K: KeyEnDe> DoubleEndedIterator for SetxVsIter<'a, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| pnk!(K::decode(&k)))
+    }
+}
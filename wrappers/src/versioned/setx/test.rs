@@ -0,0 +1,68 @@
+use super::*;
+use crate::{VersionName, VsDiff, VsView};
+
+#[test]
+fn test_insert_remove() {
+    let mut hdr: SetxVs<usize> = SetxVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..100).for_each(|i| {
+        assert!(pnk!(hdr.insert(&i)));
+        assert!(!pnk!(hdr.insert(&i)));
+        assert!(hdr.contains(&i));
+    });
+    assert_eq!(100, hdr.len());
+
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    (0..50).for_each(|i| {
+        assert!(pnk!(hdr.remove(&i)));
+        assert!(!pnk!(hdr.remove(&i)));
+    });
+    assert_eq!(50, hdr.len());
+
+    let v0 = pnk!(hdr.view_at(hdr.branch_get_default().as_deref(), VersionName(b"v0")));
+    assert_eq!(100, v0.len());
+    assert!(v0.contains(&0));
+    assert_eq!(100, v0.iter().count());
+
+    let diff = pnk!(hdr.diff(VersionName(b"v0"), VersionName(b"v1")));
+    assert_eq!(50, diff.len());
+    assert!(diff.iter().all(|(_, exists)| !exists));
+
+    pnk!(hdr.version_create(VersionName(b"v2")));
+    pnk!(hdr.insert(&1000));
+    let mut other: SetxVs<usize> = SetxVs::new();
+    pnk!(other.version_create(VersionName(b"v0")));
+    (0..50).for_each(|i| {
+        pnk!(other.insert(&i));
+    });
+    let diff = pnk!(hdr.diff(VersionName(b"v1"), VersionName(b"v2")));
+    pnk!(other.apply_diff(&diff));
+    assert!(other.contains(&1000));
+    assert_eq!(51, other.len());
+}
+
+#[test]
+fn test_set_ops() {
+    let mut a: SetxVs<usize> = SetxVs::new();
+    let mut b: SetxVs<usize> = SetxVs::new();
+    pnk!(a.version_create(VersionName(b"v0")));
+    pnk!(b.version_create(VersionName(b"v0")));
+    (0..20).for_each(|i| {
+        pnk!(a.insert(&i));
+    });
+    (10..30).for_each(|i| {
+        pnk!(b.insert(&i));
+    });
+
+    let mut union = a.union(&b).collect::<Vec<_>>();
+    union.sort_unstable();
+    assert_eq!(union, (0..30).collect::<Vec<_>>());
+
+    let mut intersection = a.intersection(&b).collect::<Vec<_>>();
+    intersection.sort_unstable();
+    assert_eq!(intersection, (10..20).collect::<Vec<_>>());
+
+    let mut difference = a.difference(&b).collect::<Vec<_>>();
+    difference.sort_unstable();
+    assert_eq!(difference, (0..10).collect::<Vec<_>>());
+}
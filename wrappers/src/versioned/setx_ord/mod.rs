@@ -0,0 +1,380 @@
+//!
+//! Documents => [MapxRawVs](crate::versioned::mapx_raw)
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic::setx::{decode_ordered, RawKeys, SetOp, SetxOpIter},
+    common::{
+        ende::KeyEnDeOrdered, BranchName, BranchNameOwned, VersionName,
+        VersionNameOwned,
+    },
+    versioned::setx::EXISTS,
+    VsDiff, VsMgmt, VsView,
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+use vsdb_core::{
+    common::PreBytes,
+    versioned::mapx_raw::{MapxRawVs, MapxRawVsIter},
+    Registrable,
+};
+
+/// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct SetxOrdVs<K> {
+    inner: MapxRawVs,
+    _p: PhantomData<K>,
+}
+
+impl<K: KeyEnDeOrdered> SetxOrdVs<K> {
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
+    #[inline(always)]
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            inner: MapxRawVs::new(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains_key(key.to_bytes())
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Return `false` if the key has been there.
+    #[inline(always)]
+    pub fn insert(&mut self, key: &K) -> Result<bool> {
+        self.inner
+            .insert(key.to_bytes(), EXISTS)
+            .c(d!())
+            .map(|old| old.is_none())
+    }
+
+    /// Return `false` if the key does not exist.
+    #[inline(always)]
+    pub fn remove(&mut self, key: &K) -> Result<bool> {
+        self.inner
+            .remove(key.to_bytes())
+            .c(d!())
+            .map(|old| old.is_some())
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> SetxOrdVsIter<'_, K> {
+        SetxOrdVsIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn range<R: RangeBounds<K>>(&self, bounds: R) -> SetxOrdVsIter<'_, K> {
+        let l = match bounds.start_bound() {
+            Bound::Included(lo) => Bound::Included(Cow::Owned(lo.to_bytes())),
+            Bound::Excluded(lo) => Bound::Excluded(Cow::Owned(lo.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let h = match bounds.end_bound() {
+            Bound::Included(hi) => Bound::Included(Cow::Owned(hi.to_bytes())),
+            Bound::Excluded(hi) => Bound::Excluded(Cow::Owned(hi.to_bytes())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        SetxOrdVsIter {
+            inner: self.inner.range((l, h)),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn first(&self) -> Option<K> {
+        self.iter().next()
+    }
+
+    #[inline(always)]
+    pub fn last(&self) -> Option<K> {
+        self.iter().next_back()
+    }
+
+    /// The keys in either `self` or `other`, in order.
+    #[inline(always)]
+    pub fn union<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(SetOp::Union, self.keys(), other.keys(), decode_ordered::<K>)
+    }
+
+    /// The keys in both `self` and `other`, in order.
+    #[inline(always)]
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(
+            SetOp::Intersection,
+            self.keys(),
+            other.keys(),
+            decode_ordered::<K>,
+        )
+    }
+
+    /// The keys in `self` but not in `other`, in order.
+    #[inline(always)]
+    pub fn difference<'a>(&'a self, other: &'a Self) -> SetxOpIter<'a, K> {
+        SetxOpIter::new(
+            SetOp::Difference,
+            self.keys(),
+            other.keys(),
+            decode_ordered::<K>,
+        )
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    #[inline(always)]
+    pub fn contains_by_branch(&self, key: &K, br_name: BranchName) -> bool {
+        self.inner.contains_key_by_branch(key.to_bytes(), br_name)
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch(&self, br_name: BranchName) -> usize {
+        self.inner.len_by_branch(br_name)
+    }
+
+    #[inline(always)]
+    pub fn insert_by_branch(&mut self, key: &K, br_name: BranchName) -> Result<bool> {
+        self.inner
+            .insert_by_branch(key.to_bytes(), EXISTS, br_name)
+            .c(d!())
+            .map(|old| old.is_none())
+    }
+
+    #[inline(always)]
+    pub fn remove_by_branch(&mut self, key: &K, br_name: BranchName) -> Result<bool> {
+        self.inner
+            .remove_by_branch(key.to_bytes(), br_name)
+            .c(d!())
+            .map(|old| old.is_some())
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch(&self, br_name: BranchName) -> SetxOrdVsIter<'_, K> {
+        SetxOrdVsIter {
+            inner: self.inner.iter_by_branch(br_name),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains_by_branch_version(
+        &self,
+        key: &K,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> bool {
+        self.inner
+            .contains_key_by_branch_version(key.to_bytes(), br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> usize {
+        self.inner.len_by_branch_version(br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> SetxOrdVsIter<'_, K> {
+        SetxOrdVsIter {
+            inner: self.inner.iter_by_branch_version(br_name, ver_name),
+            _p: PhantomData,
+        }
+    }
+
+    fn keys(&self) -> RawKeys<'_> {
+        Box::new(self.inner.iter().map(|(k, _)| k))
+    }
+}
+
+impl<K> Clone for SetxOrdVs<K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K> Registrable for SetxOrdVs<K> {
+    const KIND: &'static str = "SetxOrdVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K: KeyEnDeOrdered> Default for SetxOrdVs<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl<K: KeyEnDeOrdered> VsMgmt for SetxOrdVs<K> {
+    crate::impl_vs_methods!();
+}
+
+impl<K: KeyEnDeOrdered> VsDiff for SetxOrdVs<K> {
+    /// The changed keys, `false` means that the key has been removed.
+    type Diff = Vec<(K, bool)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| K::from_slice(&k).c(d!()).map(|k| (k, v.is_some())))
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (k, exists) in diff.iter() {
+            if *exists {
+                self.insert(k).c(d!())?;
+            } else {
+                self.remove(k).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K: KeyEnDeOrdered> VsView for SetxOrdVs<K> {
+    type View<'a>
+        = SetxOrdVsView<'a, K>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(SetxOrdVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+/// A read-only handler of [SetxOrdVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct SetxOrdVsView<'a, K> {
+    hdr: &'a SetxOrdVs<K>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, K: KeyEnDeOrdered> SetxOrdVsView<'a, K> {
+    #[inline(always)]
+    pub fn contains(&self, key: &K) -> bool {
+        self.hdr.contains_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.hdr
+            .len_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> SetxOrdVsIter<'a, K> {
+        self.hdr
+            .iter_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+pub struct SetxOrdVsIter<'a, K> {
+    inner: MapxRawVsIter<'a>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K: KeyEnDeOrdered> Iterator for SetxOrdVsIter<'a, K> {
+    type Item = K;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| pnk!(K::from_slice(&k)))
+    }
+}
+
+impl<'a, K: KeyEnDeOrdered> DoubleEndedIterator for SetxOrdVsIter<'a, K> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(k, _)| pnk!(K::from_slice(&k)))
+    }
+}
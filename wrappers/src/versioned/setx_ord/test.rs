@@ -0,0 +1,42 @@
+use super::*;
+use crate::VersionName;
+
+#[test]
+fn test_ordered() {
+    let mut hdr: SetxOrdVs<usize> = SetxOrdVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..100).rev().for_each(|i| {
+        assert!(pnk!(hdr.insert(&(i * 2))));
+    });
+    assert_eq!(
+        hdr.iter().collect::<Vec<_>>(),
+        (0..100).map(|i| i * 2).collect::<Vec<_>>()
+    );
+    assert_eq!(hdr.range(3..9).collect::<Vec<_>>(), vec![4, 6, 8]);
+    assert_eq!(Some(0), hdr.first());
+    assert_eq!(Some(198), hdr.last());
+
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    assert!(pnk!(hdr.remove(&0)));
+    assert_eq!(Some(2), hdr.first());
+    assert!(hdr.contains_by_branch_version(
+        &0,
+        hdr.branch_get_default().as_deref(),
+        VersionName(b"v0")
+    ));
+
+    let mut other: SetxOrdVs<usize> = SetxOrdVs::new();
+    pnk!(other.version_create(VersionName(b"v0")));
+    (0..10).for_each(|i| {
+        pnk!(other.insert(&i));
+    });
+    assert_eq!(
+        hdr.intersection(&other).collect::<Vec<_>>(),
+        vec![2, 4, 6, 8]
+    );
+    assert_eq!(
+        other.difference(&hdr).collect::<Vec<_>>(),
+        vec![0, 1, 3, 5, 7, 9]
+    );
+    assert_eq!(105, hdr.union(&other).count());
+}
@@ -6,23 +6,33 @@
 mod test;
 
 use crate::{
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::ValueEnDe,
+        migrate::{Migrate, Migration},
+    },
     versioned::mapx_ord_rawkey::{
         MapxOrdRawKeyVs, MapxOrdRawKeyVsIter, MapxOrdRawKeyVsIterMut, ValueIterMut,
     },
-    BranchName, ValueEnDe, VersionName, VsMgmt,
+    BranchName, BranchNameOwned, VersionName, VersionNameOwned, VsDiff, VsMgmt,
+    VsView,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
-use std::ops::{Deref, DerefMut};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+use vsdb_core::{common::PreBytes, Registrable};
 
 /// Documents => [MapxRawVs](crate::versioned::mapx_raw::MapxRawVs)
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
-pub struct VecxVs<T> {
-    inner: MapxOrdRawKeyVs<T>,
+pub struct VecxVs<T, C = DefaultCodec> {
+    inner: MapxOrdRawKeyVs<T, C>,
 }
 
-impl<T: ValueEnDe> VecxVs<T> {
+impl<T, C: Codec<T>> VecxVs<T, C> {
     /// # Safety
     ///
     /// This API breaks the semantic safety guarantees,
@@ -34,10 +44,18 @@ impl<T: ValueEnDe> VecxVs<T> {
         }
     }
 
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
     #[inline(always)]
-    pub fn new() -> Self {
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self { inner })
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
         VecxVs {
-            inner: MapxOrdRawKeyVs::new(),
+            inner: MapxOrdRawKeyVs::with_codec(),
         }
     }
 
@@ -47,7 +65,7 @@ impl<T: ValueEnDe> VecxVs<T> {
     }
 
     #[inline(always)]
-    pub fn get_mut(&mut self, idx: usize) -> Option<ValueMut<'_, T>> {
+    pub fn get_mut(&mut self, idx: usize) -> Option<ValueMut<'_, T, C>> {
         self.get(idx)
             .map(|v| ValueMut::new(&mut self.inner, idx, v))
     }
@@ -95,14 +113,14 @@ impl<T: ValueEnDe> VecxVs<T> {
     }
 
     #[inline(always)]
-    pub fn iter(&self) -> VecxVsIter<'_, T> {
+    pub fn iter(&self) -> VecxVsIter<'_, T, C> {
         VecxVsIter {
             inner: self.inner.iter(),
         }
     }
 
     #[inline(always)]
-    pub fn iter_mut(&mut self) -> VecxVsIterMut<'_, T> {
+    pub fn iter_mut(&mut self) -> VecxVsIterMut<'_, T, C> {
         VecxVsIterMut {
             inner: self.inner.iter_mut(),
         }
@@ -171,7 +189,7 @@ impl<T: ValueEnDe> VecxVs<T> {
     }
 
     #[inline(always)]
-    pub fn iter_by_branch(&self, br_name: BranchName) -> VecxVsIter<'_, T> {
+    pub fn iter_by_branch(&self, br_name: BranchName) -> VecxVsIter<'_, T, C> {
         VecxVsIter {
             inner: self.inner.iter_by_branch(br_name),
         }
@@ -229,7 +247,7 @@ impl<T: ValueEnDe> VecxVs<T> {
         &self,
         br_name: BranchName,
         ver_name: VersionName,
-    ) -> VecxVsIter<'_, T> {
+    ) -> VecxVsIter<'_, T, C> {
         VecxVsIter {
             inner: self.inner.iter_by_branch_version(br_name, ver_name),
         }
@@ -239,7 +257,19 @@ impl<T: ValueEnDe> VecxVs<T> {
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<T> Clone for VecxVs<T> {
+impl<T> VecxVs<T>
+where
+    T: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<T, C> Clone for VecxVs<T, C> {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
@@ -247,34 +277,160 @@ impl<T> Clone for VecxVs<T> {
     }
 }
 
-impl<T: ValueEnDe> Default for VecxVs<T> {
+impl<T, C> Registrable for VecxVs<T, C> {
+    const KIND: &'static str = "VecxVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<Old, New, C> Migrate<Old, New> for VecxVs<Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = VecxVs<New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((Self::Output { inner }, m))
+    }
+}
+
+impl<T, C: Codec<T>> Default for VecxVs<T, C> {
     fn default() -> Self {
-        Self::new()
+        Self::with_codec()
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-impl<T: ValueEnDe> VsMgmt for VecxVs<T> {
+impl<T, C: Codec<T>> VsMgmt for VecxVs<T, C> {
     crate::impl_vs_methods!();
 }
 
+impl<T, C: Codec<T>> VsDiff for VecxVs<T, C> {
+    /// The changed indexes, `None` means that the index is out of range.
+    type Diff = Vec<(usize, Option<T>)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| {
+                <[u8; 8]>::try_from(&k[..])
+                    .c(d!())
+                    .map(|idx| (u64::from_be_bytes(idx) as usize, v))
+            })
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (idx, v) in diff.iter() {
+            let idx = (*idx as u64).to_be_bytes();
+            if let Some(v) = v {
+                self.inner.insert(&idx, v).c(d!())?;
+            } else {
+                self.inner.remove(&idx).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T, C: Codec<T>> VsView for VecxVs<T, C> {
+    type View<'a>
+        = VecxVsView<'a, T, C>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(VecxVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+/// A read-only handler of [VecxVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct VecxVsView<'a, T, C = DefaultCodec> {
+    hdr: &'a VecxVs<T, C>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, T, C: Codec<T>> VecxVsView<'a, T, C> {
+    #[inline(always)]
+    pub fn get(&self, idx: usize) -> Option<T> {
+        self.hdr.get_by_branch_version(
+            idx,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn last(&self) -> Option<T> {
+        alt!(self.is_empty(), return None);
+        self.get(self.len() - 1)
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.hdr
+            .len_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.hdr.is_empty_by_branch_version(
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> VecxVsIter<'a, T, C> {
+        self.hdr
+            .iter_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct VecxVsIter<'a, T: ValueEnDe> {
-    inner: MapxOrdRawKeyVsIter<'a, T>,
+pub struct VecxVsIter<'a, T, C: Codec<T> = DefaultCodec> {
+    inner: MapxOrdRawKeyVsIter<'a, T, C>,
 }
 
-impl<'a, T: ValueEnDe> Iterator for VecxVsIter<'a, T> {
+impl<'a, T, C: Codec<T>> Iterator for VecxVsIter<'a, T, C> {
     type Item = T;
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|v| v.1)
     }
 }
 
-impl<'a, T: ValueEnDe> DoubleEndedIterator for VecxVsIter<'a, T> {
+impl<'a, T, C: Codec<T>> DoubleEndedIterator for VecxVsIter<'a, T, C> {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|v| v.1)
     }
@@ -284,17 +440,17 @@ impl<'a, T: ValueEnDe> DoubleEndedIterator for VecxVsIter<'a, T> {
 ////////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Debug)]
-pub struct ValueMut<'a, V: ValueEnDe> {
-    hdr: &'a mut MapxOrdRawKeyVs<V>,
+pub struct ValueMut<'a, V, C: Codec<V> = DefaultCodec> {
+    hdr: &'a mut MapxOrdRawKeyVs<V, C>,
     key: u64,
     value: V,
 }
 
-impl<'a, V> ValueMut<'a, V>
+impl<'a, V, C> ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
-    fn new(hdr: &'a mut MapxOrdRawKeyVs<V>, key: usize, value: V) -> Self {
+    fn new(hdr: &'a mut MapxOrdRawKeyVs<V, C>, key: usize, value: V) -> Self {
         ValueMut {
             hdr,
             key: key as u64,
@@ -303,18 +459,18 @@ where
     }
 }
 
-impl<'a, V> Drop for ValueMut<'a, V>
+impl<'a, V, C> Drop for ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn drop(&mut self) {
         pnk!(self.hdr.insert(&self.key.to_be_bytes(), &self.value));
     }
 }
 
-impl<'a, V> Deref for ValueMut<'a, V>
+impl<'a, V, C> Deref for ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     type Target = V;
     fn deref(&self) -> &Self::Target {
@@ -322,9 +478,9 @@ where
     }
 }
 
-impl<'a, V> DerefMut for ValueMut<'a, V>
+impl<'a, V, C> DerefMut for ValueMut<'a, V, C>
 where
-    V: ValueEnDe,
+    C: Codec<V>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.value
@@ -334,27 +490,27 @@ where
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
-pub struct VecxVsIterMut<'a, T>
+pub struct VecxVsIterMut<'a, T, C = DefaultCodec>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
-    inner: MapxOrdRawKeyVsIterMut<'a, T>,
+    inner: MapxOrdRawKeyVsIterMut<'a, T, C>,
 }
 
-impl<'a, T> Iterator for VecxVsIterMut<'a, T>
+impl<'a, T, C> Iterator for VecxVsIterMut<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
-    type Item = ValueIterMut<'a, T>;
+    type Item = ValueIterMut<'a, T, C>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|(_, v)| v)
     }
 }
 
-impl<'a, T> DoubleEndedIterator for VecxVsIterMut<'a, T>
+impl<'a, T, C> DoubleEndedIterator for VecxVsIterMut<'a, T, C>
 where
-    T: ValueEnDe,
+    C: Codec<T>,
 {
     fn next_back(&mut self) -> Option<Self::Item> {
         self.inner.next_back().map(|(_, v)| v)
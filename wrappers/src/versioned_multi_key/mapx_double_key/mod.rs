@@ -7,7 +7,7 @@ use crate::{
         RawValue,
     },
     versioned_multi_key::mapx_raw::MapxRawMkVs,
-    BranchName, VersionName, VsMgmt,
+    BranchName, VersionName, VsDiff, VsMgmt,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
@@ -15,6 +15,7 @@ use std::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
 };
+use vsdb_core::{common::PreBytes, Registrable};
 
 const KEY_SIZE: usize = 2;
 
@@ -336,6 +337,15 @@ impl<K1, K2, V> Clone for MapxDkVs<K1, K2, V> {
     }
 }
 
+impl<K1, K2, V> Registrable for MapxDkVs<K1, K2, V> {
+    const KIND: &'static str = "MapxDkVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
 impl<K1, K2, V> Default for MapxDkVs<K1, K2, V>
 where
     K1: KeyEnDe,
@@ -356,6 +366,43 @@ where
     crate::impl_vs_methods!();
 }
 
+impl<K1, K2, V> VsDiff for MapxDkVs<K1, K2, V>
+where
+    K1: KeyEnDe,
+    K2: KeyEnDe,
+    V: ValueEnDe,
+{
+    type Diff = Vec<((K1, K2), Option<V>)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| {
+                if KEY_SIZE != k.len() {
+                    return Err(eg!("key size mismatch"));
+                }
+                let k1 = KeyEnDe::decode(&k[0]).c(d!())?;
+                let k2 = KeyEnDe::decode(&k[1]).c(d!())?;
+                let v = v.map(|v| ValueEnDe::decode(&v)).transpose().c(d!())?;
+                Ok(((k1, k2), v))
+            })
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (k, v) in diff.iter() {
+            if let Some(v) = v {
+                self.insert(&(&k.0, &k.1), v).c(d!())?;
+            } else {
+                self.remove(&(&k.0, Some(&k.1))).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct ValueMut<'a, K1, K2, V>
 where
@@ -375,3 +375,33 @@ fn test_prune() {
     assert!(!hdr.version_exists(VersionName(b"b")));
     assert!(hdr.version_exists(VersionName(b"c")));
 }
+
+#[test]
+fn test_diff() {
+    let mut hdr: MapxDkVs<usize, usize, usize> = MapxDkVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.insert(&(&1, &1), &1));
+    pnk!(hdr.insert(&(&1, &2), &2));
+    pnk!(hdr.insert(&(&2, &1), &2));
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    pnk!(hdr.insert(&(&1, &1), &10));
+    pnk!(hdr.remove(&(&1, Some(&2))));
+    pnk!(hdr.insert(&(&3, &1), &3));
+    pnk!(hdr.insert(&(&4, &1), &4));
+    pnk!(hdr.remove(&(&4, Some(&1))));
+
+    let diff = pnk!(hdr.diff(VersionName(b"v0"), VersionName(b"v1")));
+    assert_eq!(
+        diff,
+        vec![((1, 1), Some(10)), ((1, 2), None), ((3, 1), Some(3))]
+    );
+    assert!(pnk!(hdr.diff(VersionName(b"v1"), VersionName(b"v1"))).is_empty());
+
+    let mut other: MapxDkVs<usize, usize, usize> = MapxDkVs::new();
+    pnk!(other.version_create(VersionName(b"v0")));
+    pnk!(other.insert(&(&1, &2), &2));
+    pnk!(other.apply_diff(&diff));
+    assert_eq!(other.get(&(&1, &1)), Some(10));
+    assert!(!other.contains_key(&(&1, &2)));
+    assert_eq!(other.get(&(&3, &1)), Some(3));
+}
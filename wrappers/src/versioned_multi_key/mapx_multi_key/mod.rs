@@ -0,0 +1,495 @@
+//!
+//! Documents => [MapxMk](crate::basic_multi_key::mapx_multi_key::MapxMk)
+//!
+
+#[cfg(test)]
+mod test;
+
+use crate::{
+    basic_multi_key::mapx_multi_key::{MultiKey, MultiKeyPrefix},
+    common::{
+        codec::{Codec, DefaultCodec},
+        ende::ValueEnDe,
+        migrate::{Migrate, Migration},
+    },
+    versioned::mapx_ord_rawkey::{MapxOrdRawKeyVs, MapxOrdRawKeyVsIter},
+    BranchName, BranchNameOwned, VersionName, VersionNameOwned, VsDiff, VsMgmt,
+    VsView,
+};
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{marker::PhantomData, sync::Arc};
+use vsdb_core::{common::PreBytes, Registrable};
+
+/// A versioned map structure with keys of any arity.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct MapxMkVs<K, V, C = DefaultCodec> {
+    inner: MapxOrdRawKeyVs<V, C>,
+    _p: PhantomData<K>,
+}
+
+impl<K, V, C> MapxMkVs<K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    /// # Safety
+    ///
+    /// This API breaks the semantic safety guarantees,
+    /// but it is safe to use in a race-free environment.
+    #[inline(always)]
+    pub unsafe fn shadow(&self) -> Self {
+        Self {
+            inner: self.inner.shadow(),
+            _p: PhantomData,
+        }
+    }
+
+    /// A cheap copy-on-write copy,
+    /// see [MapxRawVs::fork](crate::versioned::mapx_raw::MapxRawVs::fork).
+    #[inline(always)]
+    pub fn fork(&self) -> Result<Self> {
+        self.inner.fork().c(d!()).map(|inner| Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    /// Create an instance that encodes values by `C`.
+    #[inline(always)]
+    pub fn with_codec() -> Self {
+        Self {
+            inner: MapxOrdRawKeyVs::with_codec(),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn key_size(&self) -> u32 {
+        K::KEY_SIZE
+    }
+
+    #[inline(always)]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.inner.get(&key.encode_keys())
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(&key.encode_keys())
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn insert(&mut self, key: &K, value: &V) -> Result<Option<V>> {
+        self.inner.insert(&key.encode_keys(), value).c(d!())
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, key: &K) -> Result<Option<V>> {
+        self.inner.remove(&key.encode_keys()).c(d!())
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> MapxMkVsIter<'_, K, V, C> {
+        MapxMkVsIter {
+            inner: self.inner.iter(),
+            _p: PhantomData,
+        }
+    }
+
+    /// Iterate over the entries whose leading keys are equal to `prefix`.
+    #[inline(always)]
+    pub fn iter_prefix<P: MultiKeyPrefix<K>>(
+        &self,
+        prefix: &P,
+    ) -> MapxMkVsIter<'_, K, V, C> {
+        MapxMkVsIter {
+            inner: self.inner.iter_prefix(&prefix.encode_keys()),
+            _p: PhantomData,
+        }
+    }
+
+    /// Remove the entries whose leading keys are equal to `prefix`,
+    /// return the number of them.
+    #[inline(always)]
+    pub fn remove_prefix<P: MultiKeyPrefix<K>>(&mut self, prefix: &P) -> Result<usize> {
+        self.inner.remove_prefix(&prefix.encode_keys()).c(d!())
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    #[inline(always)]
+    pub fn get_by_branch(&self, key: &K, br_name: BranchName) -> Option<V> {
+        self.inner.get_by_branch(&key.encode_keys(), br_name)
+    }
+
+    #[inline(always)]
+    pub fn contains_key_by_branch(&self, key: &K, br_name: BranchName) -> bool {
+        self.inner
+            .contains_key_by_branch(&key.encode_keys(), br_name)
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch(&self, br_name: BranchName) -> usize {
+        self.inner.len_by_branch(br_name)
+    }
+
+    #[inline(always)]
+    pub fn insert_by_branch(
+        &mut self,
+        key: &K,
+        value: &V,
+        br_name: BranchName,
+    ) -> Result<Option<V>> {
+        self.inner
+            .insert_by_branch(&key.encode_keys(), value, br_name)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub fn remove_by_branch(
+        &mut self,
+        key: &K,
+        br_name: BranchName,
+    ) -> Result<Option<V>> {
+        self.inner
+            .remove_by_branch(&key.encode_keys(), br_name)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch(&self, br_name: BranchName) -> MapxMkVsIter<'_, K, V, C> {
+        MapxMkVsIter {
+            inner: self.inner.iter_by_branch(br_name),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn iter_prefix_by_branch<P: MultiKeyPrefix<K>>(
+        &self,
+        prefix: &P,
+        br_name: BranchName,
+    ) -> MapxMkVsIter<'_, K, V, C> {
+        MapxMkVsIter {
+            inner: self
+                .inner
+                .iter_prefix_by_branch(&prefix.encode_keys(), br_name),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn remove_prefix_by_branch<P: MultiKeyPrefix<K>>(
+        &mut self,
+        prefix: &P,
+        br_name: BranchName,
+    ) -> Result<usize> {
+        self.inner
+            .remove_prefix_by_branch(&prefix.encode_keys(), br_name)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub fn get_by_branch_version(
+        &self,
+        key: &K,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Option<V> {
+        self.inner
+            .get_by_branch_version(&key.encode_keys(), br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn contains_key_by_branch_version(
+        &self,
+        key: &K,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> bool {
+        self.inner
+            .contains_key_by_branch_version(&key.encode_keys(), br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn len_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> usize {
+        self.inner.len_by_branch_version(br_name, ver_name)
+    }
+
+    #[inline(always)]
+    pub fn iter_by_branch_version(
+        &self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> MapxMkVsIter<'_, K, V, C> {
+        MapxMkVsIter {
+            inner: self.inner.iter_by_branch_version(br_name, ver_name),
+            _p: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn iter_prefix_by_branch_version<P: MultiKeyPrefix<K>>(
+        &self,
+        prefix: &P,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> MapxMkVsIter<'_, K, V, C> {
+        MapxMkVsIter {
+            inner: self.inner.iter_prefix_by_branch_version(
+                &prefix.encode_keys(),
+                br_name,
+                ver_name,
+            ),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K, V> MapxMkVs<K, V>
+where
+    K: MultiKey,
+    V: ValueEnDe,
+{
+    /// Create an instance that encodes values by the [DefaultCodec],
+    /// use [with_codec](Self::with_codec) for other codecs.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::with_codec()
+    }
+}
+
+impl<K, V, C> Clone for MapxMkVs<K, V, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<K, V, C> Registrable for MapxMkVs<K, V, C> {
+    const KIND: &'static str = "MapxMkVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl<K, Old, New, C> Migrate<Old, New> for MapxMkVs<K, Old, C>
+where
+    Old: 'static,
+    New: 'static,
+    C: Codec<Old> + Codec<New>,
+{
+    type Output = MapxMkVs<K, New, C>;
+
+    fn begin_migration(
+        self,
+        f: Arc<dyn Fn(Old) -> New + Send + Sync>,
+    ) -> Result<(Self::Output, Migration)> {
+        let (inner, m) = self.inner.begin_migration(f).c(d!())?;
+        Ok((
+            Self::Output {
+                inner,
+                _p: PhantomData,
+            },
+            m,
+        ))
+    }
+}
+
+impl<K, V, C> Default for MapxMkVs<K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    fn default() -> Self {
+        Self::with_codec()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+impl<K, V, C> VsMgmt for MapxMkVs<K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    crate::impl_vs_methods!();
+}
+
+impl<K, V, C> VsDiff for MapxMkVs<K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    type Diff = Vec<(K, Option<V>)>;
+
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        self.inner
+            .diff(ver_a, ver_b)
+            .c(d!())?
+            .into_iter()
+            .map(|(k, v)| K::decode_keys(&k).c(d!()).map(|k| (k, v)))
+            .collect()
+    }
+
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        for (k, v) in diff.iter() {
+            if let Some(v) = v {
+                self.insert(k, v).c(d!())?;
+            } else {
+                self.remove(k).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K, V, C> VsView for MapxMkVs<K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    type View<'a>
+        = MapxMkVsView<'a, K, V, C>
+    where
+        Self: 'a;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(MapxMkVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+/// A read-only handler of [MapxMkVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct MapxMkVsView<'a, K, V, C = DefaultCodec> {
+    hdr: &'a MapxMkVs<K, V, C>,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a, K, V, C> MapxMkVsView<'a, K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    #[inline(always)]
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.hdr.get_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.hdr.contains_key_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.hdr
+            .len_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        0 == self.len()
+    }
+
+    #[inline(always)]
+    pub fn iter(&self) -> MapxMkVsIter<'a, K, V, C> {
+        self.hdr
+            .iter_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn iter_prefix<P: MultiKeyPrefix<K>>(
+        &self,
+        prefix: &P,
+    ) -> MapxMkVsIter<'a, K, V, C> {
+        self.hdr.iter_prefix_by_branch_version(
+            prefix,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////
+
+pub struct MapxMkVsIter<'a, K, V, C = DefaultCodec>
+where
+    C: Codec<V>,
+{
+    inner: MapxOrdRawKeyVsIter<'a, V, C>,
+    _p: PhantomData<K>,
+}
+
+impl<'a, K, V, C> Iterator for MapxMkVsIter<'a, K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(k, v)| (pnk!(K::decode_keys(&k)), v))
+    }
+}
+
+impl<'a, K, V, C> DoubleEndedIterator for MapxMkVsIter<'a, K, V, C>
+where
+    K: MultiKey,
+    C: Codec<V>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|(k, v)| (pnk!(K::decode_keys(&k)), v))
+    }
+}
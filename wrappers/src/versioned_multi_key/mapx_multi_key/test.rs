@@ -0,0 +1,68 @@
+use super::*;
+use crate::{VersionName, VsDiff, VsView};
+
+#[test]
+fn test_insert_remove() {
+    let mut hdr: MapxMkVs<(u32, u32), u32> = MapxMkVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..10u32).for_each(|i| {
+        (0..10u32).for_each(|j| {
+            assert!(pnk!(hdr.insert(&(i, j), &(i * j))).is_none());
+        });
+    });
+    assert_eq!(100, hdr.len());
+    assert_eq!(Some(6), hdr.get(&(2, 3)));
+
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    assert_eq!(Some(6), pnk!(hdr.remove(&(2, 3))));
+    assert_eq!(10, pnk!(hdr.remove_prefix(&(5u32,))));
+    assert_eq!(0, hdr.iter_prefix(&(5u32,)).count());
+    assert_eq!(89, hdr.len());
+
+    let v0 = pnk!(hdr.view_at(hdr.branch_get_default().as_deref(), VersionName(b"v0")));
+    assert_eq!(100, v0.len());
+    assert_eq!(Some(6), v0.get(&(2, 3)));
+    assert_eq!(10, v0.iter_prefix(&(5u32,)).count());
+
+    let diff = pnk!(hdr.diff(VersionName(b"v0"), VersionName(b"v1")));
+    assert_eq!(11, diff.len());
+    assert!(diff.iter().all(|(_, v)| v.is_none()));
+
+    let mut other: MapxMkVs<(u32, u32), u32> = MapxMkVs::new();
+    pnk!(other.version_create(VersionName(b"v0")));
+    pnk!(other.insert(&(2, 3), &6));
+    pnk!(other.insert(&(5, 0), &0));
+    pnk!(other.insert(&(6, 0), &0));
+    pnk!(other.apply_diff(&diff));
+    assert_eq!(other.iter().collect::<Vec<_>>(), vec![((6, 0), 0)]);
+}
+
+#[test]
+fn test_branch() {
+    let mut hdr: MapxMkVs<(u32, u32, u32), u32> = MapxMkVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..3u32).for_each(|i| {
+        pnk!(hdr.insert(&(1, 1, i), &i));
+        pnk!(hdr.insert(&(1, 2, i), &i));
+    });
+
+    pnk!(hdr.branch_create(BranchName(b"b"), VersionName(b"b0"), false));
+    assert_eq!(
+        3,
+        pnk!(hdr.remove_prefix_by_branch(&(1u32, 1u32), BranchName(b"b")))
+    );
+    pnk!(hdr.insert_by_branch(&(2, 0, 0), &9, BranchName(b"b")));
+
+    assert_eq!(6, hdr.iter_prefix(&(1u32,)).count());
+    assert_eq!(
+        3,
+        hdr.iter_prefix_by_branch(&(1u32,), BranchName(b"b"))
+            .count()
+    );
+    assert_eq!(Some(9), hdr.get_by_branch(&(2, 0, 0), BranchName(b"b")));
+    assert!(!hdr.contains_key(&(2, 0, 0)));
+    assert_eq!(4, hdr.len_by_branch(BranchName(b"b")));
+
+    let forked = pnk!(hdr.fork());
+    assert_eq!(6, forked.len());
+}
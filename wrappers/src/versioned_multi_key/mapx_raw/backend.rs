@@ -2,8 +2,8 @@ use crate::{
     basic::{mapx_ord::MapxOrd, mapx_ord_rawkey::MapxOrdRawKey},
     basic_multi_key::mapx_raw::MapxRawMk,
     common::{
-        trie_root, BranchID, BranchIDBase, BranchName, BranchNameOwned, RawKey,
-        RawValue, VersionID, VersionIDBase, VersionName, VersionNameOwned,
+        trie_root, BranchID, BranchIDBase, BranchInfo, BranchName, BranchNameOwned, PreBytes,
+        RawKey, RawValue, Registrable, VersionID, VersionIDBase, VersionName, VersionNameOwned,
         INITIAL_BRANCH_ID, INITIAL_BRANCH_NAME, NULL, RESERVED_VERSION_NUM_DEFAULT,
         TRASH_CLEANER, VER_ID_MAX, VSDB,
     },
@@ -13,10 +13,13 @@ use ruc::*;
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::Ordering,
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    ops::Bound,
     result::Result as StdResult,
     sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
 };
+use vsdb_core::{vsdb_get_branch_creator, PruneProgress, PrunePolicy, VersionInfo};
 
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
@@ -30,11 +33,32 @@ pub(super) struct MapxRawMkVs {
     br_name_to_br_id: MapxOrdRawKey<BranchID>,
     ver_name_to_ver_id: MapxOrdRawKey<VersionID>,
     br_to_its_vers: MapxOrd<BranchID, MapxOrd<VersionID, ()>>,
+    br_to_parent: MapxOrd<BranchID, BranchID>,
+    br_to_info: MapxOrd<BranchID, BranchInfo>,
+    ver_to_meta: MapxOrd<VersionID, (u64, u64)>,
     ver_to_change_set: MapxOrd<VersionID, MapxRawMk>,
 
     br_id_to_br_name: Arc<RwLock<HashMap<BranchID, RawValue>>>,
     ver_id_to_ver_name: Arc<RwLock<HashMap<VersionID, RawValue>>>,
     layered_kv: Arc<RwLock<BTreeMap<Vec<RawKey>, BTreeMap<VersionID, RawValue>>>>,
+
+    // the unfinished pruning of `prune_step`
+    pending_prune: Option<PrunePlan>,
+}
+
+// The versions to be cleaned by a pruning,
+// found out once and consumed by the following steps.
+#[derive(Debug, Default)]
+struct PrunePlan {
+    reserved_ver_num: Option<usize>,
+    // versions that are not on any branch
+    orphans: VecDeque<VersionID>,
+    // the oldest shared version, the merged versions are merged into it
+    rewrite_ver: Option<VersionID>,
+    // the versions to be merged, in ascending order
+    merges: VecDeque<VersionID>,
+    // the oldest version to be reserved
+    first_reserved: Option<VersionID>,
 }
 
 impl Clone for MapxRawMkVs {
@@ -46,11 +70,15 @@ impl Clone for MapxRawMkVs {
             br_name_to_br_id: self.br_name_to_br_id.clone(),
             ver_name_to_ver_id: self.ver_name_to_ver_id.clone(),
             br_to_its_vers: self.br_to_its_vers.clone(),
+            br_to_parent: self.br_to_parent.clone(),
+            br_to_info: self.br_to_info.clone(),
+            ver_to_meta: self.ver_to_meta.clone(),
             ver_to_change_set: self.ver_to_change_set.clone(),
 
             br_id_to_br_name: self.br_id_to_br_name.clone(),
             ver_id_to_ver_name: self.ver_id_to_ver_name.clone(),
             layered_kv: Arc::new(RwLock::new(self.layered_kv.read().clone())),
+            pending_prune: None,
         }
     }
 }
@@ -82,6 +110,9 @@ pub(super) struct MapxRawMkVsWithoutDerivedFields {
     br_name_to_br_id: MapxOrdRawKey<BranchID>,
     ver_name_to_ver_id: MapxOrdRawKey<VersionID>,
     br_to_its_vers: MapxOrd<BranchID, MapxOrd<VersionID, ()>>,
+    br_to_parent: MapxOrd<BranchID, BranchID>,
+    br_to_info: MapxOrd<BranchID, BranchInfo>,
+    ver_to_meta: MapxOrd<VersionID, (u64, u64)>,
     ver_to_change_set: MapxOrd<VersionID, MapxRawMk>,
 }
 
@@ -118,11 +149,15 @@ impl From<MapxRawMkVsWithoutDerivedFields> for MapxRawMkVs {
             br_name_to_br_id: m.br_name_to_br_id,
             ver_name_to_ver_id: m.ver_name_to_ver_id,
             br_to_its_vers: m.br_to_its_vers,
+            br_to_parent: m.br_to_parent,
+            br_to_info: m.br_to_info,
+            ver_to_meta: m.ver_to_meta,
             ver_to_change_set: m.ver_to_change_set,
 
             br_id_to_br_name: Arc::new(RwLock::new(br_id_to_br_name)),
             ver_id_to_ver_name: Arc::new(RwLock::new(ver_id_to_ver_name)),
             layered_kv: Arc::new(RwLock::new(layered_kv)),
+            pending_prune: None,
         }
     }
 }
@@ -137,6 +172,9 @@ impl From<&MapxRawMkVs> for MapxRawMkVsWithoutDerivedFields {
                 br_name_to_br_id: m.br_name_to_br_id.shadow(),
                 ver_name_to_ver_id: m.ver_name_to_ver_id.shadow(),
                 br_to_its_vers: m.br_to_its_vers.shadow(),
+                br_to_parent: m.br_to_parent.shadow(),
+                br_to_info: m.br_to_info.shadow(),
+                ver_to_meta: m.ver_to_meta.shadow(),
                 ver_to_change_set: m.ver_to_change_set.shadow(),
             }
         }
@@ -156,14 +194,23 @@ impl MapxRawMkVs {
             br_name_to_br_id: self.br_name_to_br_id.shadow(),
             ver_name_to_ver_id: self.ver_name_to_ver_id.shadow(),
             br_to_its_vers: self.br_to_its_vers.shadow(),
+            br_to_parent: self.br_to_parent.shadow(),
+            br_to_info: self.br_to_info.shadow(),
+            ver_to_meta: self.ver_to_meta.shadow(),
             ver_to_change_set: self.ver_to_change_set.shadow(),
 
             br_id_to_br_name: Arc::clone(&self.br_id_to_br_name),
             ver_id_to_ver_name: Arc::clone(&self.ver_id_to_ver_name),
             layered_kv: Arc::clone(&self.layered_kv),
+            pending_prune: None,
         }
     }
 
+    #[inline(always)]
+    pub(super) fn instance_prefix(&self) -> PreBytes {
+        self.ver_to_change_set.instance_prefix()
+    }
+
     #[inline(always)]
     pub(super) fn new(key_size: u32) -> Self {
         let mut ret = Self {
@@ -173,11 +220,15 @@ impl MapxRawMkVs {
             br_name_to_br_id: MapxOrdRawKey::new(),
             ver_name_to_ver_id: MapxOrdRawKey::new(),
             br_to_its_vers: MapxOrd::new(),
+            br_to_parent: MapxOrd::new(),
+            br_to_info: MapxOrd::new(),
+            ver_to_meta: MapxOrd::new(),
             ver_to_change_set: MapxOrd::new(),
 
             br_id_to_br_name: Arc::new(RwLock::new(Default::default())),
             ver_id_to_ver_name: Arc::new(RwLock::new(Default::default())),
             layered_kv: Arc::new(RwLock::new(Default::default())),
+            pending_prune: None,
         };
 
         ret.init();
@@ -312,19 +363,25 @@ impl MapxRawMkVs {
         ver_id: VersionID,
     ) -> Result<Option<RawValue>> {
         let mut hdr = self.ver_to_change_set.get(&ver_id).c(d!())?;
-        let hdr_shadow = unsafe { hdr.shadow() };
-        let mut op = |k: &[&[u8]], _: &[u8]| hdr.insert(k, &[]).c(d!()).map(|_| ());
-        hdr_shadow.iter_op_with_key_prefix(&mut op, key).c(d!())?;
 
+        // every removed key goes into the change set,
+        // including those that were written by earlier versions
         let key = to_owned_key(key);
+        let mut removed = vec![];
         self.layered_kv
             .write()
             .range_mut(key.clone()..)
-            .filter(|(k, _)| k.starts_with(&key))
-            .for_each(|(_, vers)| {
+            .take_while(|(k, _)| k.starts_with(&key))
+            .for_each(|(k, vers)| {
                 vers.insert(ver_id, Vec::new());
+                removed.push(k.clone());
             });
 
+        for k in removed.iter() {
+            let k = k.iter().map(|k| &k[..]).collect::<Vec<_>>();
+            hdr.insert(&k, NULL).c(d!())?;
+        }
+
         Ok(None)
     }
 
@@ -465,6 +522,9 @@ impl MapxRawMkVs {
         self.br_name_to_br_id.clear();
         self.ver_name_to_ver_id.clear();
         self.br_to_its_vers.clear();
+        self.br_to_parent.clear();
+        self.br_to_info.clear();
+        self.ver_to_meta.clear();
         self.ver_to_change_set.clear();
 
         self.br_id_to_br_name.write().clear();
@@ -494,8 +554,17 @@ impl MapxRawMkVs {
             .get_mut(&br_id)
             .c(d!("branch not found"))?;
 
+        // branches created before the meta information was introduced
+        // will count their versions once
+        let height = vers
+            .last()
+            .and_then(|(ver, _)| self.ver_to_meta.get(&ver))
+            .map(|(h, _)| h + 1)
+            .unwrap_or_else(|| vers.len() as u64);
+
         let ver_id = VSDB.alloc_ver_id().to_be_bytes();
         vers.insert(&ver_id, &());
+        self.ver_to_meta.insert(&ver_id, &(height, now_secs()));
 
         self.ver_name_to_ver_id.insert(ver_name, &ver_id);
         self.ver_id_to_ver_name
@@ -602,6 +671,7 @@ impl MapxRawMkVs {
                     .and_then(|vername| {
                         self.ver_name_to_ver_id.remove(&vername).c(d!())
                     })?;
+                self.ver_to_meta.remove(verid);
             }
         }
 
@@ -662,26 +732,29 @@ impl MapxRawMkVs {
     // clean up all orphaned versions in the global scope
     #[inline(always)]
     pub(super) fn version_clean_up_globally(&mut self) -> Result<()> {
-        let mut valid_vers = HashSet::new();
-        self.br_to_its_vers.values().for_each(|vers| {
-            vers.iter().for_each(|(ver, _)| {
-                valid_vers.insert(ver);
-            })
-        });
+        let mut plan = self.prune_plan(None, true).c(d!())?;
+        self.prune_by_plan(&mut plan, usize::MAX)
+            .c(d!())
+            .map(|_| ())
+    }
 
+    // Remove versions that are not on any branch.
+    fn versions_remove(&mut self, vers: &[VersionID]) -> Result<()> {
         let mut trash = vec![];
 
         {
             let mut lkv_hdr = self.layered_kv.write();
             let mut ver_hdr = self.ver_id_to_ver_name.write();
-            for (ver, chgset) in unsafe { self.ver_to_change_set.shadow() }
-                .iter()
-                .filter(|(ver, _)| !valid_vers.contains(ver))
-            {
+            for ver in vers.iter() {
+                // may have been removed by others since the plan was made
+                let chgset = match self.ver_to_change_set.remove(ver) {
+                    Some(chgset) => chgset,
+                    None => continue,
+                };
                 let mut chgset_ops = |k: &[&[u8]], _: &[u8]| {
                     let k = to_owned_key(k);
                     let lkv = lkv_hdr.get_mut(&k).c(d!())?;
-                    lkv.remove(&ver).c(d!())?;
+                    lkv.remove(ver).c(d!())?;
                     if lkv.is_empty() {
                         lkv_hdr.remove(&k).c(d!())?;
                     }
@@ -690,13 +763,10 @@ impl MapxRawMkVs {
                 chgset.iter_op(&mut chgset_ops).c(d!())?;
                 trash.push(chgset);
 
-                self.ver_to_change_set
-                    .remove(&ver)
-                    .c(d!())
-                    .and_then(|_| ver_hdr.remove(&ver).c(d!()))
-                    .and_then(|vername| {
-                        self.ver_name_to_ver_id.remove(&vername).c(d!())
-                    })?;
+                ver_hdr.remove(ver).c(d!()).and_then(|vername| {
+                    self.ver_name_to_ver_id.remove(&vername).c(d!())
+                })?;
+                self.ver_to_meta.remove(ver);
             }
         }
 
@@ -737,6 +807,7 @@ impl MapxRawMkVs {
         self.br_to_its_vers.values().for_each(|mut vers| {
             vers.remove(&ver_id);
         });
+        self.ver_to_meta.remove(&ver_id);
 
         self.ver_id_to_ver_name
             .write()
@@ -950,6 +1021,18 @@ impl MapxRawMkVs {
             .write()
             .insert(br_id, br_name.to_vec());
         self.br_to_its_vers.insert(&br_id, &vers_copied);
+        self.br_to_parent.insert(&br_id, &base_br_id);
+
+        let info = BranchInfo {
+            creator: vsdb_get_branch_creator(),
+            creation_version: base_ver_id.map(|ver| {
+                VersionNameOwned(
+                    self.ver_id_to_ver_name.read().get(&ver).unwrap().to_vec(),
+                )
+            }),
+            ..Default::default()
+        };
+        self.br_to_info.insert(&br_id, &info);
 
         if let Some(vername) = ver_name {
             self.version_create_by_branch(vername, br_id).c(d!())?;
@@ -978,6 +1061,10 @@ impl MapxRawMkVs {
 
     #[inline(always)]
     pub(super) fn branch_remove(&mut self, br_id: BranchID) -> Result<()> {
+        if br_id == self.default_branch {
+            return Err(eg!("the default branch can not be removed"));
+        }
+
         self.branch_truncate(br_id).c(d!())?;
 
         self.br_id_to_br_name
@@ -987,6 +1074,8 @@ impl MapxRawMkVs {
             .and_then(|brname| self.br_name_to_br_id.remove(&brname).c(d!()))?;
 
         let mut vers = self.br_to_its_vers.remove(&br_id).c(d!())?;
+        self.br_to_parent.remove(&br_id);
+        self.br_to_info.remove(&br_id);
 
         TRASH_CLEANER.lock().execute(move || {
             vers.clear();
@@ -1005,6 +1094,9 @@ impl MapxRawMkVs {
             .copied()
             .collect::<Vec<_>>();
         let brs = brs; // avoid warnings
+        if brs.contains(&self.default_branch) {
+            return Err(eg!("the default branch can not be removed"));
+        }
         for brid in brs.into_iter() {
             self.branch_remove(brid).c(d!())?;
         }
@@ -1123,6 +1215,276 @@ impl MapxRawMkVs {
         Ok(())
     }
 
+    // The versions created on `br_id` after it forked from `base_br_id`,
+    // and the versions created on `base_br_id` since then, in ascending order.
+    fn branch_fork_diff(
+        &self,
+        br_id: BranchID,
+        base_br_id: BranchID,
+    ) -> Result<(Vec<VersionID>, Vec<VersionID>)> {
+        if br_id == base_br_id {
+            return Err(eg!("can not rebase a branch onto itself"));
+        }
+
+        let vers = self.br_to_its_vers.get(&br_id).c(d!("branch not found"))?;
+        let base_vers = self
+            .br_to_its_vers
+            .get(&base_br_id)
+            .c(d!("base branch not found"))?;
+
+        let private = vers
+            .iter()
+            .filter(|(ver, _)| !base_vers.contains_key(ver))
+            .map(|(ver, _)| ver)
+            .collect();
+        let base_only = base_vers
+            .iter()
+            .filter(|(ver, _)| !vers.contains_key(ver))
+            .map(|(ver, _)| ver)
+            .collect();
+
+        Ok((private, base_only))
+    }
+
+    // All the keys changed by the `vers`.
+    fn changed_keys(&self, vers: &[VersionID]) -> Result<HashSet<Vec<RawKey>>> {
+        let mut ret = HashSet::new();
+        for ver in vers.iter() {
+            if let Some(chgset) = self.ver_to_change_set.get(ver) {
+                let mut op = |k: &[&[u8]], _: &[u8]| {
+                    ret.insert(to_owned_key(k));
+                    Ok(())
+                };
+                chgset.iter_op(&mut op).c(d!())?;
+            }
+        }
+        Ok(ret)
+    }
+
+    // The keys changed between two versions of a branch, with their values in `ver_b`,
+    // `None` means that the key does not exist in `ver_b`.
+    #[allow(clippy::type_complexity)]
+    pub(super) fn diff_by_branch(
+        &self,
+        br_id: BranchID,
+        ver_a: VersionID,
+        ver_b: VersionID,
+    ) -> Result<Vec<(Vec<RawKey>, Option<RawValue>)>> {
+        let vers = self.br_to_its_vers.get(&br_id).c(d!("branch not found"))?;
+        if !vers.contains_key(&ver_a) || !vers.contains_key(&ver_b) {
+            return Err(eg!("version not found on this branch"));
+        }
+
+        let (lo, hi) = alt!(ver_a <= ver_b, (ver_a, ver_b), (ver_b, ver_a));
+        let changed = vers
+            .range((Bound::Excluded(lo), Bound::Included(hi)))
+            .map(|(ver, _)| ver)
+            .collect::<Vec<_>>();
+        let keys = self
+            .changed_keys(&changed)
+            .c(d!())?
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+
+        Ok(keys
+            .into_iter()
+            .filter_map(|k| {
+                let key = k.iter().map(|k| &k[..]).collect::<Vec<_>>();
+                let old = self.get_by_branch_version(&key, br_id, ver_a);
+                let new = self.get_by_branch_version(&key, br_id, ver_b);
+                alt!(old == new, None, Some((k, new)))
+            })
+            .collect())
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_rebase_conflicts(
+        &self,
+        br_id: BranchID,
+        base_br_id: BranchID,
+    ) -> Result<Vec<Vec<RawKey>>> {
+        let (private, base_only) = self.branch_fork_diff(br_id, base_br_id).c(d!())?;
+        let others = self.changed_keys(&base_only).c(d!())?;
+        let mut ret = self
+            .changed_keys(&private)
+            .c(d!())?
+            .into_iter()
+            .filter(|k| others.contains(k))
+            .collect::<Vec<_>>();
+        ret.sort_unstable();
+        Ok(ret)
+    }
+
+    pub(super) fn branch_rebase_onto(
+        &mut self,
+        br_id: BranchID,
+        base_br_id: BranchID,
+    ) -> Result<()> {
+        let conflicts = self.branch_rebase_conflicts(br_id, base_br_id).c(d!())?;
+        if !conflicts.is_empty() {
+            return Err(eg!(
+                "unable to rebase, {} keys have been changed on both branches",
+                conflicts.len()
+            ));
+        }
+
+        let (private, _) = self.branch_fork_diff(br_id, base_br_id).c(d!())?;
+
+        if self.br_to_its_vers.iter().any(|(br, vers)| {
+            br != br_id && private.iter().any(|ver| vers.contains_key(ver))
+        }) {
+            return Err(eg!("versions of this branch are used by other branches"));
+        }
+
+        let moves = private
+            .iter()
+            .map(|ver| (*ver, VSDB.alloc_ver_id().to_be_bytes()))
+            .collect::<Vec<_>>();
+
+        {
+            let mut lkv_hdr = self.layered_kv.write();
+            let mut ver_hdr = self.ver_id_to_ver_name.write();
+            for (old, new) in moves.iter() {
+                let chgset = self.ver_to_change_set.remove(old).c(d!())?;
+                let mut chgset_ops = |k: &[&[u8]], _: &[u8]| {
+                    let kvers = lkv_hdr.get_mut(&to_owned_key(k)).c(d!())?;
+                    let v = kvers.remove(old).c(d!())?;
+                    kvers.insert(*new, v);
+                    Ok(())
+                };
+                chgset.iter_op(&mut chgset_ops).c(d!())?;
+                self.ver_to_change_set.insert(new, &chgset);
+
+                let vername = ver_hdr.remove(old).c(d!())?;
+                self.ver_name_to_ver_id.insert(&vername, new);
+                ver_hdr.insert(*new, vername);
+                if let Some(meta) = self.ver_to_meta.remove(old) {
+                    self.ver_to_meta.insert(new, &meta);
+                }
+            }
+        }
+
+        let mut vers = self.br_to_its_vers.get(&br_id).c(d!())?;
+        vers.clear();
+        self.br_to_its_vers
+            .get(&base_br_id)
+            .c(d!())?
+            .iter()
+            .for_each(|(ver, _)| {
+                vers.insert(&ver, &());
+            });
+        moves.iter().for_each(|(_, new)| {
+            vers.insert(new, &());
+        });
+        self.br_to_parent.insert(&br_id, &base_br_id);
+
+        Ok(())
+    }
+
+    // The latest version shared by the two branches,
+    // searched backwards from the head of `br_id`.
+    pub(super) fn branch_merge_base(
+        &self,
+        br_id: BranchID,
+        other_br_id: BranchID,
+    ) -> Result<Option<VersionNameOwned>> {
+        let vers = self.br_to_its_vers.get(&br_id).c(d!("branch not found"))?;
+        let other_vers = self
+            .br_to_its_vers
+            .get(&other_br_id)
+            .c(d!("other branch not found"))?;
+        Ok(vers
+            .iter()
+            .rev()
+            .find(|(ver, _)| other_vers.contains_key(ver))
+            .map(|(ver, _)| {
+                VersionNameOwned(
+                    self.ver_id_to_ver_name.read().get(&ver).unwrap().to_vec(),
+                )
+            }))
+    }
+
+    #[inline(always)]
+    pub(super) fn version_is_ancestor(
+        &self,
+        ver_id: VersionID,
+        br_id: BranchID,
+    ) -> Result<bool> {
+        self.br_to_its_vers
+            .get(&br_id)
+            .c(d!("branch not found"))
+            .map(|vers| vers.contains_key(&ver_id))
+    }
+
+    #[inline(always)]
+    pub(super) fn branches_containing(&self, ver_id: VersionID) -> Vec<BranchNameOwned> {
+        let brname_hdr = self.br_id_to_br_name.read();
+        self.br_to_its_vers
+            .iter()
+            .filter(|(_, vers)| vers.contains_key(&ver_id))
+            .filter_map(|(br, _)| brname_hdr.get(&br).cloned())
+            .map(BranchNameOwned)
+            .collect()
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_parent(
+        &self,
+        br_id: BranchID,
+    ) -> Result<Option<BranchNameOwned>> {
+        if !self.branch_exists(br_id) {
+            return Err(eg!("branch not found"));
+        }
+        Ok(self.br_to_parent.get(&br_id).and_then(|parent| {
+            self.br_id_to_br_name
+                .read()
+                .get(&parent)
+                .cloned()
+                .map(BranchNameOwned)
+        }))
+    }
+
+    pub(super) fn branch_rename(
+        &mut self,
+        br_id: BranchID,
+        new_br_name: &[u8],
+    ) -> Result<()> {
+        if self.br_name_to_br_id.contains_key(new_br_name) {
+            return Err(eg!("branch already exists"));
+        }
+
+        let mut brname_hdr = self.br_id_to_br_name.write();
+        let brname = brname_hdr.get_mut(&br_id).c(d!("branch not found"))?;
+        self.br_name_to_br_id.remove(&brname[..]).c(d!())?;
+        self.br_name_to_br_id.insert(new_br_name, &br_id);
+        *brname = new_br_name.to_vec();
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub(super) fn branch_info(&self, br_id: BranchID) -> Result<BranchInfo> {
+        if !self.branch_exists(br_id) {
+            return Err(eg!("branch not found"));
+        }
+        Ok(self.br_to_info.get(&br_id).unwrap_or_default())
+    }
+
+    // The `creation_version` is kept as recorded.
+    pub(super) fn branch_set_info(
+        &mut self,
+        br_id: BranchID,
+        info: &BranchInfo,
+    ) -> Result<()> {
+        let creation_version = self.branch_info(br_id).c(d!())?.creation_version;
+        let info = BranchInfo {
+            creation_version,
+            ..info.clone()
+        };
+        self.br_to_info.insert(&br_id, &info);
+        Ok(())
+    }
+
     #[inline(always)]
     pub(super) fn branch_set_default(&mut self, br_id: BranchID) -> Result<()> {
         if !self.branch_exists(br_id) {
@@ -1204,59 +1566,139 @@ impl MapxRawMkVs {
 
     #[inline(always)]
     pub(super) fn prune(&mut self, reserved_ver_num: Option<usize>) -> Result<()> {
-        self.version_clean_up_globally()
+        let mut plan = self.prune_plan(reserved_ver_num, false).c(d!())?;
+        self.prune_by_plan(&mut plan, usize::MAX)
             .c(d!())
-            .and_then(|_| self.do_prune(reserved_ver_num).c(d!()))
+            .map(|_| ())
+    }
+
+    // Like `prune`, but at most `budget` versions are cleaned in one step,
+    // the result of repeated steps is the same as a whole `prune`.
+    //
+    // The candidates are found out by the first step,
+    // and kept for the following steps.
+    pub(super) fn prune_step(
+        &mut self,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> Result<PruneProgress> {
+        let budget = budget.max(1);
+
+        let mut plan = match self.pending_prune.take() {
+            Some(p)
+                if p.reserved_ver_num == reserved_ver_num
+                    && self.prune_plan_is_valid(&p, budget) =>
+            {
+                p
+            }
+            _ => self.prune_plan(reserved_ver_num, false).c(d!())?,
+        };
+
+        let progress = self.prune_by_plan(&mut plan, budget).c(d!())?;
+        if !progress.is_finished() {
+            self.pending_prune = Some(plan);
+        }
+        Ok(progress)
     }
 
-    fn do_prune(&mut self, reserved_ver_num: Option<usize>) -> Result<()> {
+    // Find out all the versions to be cleaned.
+    fn prune_plan(
+        &self,
+        reserved_ver_num: Option<usize>,
+        clean_only: bool,
+    ) -> Result<PrunePlan> {
+        let mut valid_vers = HashSet::new();
+        self.br_to_its_vers.values().for_each(|vers| {
+            vers.iter().for_each(|(ver, _)| {
+                valid_vers.insert(ver);
+            })
+        });
+
+        let orphans = self
+            .ver_to_change_set
+            .iter()
+            .map(|(ver, _)| ver)
+            .filter(|ver| !valid_vers.contains(ver))
+            .collect::<VecDeque<_>>();
+
+        let mut plan = PrunePlan {
+            reserved_ver_num,
+            orphans,
+            ..Default::default()
+        };
+
+        if clean_only {
+            return Ok(plan);
+        }
+
         // the '1' of this 'add 1' means the never-deleted initial version.
-        let reserved_ver_num =
-            1 + reserved_ver_num.unwrap_or(RESERVED_VERSION_NUM_DEFAULT);
-        if 0 == reserved_ver_num {
+        let reserved = 1 + reserved_ver_num.unwrap_or(RESERVED_VERSION_NUM_DEFAULT);
+        if 0 == reserved {
             return Err(eg!("reserved version number should NOT be zero"));
         }
 
-        let mut brvers_non_empty = self
+        let (_, shared) = self.vers_shared_by_all();
+        let l = shared.len();
+        if l > reserved {
+            plan.rewrite_ver = Some(shared[0]);
+            plan.merges = shared[1..l - reserved + 1].iter().copied().collect();
+            plan.first_reserved = shared.get(l - reserved + 1).copied();
+        }
+
+        Ok(plan)
+    }
+
+    // The versions to be merged in the next step, the version they are merged into,
+    // and the oldest reserved version must still be shared by all non-empty branches.
+    fn prune_plan_is_valid(&self, plan: &PrunePlan, budget: usize) -> bool {
+        let n = budget.saturating_sub(plan.orphans.len()).min(plan.merges.len());
+        if 0 == n {
+            return true;
+        }
+        let brvers = self
             .br_to_its_vers
             .values()
             .filter(|vers| !vers.is_empty())
             .collect::<Vec<_>>();
-        alt!(brvers_non_empty.is_empty(), return Ok(()));
-        let mut brvers = (0..brvers_non_empty.len())
-            .map(|i| brvers_non_empty[i].iter())
-            .collect::<Vec<_>>();
+        plan.merges
+            .iter()
+            .take(n)
+            .chain(plan.rewrite_ver.iter())
+            .chain(plan.first_reserved.iter())
+            .all(|ver| brvers.iter().all(|vers| vers.contains_key(ver)))
+    }
 
-        let mut guard = VER_ID_MAX;
-        let mut vers_to_be_merged: Vec<VersionID> = vec![];
-        'x: loop {
-            for (idx, vers) in brvers.iter_mut().enumerate() {
-                if let Some((ver, _)) = vers.next() {
-                    alt!(0 == idx, guard = ver);
-                    alt!(guard != ver, break 'x);
-                } else {
-                    break 'x;
-                }
-            }
-            assert_ne!(guard, VER_ID_MAX);
-            vers_to_be_merged.push(guard);
-        }
+    fn prune_by_plan(
+        &mut self,
+        plan: &mut PrunePlan,
+        budget: usize,
+    ) -> Result<PruneProgress> {
+        let n = plan.orphans.len().min(budget);
+        let orphanvers = plan.orphans.drain(..n).collect::<Vec<_>>();
+        self.versions_remove(&orphanvers).c(d!())?;
+
+        let mut progress = PruneProgress {
+            done: n,
+            remaining: plan.orphans.len(),
+        };
 
-        let l = vers_to_be_merged.len();
-        if l <= reserved_ver_num {
-            return Ok(());
+        // the oldest versions are merged first,
+        // so the result of every step is a valid pruning state.
+        let n = plan.merges.len().min(budget - progress.done);
+        progress.done += n;
+        progress.remaining += plan.merges.len() - n;
+        if 0 == n {
+            return Ok(progress);
         }
 
-        let (vers_to_be_merged, rewrite_ver) = {
-            let guard_idx = l - reserved_ver_num;
-            (&vers_to_be_merged[1..=guard_idx], &vers_to_be_merged[0])
-        };
+        let rewrite_ver = plan.rewrite_ver.c(d!())?;
+        let vers_to_be_merged = plan.merges.drain(..n).collect::<Vec<_>>();
 
-        let mut rewrite_chgset = self.ver_to_change_set.get(rewrite_ver).c(d!())?;
+        let mut rewrite_chgset = self.ver_to_change_set.get(&rewrite_ver).c(d!())?;
 
-        for vers in brvers_non_empty.iter_mut() {
+        for mut vers in self.br_to_its_vers.values() {
             for ver in vers_to_be_merged.iter() {
-                vers.remove(ver).c(d!())?;
+                vers.remove(ver);
             }
         }
 
@@ -1270,7 +1712,7 @@ impl MapxRawMkVs {
                     let kvers = lkv_hdr.get_mut(&key).c(d!())?;
                     let vv = kvers.get(ver).c(d!())?.clone();
                     assert_eq!(&vv, v);
-                    kvers.insert(*rewrite_ver, vv);
+                    kvers.insert(rewrite_ver, vv);
 
                     rewrite_chgset.insert(k, v).c(d!())?;
                     chgkeys.insert(key);
@@ -1283,8 +1725,8 @@ impl MapxRawMkVs {
             for k in chgkeys.iter() {
                 if let Some(vers) = lkv_hdr.get_mut(k.as_slice()) {
                     // A 'NULL' value means 'not exist'.
-                    if vers.get(rewrite_ver).c(d!())?.is_empty() {
-                        vers.remove(rewrite_ver).c(d!())?;
+                    if vers.get(&rewrite_ver).c(d!())?.is_empty() {
+                        vers.remove(&rewrite_ver).c(d!())?;
                         rewrite_chgset
                             .remove(&k.iter().map(|k| &k[..]).collect::<Vec<_>>())
                             .c(d!())?;
@@ -1296,10 +1738,124 @@ impl MapxRawMkVs {
             }
         }
 
+        Ok(progress)
+    }
+
+    // The versions shared by all non-empty branches, in ascending order,
+    // they are the only candidates of pruning.
+    #[allow(clippy::type_complexity)]
+    fn vers_shared_by_all(&self) -> (Vec<MapxOrd<VersionID, ()>>, Vec<VersionID>) {
+        let brvers_non_empty = self
+            .br_to_its_vers
+            .values()
+            .filter(|vers| !vers.is_empty())
+            .collect::<Vec<_>>();
+        let mut brvers = (0..brvers_non_empty.len())
+            .map(|i| brvers_non_empty[i].iter())
+            .collect::<Vec<_>>();
+
+        let mut guard = VER_ID_MAX;
+        let mut shared: Vec<VersionID> = vec![];
+        'x: loop {
+            alt!(brvers.is_empty(), break 'x);
+            for (idx, vers) in brvers.iter_mut().enumerate() {
+                if let Some((ver, _)) = vers.next() {
+                    alt!(0 == idx, guard = ver);
+                    alt!(guard != ver, break 'x);
+                } else {
+                    break 'x;
+                }
+            }
+            assert_ne!(guard, VER_ID_MAX);
+            shared.push(guard);
+        }
+
+        (brvers_non_empty, shared)
+    }
+
+    // The oldest shared version is kept as the final data container like `prune`,
+    // and the newest one is kept for the changes of dropped versions to be merged into.
+    pub(super) fn prune_with_policy(&mut self, policy: &dyn PrunePolicy) -> Result<()> {
+        self.version_clean_up_globally().c(d!())?;
+
+        let (mut brvers_non_empty, shared) = self.vers_shared_by_all();
+        let l = shared.len();
+        if l < 3 {
+            return Ok(());
+        }
+
+        // (the target version, the dropped versions merged into it)
+        let mut merges: Vec<(VersionID, Vec<VersionID>)> = vec![];
+        let mut dropped = vec![];
+        {
+            let ver_names = self.ver_id_to_ver_name.read();
+            for (idx, ver) in shared.iter().enumerate().skip(1) {
+                let meta = self.ver_to_meta.get(ver);
+                let info = VersionInfo {
+                    name: VersionName(ver_names.get(ver).c(d!())?),
+                    height: meta.map(|m| m.0),
+                    created_at: meta.map(|m| m.1),
+                    depth: l - 1 - idx,
+                };
+                if idx < l - 1 && !policy.keep(&info) {
+                    dropped.push(*ver);
+                } else if !dropped.is_empty() {
+                    merges.push((*ver, dropped));
+                    dropped = vec![];
+                }
+            }
+        }
+
+        let mut trash = vec![];
+        for (target, vers) in merges.iter() {
+            let mut target_chgset = self.ver_to_change_set.get(target).c(d!())?;
+            let mut lkv_hdr = self.layered_kv.write();
+            let mut ver_hdr = self.ver_id_to_ver_name.write();
+
+            // the newer changes are merged first,
+            // the changes made by the target version itself take precedence
+            for ver in vers.iter().rev() {
+                let chgset = self.ver_to_change_set.remove(ver).c(d!())?;
+                let mut chgset_ops = |k: &[&[u8]], v: &[u8]| {
+                    let kvers = lkv_hdr.get_mut(&to_owned_key(k)).c(d!())?;
+                    kvers.remove(ver).c(d!())?;
+                    if !kvers.contains_key(target) {
+                        kvers.insert(*target, v.to_vec());
+                        target_chgset.insert(k, v).c(d!())?;
+                    }
+                    Ok(())
+                };
+                chgset.iter_op(&mut chgset_ops).c(d!())?;
+                trash.push(chgset);
+
+                for brvers in brvers_non_empty.iter_mut() {
+                    brvers.remove(ver).c(d!())?;
+                }
+                ver_hdr.remove(ver).c(d!()).and_then(|vername| {
+                    self.ver_name_to_ver_id.remove(&vername).c(d!())
+                })?;
+                self.ver_to_meta.remove(ver);
+            }
+        }
+
+        TRASH_CLEANER.lock().execute(move || {
+            trash.into_iter().for_each(|mut cs| {
+                cs.clear();
+            });
+        });
+
         Ok(())
     }
 }
 
+#[inline(always)]
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
 ////////////////////////////////////////////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////
 
@@ -8,8 +8,9 @@ mod backend;
 mod test;
 
 use crate::{
-    common::{BranchName, ParentBranchName, RawValue, VersionName},
-    BranchNameOwned, VersionNameOwned, VsMgmt,
+    common::{BranchName, ParentBranchName, RawKey, RawValue, VersionName},
+    BranchInfo, BranchNameOwned, PruneProgress, PrunePolicy, VersionGraph, VersionNameOwned,
+    VsDiff, VsMgmt, VsView,
 };
 use ruc::*;
 use serde::{Deserialize, Serialize};
@@ -17,6 +18,7 @@ use std::{
     collections::BTreeSet,
     ops::{Deref, DerefMut},
 };
+use vsdb_core::{common::PreBytes, Registrable};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MapxRawMkVs {
@@ -211,6 +213,102 @@ impl MapxRawMkVs {
             .iter_op_with_key_prefix_by_branch_version(br_id, ver_id, op, key_prefix)
             .c(d!())
     }
+
+    /// The keys that have been changed both on `br_name`
+    /// and on `base_br_name` since they forked,
+    /// a [branch_rebase_onto](VsMgmt::branch_rebase_onto) will fail if it is not empty.
+    #[inline(always)]
+    pub fn branch_rebase_conflicts(
+        &self,
+        br_name: BranchName,
+        base_br_name: BranchName,
+    ) -> Result<Vec<Vec<RawKey>>> {
+        let br_id = self
+            .inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))?;
+        let base_br_id = self
+            .inner
+            .branch_get_id_by_name(base_br_name)
+            .c(d!("base branch not found"))?;
+        self.inner.branch_rebase_conflicts(br_id, base_br_id).c(d!())
+    }
+
+    /// The changes made from `ver_a` to `ver_b` on the default branch,
+    /// every changed key is listed with its value in `ver_b`,
+    /// a `None` value means that the key does not exist in `ver_b`.
+    #[inline(always)]
+    #[allow(clippy::type_complexity)]
+    pub fn diff(
+        &self,
+        ver_a: VersionName,
+        ver_b: VersionName,
+    ) -> Result<Vec<(Vec<RawKey>, Option<RawValue>)>> {
+        self.diff_by_branch(self.branch_get_default().as_deref(), ver_a, ver_b)
+            .c(d!())
+    }
+
+    /// The changes made from `ver_a` to `ver_b` on a specified branch.
+    #[inline(always)]
+    #[allow(clippy::type_complexity)]
+    pub fn diff_by_branch(
+        &self,
+        br_name: BranchName,
+        ver_a: VersionName,
+        ver_b: VersionName,
+    ) -> Result<Vec<(Vec<RawKey>, Option<RawValue>)>> {
+        let br_id = self
+            .inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))?;
+        let ver_a = self
+            .inner
+            .version_get_id_by_name(ver_a)
+            .c(d!("version not found"))?;
+        let ver_b = self
+            .inner
+            .version_get_id_by_name(ver_b)
+            .c(d!("version not found"))?;
+        self.inner.diff_by_branch(br_id, ver_a, ver_b).c(d!())
+    }
+
+    /// Apply changes, eg. the result of [diff](Self::diff),
+    /// to the head of the default branch.
+    #[inline(always)]
+    pub fn apply_diff(&mut self, diff: &[(Vec<RawKey>, Option<RawValue>)]) -> Result<()> {
+        for (k, v) in diff.iter() {
+            let key = k.iter().map(|k| &k[..]).collect::<Vec<_>>();
+            if let Some(v) = v {
+                self.insert(&key, v).c(d!())?;
+            } else {
+                self.remove(&key).c(d!())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Registrable for MapxRawMkVs {
+    const KIND: &'static str = "MapxRawMkVs";
+
+    #[inline(always)]
+    fn instance_prefix(&self) -> PreBytes {
+        self.inner.instance_prefix()
+    }
+}
+
+impl VsDiff for MapxRawMkVs {
+    type Diff = Vec<(Vec<RawKey>, Option<RawValue>)>;
+
+    #[inline(always)]
+    fn diff(&self, ver_a: VersionName, ver_b: VersionName) -> Result<Self::Diff> {
+        MapxRawMkVs::diff(self, ver_a, ver_b).c(d!())
+    }
+
+    #[inline(always)]
+    fn apply_diff(&mut self, diff: &Self::Diff) -> Result<()> {
+        MapxRawMkVs::apply_diff(self, diff).c(d!())
+    }
 }
 
 impl VsMgmt for MapxRawMkVs {
@@ -599,6 +697,24 @@ impl VsMgmt for MapxRawMkVs {
             })
     }
 
+    #[inline(always)]
+    fn branch_rebase_onto(
+        &mut self,
+        br_name: BranchName,
+        base_br_name: BranchName,
+    ) -> Result<()> {
+        self.inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|brid| {
+                let base_brid = self
+                    .inner
+                    .branch_get_id_by_name(base_br_name)
+                    .c(d!("base branch not found"))?;
+                self.inner.branch_rebase_onto(brid, base_brid).c(d!())
+            })
+    }
+
     #[inline(always)]
     fn branch_set_default(&mut self, br_name: BranchName) -> Result<()> {
         self.inner
@@ -625,6 +741,84 @@ impl VsMgmt for MapxRawMkVs {
         self.inner.branch_get_default_name()
     }
 
+    #[inline(always)]
+    fn branch_merge_base(
+        &self,
+        br_name: BranchName,
+        other_br_name: BranchName,
+    ) -> Result<Option<VersionNameOwned>> {
+        let br_id = self
+            .inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))?;
+        let other_br_id = self
+            .inner
+            .branch_get_id_by_name(other_br_name)
+            .c(d!("other branch not found"))?;
+        self.inner.branch_merge_base(br_id, other_br_id).c(d!())
+    }
+
+    #[inline(always)]
+    fn version_is_ancestor(
+        &self,
+        ver_name: VersionName,
+        br_name: BranchName,
+    ) -> Result<bool> {
+        let ver_id = self
+            .inner
+            .version_get_id_by_name(ver_name)
+            .c(d!("version not found"))?;
+        let br_id = self
+            .inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))?;
+        self.inner.version_is_ancestor(ver_id, br_id).c(d!())
+    }
+
+    #[inline(always)]
+    fn branches_containing(&self, ver_name: VersionName) -> Result<Vec<BranchNameOwned>> {
+        self.inner
+            .version_get_id_by_name(ver_name)
+            .c(d!("version not found"))
+            .map(|ver_id| self.inner.branches_containing(ver_id))
+    }
+
+    #[inline(always)]
+    fn branch_parent(&self, br_name: BranchName) -> Result<Option<BranchNameOwned>> {
+        self.inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| self.inner.branch_parent(br_id).c(d!()))
+    }
+
+    #[inline(always)]
+    fn branch_rename(
+        &mut self,
+        br_name: BranchName,
+        new_br_name: BranchName,
+    ) -> Result<()> {
+        self.inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| self.inner.branch_rename(br_id, new_br_name.0).c(d!()))
+    }
+
+    #[inline(always)]
+    fn branch_info(&self, br_name: BranchName) -> Result<BranchInfo> {
+        self.inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| self.inner.branch_info(br_id).c(d!()))
+    }
+
+    #[inline(always)]
+    fn branch_set_info(&mut self, br_name: BranchName, info: &BranchInfo) -> Result<()> {
+        self.inner
+            .branch_get_id_by_name(br_name)
+            .c(d!("branch not found"))
+            .and_then(|br_id| self.inner.branch_set_info(br_id, info).c(d!()))
+    }
+
     #[inline(always)]
     unsafe fn branch_swap(
         &mut self,
@@ -638,6 +832,106 @@ impl VsMgmt for MapxRawMkVs {
     fn prune(&mut self, reserved_ver_num: Option<usize>) -> Result<()> {
         self.inner.prune(reserved_ver_num).c(d!())
     }
+
+    #[inline(always)]
+    fn prune_with_policy(&mut self, policy: &dyn PrunePolicy) -> Result<()> {
+        self.inner.prune_with_policy(policy).c(d!())
+    }
+
+    #[inline(always)]
+    fn prune_step(
+        &mut self,
+        reserved_ver_num: Option<usize>,
+        budget: usize,
+    ) -> Result<PruneProgress> {
+        self.inner.prune_step(reserved_ver_num, budget).c(d!())
+    }
+
+    /// Multi-key collections manage their versions by themselves.
+    #[inline(always)]
+    fn version_graphs(&self) -> Option<Vec<VersionGraph>> {
+        None
+    }
+
+    /// Multi-key collections manage their versions by themselves,
+    /// so the shared version graph is ignored.
+    #[inline(always)]
+    fn version_graph_attach(&mut self, _: &VersionGraph) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl VsView for MapxRawMkVs {
+    type View<'a> = MapxRawMkVsView<'a>;
+
+    fn view_at<'a>(
+        &'a self,
+        br_name: BranchName,
+        ver_name: VersionName,
+    ) -> Result<Self::View<'a>> {
+        if !self.version_exists_on_branch(ver_name, br_name) {
+            return Err(eg!("version not found on this branch"));
+        }
+        Ok(MapxRawMkVsView {
+            hdr: self,
+            br_name: br_name.into(),
+            ver_name: ver_name.into(),
+        })
+    }
+}
+
+/// A read-only handler of [MapxRawMkVs] pinned to a version,
+/// created by [view_at](VsView::view_at).
+pub struct MapxRawMkVsView<'a> {
+    hdr: &'a MapxRawMkVs,
+    br_name: BranchNameOwned,
+    ver_name: VersionNameOwned,
+}
+
+impl<'a> MapxRawMkVsView<'a> {
+    #[inline(always)]
+    pub fn get(&self, key: &[&[u8]]) -> Option<RawValue> {
+        self.hdr
+            .get_by_branch_version(key, self.br_name.as_deref(), self.ver_name.as_deref())
+    }
+
+    #[inline(always)]
+    pub fn contains_key(&self, key: &[&[u8]]) -> bool {
+        self.hdr.contains_key_by_branch_version(
+            key,
+            self.br_name.as_deref(),
+            self.ver_name.as_deref(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn iter_op<F>(&self, op: &mut F) -> Result<()>
+    where
+        F: FnMut(&[&[u8]], RawValue) -> Result<()>,
+    {
+        self.hdr
+            .iter_op_by_branch_version(self.br_name.as_deref(), self.ver_name.as_deref(), op)
+            .c(d!())
+    }
+
+    #[inline(always)]
+    pub fn iter_op_with_key_prefix<F>(
+        &self,
+        op: &mut F,
+        key_prefix: &[&[u8]],
+    ) -> Result<()>
+    where
+        F: FnMut(&[&[u8]], RawValue) -> Result<()>,
+    {
+        self.hdr
+            .iter_op_with_key_prefix_by_branch_version(
+                self.br_name.as_deref(),
+                self.ver_name.as_deref(),
+                op,
+                key_prefix,
+            )
+            .c(d!())
+    }
 }
 
 #[derive(Debug)]
@@ -1,7 +1,7 @@
 use super::*;
 use crate::{
     common::{BranchName, ParentBranchName, INITIAL_BRANCH_NAME},
-    ValueEnDe, VsMgmt, NULL,
+    KeepEveryNth, KeepLastN, PrunePolicy, ValueEnDe, VsMgmt, NULL,
 };
 
 #[test]
@@ -375,3 +375,206 @@ fn test_prune() {
     assert!(!hdr.version_exists(VersionName(b"b")));
     assert!(hdr.version_exists(VersionName(b"c")));
 }
+
+#[test]
+fn test_prune_with_policy() {
+    let mut hdr = MapxRawMkVs::new(2);
+    let master = INITIAL_BRANCH_NAME;
+    let name = |i: u8| format!("v{}", i).into_bytes();
+
+    for i in 0..=6u8 {
+        pnk!(hdr.version_create(VersionName(&name(i))));
+        pnk!(hdr.insert(&[&[0], &[0]], &[i]));
+        pnk!(hdr.insert(&[&[i], &[i]], &[i]));
+    }
+    pnk!(hdr.remove(&[&[1], &[1]]));
+
+    let policy = KeepEveryNth(2).or(KeepLastN(2));
+    pnk!(hdr.prune_with_policy(&policy));
+
+    let kept = [0, 2, 4, 5, 6];
+    for i in 0..=6u8 {
+        assert_eq!(kept.contains(&i), hdr.version_exists(VersionName(&name(i))));
+    }
+
+    let get = |k: u8, v: u8| {
+        hdr.get_by_branch_version(&[&[k], &[k]], master, VersionName(&name(v)))
+    };
+    assert_eq!(Some(vec![2]), get(1, 2).and(get(2, 2)));
+    assert_eq!(Some(vec![4]), get(3, 4).and(get(4, 4)));
+    assert!(get(3, 2).is_none());
+    assert!(get(1, 6).is_none());
+    assert_eq!(Some(vec![6]), hdr.get(&[&[0], &[0]]));
+
+    // reloaded instances keep the heights
+    let mut hdr = pnk!(<MapxRawMkVs as ValueEnDe>::decode(&hdr.encode()));
+    pnk!(hdr.prune_with_policy(&KeepEveryNth(4)));
+    assert!(hdr.version_exists(VersionName(b"v4")));
+    assert!(!hdr.version_exists(VersionName(b"v2")));
+    assert!(!hdr.version_exists(VersionName(b"v5")));
+    assert_eq!(
+        Some(vec![2]),
+        hdr.get_by_branch_version(&[&[2], &[2]], master, VersionName(b"v4"))
+    );
+}
+
+#[test]
+fn test_prune_step() {
+    let mut hdr = MapxRawMkVs::new(2);
+
+    for i in 0u8..10 {
+        pnk!(hdr.version_create(VersionName(&[i])));
+        pnk!(hdr.insert(&[&[0], &[0]], &[i]));
+        pnk!(hdr.insert(&[&[i], &[i]], &[i]));
+    }
+
+    let p = pnk!(hdr.prune_step(Some(2), 3));
+    assert_eq!(3, p.done);
+    assert_eq!(4, p.remaining);
+    assert!(!hdr.version_exists(VersionName(&[3])));
+    assert!(hdr.version_exists(VersionName(&[4])));
+
+    // the remembered candidates are dropped if the reserved number changes
+    let p = pnk!(hdr.prune_step(Some(4), 10));
+    assert_eq!(2, p.done);
+    assert!(p.is_finished());
+    assert!(!hdr.version_exists(VersionName(&[5])));
+    assert!(hdr.version_exists(VersionName(&[6])));
+
+    let p = pnk!(hdr.prune_step(Some(2), 10));
+    assert_eq!(2, p.done);
+    assert!(p.is_finished());
+    assert!(!hdr.version_exists(VersionName(&[7])));
+    assert!(hdr.version_exists(VersionName(&[8])));
+
+    assert_eq!(Some(vec![9]), hdr.get(&[&[0], &[0]]));
+    for i in 1u8..10 {
+        assert_eq!(Some(vec![i]), hdr.get(&[&[i], &[i]]));
+    }
+}
+
+#[test]
+fn test_branch_rebase_onto() {
+    let mut hdr = MapxRawMkVs::new(2);
+    let master = INITIAL_BRANCH_NAME;
+    let fork = BranchName(b"fork");
+
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.insert(&[&[0], &[0]], &[0]));
+    pnk!(hdr.branch_create(fork, VersionName(b"f0"), false));
+    pnk!(hdr.insert_by_branch(&[&[1], &[1]], &[1], fork));
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    pnk!(hdr.insert(&[&[2], &[2]], &[2]));
+
+    pnk!(hdr.branch_rebase_onto(fork, master));
+    assert!(hdr.version_exists_on_branch(VersionName(b"v1"), fork));
+    assert!(hdr.get(&[&[1], &[1]]).is_none());
+    assert_eq!(&[2], &pnk!(hdr.get_by_branch(&[&[2], &[2]], fork))[..]);
+    assert_eq!(
+        &[1],
+        &pnk!(hdr.get_by_branch_version(&[&[1], &[1]], fork, VersionName(b"f0")))[..]
+    );
+
+    pnk!(hdr.insert_by_branch(&[&[2], &[2]], &[20], fork));
+    pnk!(hdr.version_create(VersionName(b"v2")));
+    pnk!(hdr.insert(&[&[2], &[2]], &[21]));
+    assert_eq!(
+        vec![vec![vec![2], vec![2]]],
+        pnk!(hdr.branch_rebase_conflicts(fork, master))
+    );
+    assert!(hdr.branch_rebase_onto(fork, master).is_err());
+    assert!(!hdr.version_exists_on_branch(VersionName(b"v2"), fork));
+}
+
+#[test]
+fn test_branch_ancestry() {
+    let mut hdr = MapxRawMkVs::new(2);
+    let master = INITIAL_BRANCH_NAME;
+    let fork = BranchName(b"fork");
+
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.branch_create(fork, VersionName(b"f0"), false));
+    pnk!(hdr.version_create(VersionName(b"v1")));
+
+    assert_eq!(
+        Some(b"v0".to_vec()),
+        pnk!(hdr.branch_merge_base(fork, master)).map(|v| v.0)
+    );
+    assert!(pnk!(hdr.version_is_ancestor(VersionName(b"v0"), fork)));
+    assert!(!pnk!(hdr.version_is_ancestor(VersionName(b"v1"), fork)));
+    assert_eq!(
+        pnk!(hdr.branches_containing(VersionName(b"f0"))),
+        vec![fork.into()]
+    );
+    assert_eq!(None, pnk!(hdr.branch_parent(master)));
+    assert_eq!(Some(master.into()), pnk!(hdr.branch_parent(fork)));
+
+    // reloaded instances keep the parents
+    let hdr = pnk!(<MapxRawMkVs as ValueEnDe>::decode(&hdr.encode()));
+    assert_eq!(Some(master.into()), pnk!(hdr.branch_parent(fork)));
+}
+
+#[test]
+fn test_branch_rename_and_info() {
+    let mut hdr = MapxRawMkVs::new(2);
+    let fork = BranchName(b"fork");
+    let renamed = BranchName(b"renamed");
+
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.branch_create(fork, VersionName(b"f0"), false));
+    pnk!(hdr.insert_by_branch(&[&[1], &[1]], &[1], fork));
+
+    let mut info = pnk!(hdr.branch_info(fork));
+    assert_eq!(
+        Some(VersionNameOwned(b"v0".to_vec())),
+        info.creation_version
+    );
+    assert_eq!(crate::vsdb_get_branch_creator(), info.creator);
+    info.description = "trial run".to_owned();
+    info.creation_version = None;
+    pnk!(hdr.branch_set_info(fork, &info));
+
+    assert!(hdr.branch_rename(fork, INITIAL_BRANCH_NAME).is_err());
+    pnk!(hdr.branch_rename(fork, renamed));
+    assert!(!hdr.branch_exists(fork));
+    assert_eq!(&[1], &pnk!(hdr.get_by_branch(&[&[1], &[1]], renamed))[..]);
+
+    let hdr = pnk!(<MapxRawMkVs as ValueEnDe>::decode(&hdr.encode()));
+    let info = pnk!(hdr.branch_info(renamed));
+    assert_eq!("trial run", info.description);
+    assert_eq!(
+        Some(VersionNameOwned(b"v0".to_vec())),
+        info.creation_version
+    );
+}
+
+#[test]
+fn test_diff() {
+    let mut hdr = MapxRawMkVs::new(2);
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.insert(&[&[1], &[1]], &[1]));
+    pnk!(hdr.insert(&[&[1], &[2]], &[2]));
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    pnk!(hdr.insert(&[&[1], &[1]], &[10]));
+    pnk!(hdr.remove(&[&[1], &[2]]));
+    pnk!(hdr.insert(&[&[2], &[1]], &[3]));
+
+    let diff = pnk!(hdr.diff(VersionName(b"v0"), VersionName(b"v1")));
+    assert_eq!(
+        diff,
+        vec![
+            (vec![vec![1], vec![1]], Some(vec![10])),
+            (vec![vec![1], vec![2]], None),
+            (vec![vec![2], vec![1]], Some(vec![3])),
+        ]
+    );
+    assert!(hdr.diff(VersionName(b"v0"), VersionName(b"v9")).is_err());
+
+    let mut other = MapxRawMkVs::new(2);
+    pnk!(other.version_create(VersionName(b"v0")));
+    pnk!(other.insert(&[&[1], &[2]], &[2]));
+    pnk!(other.apply_diff(&diff));
+    assert_eq!(other.get(&[&[1], &[1]]), Some(vec![10]));
+    assert!(other.get(&[&[1], &[2]]).is_none());
+    assert_eq!(other.get(&[&[2], &[1]]), Some(vec![3]));
+}
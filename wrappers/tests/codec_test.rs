@@ -0,0 +1,220 @@
+use ruc::*;
+use vsdb::{
+    basic::{mapx_ord_rawkey::MapxOrdRawKey, orphan::Orphan},
+    common::{codec::RawCodec, RawBytes},
+    vsdb_set_base_dir, Codec, Mapx, MapxOrd, Vecx,
+};
+
+// A fixed-size little-endian encoding, different from all builtin codecs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Le64;
+
+impl Codec<u64> for Le64 {
+    fn try_encode(t: &u64) -> Result<RawBytes> {
+        Ok(t.to_le_bytes().to_vec())
+    }
+
+    fn decode(bytes: &[u8]) -> Result<u64> {
+        <[u8; 8]>::try_from(bytes).c(d!()).map(u64::from_le_bytes)
+    }
+}
+
+#[test]
+fn basic_cases() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: Mapx<u32, u64, Le64> = Mapx::with_codec();
+    (0..100u32).for_each(|i| {
+        hdr.insert(&i, &(i as u64));
+    });
+    (0..100u32).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(&i)), i as u64);
+    });
+    for (_, mut v) in hdr.iter_mut() {
+        *v += 1;
+    }
+    assert_eq!(pnk!(hdr.get(&9)), 10);
+
+    // the stored bytes are exactly what the codec produced
+    let raw: MapxOrdRawKey<RawBytes, RawCodec> =
+        unsafe { MapxOrdRawKey::from_bytes(hdr.as_bytes()) };
+    assert_eq!(100, raw.len());
+    raw.iter().zip(hdr.values()).for_each(|((_, r), v)| {
+        assert_eq!(r, v.to_le_bytes().to_vec());
+    });
+
+    let mut hdr: MapxOrd<u32, u64, Le64> = MapxOrd::with_codec();
+    hdr.insert(&1, &1);
+    hdr.insert(&2, &2);
+    assert_eq!(pnk!(hdr.get_le(&9)), (2, 2));
+
+    let mut hdr: Vecx<u64, Le64> = Vecx::with_codec();
+    (0..10u64).for_each(|i| hdr.push(&i));
+    assert_eq!(pnk!(hdr.last()), 9);
+    assert_eq!(hdr.iter().sum::<u64>(), 45);
+
+    let mut hdr: Orphan<u64, Le64> = Orphan::with_codec(1);
+    *hdr.get_mut() += 1;
+    assert_eq!(hdr, 2);
+
+    // collections with different codecs can coexist
+    let mut a: Mapx<u32, RawBytes, RawCodec> = Mapx::with_codec();
+    let mut b: Mapx<u32, RawBytes> = Mapx::new();
+    a.insert(&0, &vec![1, 2, 3]);
+    b.insert(&0, &vec![1, 2, 3]);
+    assert_eq!(a.get(&0), b.get(&0));
+}
+
+#[cfg(feature = "vs")]
+#[test]
+fn versioned_cases() {
+    use vsdb::{MapxOrdVs, MapxVs, OrphanVs, VecxVs, VersionName, VsMgmt};
+
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: MapxVs<u32, u64, Le64> = MapxVs::with_codec();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..100u32).for_each(|i| {
+        pnk!(hdr.insert(&i, &(i as u64)));
+    });
+    (0..100u32).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(&i)), i as u64);
+    });
+
+    let mut hdr: MapxOrdVs<u32, u64, Le64> = MapxOrdVs::with_codec();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.insert(&1, &1));
+    pnk!(hdr.insert(&2, &2));
+    assert_eq!(pnk!(hdr.get_le(&9)), (2, 2));
+
+    let mut hdr: VecxVs<u64, Le64> = VecxVs::with_codec();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..10u64).for_each(|i| hdr.push(&i));
+    assert_eq!(pnk!(hdr.last()), 9);
+
+    let mut hdr: OrphanVs<u64, Le64> = OrphanVs::with_codec();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    pnk!(hdr.set_value(&7));
+    assert_eq!(pnk!(hdr.get_value()), 7);
+}
+
+#[cfg(any(feature = "bincode_codec", feature = "postcard_codec"))]
+#[test]
+fn serde_codecs() {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+    struct Account {
+        balance: u128,
+        memo: String,
+    }
+
+    fn check<C: Codec<Account>>() {
+        let mut hdr: Mapx<u32, Account, C> = Mapx::with_codec();
+        (0..100u32).for_each(|i| {
+            hdr.insert(
+                &i,
+                &Account {
+                    balance: i as u128,
+                    memo: format!("{}", i),
+                },
+            );
+        });
+        (0..100u32).for_each(|i| {
+            assert_eq!(pnk!(hdr.get(&i)).memo, format!("{}", i));
+        });
+    }
+
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    #[cfg(feature = "bincode_codec")]
+    check::<vsdb::common::codec::BincodeCodec>();
+
+    #[cfg(feature = "postcard_codec")]
+    check::<vsdb::common::codec::PostcardCodec>();
+}
+
+#[cfg(feature = "borsh_codec")]
+#[test]
+fn borsh_codec() {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use vsdb::common::codec::BorshCodec;
+
+    #[derive(Debug, Clone, Eq, PartialEq, BorshSerialize, BorshDeserialize)]
+    struct Account {
+        balance: u128,
+        nonce: u64,
+    }
+
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: MapxOrd<u32, Account, BorshCodec> = MapxOrd::with_codec();
+    (0..100u32).for_each(|i| {
+        hdr.insert(
+            &i,
+            &Account {
+                balance: i as u128,
+                nonce: 0,
+            },
+        );
+    });
+    for (_, mut v) in hdr.iter_mut() {
+        v.nonce += 1;
+    }
+    (0..100u32).for_each(|i| {
+        let v = pnk!(hdr.get(&i));
+        assert_eq!(v.balance, i as u128);
+        assert_eq!(v.nonce, 1);
+    });
+}
+
+#[cfg(feature = "scale_codec")]
+#[test]
+fn scale_codec() {
+    use parity_scale_codec::{Decode, Encode};
+    use vsdb::common::codec::ScaleCodec;
+
+    #[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
+    struct Account {
+        #[codec(compact)]
+        balance: u128,
+        nonce: u64,
+    }
+
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: Vecx<Account, ScaleCodec> = Vecx::with_codec();
+    (0..100u64).for_each(|i| {
+        hdr.push(&Account {
+            balance: i as u128,
+            nonce: i,
+        });
+    });
+    (0..100usize).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(i)).nonce, i as u64);
+    });
+
+    // trailing bytes are rejected
+    let mut raw = Account {
+        balance: 1,
+        nonce: 1,
+    }
+    .encode();
+    raw.push(0);
+    assert!(<ScaleCodec as Codec<Account>>::decode(&raw).is_err());
+}
@@ -0,0 +1,90 @@
+//!
+//! A migration left by a previous process,
+//! the previous process is simulated by a child one.
+//!
+
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::{env, process::Command};
+use vsdb::{
+    DefaultCodec, Mapx, migrate_lazily, register_converter, vsdb_flush,
+    vsdb_get_schema_version, vsdb_open_named, vsdb_register, vsdb_set_base_dir,
+};
+
+const DIR_VAR: &str = "VSDB_MIGRATE_RESUME_DIR";
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct AccountV0 {
+    balance: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct AccountV1 {
+    balance: u128,
+    memo: String,
+}
+
+fn upgrade(old: AccountV0) -> AccountV1 {
+    AccountV1 {
+        balance: old.balance as u128,
+        memo: format!("{}", old.balance),
+    }
+}
+
+#[test]
+fn resume_cases() {
+    if let Ok(dir) = env::var(DIR_VAR) {
+        previous_process(&dir);
+        return;
+    }
+
+    let dir = format!("/tmp/vsdb_testing/{}", rand::random::<u64>());
+    let status = pnk!(
+        Command::new(pnk!(env::current_exe()))
+            .args(["resume_cases", "--exact", "--test-threads=1"])
+            .env(DIR_VAR, &dir)
+            .status()
+    );
+    assert!(status.success());
+
+    info_omit!(vsdb_set_base_dir(&dir));
+    register_converter::<AccountV0, AccountV1, DefaultCodec>(upgrade);
+
+    let mut hdr = pnk!(vsdb_open_named::<Mapx<u64, AccountV1>>("accounts"));
+    assert_eq!(0, vsdb_get_schema_version(&hdr));
+
+    // the converted values are not converted again
+    hdr.iter().for_each(|(i, v)| {
+        assert_eq!(v, upgrade(AccountV0 { balance: i }));
+    });
+    assert_eq!(100, hdr.len());
+
+    (0..100u64).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(&i)), upgrade(AccountV0 { balance: i }));
+    });
+    let v = AccountV1 {
+        balance: 0,
+        memo: "new".to_owned(),
+    };
+    assert_eq!(pnk!(hdr.insert(&0, &v)), upgrade(AccountV0 { balance: 0 }));
+    assert_eq!(pnk!(hdr.get(&0)), v);
+}
+
+// Convert some values and exit without finishing the migration.
+fn previous_process(dir: &str) {
+    pnk!(vsdb_set_base_dir(dir));
+
+    let mut hdr: Mapx<u64, AccountV0> = Mapx::new();
+    (0..100u64).for_each(|i| {
+        hdr.insert(&i, &AccountV0 { balance: i });
+    });
+    pnk!(vsdb_register("accounts", &hdr));
+
+    let (hdr, _m) = pnk!(migrate_lazily(hdr, upgrade));
+    let hdr: Mapx<u64, AccountV1> = hdr;
+    (0..100u64).step_by(3).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(&i)), upgrade(AccountV0 { balance: i }));
+    });
+
+    vsdb_flush();
+}
@@ -0,0 +1,232 @@
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use vsdb::{
+    migrate, migrate_in_background, migrate_lazily, vsdb_get_schema_version,
+    vsdb_list_pending_migrations, vsdb_set_base_dir, Mapx, Registrable, Vecx,
+};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct AccountV0 {
+    balance: u64,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct AccountV1 {
+    balance: u128,
+    memo: String,
+}
+
+fn upgrade(old: AccountV0) -> AccountV1 {
+    AccountV1 {
+        balance: old.balance as u128,
+        memo: format!("{}", old.balance),
+    }
+}
+
+#[test]
+fn basic_cases() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: Mapx<u64, AccountV0> = Mapx::new();
+    (0..100u64).for_each(|i| {
+        hdr.insert(&i, &AccountV0 { balance: i });
+    });
+    assert_eq!(0, vsdb_get_schema_version(&hdr));
+
+    let hdr: Mapx<u64, AccountV1> = pnk!(migrate(hdr, upgrade));
+    assert_eq!(1, vsdb_get_schema_version(&hdr));
+    assert_eq!(100, hdr.len());
+    (0..100u64).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(&i)), upgrade(AccountV0 { balance: i }));
+    });
+
+    let mut hdr: Vecx<AccountV0> = Vecx::new();
+    (0..100u64).for_each(|i| {
+        hdr.push(&AccountV0 { balance: i });
+    });
+
+    let (hdr, handle) = pnk!(migrate_in_background(hdr, upgrade));
+    let hdr: Vecx<AccountV1> = hdr;
+
+    // readable while being migrated
+    (0..100u64).rev().for_each(|i| {
+        assert_eq!(pnk!(hdr.get(i as usize)), upgrade(AccountV0 { balance: i }));
+    });
+
+    pnk!(handle.join().unwrap());
+    assert_eq!(1, vsdb_get_schema_version(&hdr));
+    (0..100u64).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(i as usize)), upgrade(AccountV0 { balance: i }));
+    });
+}
+
+#[test]
+fn lazy_cases() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    static CNT: AtomicUsize = AtomicUsize::new(0);
+
+    let counted_upgrade = |old: AccountV0| {
+        CNT.fetch_add(1, Ordering::Relaxed);
+        upgrade(old)
+    };
+
+    let mut hdr: Mapx<u64, AccountV0> = Mapx::new();
+    (0..100u64).for_each(|i| {
+        hdr.insert(&i, &AccountV0 { balance: i });
+    });
+    let shadow: Mapx<u64, AccountV0> = unsafe { hdr.shadow() };
+
+    let (mut hdr, m) = pnk!(migrate_lazily(hdr, counted_upgrade));
+    let hdr_prefix = hdr.instance_prefix();
+    assert_eq!(0, CNT.load(Ordering::Relaxed));
+    assert_eq!(0, vsdb_get_schema_version(&hdr));
+    assert!(vsdb_list_pending_migrations()
+        .iter()
+        .any(|(prefix, target, _)| *prefix == hdr_prefix && 1 == *target));
+
+    // only one migration at a time
+    assert!(migrate_lazily(shadow, upgrade).is_err());
+
+    // converted on read, only once
+    (0..10u64).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(&i)), upgrade(AccountV0 { balance: i }));
+        assert_eq!(pnk!(hdr.get(&i)), upgrade(AccountV0 { balance: i }));
+    });
+    assert_eq!(10, CNT.load(Ordering::Relaxed));
+
+    // new values are never converted,
+    // the replaced one is converted before being returned
+    let v = AccountV1 {
+        balance: 1000,
+        memo: "new".to_owned(),
+    };
+    assert_eq!(
+        pnk!(hdr.insert(&10, &v)),
+        upgrade(AccountV0 { balance: 10 })
+    );
+    assert!(hdr.insert(&100, &v).is_none());
+    assert_eq!(pnk!(hdr.get(&10)), v);
+    assert_eq!(pnk!(hdr.get(&100)), v);
+    assert_eq!(11, CNT.load(Ordering::Relaxed));
+
+    // removed values are converted before being returned
+    assert_eq!(
+        pnk!(hdr.remove(&11)),
+        upgrade(AccountV0 { balance: 11 })
+    );
+    assert_eq!(12, CNT.load(Ordering::Relaxed));
+
+    pnk!(m.run());
+    assert_eq!(100, CNT.load(Ordering::Relaxed));
+    assert_eq!(1, vsdb_get_schema_version(&hdr));
+    assert!(!vsdb_list_pending_migrations()
+        .iter()
+        .any(|(prefix, _, _)| *prefix == hdr_prefix));
+
+    assert_eq!(100, hdr.len());
+    assert!(hdr.get(&11).is_none());
+    assert_eq!(pnk!(hdr.get(&10)), v);
+    assert_eq!(pnk!(hdr.get(&100)), v);
+    (0..100u64).filter(|i| ![10, 11].contains(i)).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(&i)), upgrade(AccountV0 { balance: i }));
+    });
+
+    // range queries convert the values one by one
+    let (hdr, m) = pnk!(migrate_lazily(hdr, |old: AccountV1| old.balance));
+    let mut hdr: Mapx<u64, u128> = hdr;
+    assert_eq!(pnk!(hdr.get(&99)), 99);
+    // the iteration order of `Mapx` depends on the codec
+    let expected = |k: u64| if [10, 100].contains(&k) { 1000 } else { k as u128 };
+    let (k, v) = pnk!(hdr.iter().last());
+    assert_eq!(v, expected(k));
+    assert_eq!(pnk!(hdr.get(&99)), 99);
+    let bumped = hdr.iter().take(5).map(|(k, _)| k).collect::<Vec<_>>();
+    hdr.values_mut().take(5).for_each(|mut v| *v += 1);
+    assert_eq!(100, hdr.iter().count());
+    assert_eq!(1, vsdb_get_schema_version(&hdr));
+    pnk!(m.run());
+    assert_eq!(2, vsdb_get_schema_version(&hdr));
+    hdr.iter().for_each(|(k, v)| {
+        assert_eq!(v, expected(k) + bumped.contains(&k) as u128);
+    });
+}
+
+#[cfg(feature = "vs")]
+#[test]
+fn versioned_cases() {
+    use vsdb::{BranchName, MapxVs, ParentBranchName, VersionName, VsMgmt};
+
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: MapxVs<u64, AccountV0> = MapxVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..10u64).for_each(|i| {
+        pnk!(hdr.insert(&i, &AccountV0 { balance: i }));
+    });
+    pnk!(hdr.branch_create(BranchName(b"b1"), VersionName(b"v1"), false));
+    pnk!(hdr.branch_set_default(BranchName(b"b1")));
+    (0..10u64).for_each(|i| {
+        pnk!(hdr.insert(&i, &AccountV0 { balance: 10 * i }));
+    });
+    pnk!(hdr.remove(&0));
+    pnk!(hdr.branch_set_default(BranchName(b"master")));
+
+    let (hdr, m) = pnk!(migrate_lazily(hdr, upgrade));
+    let mut hdr: MapxVs<u64, AccountV1> = hdr;
+
+    // all versions of the key are converted on the first read
+    assert_eq!(
+        pnk!(hdr.get_by_branch(&1, BranchName(b"b1"))),
+        upgrade(AccountV0 { balance: 10 })
+    );
+    assert_eq!(pnk!(hdr.get(&1)), upgrade(AccountV0 { balance: 1 }));
+    assert_eq!(0, vsdb_get_schema_version(&hdr));
+
+    pnk!(m.run());
+    assert_eq!(1, vsdb_get_schema_version(&hdr));
+
+    // every version of every branch has been migrated
+    (0..10u64).for_each(|i| {
+        assert_eq!(pnk!(hdr.get(&i)), upgrade(AccountV0 { balance: i }));
+        assert_eq!(
+            pnk!(hdr.get_by_branch_version(
+                &i,
+                BranchName(b"master"),
+                VersionName(b"v0")
+            )),
+            upgrade(AccountV0 { balance: i })
+        );
+    });
+    assert!(hdr.get_by_branch(&0, BranchName(b"b1")).is_none());
+    (1..10u64).for_each(|i| {
+        assert_eq!(
+            pnk!(hdr.get_by_branch(&i, BranchName(b"b1"))),
+            upgrade(AccountV0 { balance: 10 * i })
+        );
+    });
+
+    // still writable after the migration
+    pnk!(hdr.branch_create_by_base_branch(
+        BranchName(b"b2"),
+        VersionName(b"v2"),
+        ParentBranchName(b"b1"),
+        false
+    ));
+    pnk!(hdr.insert_by_branch(
+        &0,
+        &upgrade(AccountV0 { balance: 0 }),
+        BranchName(b"b2")
+    ));
+    assert!(hdr.get_by_branch(&0, BranchName(b"b2")).is_some());
+}
@@ -0,0 +1,179 @@
+use ruc::*;
+use vsdb::{migrate_lazily, vsdb_set_base_dir, Mapx, MapxOrd, Overlay};
+
+#[test]
+fn basic_cases() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: Mapx<u64, u64> = Mapx::new();
+    (0..10u64).for_each(|i| {
+        hdr.insert(&i, &i);
+    });
+
+    let mut o = Overlay::new(hdr);
+    assert!(!o.is_dirty());
+    assert_eq!(Some(1), o.insert(&1, &100));
+    assert_eq!(None, o.insert(&100, &100));
+    assert_eq!(Some(2), o.remove(&2));
+    assert_eq!(None, o.remove(&2));
+    assert!(o.is_dirty());
+
+    assert_eq!(Some(100), o.get(&1));
+    assert!(!o.contains_key(&2));
+    assert_eq!(10, o.len());
+    assert_eq!(1, o.base().get(&1).unwrap());
+    assert!(o.base().get(&100).is_none());
+
+    let hdr = o.discard();
+    assert_eq!(10, hdr.len());
+    assert_eq!(Some(1), hdr.get(&1));
+    assert_eq!(Some(2), hdr.get(&2));
+
+    let mut o = Overlay::new(hdr);
+    o.insert(&1, &100);
+    o.remove(&2);
+    let hdr = pnk!(o.commit());
+    assert_eq!(9, hdr.len());
+    assert_eq!(Some(100), hdr.get(&1));
+    assert!(hdr.get(&2).is_none());
+}
+
+#[test]
+fn savepoints() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: MapxOrd<u64, u64> = MapxOrd::new();
+    (0..10u64).for_each(|i| {
+        hdr.insert(&i, &i);
+    });
+
+    let mut o = Overlay::new(hdr);
+    o.insert(&20, &20);
+
+    let sp0 = o.savepoint();
+    o.remove(&0);
+    o.insert(&5, &50);
+
+    let sp1 = o.savepoint();
+    o.insert(&5, &500);
+    o.insert(&15, &15);
+    assert_eq!(Some(500), o.get(&5));
+
+    let sp2 = o.savepoint();
+    o.remove(&15);
+    pnk!(o.release(sp2));
+    assert!(o.get(&15).is_none());
+
+    pnk!(o.rollback_to(sp1));
+    assert_eq!(Some(50), o.get(&5));
+    assert!(o.get(&15).is_none());
+
+    let sp1 = o.savepoint();
+    o.insert(&15, &15);
+    pnk!(o.release(sp1));
+
+    // entries are merged in order
+    assert_eq!(
+        o.iter().collect::<Vec<_>>(),
+        vec![
+            (1, 1),
+            (2, 2),
+            (3, 3),
+            (4, 4),
+            (5, 50),
+            (6, 6),
+            (7, 7),
+            (8, 8),
+            (9, 9),
+            (15, 15),
+            (20, 20)
+        ]
+    );
+    assert_eq!(11, o.len());
+
+    let stale = o.savepoint();
+    pnk!(o.rollback_to(sp0));
+    assert!(o.rollback_to(stale).is_err());
+    assert_eq!(Some(0), o.get(&0));
+    assert_eq!(Some(20), o.get(&20));
+    assert_eq!(11, o.len());
+
+    let hdr = pnk!(o.commit());
+    assert_eq!(11, hdr.len());
+    assert_eq!(Some(20), hdr.get(&20));
+    assert_eq!(Some(5), hdr.get(&5));
+}
+
+#[test]
+fn migrating_base() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: Mapx<u64, u64> = Mapx::new();
+    (0..10u64).for_each(|i| {
+        hdr.insert(&i, &i);
+    });
+
+    let (hdr, m) = pnk!(migrate_lazily(hdr, |old: u64| old as u128 + 1000));
+    let hdr: Mapx<u64, u128> = hdr;
+
+    // the committed values are never converted
+    let mut o = Overlay::new(hdr);
+    o.insert(&1, &1);
+    o.insert(&100, &100);
+    o.remove(&2);
+    let hdr = pnk!(o.commit());
+
+    pnk!(m.run());
+    assert_eq!(10, hdr.len());
+    assert_eq!(Some(1), hdr.get(&1));
+    assert_eq!(Some(100), hdr.get(&100));
+    assert!(hdr.get(&2).is_none());
+    assert_eq!(Some(1003), hdr.get(&3));
+}
+
+#[cfg(feature = "vs")]
+#[test]
+fn versioned_cases() {
+    use vsdb::{BranchName, MapxVs, VersionName, VsMgmt};
+
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut hdr: MapxVs<u64, u64> = MapxVs::new();
+    pnk!(hdr.version_create(VersionName(b"v0")));
+    (0..10u64).for_each(|i| {
+        pnk!(hdr.insert(&i, &i));
+    });
+
+    let mut o = Overlay::new(hdr);
+    o.insert(&3, &33);
+    o.remove(&4);
+    assert_eq!(9, o.len());
+    assert_eq!(Some(3), o.base().get(&3));
+
+    let mut hdr = o.discard();
+    assert_eq!(10, hdr.len());
+
+    pnk!(hdr.version_create(VersionName(b"v1")));
+    let mut o = Overlay::new(hdr);
+    o.insert(&3, &33);
+    o.remove(&4);
+    let hdr = pnk!(o.commit());
+    assert_eq!(Some(33), hdr.get(&3));
+    assert!(hdr.get(&4).is_none());
+    assert_eq!(
+        Some(3),
+        hdr.get_by_branch_version(&3, BranchName(b"master"), VersionName(b"v0"))
+    );
+}
@@ -0,0 +1,58 @@
+use ruc::*;
+use vsdb::{
+    vsdb_get_named_raw, vsdb_list_instances, vsdb_list_instances_with_sizes,
+    vsdb_open_named, vsdb_register, vsdb_set_base_dir, vsdb_unregister, Mapx, Vecx,
+};
+
+#[test]
+fn registry_cases() {
+    info_omit!(vsdb_set_base_dir(format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut balances: Mapx<u64, u64> = Mapx::new();
+    (0..100u64).for_each(|i| {
+        balances.insert(&i, &(i * 10));
+    });
+    pnk!(vsdb_register("balances", &balances));
+
+    // registering the same instance again is ok
+    pnk!(vsdb_register("balances", &balances));
+
+    // the name has been used by another instance
+    let logs: Vecx<u64> = Vecx::new();
+    assert!(vsdb_register("balances", &logs).is_err());
+    pnk!(vsdb_register("logs", &logs));
+
+    let reopened = pnk!(vsdb_open_named::<Mapx<u64, u64>>("balances"));
+    (0..100u64).for_each(|i| {
+        assert_eq!(reopened.get(&i), Some(i * 10));
+    });
+
+    // type mismatch
+    assert!(vsdb_open_named::<Vecx<u64>>("balances").is_err());
+    assert!(vsdb_open_named::<Mapx<u64, u64>>("not_exist").is_err());
+    assert!(pnk!(vsdb_get_named_raw("not_exist")).is_none());
+    assert_eq!("Mapx", pnk!(vsdb_get_named_raw("balances")).unwrap().0);
+
+    let infos = vsdb_list_instances();
+    let info = infos.iter().find(|i| i.name == "balances").unwrap();
+    assert_eq!("Mapx", info.kind);
+    assert_eq!(100, info.entry_count);
+    assert!(info.disk_size.is_none());
+    let info = infos.iter().find(|i| i.name == "logs").unwrap();
+    assert_eq!("Vecx", info.kind);
+    assert_eq!(0, info.entry_count);
+
+    let infos = vsdb_list_instances_with_sizes();
+    let info = infos.iter().find(|i| i.name == "balances").unwrap();
+    assert!(0 < info.disk_size.unwrap());
+    let info = infos.iter().find(|i| i.name == "logs").unwrap();
+    assert_eq!(Some(0), info.disk_size);
+
+    pnk!(vsdb_unregister("logs"));
+    assert!(vsdb_unregister("logs").is_err());
+    assert!(vsdb_open_named::<Vecx<u64>>("logs").is_err());
+    assert!(vsdb_list_instances().iter().all(|i| i.name != "logs"));
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "vs")]
+
+use ruc::*;
+use std::collections::HashMap;
+use vsdb::{
+    vsdb_set_base_dir, BranchName, MapxVs, OrphanVs, VecxVs, VersionGraph, VersionName,
+    Vs, VsMgmt,
+};
+
+#[derive(Vs)]
+struct Unit;
+
+#[derive(Vs)]
+enum State {
+    Empty,
+    Ledger {
+        txs: VecxVs<u64>,
+        #[vs(skip)]
+        cache: HashMap<u64, u64>,
+        height: OrphanVs<u64>,
+    },
+    Accounts(#[vs(skip)] String, MapxVs<u64, u64>),
+}
+
+// The versioned part lives behind a plain wrapper.
+struct Boxed(MapxVs<u64, u64>);
+
+mod boxed {
+    use super::*;
+
+    pub fn as_vs(b: &Boxed) -> &MapxVs<u64, u64> {
+        &b.0
+    }
+
+    pub fn as_vs_mut(b: &mut Boxed) -> &mut MapxVs<u64, u64> {
+        &mut b.0
+    }
+}
+
+#[derive(Vs)]
+struct WithPath {
+    #[vs(with = "boxed")]
+    inner: Boxed,
+    unit: Unit,
+}
+
+fn set_base_dir() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+}
+
+#[test]
+fn unit_struct() {
+    let mut u = Unit;
+    pnk!(u.version_create(VersionName(b"v0")));
+    assert!(u.version_exists(VersionName(b"v0")));
+    assert!(pnk!(u.version_list()).is_empty());
+    assert_eq!(Some(vec![]), u.version_graphs());
+}
+
+#[test]
+fn enum_variants() {
+    set_base_dir();
+
+    let mut e = State::Empty;
+    pnk!(e.version_create(VersionName(b"v0")));
+    assert!(pnk!(e.version_list()).is_empty());
+
+    let graph = VersionGraph::new();
+    let mut ledger = State::Ledger {
+        txs: VecxVs::new(),
+        cache: HashMap::new(),
+        height: OrphanVs::new(),
+    };
+    pnk!(ledger.version_graph_attach(&graph));
+    assert_eq!(Some(vec![graph.clone()]), ledger.version_graphs());
+
+    pnk!(ledger.version_create(VersionName(b"v0")));
+    if let State::Ledger {
+        ref mut txs,
+        ref mut cache,
+        ref mut height,
+    } = ledger
+    {
+        txs.push(&1);
+        cache.insert(1, 1);
+        pnk!(height.set_value(&1));
+        assert!(txs.version_exists(VersionName(b"v0")));
+        assert!(height.version_exists(VersionName(b"v0")));
+    }
+    assert_eq!(2, graph.members_num());
+
+    pnk!(ledger.branch_create(BranchName(b"fork"), VersionName(b"v1"), false));
+    assert!(ledger.branch_exists(BranchName(b"fork")));
+    assert!(!pnk!(ledger.branch_is_empty(BranchName(b"fork"))));
+    pnk!(ledger.branch_remove(BranchName(b"fork")));
+    assert!(!ledger.branch_exists(BranchName(b"fork")));
+
+    pnk!(ledger.version_pop());
+    if let State::Ledger {
+        ref txs,
+        ref cache,
+        ref height,
+    } = ledger
+    {
+        assert!(txs.is_empty());
+        assert_eq!(1, cache.len());
+        assert_eq!(None, height.get_value());
+    }
+
+    let mut accounts = State::Accounts("skipped".to_owned(), MapxVs::new());
+    pnk!(accounts.version_create(VersionName(b"v0")));
+    if let State::Accounts(ref name, ref mut m) = accounts {
+        assert_eq!("skipped", name);
+        pnk!(m.insert(&1, &1));
+    }
+    assert!(pnk!(accounts.version_has_change_set(VersionName(b"v0"))));
+}
+
+#[test]
+fn with_path() {
+    set_base_dir();
+
+    let mut w = WithPath {
+        inner: Boxed(MapxVs::new()),
+        unit: Unit,
+    };
+    pnk!(w.version_create(VersionName(b"v0")));
+    assert!(w.inner.0.version_exists(VersionName(b"v0")));
+    pnk!(w.inner.0.insert(&1, &1));
+    pnk!(w.version_pop());
+    assert!(w.inner.0.get(&1).is_none());
+}
@@ -0,0 +1,117 @@
+#![cfg(feature = "vs")]
+
+use ruc::*;
+use vsdb::{
+    vsdb_set_base_dir, MapxOrdVs, MapxVs, OrphanVs, VecxVs, VersionName, Vs, VsDiff,
+    VsMgmt,
+};
+
+#[derive(Vs, VsDiff)]
+struct WorldState {
+    transactions: VecxVs<String>,
+    balances: MapxVs<String, u64>,
+    nonces: MapxOrdVs<u64, u64>,
+    height: OrphanVs<u64>,
+    #[vs(skip)]
+    cache: Vec<u64>,
+}
+
+#[derive(Vs, VsDiff)]
+struct Nested(WorldState, #[vs(skip)] u8, OrphanVs<u8>);
+
+fn set_base_dir() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+}
+
+fn world_state() -> WorldState {
+    WorldState {
+        transactions: VecxVs::new(),
+        balances: MapxVs::new(),
+        nonces: MapxOrdVs::new(),
+        height: OrphanVs::new(),
+        cache: vec![],
+    }
+}
+
+#[test]
+fn derived_diff() {
+    set_base_dir();
+
+    let mut ws = world_state();
+    pnk!(ws.version_create(VersionName(b"b0")));
+    pnk!(ws.balances.insert(&"alice".to_owned(), &100));
+    pnk!(ws.balances.insert(&"bob".to_owned(), &100));
+    pnk!(ws.nonces.insert(&0, &0));
+    pnk!(ws.height.set_value(&0));
+
+    pnk!(ws.version_create(VersionName(b"b1")));
+    ws.transactions.push(&"alice -> bob".to_owned());
+    pnk!(ws.balances.insert(&"alice".to_owned(), &90));
+    pnk!(ws.balances.insert(&"bob".to_owned(), &110));
+    pnk!(ws.nonces.insert(&0, &1));
+    pnk!(ws.height.set_value(&1));
+    ws.cache.push(1);
+
+    let mut d: WorldStateDiff = pnk!(ws.diff(VersionName(b"b0"), VersionName(b"b1")));
+    d.balances.sort();
+    assert_eq!(d.transactions, vec![(0, Some("alice -> bob".to_owned()))]);
+    assert_eq!(
+        d.balances,
+        vec![
+            ("alice".to_owned(), Some(90)),
+            ("bob".to_owned(), Some(110))
+        ]
+    );
+    assert_eq!(d.nonces, vec![(0, Some(1))]);
+    assert_eq!(d.height, Some(Some(1)));
+
+    // nothing changed since `b1`
+    pnk!(ws.version_create(VersionName(b"b2")));
+    let d = pnk!(ws.diff(VersionName(b"b1"), VersionName(b"b2")));
+    assert!(d.transactions.is_empty() && d.balances.is_empty() && d.nonces.is_empty());
+    assert_eq!(d.height, None);
+
+    // replay the changes of a block on another instance
+    let mut replica = world_state();
+    pnk!(replica.version_create(VersionName(b"b0")));
+    pnk!(replica.apply_diff(&pnk!(ws.diff(VersionName(b"b2"), VersionName(b"b0")))));
+    pnk!(replica.version_create(VersionName(b"b1")));
+    pnk!(replica.apply_diff(&pnk!(ws.diff(VersionName(b"b0"), VersionName(b"b1")))));
+    assert_eq!(1, replica.transactions.len());
+    assert_eq!(Some(90), replica.balances.get(&"alice".to_owned()));
+    assert_eq!(Some(1), replica.nonces.get(&0));
+    assert_eq!(Some(1), replica.height.get_value());
+    assert!(replica.cache.is_empty());
+
+    // revert to `b0` by applying the reversed diff
+    pnk!(ws.version_create(VersionName(b"b3")));
+    pnk!(ws.apply_diff(&pnk!(ws.diff(VersionName(b"b3"), VersionName(b"b0")))));
+    assert!(ws.transactions.is_empty());
+    assert_eq!(Some(100), ws.balances.get(&"bob".to_owned()));
+    assert_eq!(Some(0), ws.height.get_value());
+}
+
+#[test]
+fn nested_diff() {
+    set_base_dir();
+
+    let mut n = Nested(world_state(), 0, OrphanVs::new());
+    pnk!(n.version_create(VersionName(b"v0")));
+    pnk!(n.version_create(VersionName(b"v1")));
+    pnk!(n.0.balances.insert(&"alice".to_owned(), &1));
+    pnk!(n.2.set_value(&1));
+
+    let d: NestedDiff = pnk!(n.diff(VersionName(b"v0"), VersionName(b"v1")));
+    assert_eq!(d.0.balances, vec![("alice".to_owned(), Some(1))]);
+    assert_eq!(d.1, Some(Some(1)));
+
+    let d = NestedDiff::default();
+    assert!(d.0.balances.is_empty());
+    assert_eq!(d.1, None);
+    pnk!(n.apply_diff(&d));
+    assert_eq!(Some(1), n.2.get_value());
+    assert_eq!(0, n.1);
+}
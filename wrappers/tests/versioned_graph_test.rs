@@ -0,0 +1,397 @@
+#![cfg(feature = "vs")]
+
+use ruc::*;
+use serde::{Deserialize, Serialize};
+use vsdb::{
+    vsdb_set_base_dir, BranchName, BranchNameOwned, MapxDkVs, MapxVs, OrphanVs, ValueEnDe, VecxVs, VersionGraph,
+    VersionName, Vs, VsMgmt,
+};
+
+#[derive(Vs, Debug, Serialize, Deserialize)]
+struct WorldState {
+    transactions: VecxVs<Vec<u8>>,
+    balances: MapxVs<Vec<u8>, u64>,
+    height: OrphanVs<u64>,
+}
+
+impl WorldState {
+    fn new() -> Self {
+        Self {
+            transactions: VecxVs::new(),
+            balances: MapxVs::new(),
+            height: OrphanVs::new(),
+        }
+    }
+}
+
+#[test]
+fn shared_graph() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let graph = VersionGraph::new();
+    let mut ws = WorldState::new();
+    pnk!(ws.version_graph_attach(&graph));
+    assert_eq!(3, graph.members_num());
+    assert_eq!(Some(vec![graph.clone()]), ws.version_graphs());
+
+    pnk!(ws.version_create(VersionName(b"v0")));
+    assert!(ws.transactions.version_exists(VersionName(b"v0")));
+    assert!(ws.balances.version_exists(VersionName(b"v0")));
+    assert!(ws.height.version_exists(VersionName(b"v0")));
+
+    ws.transactions.push(&b"tx0".to_vec());
+    pnk!(ws.balances.insert(&b"alice".to_vec(), &100));
+    pnk!(ws.height.set_value(&1));
+
+    pnk!(ws.branch_create(BranchName(b"fork"), VersionName(b"v1"), false));
+    assert!(ws.balances.branch_exists(BranchName(b"fork")));
+    assert!(ws.height.branch_exists(BranchName(b"fork")));
+
+    // the metadata is checked once, nothing is left half-done on failures
+    assert!(ws.version_create(VersionName(b"v0")).is_err());
+    assert!(
+        ws.branch_create(BranchName(b"fork"), VersionName(b"v2"), false)
+            .is_err()
+    );
+    assert!(!ws.version_exists_globally(VersionName(b"v2")));
+    assert_eq!(2, ws.version_list_globally().len());
+
+    pnk!(ws.branch_remove(BranchName(b"fork")));
+    assert!(!ws.transactions.branch_exists(BranchName(b"fork")));
+    assert!(!ws.height.branch_exists(BranchName(b"fork")));
+
+    // reloaded fields keep sharing one graph
+    let ws2 = pnk!(WorldState::decode(&ws.encode()));
+    assert_eq!(Some(vec![graph]), ws2.version_graphs());
+    assert_eq!(1, ws2.transactions.len());
+    assert_eq!(Some(100), ws2.balances.get(&b"alice".to_vec()));
+}
+
+#[test]
+fn shared_graph_by_default() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    // fields are moved to one graph by the first versioned operation
+    let mut ws = WorldState::new();
+    assert_eq!(3, pnk!(ws.version_graphs()).len());
+    pnk!(ws.version_create(VersionName(b"v0")));
+    let graphs = pnk!(ws.version_graphs());
+    assert_eq!(1, graphs.len());
+    assert_eq!(3, graphs[0].members_num());
+
+    ws.transactions.push(&b"tx0".to_vec());
+    let ws2 = pnk!(WorldState::decode(&ws.encode()));
+    assert_eq!(Some(graphs), ws2.version_graphs());
+
+    // fields that have been used keep their own graphs,
+    // the others join the first one of them
+    let mut transactions = VecxVs::new();
+    pnk!(transactions.version_create(VersionName(b"v0")));
+    transactions.push(&b"tx0".to_vec());
+    let mut balances = MapxVs::new();
+    pnk!(balances.version_create(VersionName(b"b0")));
+    let mut ws = WorldState {
+        transactions,
+        balances,
+        height: OrphanVs::new(),
+    };
+    pnk!(ws.version_create(VersionName(b"v1")));
+    let graphs = pnk!(ws.version_graphs());
+    assert_eq!(2, graphs.len());
+    assert_eq!(2, graphs[0].members_num());
+    assert_eq!(1, graphs[1].members_num());
+    assert!(ws.height.version_exists(VersionName(b"v0")));
+    assert!(!ws.balances.version_exists(VersionName(b"v0")));
+    assert!(ws.balances.version_exists(VersionName(b"v1")));
+}
+
+#[derive(Vs)]
+struct WithGraph {
+    graph: VersionGraph,
+    balances: MapxVs<Vec<u8>, u64>,
+    nonces: MapxVs<Vec<u8>, u64>,
+}
+
+#[test]
+fn shared_graph_with_graph_field() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    // the graph shared by the most fields is used
+    let graph = VersionGraph::new();
+    let mut balances = MapxVs::new();
+    pnk!(balances.version_graph_attach(&graph));
+    let mut w = WithGraph {
+        graph,
+        balances,
+        nonces: MapxVs::new(),
+    };
+    pnk!(w.version_create(VersionName(b"v0")));
+    assert_eq!(Some(vec![w.graph.clone()]), w.version_graphs());
+    assert_eq!(2, w.graph.members_num());
+
+    // a bare graph without members is kept as is
+    let mut w = WithGraph {
+        graph: VersionGraph::new(),
+        balances: MapxVs::new(),
+        nonces: MapxVs::new(),
+    };
+    pnk!(w.version_create(VersionName(b"v0")));
+    assert_eq!(2, pnk!(w.version_graphs()).len());
+    assert_eq!(0, w.graph.members_num());
+    assert!(w.nonces.version_exists(VersionName(b"v0")));
+
+    // multi-key collections manage their versions by themselves
+    let mut mk = vsdb::MapxRawMkVs::new(2);
+    pnk!(mk.version_graph_attach(&VersionGraph::new()));
+    assert_eq!(None, mk.version_graphs());
+}
+
+#[derive(Vs)]
+struct WithMultiKey {
+    balances: MapxVs<Vec<u8>, u64>,
+    allowances: MapxDkVs<Vec<u8>, Vec<u8>, u64>,
+}
+
+#[test]
+fn shared_graph_with_multi_key() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    // the multi-key field is skipped, the other one is attached
+    let graph = VersionGraph::new();
+    let mut w = WithMultiKey {
+        balances: MapxVs::new(),
+        allowances: MapxDkVs::new(),
+    };
+    pnk!(w.version_graph_attach(&graph));
+    assert_eq!(1, graph.members_num());
+    assert_eq!(Some(vec![graph.clone()]), w.balances.version_graphs());
+    assert_eq!(None, w.version_graphs());
+
+    pnk!(w.version_create(VersionName(b"v0")));
+    assert!(graph.version_exists(VersionName(b"v0")));
+    assert!(w.allowances.version_exists(VersionName(b"v0")));
+
+    let (alice, bob) = (b"alice".to_vec(), b"bob".to_vec());
+    pnk!(w.balances.insert(&alice, &100));
+    pnk!(w.allowances.insert(&(&alice, &bob), &10));
+
+    pnk!(w.branch_create(BranchName(b"fork"), VersionName(b"v1"), false));
+    assert!(graph.branch_exists(BranchName(b"fork")));
+    assert!(w.allowances.branch_exists(BranchName(b"fork")));
+    assert_eq!(
+        Some(10),
+        w.allowances.get_by_branch(&(&alice, &bob), BranchName(b"fork"))
+    );
+    assert_eq!(Some(100), w.balances.get_by_branch(&alice, BranchName(b"fork")));
+}
+
+#[test]
+fn shared_graph_rebase() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    // the rebasing is atomic among the fields sharing one graph
+    let mut ws = WorldState::new();
+    pnk!(ws.version_graph_attach(&VersionGraph::new()));
+    let master = ws.branch_get_default();
+    let fork = BranchName(b"fork");
+
+    pnk!(ws.version_create(VersionName(b"b0")));
+    pnk!(ws.balances.insert(&b"alice".to_vec(), &100));
+    pnk!(ws.branch_create(fork, VersionName(b"f1"), false));
+    pnk!(ws.balances.insert_by_branch(&b"bob".to_vec(), &1, fork));
+    pnk!(ws.height.set_value_by_branch(&1, fork));
+
+    pnk!(ws.version_create(VersionName(b"b1")));
+    ws.transactions.push(&b"tx0".to_vec());
+    pnk!(ws.height.set_value(&1));
+
+    // `height` has been changed on both branches
+    assert!(ws.branch_rebase_onto(fork, master.as_deref()).is_err());
+    pnk!(ws.branch_pop_version(master.as_deref()));
+    pnk!(ws.version_create(VersionName(b"b2")));
+    ws.transactions.push(&b"tx0".to_vec());
+
+    pnk!(ws.branch_rebase_onto(fork, master.as_deref()));
+    assert!(ws.height.version_exists_on_branch(VersionName(b"b2"), fork));
+    assert_eq!(1, ws.transactions.len_by_branch(fork));
+    assert_eq!(Some(1), ws.balances.get_by_branch(&b"bob".to_vec(), fork));
+    assert_eq!(Some(1), ws.height.get_value_by_branch(fork));
+    assert_eq!(None, ws.height.get_value());
+
+    assert_eq!(Some(master.clone()), pnk!(ws.branch_parent(fork)));
+    assert_eq!(
+        Some(b"b2".to_vec()),
+        pnk!(ws.branch_merge_base(fork, master.as_deref())).map(|v| v.0)
+    );
+    assert!(pnk!(ws.version_is_ancestor(VersionName(b"b2"), fork)));
+    assert_eq!(
+        vec![BranchNameOwned::from(fork)],
+        pnk!(ws.branches_containing(VersionName(b"f1")))
+    );
+}
+
+#[derive(Vs)]
+struct Mixed {
+    balances: MapxVs<Vec<u8>, u64>,
+    allowances: vsdb::MapxRawMkVs,
+    #[vs(skip)]
+    cache: u8,
+    flag: u8,
+}
+
+#[test]
+fn rename_across_graphs() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut m = Mixed {
+        balances: MapxVs::new(),
+        allowances: vsdb::MapxRawMkVs::new(2),
+        cache: 0,
+        flag: 0,
+    };
+    let fork = BranchName(b"fork");
+    let renamed = BranchName(b"renamed");
+
+    pnk!(m.version_create(VersionName(b"v0")));
+    pnk!(m.branch_create(fork, VersionName(b"f0"), false));
+    pnk!(m.balances.insert_by_branch(&b"alice".to_vec(), &1, fork));
+    pnk!(
+        m.allowances
+            .insert_by_branch(&[b"alice", b"bob"], &[1], fork)
+    );
+
+    // the name is taken in one of the fields, nothing will be renamed
+    pnk!(
+        m.allowances
+            .branch_create(renamed, VersionName(b"r0"), false)
+    );
+    assert!(m.branch_rename(fork, renamed).is_err());
+    assert!(m.balances.branch_exists(fork));
+    assert!(m.allowances.branch_exists(fork));
+
+    pnk!(m.allowances.branch_remove(renamed));
+    pnk!(m.branch_rename(fork, renamed));
+    assert!(!m.branch_exists(fork));
+    assert_eq!(
+        Some(1),
+        m.balances.get_by_branch(&b"alice".to_vec(), renamed)
+    );
+    assert!(
+        m.allowances
+            .contains_key_by_branch(&[b"alice", b"bob"], renamed)
+    );
+
+    let info = vsdb::BranchInfo {
+        creator: "alice".to_owned(),
+        ..Default::default()
+    };
+    pnk!(m.branch_set_info(renamed, &info));
+    assert_eq!("alice", pnk!(m.allowances.branch_info(renamed)).creator);
+    let info = pnk!(m.branch_info(renamed));
+    assert_eq!("alice", info.creator);
+    assert_eq!(Some(b"v0".to_vec()), info.creation_version.map(|v| v.0));
+    assert_eq!(0, m.cache + m.flag);
+}
+
+#[test]
+fn prune_with_policy_across_graphs() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut m = Mixed {
+        balances: MapxVs::new(),
+        allowances: vsdb::MapxRawMkVs::new(2),
+        cache: 0,
+        flag: 0,
+    };
+
+    for i in 0..10u64 {
+        pnk!(m.version_create(VersionName(format!("b{}", i).as_bytes())));
+        pnk!(m.balances.insert(&b"alice".to_vec(), &i));
+        pnk!(m.allowances.insert(&[b"alice", b"bob"], &i.to_be_bytes()));
+    }
+
+    pnk!(m.prune_with_policy(&vsdb::KeepEveryNth(5)));
+    for ver in [&b"b0"[..], b"b5", b"b9"] {
+        assert!(m.balances.version_exists(VersionName(ver)));
+        assert!(m.allowances.version_exists(VersionName(ver)));
+    }
+    assert!(!m.balances.version_exists(VersionName(b"b4")));
+    assert!(!m.allowances.version_exists(VersionName(b"b4")));
+
+    let master = m.branch_get_default();
+    assert_eq!(
+        Some(5),
+        m.balances.get_by_branch_version(
+            &b"alice".to_vec(),
+            master.as_deref(),
+            VersionName(b"b5")
+        )
+    );
+    assert_eq!(
+        Some(9u64.to_be_bytes().to_vec()),
+        m.allowances.get(&[b"alice", b"bob"])
+    );
+}
+
+#[test]
+fn prune_step_across_graphs() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+
+    let mut m = Mixed {
+        balances: MapxVs::new(),
+        allowances: vsdb::MapxRawMkVs::new(2),
+        cache: 0,
+        flag: 0,
+    };
+
+    for i in 0..10u64 {
+        pnk!(m.version_create(VersionName(format!("b{}", i).as_bytes())));
+        pnk!(m.balances.insert(&b"alice".to_vec(), &i));
+        pnk!(m.allowances.insert(&[b"alice", b"bob"], &i.to_be_bytes()));
+    }
+
+    // the progress of all graphs is accumulated
+    let p = pnk!(m.prune_step(Some(2), 2));
+    assert_eq!(4, p.done);
+    assert_eq!(10, p.remaining);
+
+    let mut steps = 1;
+    loop {
+        steps += 1;
+        alt!(pnk!(m.prune_step(Some(2), 2)).is_finished(), break);
+    }
+    assert_eq!(4, steps);
+
+    assert!(!m.balances.version_exists(VersionName(b"b7")));
+    assert!(!m.allowances.version_exists(VersionName(b"b7")));
+    assert!(m.version_exists(VersionName(b"b8")));
+    assert_eq!(Some(9), m.balances.get(&b"alice".to_vec()));
+    assert_eq!(
+        Some(9u64.to_be_bytes().to_vec()),
+        m.allowances.get(&[b"alice", b"bob"])
+    );
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "vs")]
+
+use ruc::*;
+use vsdb::{
+    vsdb_set_base_dir, BranchName, MapxOrdVs, MapxRawMkVs, MapxVs, OrphanVs, VecxVs,
+    VersionName, Vs, VsMgmt, VsView,
+};
+
+#[derive(Vs, VsView)]
+struct WorldState {
+    transactions: VecxVs<String>,
+    balances: MapxVs<String, u64>,
+    nonces: MapxOrdVs<u64, u64>,
+    height: OrphanVs<u64>,
+    allowances: MapxRawMkVs,
+    #[vs(skip)]
+    cache: Vec<u64>,
+}
+
+#[derive(Vs, VsView)]
+struct Generic<V: vsdb::ValueEnDe>(#[vs(skip)] u8, MapxVs<u64, V>);
+
+#[derive(Vs, VsView)]
+struct Empty;
+
+fn set_base_dir() {
+    info_omit!(vsdb_set_base_dir(&format!(
+        "/tmp/vsdb_testing/{}",
+        rand::random::<u64>()
+    )));
+}
+
+// The query code written against the live collections
+// is reused against historical views without changes.
+fn total_balance(view: &WorldStateView<'_>) -> u64 {
+    view.balances.iter().map(|(_, v)| v).sum()
+}
+
+#[test]
+fn derived_view() {
+    set_base_dir();
+
+    let mut ws = WorldState {
+        transactions: VecxVs::new(),
+        balances: MapxVs::new(),
+        nonces: MapxOrdVs::new(),
+        height: OrphanVs::new(),
+        allowances: MapxRawMkVs::new(2),
+        cache: vec![],
+    };
+
+    pnk!(ws.version_create(VersionName(b"b0")));
+    pnk!(ws.balances.insert(&"alice".to_owned(), &100));
+    pnk!(ws.nonces.insert(&0, &0));
+    pnk!(ws.height.set_value(&0));
+    pnk!(ws.allowances.insert(&[b"alice", b"bob"], &[1]));
+
+    pnk!(ws.version_create(VersionName(b"b1")));
+    ws.transactions.push(&"alice -> bob".to_owned());
+    pnk!(ws.balances.insert(&"alice".to_owned(), &90));
+    pnk!(ws.balances.insert(&"bob".to_owned(), &10));
+    pnk!(ws.nonces.insert(&0, &1));
+    pnk!(ws.nonces.insert(&1, &0));
+    pnk!(ws.height.set_value(&1));
+    pnk!(ws.allowances.remove(&[b"alice", b"bob"]));
+    ws.cache.push(1);
+
+    let master = ws.branch_get_default();
+    let v0 = pnk!(ws.view_at(master.as_deref(), VersionName(b"b0")));
+    assert!(v0.transactions.is_empty());
+    assert!(v0.transactions.last().is_none());
+    assert_eq!(Some(100), v0.balances.get(&"alice".to_owned()));
+    assert!(!v0.balances.contains_key(&"bob".to_owned()));
+    assert_eq!(1, v0.balances.len());
+    assert_eq!(100, total_balance(&v0));
+    assert_eq!(Some((0, 0)), v0.nonces.last());
+    assert_eq!(1, v0.nonces.range(0..).count());
+    assert_eq!(Some(0), v0.height.get_value());
+    assert_eq!(Some(vec![1]), v0.allowances.get(&[b"alice", b"bob"]));
+
+    let v1 = pnk!(ws.view_at(master.as_deref(), VersionName(b"b1")));
+    assert_eq!(Some("alice -> bob".to_owned()), v1.transactions.last());
+    assert_eq!(1, v1.transactions.iter().count());
+    assert_eq!(Some(10), v1.balances.get(&"bob".to_owned()));
+    assert_eq!(100, total_balance(&v1));
+    assert_eq!(Some((1, 0)), v1.nonces.get_ge(&1));
+    assert_eq!(Some(1), v1.height.get_value());
+    assert!(!v1.allowances.contains_key(&[b"alice", b"bob"]));
+
+    // views stay pinned while the live state moves on
+    pnk!(ws.version_create(VersionName(b"b2")));
+    pnk!(ws.height.set_value(&2));
+    let v1 = pnk!(ws.view_at(master.as_deref(), VersionName(b"b1")));
+    assert_eq!(Some(1), v1.height.get_value());
+    assert_eq!(Some(2), ws.height.get_value());
+
+    // other branches
+    pnk!(ws.branch_create(BranchName(b"fork"), VersionName(b"f0"), false));
+    pnk!(ws.height.set_value_by_branch(&100, BranchName(b"fork")));
+    let f0 = pnk!(ws.view_at(BranchName(b"fork"), VersionName(b"f0")));
+    assert_eq!(Some(100), f0.height.get_value());
+    let f2 = pnk!(ws.view_at(BranchName(b"fork"), VersionName(b"b2")));
+    assert_eq!(Some(2), f2.height.get_value());
+
+    assert!(ws.view_at(master.as_deref(), VersionName(b"f0")).is_err());
+    assert!(ws.view_at(BranchName(b"none"), VersionName(b"b0")).is_err());
+    assert!(ws.cache.len() == 1);
+}
+
+#[test]
+fn generic_and_empty_view() {
+    set_base_dir();
+
+    let mut g = Generic(0, MapxVs::<u64, String>::new());
+    pnk!(g.version_create(VersionName(b"v0")));
+    pnk!(g.1.insert(&1, &"one".to_owned()));
+    pnk!(g.version_create(VersionName(b"v1")));
+    pnk!(g.1.insert(&1, &"uno".to_owned()));
+
+    let br = g.branch_get_default();
+    let v: GenericView<'_, String> = pnk!(g.view_at(br.as_deref(), VersionName(b"v0")));
+    assert_eq!(Some("one".to_owned()), v.0.get(&1));
+    assert_eq!(Some("uno".to_owned()), g.1.get(&1));
+    assert_eq!(0, g.0);
+
+    let e = Empty;
+    let _: EmptyView<'_> = pnk!(e.view_at(br.as_deref(), VersionName(b"v0")));
+}